@@ -1,6 +1,7 @@
 /* Linker script for the STM32G070RB */
 MEMORY
 {
-  FLASH : ORIGIN = 0x08000000, LENGTH = 128K
+  /* the last two 2K pages (0x0801F000 - 0x0801FFFF) hold the configuration, see flash.rs */
+  FLASH : ORIGIN = 0x08000000, LENGTH = 124K
   RAM : ORIGIN = 0x20000000, LENGTH = 36K
-}
\ No newline at end of file
+}
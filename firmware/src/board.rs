@@ -0,0 +1,85 @@
+//! Board peripherals behind traits
+//!
+//! The application reaches the hardware through these traits. `stm32g070` holds
+//! the concrete pins and peripherals of the logger board, built for the target
+//! only, and `mock` stands in for them on the host, so code written against the
+//! traits runs in host tests: `selftest` and the task bodies in `logger`.
+//!
+//! The display sink is the `display-interface` the OLED driver already takes,
+//! persistent storage is the `ConfigFlash` of the configuration store and delays
+//! use `embedded-hal`.
+
+use display_interface::WriteOnlyDataCommand;
+
+use crate::clock::DateTime;
+use crate::config::Config;
+use crate::line::LineError;
+
+pub use crate::config::ConfigFlash as Storage;
+
+#[cfg(not(target_os = "none"))]
+pub mod mock;
+#[cfg(target_os = "none")]
+mod stm32g070;
+#[cfg(target_os = "none")]
+pub use stm32g070::*;
+
+/// The UART to the target, whose data is captured
+pub trait CaptureUart {
+    type Error;
+
+    /// A received byte, `WouldBlock` while there is none
+    fn read(&mut self) -> nb::Result<u8, Self::Error>;
+
+    fn write(&mut self, byte: u8) -> nb::Result<(), Self::Error>;
+}
+
+/// Line settings of the capture UART
+pub trait CaptureLine {
+    /// Change the baud rate, the word format and the receive timeout to those of
+    /// `config`
+    fn configure(&mut self, config: &Config) -> Result<(), LineError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Led {
+    Red,
+    Green,
+}
+
+pub trait StatusLeds {
+    fn set(&mut self, led: Led, on: bool);
+}
+
+/// What the encoder and the button did
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    /// Encoder steps, positive to the right
+    Turn(i32),
+    Press,
+}
+
+pub trait InputEvents {
+    /// The next input since the last call, None when there is none
+    fn poll(&mut self) -> Option<InputEvent>;
+
+    fn button_held(&mut self) -> bool;
+}
+
+/// Takes the commands and the display RAM data of the OLED
+pub trait DisplaySink: WriteOnlyDataCommand {}
+
+impl<DI: WriteOnlyDataCommand> DisplaySink for DI {}
+
+pub trait BatterySensor {
+    /// Battery voltage, None when it can't be read
+    fn battery_mv(&mut self) -> Option<u16>;
+}
+
+/// The real-time clock
+pub trait Calendar {
+    /// Date and time with the milliseconds, None while the clock is not set
+    fn now(&self) -> Option<(DateTime, u16)>;
+
+    fn set(&mut self, time: DateTime);
+}
@@ -0,0 +1,276 @@
+//! Host stand-ins for the board
+//!
+//! Time only passes through the delays, so a run is the same every time. The
+//! inputs are a script over that time, everything written is kept to be checked
+//! afterwards.
+
+use core::cell::Cell;
+use core::convert::Infallible;
+use core::ops::Range;
+
+use arrayvec::ArrayVec;
+use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+use embedded_hal::blocking::delay::DelayMs;
+use embedded_hal::serial;
+
+use super::{
+    BatterySensor, Calendar, CaptureLine, CaptureUart, InputEvent, InputEvents, Led, StatusLeds,
+};
+use crate::clock::DateTime;
+use crate::config::{Config, ConfigFlash, UartConfig, PAGE_LEN};
+use crate::line::LineError;
+
+/// Bytes kept of what was sent on the UART
+pub const SENT_LEN: usize = 64;
+/// Bytes kept of what was sent on the debug port
+pub const DEBUG_LEN: usize = 256;
+
+/// Milliseconds since the start, shared by the delay and the inputs
+pub struct MockClock {
+    ms: Cell<u32>,
+}
+
+impl MockClock {
+    pub const fn new() -> Self {
+        Self { ms: Cell::new(0) }
+    }
+
+    pub fn now(&self) -> u32 {
+        self.ms.get()
+    }
+}
+
+impl DelayMs<u32> for &MockClock {
+    fn delay_ms(&mut self, ms: u32) {
+        self.ms.set(self.ms.get().wrapping_add(ms));
+    }
+}
+
+/// UART with TX1 connected to RX1 when `connected`
+pub struct MockUart {
+    pub connected: bool,
+    received: Option<u8>,
+    pub sent: ArrayVec<[u8; SENT_LEN]>,
+}
+
+impl MockUart {
+    pub fn new(connected: bool) -> Self {
+        Self {
+            connected,
+            received: None,
+            sent: ArrayVec::new(),
+        }
+    }
+}
+
+impl CaptureUart for MockUart {
+    type Error = Infallible;
+
+    fn read(&mut self) -> nb::Result<u8, Infallible> {
+        self.received.take().ok_or(nb::Error::WouldBlock)
+    }
+
+    fn write(&mut self, byte: u8) -> nb::Result<(), Infallible> {
+        self.sent.try_push(byte).ok();
+        if self.connected {
+            self.received = Some(byte);
+        }
+        Ok(())
+    }
+}
+
+/// Line settings of the capture UART, as last configured
+#[derive(Default)]
+pub struct MockLine {
+    pub uart: Option<UartConfig>,
+}
+
+impl CaptureLine for MockLine {
+    fn configure(&mut self, config: &Config) -> Result<(), LineError> {
+        // the word formats the USART can't do are refused
+        config.uart.line.cr1()?;
+        self.uart = Some(config.uart);
+        Ok(())
+    }
+}
+
+/// Debug port that keeps what is sent
+pub struct MockDebug {
+    pub sent: ArrayVec<[u8; DEBUG_LEN]>,
+}
+
+impl MockDebug {
+    pub fn new() -> Self {
+        Self {
+            sent: ArrayVec::new(),
+        }
+    }
+}
+
+impl serial::Write<u8> for MockDebug {
+    type Error = Infallible;
+
+    fn write(&mut self, byte: u8) -> nb::Result<(), Infallible> {
+        self.sent.try_push(byte).ok();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct MockLeds {
+    pub red: bool,
+    pub green: bool,
+    /// Calls to `set`
+    pub changes: u32,
+}
+
+impl StatusLeds for MockLeds {
+    fn set(&mut self, led: Led, on: bool) {
+        match led {
+            Led::Red => self.red = on,
+            Led::Green => self.green = on,
+        }
+        self.changes += 1;
+    }
+}
+
+/// Inputs made at given times of `clock`
+pub struct MockInputs<'a> {
+    clock: &'a MockClock,
+    /// Times the button is held, in order
+    held: &'a [Range<u32>],
+    /// Encoder steps and the time they are made, in order
+    turns: &'a [(u32, i32)],
+    next_press: usize,
+    next_turn: usize,
+}
+
+impl<'a> MockInputs<'a> {
+    pub fn new(clock: &'a MockClock, held: &'a [Range<u32>], turns: &'a [(u32, i32)]) -> Self {
+        Self {
+            clock,
+            held,
+            turns,
+            next_press: 0,
+            next_turn: 0,
+        }
+    }
+}
+
+impl InputEvents for MockInputs<'_> {
+    fn poll(&mut self) -> Option<InputEvent> {
+        let now = self.clock.now();
+        if let Some(&(at, steps)) = self.turns.get(self.next_turn) {
+            if at <= now {
+                self.next_turn += 1;
+                return Some(InputEvent::Turn(steps));
+            }
+        }
+        if let Some(held) = self.held.get(self.next_press) {
+            if held.start <= now {
+                self.next_press += 1;
+                return Some(InputEvent::Press);
+            }
+        }
+        None
+    }
+
+    fn button_held(&mut self) -> bool {
+        let now = self.clock.now();
+        self.held.iter().any(|held| held.contains(&now))
+    }
+}
+
+/// Counts what the OLED driver sends
+#[derive(Default)]
+pub struct MockDisplay {
+    pub commands: u32,
+    pub data_bytes: u32,
+    /// First byte of the last command
+    pub last_command: Option<u8>,
+}
+
+impl WriteOnlyDataCommand for MockDisplay {
+    fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+        match cmd {
+            DataFormat::U8(bytes) => {
+                if let Some(&first) = bytes.first() {
+                    self.last_command = Some(first);
+                }
+            }
+            _ => return Err(DisplayError::DataFormatNotImplemented),
+        }
+        self.commands += 1;
+        Ok(())
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        self.data_bytes += match buf {
+            DataFormat::U8(bytes) => bytes.len() as u32,
+            DataFormat::U8Iter(bytes) => bytes.count() as u32,
+            _ => return Err(DisplayError::DataFormatNotImplemented),
+        };
+        Ok(())
+    }
+}
+
+/// Real-time clock that stands still, None while it is not set
+pub struct MockCalendar(pub Option<DateTime>);
+
+impl Calendar for MockCalendar {
+    fn now(&self) -> Option<(DateTime, u16)> {
+        self.0.map(|time| (time, 0))
+    }
+
+    fn set(&mut self, time: DateTime) {
+        self.0 = Some(time);
+    }
+}
+
+/// Battery voltage to report, None for a failing ADC
+pub struct MockBattery(pub Option<u16>);
+
+impl BatterySensor for MockBattery {
+    fn battery_mv(&mut self) -> Option<u16> {
+        self.0
+    }
+}
+
+/// The two configuration pages in RAM
+pub struct MockFlash {
+    pub pages: [[u8; PAGE_LEN]; 2],
+}
+
+impl MockFlash {
+    /// Erased pages
+    pub fn new() -> Self {
+        Self {
+            pages: [[0xFF; PAGE_LEN]; 2],
+        }
+    }
+}
+
+impl ConfigFlash for MockFlash {
+    type Error = Infallible;
+
+    fn read(&mut self, page: usize, offset: usize, buf: &mut [u8]) {
+        buf.copy_from_slice(&self.pages[page][offset..offset + buf.len()]);
+    }
+
+    fn erase(&mut self, page: usize) -> Result<(), Infallible> {
+        self.pages[page] = [0xFF; PAGE_LEN];
+        Ok(())
+    }
+
+    fn program(&mut self, page: usize, offset: usize, data: &[u8]) -> Result<(), Infallible> {
+        // programming only clears bits
+        for (cell, &byte) in self.pages[page][offset..].iter_mut().zip(data) {
+            *cell &= byte;
+        }
+        Ok(())
+    }
+}
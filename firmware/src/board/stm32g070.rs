@@ -0,0 +1,165 @@
+//! The logger board: STM32G070 with the SSD1362 OLED on SPI1
+//!
+//! Every concrete pin and peripheral type of the board is named here, `main`
+//! only sets them up and hands them to the tasks.
+
+use embedded_hal::adc::OneShot;
+use embedded_hal::digital::v2::{InputPin, OutputPin};
+use embedded_hal::serial::{Read, Write};
+use stm32g0xx_hal::{
+    analog::adc::Adc,
+    exti::{Event, ExtiExt},
+    gpio::{self, SignalEdge},
+    serial::{self, BasicConfig, FullConfig},
+    stm32::{self, EXTI},
+};
+
+use super::{
+    BatterySensor, Calendar, CaptureLine, CaptureUart, InputEvent, InputEvents, Led, StatusLeds,
+};
+use crate::clock::DateTime;
+use crate::config::Config;
+use crate::dma::Spi1TxDma;
+use crate::dma_display::DmaDisplay;
+use crate::encoder::{self, Channel};
+use crate::line::LineError;
+use crate::rtc::Rtc;
+use crate::usart;
+
+pub use crate::flash::ConfigPages as Flash;
+
+/// SPI1 TX DMA with D/C on PB7, CS on PB4 is held low
+pub type Display = DmaDisplay<Spi1TxDma, gpio::gpiob::PB7<gpio::Output<gpio::PushPull>>>;
+
+/// Channel A on PB1, B on PB2
+pub type Encoder = encoder::Encoder<
+    gpio::gpiob::PB<gpio::Input<gpio::PushPull>>,
+    gpio::gpiob::PB<gpio::Input<gpio::PushPull>>,
+>;
+
+/// Low while pressed
+pub type Button = gpio::gpioa::PA8<gpio::Input<gpio::PullUp>>;
+
+/// USART1 on PA9 (TX1) and PA10 (RX1)
+pub type CaptureTx = serial::Tx<stm32::USART1, FullConfig>;
+pub type CaptureRx = serial::Rx<stm32::USART1, FullConfig>;
+
+/// USART2 on PA3 (RX2), the second capture input, PA2 is left unused
+pub type Capture2Rx = serial::Rx<stm32::USART2, FullConfig>;
+
+/// USART3 on PB8 and PB9
+pub type DebugTx = serial::Tx<stm32::USART3, BasicConfig>;
+pub type DebugRx = serial::Rx<stm32::USART3, BasicConfig>;
+
+/// Red LED on PB0, green on PA7
+pub struct Leds {
+    pub red: gpio::gpiob::PB0<gpio::Output<gpio::PushPull>>,
+    pub green: gpio::gpioa::PA7<gpio::Output<gpio::PushPull>>,
+}
+
+impl StatusLeds for Leds {
+    fn set(&mut self, led: Led, on: bool) {
+        match (led, on) {
+            (Led::Red, true) => self.red.set_high().unwrap(),
+            (Led::Red, false) => self.red.set_low().unwrap(),
+            (Led::Green, true) => self.green.set_high().unwrap(),
+            (Led::Green, false) => self.green.set_low().unwrap(),
+        }
+    }
+}
+
+/// ADC reference voltage
+const VDDA_MV: u32 = 3300;
+/// The battery is measured on PA4 through a 1:2 resistor divider
+const BATTERY_DIVIDER: u32 = 2;
+
+pub struct Battery {
+    adc: Adc,
+    pin: gpio::gpioa::PA4<gpio::Analog>,
+}
+
+impl Battery {
+    pub fn new(adc: Adc, pin: gpio::gpioa::PA4<gpio::Analog>) -> Self {
+        Self { adc, pin }
+    }
+}
+
+impl BatterySensor for Battery {
+    fn battery_mv(&mut self) -> Option<u16> {
+        self.adc.read(&mut self.pin).ok().map(|raw: u16| {
+            (raw as u32 * VDDA_MV * BATTERY_DIVIDER / 4095) as u16
+        })
+    }
+}
+
+/// Both halves of the capture UART, polled before they go to their tasks
+pub struct Capture<'a> {
+    pub tx: &'a mut CaptureTx,
+    pub rx: &'a mut CaptureRx,
+}
+
+impl CaptureUart for Capture<'_> {
+    type Error = serial::Error;
+
+    fn read(&mut self) -> nb::Result<u8, serial::Error> {
+        self.rx.read()
+    }
+
+    fn write(&mut self, byte: u8) -> nb::Result<(), serial::Error> {
+        self.tx.write(byte)
+    }
+}
+
+/// Line settings of USART1 and USART2, changed with `rx` locked
+pub struct Line<R> {
+    pub rx: R,
+    /// USART kernel clock in Hz
+    pub clock: u32,
+}
+
+impl<R: rtic::Mutex<T = CaptureRx>> CaptureLine for Line<R> {
+    fn configure(&mut self, config: &Config) -> Result<(), LineError> {
+        let clock = self.clock;
+        // keep usart_in out while the USART is disabled
+        self.rx
+            .lock(|_rx| usart::configure(&config.uart, config.rx_timeout_bits(), clock))
+    }
+}
+
+impl Calendar for Rtc {
+    fn now(&self) -> Option<(DateTime, u16)> {
+        Rtc::now(self)
+    }
+
+    fn set(&mut self, time: DateTime) {
+        Rtc::set(self, time)
+    }
+}
+
+/// Encoder polled from its EXTI flags, while the interrupts are not enabled yet.
+/// The button edge is only listened to after the self-test, so it reports no
+/// `Press` and the button is read with `button_held`.
+pub struct PolledInputs<'a> {
+    pub exti: &'a mut EXTI,
+    pub encoder: &'a mut Encoder,
+    pub button: &'a Button,
+}
+
+impl InputEvents for PolledInputs<'_> {
+    fn poll(&mut self) -> Option<InputEvent> {
+        if self.exti.is_pending(Event::GPIO1, SignalEdge::Rising) {
+            self.exti.unpend(Event::GPIO1);
+        }
+        // one B edge per detent, like `encoder_b`
+        if self.exti.is_pending(Event::GPIO2, SignalEdge::Rising) {
+            let (_position, step) = self.encoder.update(Channel::B);
+            self.exti.unpend(Event::GPIO2);
+            return Some(InputEvent::Turn(step));
+        }
+        None
+    }
+
+    fn button_held(&mut self) -> bool {
+        self.button.is_low().unwrap()
+    }
+}
@@ -0,0 +1,494 @@
+//! Bridge output on the debug port
+//!
+//! In bridge mode the captured bytes are sent out of the debug UART. Without tags
+//! and timestamps they are passed on as they are. Otherwise they are sent as text
+//! records, one per line:
+//!
+//! ```text
+//! record = [tag " "] [time " "] data ["\"] LF
+//! tag    = "RX1" / "RX2" / "TX" / "MARK" / "LOST"
+//! time   = 1*DIGIT "." 3DIGIT           ; seconds since boot, first byte
+//! time   =/ date "T" clock "." 3DIGIT   ; wall clock time once the RTC is set
+//! date   = 4DIGIT "-" 2DIGIT "-" 2DIGIT
+//! clock  = 2DIGIT ":" 2DIGIT ":" 2DIGIT
+//! data   = *(%x20-5B / %x5D-7E / "\\" / "\x" 2HEXDIG)
+//! ```
+//!
+//! For example `RX1 12.345 temp=21.5\x0D` for the line "temp=21.5\r\n" received
+//! 12.345 s after boot. A record ends with the end of a received line, the '\n'
+//! itself is not in the data. A record that ends in a single `\` is cut off and
+//! continues in the next record of its channel: when it reaches `MAX_RECORD`
+//! bytes, when bytes of another channel come in and when the input is idle.
+//!
+//! Received bytes that could not be sent in time are dropped. With tags a `LOST`
+//! record with their number in decimal takes their place, like `LOST 12.345 17`.
+//!
+//! `decode` turns a record back into its bytes, so the host sees exactly what was
+//! received.
+
+use core::fmt::{self, Write};
+
+use arrayvec::{ArrayString, ArrayVec};
+
+use crate::clock::{Timestamp, WallClock};
+
+/// Most data bytes in a record
+pub const MAX_RECORD: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channel {
+    Rx1,
+    Rx2,
+    /// Sent by the logger
+    Tx,
+    /// Session marker lines, see `markers`
+    Mark,
+    /// Number of dropped bytes
+    Lost,
+}
+
+impl Channel {
+    pub fn tag(self) -> &'static str {
+        match self {
+            Channel::Rx1 => "RX1",
+            Channel::Rx2 => "RX2",
+            Channel::Tx => "TX",
+            Channel::Mark => "MARK",
+            Channel::Lost => "LOST",
+        }
+    }
+
+    fn from_tag(tag: &[u8]) -> Option<Self> {
+        match tag {
+            b"RX1" => Some(Channel::Rx1),
+            b"RX2" => Some(Channel::Rx2),
+            b"TX" => Some(Channel::Tx),
+            b"MARK" => Some(Channel::Mark),
+            b"LOST" => Some(Channel::Lost),
+            _ => None,
+        }
+    }
+}
+
+/// What the records start with, both off passes the bytes on as they are
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Format {
+    pub tags: bool,
+    pub timestamps: bool,
+}
+
+impl Format {
+    fn is_raw(self) -> bool {
+        !self.tags && !self.timestamps
+    }
+}
+
+/// Writes captured bytes as records
+pub struct Encoder {
+    format: Format,
+    /// Channel of the open record, None when no record is open
+    channel: Option<Channel>,
+    len: usize,
+    /// No bytes came in since the last `idle`
+    idle: bool,
+    /// Timestamps are wall clock times
+    wall_clock: Option<WallClock>,
+}
+
+impl Encoder {
+    pub const fn new(format: Format) -> Self {
+        Self {
+            format,
+            channel: None,
+            len: 0,
+            idle: false,
+            wall_clock: None,
+        }
+    }
+
+    /// Write timestamps from `wall_clock`, None for the time since boot
+    pub fn set_wall_clock(&mut self, wall_clock: Option<WallClock>) {
+        self.wall_clock = wall_clock;
+    }
+
+    /// Use `format` from the next record on, an open record is ended
+    pub fn set_format<F: FnMut(u8)>(&mut self, format: Format, out: &mut F) {
+        if format != self.format {
+            self.end(false, out);
+            self.format = format;
+        }
+    }
+
+    /// Write byte `b` of `channel`, received `time_ms` after boot
+    pub fn push<F: FnMut(u8)>(&mut self, channel: Channel, b: u8, time_ms: u32, out: &mut F) {
+        self.idle = false;
+        if self.format.is_raw() {
+            out(b);
+            return;
+        }
+
+        // a full record can still take the end of the line
+        if self.channel != Some(channel) || (self.len == MAX_RECORD && b != b'\n') {
+            self.end(false, out);
+        }
+        if b == b'\n' {
+            if self.channel.is_none() {
+                self.start(channel, time_ms, out);
+            }
+            self.end(true, out);
+            return;
+        }
+        if self.channel.is_none() {
+            self.start(channel, time_ms, out);
+        }
+
+        match b {
+            b'\\' => write_bytes(out, b"\\\\"),
+            0x20..=0x7E => out(b),
+            _ => {
+                let mut escape = ArrayString::<[u8; 4]>::new();
+                write!(escape, "\\x{:02X}", b).ok();
+                write_bytes(out, escape.as_bytes());
+            }
+        }
+        self.len += 1;
+    }
+
+    /// Report `count` bytes dropped before the next one, at `time_ms` after boot.
+    /// Without tags there is no way to tell it from received data.
+    pub fn lost<F: FnMut(u8)>(&mut self, count: u32, time_ms: u32, out: &mut F) {
+        if !self.format.tags || count == 0 {
+            return;
+        }
+        self.end(false, out);
+        self.start(Channel::Lost, time_ms, out);
+        let mut number = ArrayString::<[u8; 10]>::new();
+        write!(number, "{}", count).ok();
+        write_bytes(out, number.as_bytes());
+        self.end(true, out);
+    }
+
+    /// Called regularly, ends an open record once no bytes came in since the last call
+    pub fn idle<F: FnMut(u8)>(&mut self, out: &mut F) {
+        if self.idle {
+            self.end(false, out);
+        }
+        self.idle = true;
+    }
+
+    fn start<F: FnMut(u8)>(&mut self, channel: Channel, time_ms: u32, out: &mut F) {
+        let mut header = ArrayString::<[u8; 32]>::new();
+        if self.format.tags {
+            write!(header, "{} ", channel.tag()).ok();
+        }
+        if self.format.timestamps {
+            let time = match self.wall_clock {
+                Some(clock) => Time::Wall(clock.at(time_ms)),
+                None => Time::Boot(time_ms),
+            };
+            write!(header, "{} ", time).ok();
+        }
+        write_bytes(out, header.as_bytes());
+        self.channel = Some(channel);
+        self.len = 0;
+    }
+
+    /// End the open record, `line_end` when the received line ended
+    fn end<F: FnMut(u8)>(&mut self, line_end: bool, out: &mut F) {
+        if self.channel.take().is_some() {
+            write_bytes(out, if line_end { b"\n" } else { b"\\\n" });
+        }
+    }
+}
+
+fn write_bytes<F: FnMut(u8)>(out: &mut F, bytes: &[u8]) {
+    for &b in bytes {
+        out(b);
+    }
+}
+
+/// Timestamp of a record
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Time {
+    /// Milliseconds since boot
+    Boot(u32),
+    Wall(Timestamp),
+}
+
+/// "12.345" or "2026-10-19T12:34:56.789"
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Time::Boot(ms) => write!(f, "{}.{:03}", ms / 1000, ms % 1000),
+            Time::Wall(time) => write!(f, "{}", time),
+        }
+    }
+}
+
+/// A record turned back into the received bytes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub channel: Option<Channel>,
+    pub time: Option<Time>,
+    pub data: ArrayVec<[u8; MAX_RECORD]>,
+    /// The received line ended here, a '\n' follows the data
+    pub line_end: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodeError {
+    Tag,
+    Time,
+    /// Bad escape at this offset
+    Escape(usize),
+    TooLong,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Tag => f.write_str("bad tag"),
+            DecodeError::Time => f.write_str("bad timestamp"),
+            DecodeError::Escape(at) => write!(f, "bad escape at {}", at),
+            DecodeError::TooLong => f.write_str("record too long"),
+        }
+    }
+}
+
+/// Decode a record written in `format`, without its '\n'
+pub fn decode(format: Format, line: &[u8]) -> Result<Record, DecodeError> {
+    let mut rest = line;
+    let mut record = Record {
+        channel: None,
+        time: None,
+        data: ArrayVec::new(),
+        line_end: true,
+    };
+
+    if format.tags {
+        let (tag, after) = split_field(rest).ok_or(DecodeError::Tag)?;
+        record.channel = Some(Channel::from_tag(tag).ok_or(DecodeError::Tag)?);
+        rest = after;
+    }
+    if format.timestamps {
+        let (time, after) = split_field(rest).ok_or(DecodeError::Time)?;
+        record.time = Some(parse_time(time).ok_or(DecodeError::Time)?);
+        rest = after;
+    }
+
+    let start = line.len() - rest.len();
+    let mut i = 0;
+    while i < rest.len() {
+        let b = if rest[i] == b'\\' {
+            match rest.get(i + 1) {
+                None => {
+                    record.line_end = false;
+                    break;
+                }
+                Some(b'\\') => {
+                    i += 1;
+                    b'\\'
+                }
+                Some(b'x') => {
+                    let byte = rest
+                        .get(i + 2..i + 4)
+                        .filter(|digits| digits.iter().all(u8::is_ascii_hexdigit))
+                        .and_then(|digits| core::str::from_utf8(digits).ok())
+                        .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                        .ok_or(DecodeError::Escape(start + i))?;
+                    i += 3;
+                    byte
+                }
+                Some(_) => return Err(DecodeError::Escape(start + i)),
+            }
+        } else {
+            rest[i]
+        };
+        record.data.try_push(b).map_err(|_| DecodeError::TooLong)?;
+        i += 1;
+    }
+    Ok(record)
+}
+
+/// Text up to the first space and the text after it
+fn split_field(text: &[u8]) -> Option<(&[u8], &[u8])> {
+    let space = text.iter().position(|&c| c == b' ')?;
+    Some((&text[..space], &text[space + 1..]))
+}
+
+/// "12.345" or "2026-10-19T12:34:56.789"
+fn parse_time(text: &[u8]) -> Option<Time> {
+    if text.contains(&b'T') {
+        let text = core::str::from_utf8(text).ok()?;
+        return Timestamp::parse(text).map(Time::Wall);
+    }
+    let point = text.iter().position(|&c| c == b'.')?;
+    let (seconds, millis) = (&text[..point], &text[point + 1..]);
+    if seconds.is_empty() || millis.len() != 3 {
+        return None;
+    }
+    let number = |digits: &[u8]| -> Option<u32> {
+        digits.iter().try_fold(0u32, |n, &c| {
+            let digit = (c as char).to_digit(10)?;
+            n.checked_mul(10)?.checked_add(digit)
+        })
+    };
+    let ms = number(seconds)?.checked_mul(1000)?.checked_add(number(millis)?)?;
+    Some(Time::Boot(ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::DateTime;
+
+    const TAGGED: Format = Format { tags: true, timestamps: true };
+
+    /// Bridge output of `bytes` of each channel, pushed in turn
+    fn encode(format: Format, input: &[(Channel, &[u8], u32)]) -> String {
+        let mut encoder = Encoder::new(format);
+        let mut out = Vec::new();
+        for &(channel, bytes, time_ms) in input {
+            for &b in bytes {
+                encoder.push(channel, b, time_ms, &mut |b| out.push(b));
+            }
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    fn decode_all(format: Format, text: &str) -> Vec<Record> {
+        text.lines().map(|line| decode(format, line.as_bytes()).unwrap()).collect()
+    }
+
+    #[test]
+    fn raw_passes_bytes_on() {
+        let raw = Format { tags: false, timestamps: false };
+        let text = encode(raw, &[(Channel::Rx1, b"a\\b\r\n", 0), (Channel::Rx2, b"c", 5)]);
+        assert_eq!(text, "a\\b\r\nc");
+    }
+
+    #[test]
+    fn records_per_line() {
+        let text = encode(TAGGED, &[(Channel::Rx1, b"temp=21.5\r\n", 12_345), (Channel::Rx1, b"\n", 13_000)]);
+        assert_eq!(text, "RX1 12.345 temp=21.5\\x0D\nRX1 13.000 \n");
+
+        let tags = Format { tags: true, timestamps: false };
+        assert_eq!(encode(tags, &[(Channel::Tx, b"AT\n", 0)]), "TX AT\n");
+        let time = Format { tags: false, timestamps: true };
+        assert_eq!(encode(time, &[(Channel::Rx1, b"x\n", 7)]), "0.007 x\n");
+    }
+
+    #[test]
+    fn escapes_round_trip() {
+        let data: Vec<u8> = (0..=255u8).filter(|&b| b != b'\n').take(MAX_RECORD).collect();
+        let mut input = data.clone();
+        input.push(b'\n');
+        let text = encode(TAGGED, &[(Channel::Rx2, &input, 1)]);
+        assert!(text.bytes().all(|b| (0x20..=0x7E).contains(&b) || b == b'\n'));
+        let records = decode_all(TAGGED, &text);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].channel, Some(Channel::Rx2));
+        assert_eq!(records[0].time, Some(Time::Boot(1)));
+        assert_eq!(&records[0].data[..], &data[..]);
+        assert!(records[0].line_end);
+    }
+
+    #[test]
+    fn channels_take_turns() {
+        let text = encode(TAGGED, &[
+            (Channel::Rx1, b"ab", 0),
+            (Channel::Rx2, b"12\n", 1),
+            (Channel::Rx1, b"c\n", 2),
+        ]);
+        assert_eq!(text, "RX1 0.000 ab\\\nRX2 0.001 12\nRX1 0.002 c\n");
+
+        let records = decode_all(TAGGED, &text);
+        assert_eq!(&records[0].data[..], b"ab");
+        assert!(!records[0].line_end);
+        assert_eq!(&records[2].data[..], b"c");
+        assert!(records[2].line_end);
+    }
+
+    #[test]
+    fn long_lines_are_cut_off() {
+        let line = [b'x'; MAX_RECORD + 1];
+        let text = encode(TAGGED, &[(Channel::Rx1, &line, 0), (Channel::Rx1, b"\n", 9)]);
+        let records = decode_all(TAGGED, &text);
+        assert_eq!(records.len(), 2);
+        assert_eq!((records[0].data.len(), records[0].line_end), (MAX_RECORD, false));
+        assert_eq!((&records[1].data[..], records[1].line_end), (&b"x"[..], true));
+        assert_eq!(records[1].time, Some(Time::Boot(0)));
+    }
+
+    #[test]
+    fn idle_ends_a_record() {
+        let mut encoder = Encoder::new(TAGGED);
+        let mut out = Vec::new();
+        encoder.push(Channel::Rx1, b'a', 0, &mut |b| out.push(b));
+        // bytes came in since the last call
+        encoder.idle(&mut |b| out.push(b));
+        assert_eq!(out, b"RX1 0.000 a");
+        encoder.idle(&mut |b| out.push(b));
+        assert_eq!(out, b"RX1 0.000 a\\\n");
+        // nothing open
+        encoder.idle(&mut |b| out.push(b));
+        assert_eq!(out, b"RX1 0.000 a\\\n");
+    }
+
+    #[test]
+    fn lost_bytes() {
+        let mut encoder = Encoder::new(TAGGED);
+        let mut out = Vec::new();
+        encoder.push(Channel::Rx1, b'a', 0, &mut |b| out.push(b));
+        encoder.lost(0, 1, &mut |b| out.push(b));
+        encoder.lost(17, 2, &mut |b| out.push(b));
+        encoder.push(Channel::Rx1, b'\n', 3, &mut |b| out.push(b));
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text, "RX1 0.000 a\\\nLOST 0.002 17\nRX1 0.003 \n");
+        assert_eq!(decode_all(TAGGED, &text)[1].channel, Some(Channel::Lost));
+
+        // can't be told from data without tags
+        let mut encoder = Encoder::new(Format { tags: false, timestamps: true });
+        let mut out = Vec::new();
+        encoder.lost(17, 2, &mut |b| out.push(b));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn format_change_ends_the_record() {
+        let mut encoder = Encoder::new(TAGGED);
+        let mut out = Vec::new();
+        encoder.push(Channel::Rx1, b'a', 0, &mut |b| out.push(b));
+        encoder.set_format(Format { tags: true, timestamps: false }, &mut |b| out.push(b));
+        encoder.push(Channel::Rx1, b'b', 0, &mut |b| out.push(b));
+        assert_eq!(out, b"RX1 0.000 a\\\nRX1 b");
+    }
+
+    #[test]
+    fn wall_clock_timestamps() {
+        let now = DateTime { year: 2026, month: 10, day: 19, hour: 12, minute: 34, second: 56 };
+        let mut encoder = Encoder::new(TAGGED);
+        encoder.set_wall_clock(Some(WallClock::new(now, 789, 1000)));
+        let mut out = Vec::new();
+        for &b in b"ok\n" {
+            encoder.push(Channel::Rx1, b, 2000, &mut |b| out.push(b));
+        }
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text, "RX1 2026-10-19T12:34:57.789 ok\n");
+        let record = decode(TAGGED, text.trim_end().as_bytes()).unwrap();
+        assert_eq!(format!("{}", record.time.unwrap()), "2026-10-19T12:34:57.789");
+    }
+
+    #[test]
+    fn bad_records() {
+        assert_eq!(decode(TAGGED, b"RX3 0.000 a"), Err(DecodeError::Tag));
+        assert_eq!(decode(TAGGED, b"RX1"), Err(DecodeError::Tag));
+        assert_eq!(decode(TAGGED, b"RX1 0.00 a"), Err(DecodeError::Time));
+        assert_eq!(decode(TAGGED, b"RX1 .000 a"), Err(DecodeError::Time));
+        assert_eq!(decode(TAGGED, b"RX1 4294967.296 a"), Err(DecodeError::Time));
+        assert_eq!(decode(TAGGED, b"RX1 0.000 a\\q"), Err(DecodeError::Escape(11)));
+        assert_eq!(decode(TAGGED, b"RX1 0.000 \\x4"), Err(DecodeError::Escape(10)));
+        let long = [b'x'; MAX_RECORD + 1];
+        assert_eq!(decode(Format { tags: false, timestamps: false }, &long), Err(DecodeError::TooLong));
+    }
+}
@@ -0,0 +1,319 @@
+//! OLED burn-in protection
+//!
+//! A logger left on a bench shows the same status bar and often the same lines
+//! for days. Four things keep that from burning into the panel:
+//!
+//! - pixel shift: the whole image moves up and down by up to two rows, one row
+//!   every `SHIFT_PERIOD_MS`, with the display offset of the controller
+//! - static regions: the status bar stays the same while data comes in, so it is
+//!   drawn at a quarter of its levels through `Dimmed` once no input was made for
+//!   `dim_after_s`. The received data around it stays bright.
+//! - dimming: once nothing came in and no input was made for `dim_after_s`, the
+//!   contrast of the whole panel drops to a quarter
+//! - screensaver: after `off_after_s` without activity the panel is turned off
+//!
+//! Received lines count as activity, the button and the encoder as input, which
+//! is activity as well. The panel state follows from the time since the last
+//! activity and the settings, `Protection` only reports when it changes, so
+//! changed settings are sent to the panel too. The full screen layout has no
+//! static region, the terminal only dims with the whole panel.
+
+use embedded_graphics::{
+    pixelcolor::{Gray4, GrayColor},
+    prelude::*,
+    primitives::Rectangle,
+};
+
+use crate::oled::HEIGHT;
+
+/// Time between pixel shift steps
+pub const SHIFT_PERIOD_MS: u32 = 60_000;
+/// Contrast, and the levels of static regions, are divided by this while dimmed
+const DIM_DIVIDER: u8 = 4;
+
+/// Row offsets in turn, negative ones wrap around the 64 rows
+const SHIFTS: [i8; 8] = [0, 1, 2, 1, 0, -1, -2, -1];
+
+/// From the configuration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+    pub contrast: u8,
+    pub pre_charge: u8,
+    pub pixel_shift: bool,
+    /// 0 never dims
+    pub dim_after_s: u16,
+    /// 0 never turns the panel off
+    pub off_after_s: u16,
+}
+
+/// What is set on the controller
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Panel {
+    pub on: bool,
+    pub contrast: u8,
+    pub pre_charge: u8,
+    /// Display offset in rows
+    pub offset: u8,
+}
+
+pub struct Protection {
+    last_activity_ms: u32,
+    /// Button or encoder
+    last_input_ms: u32,
+    /// Set on the controller, None until the first update
+    panel: Option<Panel>,
+}
+
+impl Protection {
+    pub const fn new() -> Self {
+        Self {
+            last_activity_ms: 0,
+            last_input_ms: 0,
+            panel: None,
+        }
+    }
+
+    /// Data came in at `now_ms`, returns true when the panel was off
+    pub fn activity(&mut self, now_ms: u32) -> bool {
+        self.last_activity_ms = now_ms;
+        self.panel.is_some_and(|panel| !panel.on)
+    }
+
+    /// The button or the encoder was used at `now_ms`, returns true when the
+    /// panel was off
+    pub fn input(&mut self, now_ms: u32) -> bool {
+        self.last_input_ms = now_ms;
+        self.activity(now_ms)
+    }
+
+    /// Divider for the levels of static regions at `now_ms`, 1 while they are
+    /// drawn as they are
+    pub fn static_divider(&self, now_ms: u32, settings: Settings) -> u8 {
+        let idle_s = now_ms.wrapping_sub(self.last_input_ms) / 1000;
+        if settings.dim_after_s != 0 && idle_s >= settings.dim_after_s as u32 {
+            DIM_DIVIDER
+        } else {
+            1
+        }
+    }
+
+    /// Panel state at `now_ms`
+    pub fn panel(&self, now_ms: u32, settings: Settings) -> Panel {
+        let idle_s = now_ms.wrapping_sub(self.last_activity_ms) / 1000;
+        let reached = |after_s: u16| after_s != 0 && idle_s >= after_s as u32;
+        Panel {
+            on: !reached(settings.off_after_s),
+            contrast: if reached(settings.dim_after_s) {
+                settings.contrast / DIM_DIVIDER
+            } else {
+                settings.contrast
+            },
+            pre_charge: settings.pre_charge,
+            offset: if settings.pixel_shift { shift(now_ms) } else { 0 },
+        }
+    }
+
+    /// Returns the panel state at `now_ms` when it changed since the last call
+    pub fn update(&mut self, now_ms: u32, settings: Settings) -> Option<Panel> {
+        let panel = self.panel(now_ms, settings);
+        if self.panel == Some(panel) {
+            return None;
+        }
+        self.panel = Some(panel);
+        Some(panel)
+    }
+}
+
+/// Display offset at `now_ms`
+pub fn shift(now_ms: u32) -> u8 {
+    let step = (now_ms / SHIFT_PERIOD_MS) as usize % SHIFTS.len();
+    (SHIFTS[step] as i16).rem_euclid(HEIGHT as i16) as u8
+}
+
+/// Draws on the target with the levels divided by `divider`, for static regions
+pub struct Dimmed<'a, D> {
+    target: &'a mut D,
+    divider: u8,
+}
+
+impl<'a, D> Dimmed<'a, D> {
+    pub fn new(target: &'a mut D, divider: u8) -> Self {
+        Self {
+            target,
+            divider: divider.max(1),
+        }
+    }
+}
+
+impl<D: Dimensions> Dimensions for Dimmed<'_, D> {
+    fn bounding_box(&self) -> Rectangle {
+        self.target.bounding_box()
+    }
+}
+
+impl<D: DrawTarget<Color = Gray4>> DrawTarget for Dimmed<'_, D> {
+    type Color = Gray4;
+    type Error = D::Error;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Gray4>>,
+    {
+        let divider = self.divider;
+        self.target.draw_iter(
+            pixels
+                .into_iter()
+                .map(|Pixel(point, color)| Pixel(point, Gray4::new(color.luma() / divider))),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+
+    fn settings() -> Settings {
+        Settings {
+            contrast: 200,
+            pre_charge: 4,
+            pixel_shift: true,
+            dim_after_s: 300,
+            off_after_s: 1800,
+        }
+    }
+
+    #[test]
+    fn shift_schedule() {
+        let offsets: Vec<u8> = (0..10).map(|step| shift(step * SHIFT_PERIOD_MS)).collect();
+        assert_eq!(offsets, [0, 1, 2, 1, 0, HEIGHT - 1, HEIGHT - 2, HEIGHT - 1, 0, 1]);
+        // one step a period
+        assert_eq!(shift(SHIFT_PERIOD_MS - 1), 0);
+        assert_eq!(shift(2 * SHIFT_PERIOD_MS - 1), 1);
+        // the millisecond count wraps inside a step
+        // step 71582 of the last period, -2
+        assert_eq!(shift(u32::MAX), HEIGHT - 2);
+    }
+
+    #[test]
+    fn pixel_shift_off() {
+        let protection = Protection::new();
+        let settings = Settings { pixel_shift: false, ..settings() };
+        assert_eq!(protection.panel(2 * SHIFT_PERIOD_MS, settings).offset, 0);
+        assert_eq!(protection.panel(2 * SHIFT_PERIOD_MS, self::settings()).offset, 2);
+    }
+
+    #[test]
+    fn dim_and_screensaver_timing() {
+        let mut protection = Protection::new();
+        protection.activity(1000);
+        let at = |s: u32| protection.panel(1000 + s * 1000, Settings { pixel_shift: false, ..settings() });
+        assert_eq!(at(0), Panel { on: true, contrast: 200, pre_charge: 4, offset: 0 });
+        assert_eq!(at(299).contrast, 200);
+        assert_eq!(at(300).contrast, 50);
+        assert!(at(1799).on);
+        assert!(!at(1800).on);
+        // the settings are kept while off
+        assert_eq!(at(1800).contrast, 50);
+    }
+
+    #[test]
+    fn zero_never_dims_or_turns_off() {
+        let protection = Protection::new();
+        let settings = Settings { dim_after_s: 0, off_after_s: 0, ..settings() };
+        let panel = protection.panel(u32::MAX, settings);
+        assert!(panel.on);
+        assert_eq!(panel.contrast, 200);
+        assert_eq!(protection.static_divider(u32::MAX, settings), 1);
+    }
+
+    #[test]
+    fn activity_wakes_the_panel() {
+        let mut protection = Protection::new();
+        // nothing set yet
+        assert!(!protection.activity(0));
+        let off = protection.update(1_800_000, settings()).unwrap();
+        assert!(!off.on);
+        // the input that wakes the panel does nothing else
+        assert!(protection.input(1_800_500));
+        let on = protection.update(1_800_600, settings()).unwrap();
+        assert!(on.on);
+        assert_eq!(on.contrast, 200);
+        assert!(!protection.activity(1_800_700));
+    }
+
+    #[test]
+    fn only_changes_are_reported() {
+        let mut protection = Protection::new();
+        assert!(protection.update(0, settings()).is_some());
+        assert_eq!(protection.update(1000, settings()), None);
+        // a new contrast goes out right away
+        let brighter = Settings { contrast: 255, ..settings() };
+        assert_eq!(protection.update(2000, brighter).map(|panel| panel.contrast), Some(255));
+        assert_eq!(protection.update(SHIFT_PERIOD_MS, brighter).map(|panel| panel.offset), Some(1));
+        assert_eq!(protection.update(SHIFT_PERIOD_MS + 1, brighter), None);
+    }
+
+    #[test]
+    fn millisecond_count_wraps() {
+        let mut protection = Protection::new();
+        protection.activity(u32::MAX - 999);
+        // 300 s after the activity, past the wrap
+        assert_eq!(protection.panel(298_999, settings()).contrast, 200);
+        assert_eq!(protection.panel(299_000, settings()).contrast, 50);
+    }
+
+    #[test]
+    fn static_regions_dim_without_input() {
+        let mut protection = Protection::new();
+        protection.input(0);
+        for s in (0..600).step_by(10) {
+            // data keeps coming in
+            protection.activity(s * 1000);
+            let divider = protection.static_divider(s * 1000, settings());
+            assert_eq!(divider, if s < 300 { 1 } else { DIM_DIVIDER }, "{} s", s);
+            // the panel stays bright
+            assert_eq!(protection.panel(s * 1000, settings()).contrast, 200);
+        }
+        protection.input(600_000);
+        assert_eq!(protection.static_divider(600_000, settings()), 1);
+    }
+
+    /// Levels of a row of pixels
+    struct Row([u8; 4]);
+
+    impl OriginDimensions for Row {
+        fn size(&self) -> Size {
+            Size::new(4, 1)
+        }
+    }
+
+    impl DrawTarget for Row {
+        type Color = Gray4;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+        where
+            I: IntoIterator<Item = Pixel<Gray4>>,
+        {
+            for Pixel(point, color) in pixels {
+                self.0[point.x as usize] = color.luma();
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn dimmed_levels() {
+        let mut row = Row([0; 4]);
+        let levels = [0xF, 0x3, 0x4, 0x0];
+        let pixels = || (0..4).map(|x| Pixel(Point::new(x, 0), Gray4::new(levels[x as usize])));
+        let mut dimmed = Dimmed::new(&mut row, DIM_DIVIDER);
+        assert_eq!(dimmed.bounding_box().size, Size::new(4, 1));
+        dimmed.draw_iter(pixels()).unwrap();
+        assert_eq!(row.0, [0x3, 0x0, 0x1, 0x0]);
+        // 1 and 0 draw as they are
+        Dimmed::new(&mut row, 0).draw_iter(pixels()).unwrap();
+        assert_eq!(row.0, levels);
+    }
+}
@@ -0,0 +1,107 @@
+//! Frame buffer for embedded-graphics
+//!
+//! Holds a whole screen in the display RAM format, two pixels per byte with the
+//! left pixel in the high nibble, so rows can be sent to the display as they are.
+
+use core::convert::{Infallible, TryInto};
+
+use embedded_graphics::{
+    pixelcolor::{Gray4, GrayColor},
+    prelude::*,
+};
+
+use crate::oled::{HEIGHT, ROW_BYTES, WIDTH};
+
+pub struct Canvas {
+    buf: [u8; ROW_BYTES * HEIGHT as usize],
+}
+
+impl Canvas {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; ROW_BYTES * HEIGHT as usize],
+        }
+    }
+
+    /// Display RAM data of screen rows `rows`
+    pub fn rows(&self, rows: core::ops::Range<u8>) -> &[u8] {
+        &self.buf[rows.start as usize * ROW_BYTES..rows.end as usize * ROW_BYTES]
+    }
+
+    /// Display RAM data of screen row `row`
+    pub fn row_mut(&mut self, row: u8) -> &mut [u8; ROW_BYTES] {
+        let start = row as usize * ROW_BYTES;
+        (&mut self.buf[start..start + ROW_BYTES]).try_into().unwrap()
+    }
+}
+
+impl OriginDimensions for Canvas {
+    fn size(&self) -> Size {
+        Size::new(WIDTH as u32, HEIGHT as u32)
+    }
+}
+
+impl DrawTarget for Canvas {
+    type Color = Gray4;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x < 0 || point.y < 0 || point.x >= WIDTH as i32 || point.y >= HEIGHT as i32 {
+                continue;
+            }
+            let byte = &mut self.buf[point.y as usize * ROW_BYTES + point.x as usize / 2];
+            let level = color.luma();
+            if point.x % 2 == 0 {
+                *byte = (*byte & 0x0F) | level << 4;
+            } else {
+                *byte = (*byte & 0xF0) | level;
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+        let level = color.luma();
+        self.buf = [level << 4 | level; ROW_BYTES * HEIGHT as usize];
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn left_pixel_in_the_high_nibble() {
+        let mut canvas = Canvas::new();
+        let pixels = [
+            Pixel(Point::new(0, 0), Gray4::new(0xA)),
+            Pixel(Point::new(1, 0), Gray4::new(0x5)),
+            Pixel(Point::new(255, 63), Gray4::WHITE),
+            // clipped
+            Pixel(Point::new(-1, 0), Gray4::WHITE),
+            Pixel(Point::new(256, 0), Gray4::WHITE),
+            Pixel(Point::new(0, 64), Gray4::WHITE),
+        ];
+        canvas.draw_iter(pixels.iter().copied()).unwrap();
+        assert_eq!(canvas.rows(0..1)[..2], [0xA5, 0x00]);
+        assert_eq!(canvas.rows(63..64)[ROW_BYTES - 1], 0x0F);
+        assert!(canvas.rows(1..63).iter().all(|&b| b == 0));
+
+        // the other nibble is kept
+        canvas.row_mut(0)[0] = 0x12;
+        canvas.draw_iter([Pixel(Point::new(1, 0), Gray4::new(0x9))].iter().copied()).unwrap();
+        assert_eq!(canvas.rows(0..1)[0], 0x19);
+    }
+
+    #[test]
+    fn clear_fills_both_nibbles() {
+        let mut canvas = Canvas::new();
+        canvas.clear(Gray4::new(0x3)).unwrap();
+        assert!(canvas.rows(0..HEIGHT).iter().all(|&b| b == 0x33));
+    }
+}
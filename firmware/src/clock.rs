@@ -0,0 +1,391 @@
+//! Calendar time
+//!
+//! `DateTime` is a date and time of day between 2000 and 2099, the range of the
+//! RTC (see `rtc`), counted as seconds since 2000-01-01 00:00:00 for arithmetic.
+//! `WallClock` gives the time of a millisecond count since boot, so timestamps
+//! are taken without reading the RTC.
+
+use core::fmt;
+
+const FIRST_YEAR: u16 = 2000;
+const LAST_YEAR: u16 = 2099;
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DateTime {
+    pub year: u16,
+    /// 1 - 12
+    pub month: u8,
+    /// 1 - 31
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl Default for DateTime {
+    /// 2000-01-01 00:00:00
+    fn default() -> Self {
+        Self::from_seconds(0)
+    }
+}
+
+impl DateTime {
+    /// Date and time `seconds` after 2000-01-01 00:00:00
+    pub fn from_seconds(seconds: u32) -> Self {
+        let mut days = seconds / SECONDS_PER_DAY;
+        let time = seconds % SECONDS_PER_DAY;
+
+        let mut year = FIRST_YEAR;
+        while days >= days_in_year(year) {
+            days -= days_in_year(year);
+            year += 1;
+        }
+        let mut month = 1;
+        while days >= days_in_month(year, month) as u32 {
+            days -= days_in_month(year, month) as u32;
+            month += 1;
+        }
+
+        Self {
+            year,
+            month,
+            day: days as u8 + 1,
+            hour: (time / 3600) as u8,
+            minute: (time / 60 % 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+
+    /// Seconds since 2000-01-01 00:00:00, for a valid date
+    pub fn to_seconds(&self) -> u32 {
+        let days = (FIRST_YEAR..self.year).map(days_in_year).sum::<u32>()
+            + (1..self.month).map(|month| days_in_month(self.year, month) as u32).sum::<u32>()
+            + self.day as u32
+            - 1;
+        days * SECONDS_PER_DAY + self.hour as u32 * 3600 + self.minute as u32 * 60 + self.second as u32
+    }
+
+    /// All fields are in range
+    pub fn is_valid(&self) -> bool {
+        (FIRST_YEAR..=LAST_YEAR).contains(&self.year)
+            && (1..=12).contains(&self.month)
+            && self.day >= 1
+            && self.day <= days_in_month(self.year, self.month)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+
+    /// Day of the week, 1 is Monday and 7 is Sunday
+    pub fn weekday(&self) -> u8 {
+        // 2000-01-01 was a Saturday
+        ((self.to_seconds() / SECONDS_PER_DAY + 5) % 7 + 1) as u8
+    }
+
+    /// "2026-10-19 12:34:56" or "2026-10-19T12:34:56", the seconds may be left out
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().as_bytes();
+        if text.len() != 16 && text.len() != 19 {
+            return None;
+        }
+        let number = |range: core::ops::Range<usize>| -> Option<u16> {
+            text[range].iter().try_fold(0u16, |n, &c| Some(n * 10 + (c as char).to_digit(10)? as u16))
+        };
+        let separators = [(4, b'-'), (7, b'-'), (13, b':'), (16, b':')];
+        if separators.iter().any(|&(i, c)| text.get(i).is_some_and(|&b| b != c))
+            || !matches!(text[10], b' ' | b'T')
+        {
+            return None;
+        }
+
+        let time = Self {
+            year: number(0..4)?,
+            month: number(5..7)? as u8,
+            day: number(8..10)? as u8,
+            hour: number(11..13)? as u8,
+            minute: number(14..16)? as u8,
+            second: if text.len() == 19 { number(17..19)? as u8 } else { 0 },
+        };
+        Some(time).filter(DateTime::is_valid)
+    }
+
+    /// RTC_TR and RTC_DR in BCD, 24 hour format
+    pub fn to_bcd(&self) -> (u32, u32) {
+        let tr = bcd(self.hour) << 16 | bcd(self.minute) << 8 | bcd(self.second);
+        let year = (self.year - FIRST_YEAR) as u8;
+        let dr = bcd(year) << 16 | (self.weekday() as u32) << 13 | bcd(self.month) << 8 | bcd(self.day);
+        (tr, dr)
+    }
+
+    /// From RTC_TR and RTC_DR, None when they don't hold a valid date
+    pub fn from_bcd(tr: u32, dr: u32) -> Option<Self> {
+        let time = Self {
+            year: FIRST_YEAR + from_bcd(dr >> 16) as u16,
+            month: from_bcd(dr >> 8 & 0x1F),
+            day: from_bcd(dr & 0x3F),
+            hour: from_bcd(tr >> 16 & 0x3F),
+            minute: from_bcd(tr >> 8 & 0x7F),
+            second: from_bcd(tr & 0x7F),
+        };
+        Some(time).filter(DateTime::is_valid)
+    }
+}
+
+/// "2026-10-19 12:34:56"
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+pub fn is_leap_year(year: u16) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_year(year: u16) -> u32 {
+    if is_leap_year(year) {
+        366
+    } else {
+        365
+    }
+}
+
+pub fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Two BCD digits
+fn bcd(value: u8) -> u32 {
+    (((value / 10) << 4) | (value % 10)) as u32
+}
+
+fn from_bcd(bcd: u32) -> u8 {
+    ((bcd >> 4 & 0xF) * 10 + (bcd & 0xF)) as u8
+}
+
+/// Milliseconds since 2000-01-01 00:00:00
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timestamp(pub u64);
+
+impl Timestamp {
+    pub fn date_time(self) -> DateTime {
+        DateTime::from_seconds((self.0 / 1000) as u32)
+    }
+
+    /// Milliseconds into the second
+    pub fn millis(self) -> u16 {
+        (self.0 % 1000) as u16
+    }
+
+    /// "2026-10-19T12:34:56.789", see `parse`
+    pub fn parse(text: &str) -> Option<Self> {
+        let (time, millis) = text.split_at(text.find('.')?);
+        let millis = &millis[1..];
+        if millis.len() != 3 || !millis.bytes().all(|c| c.is_ascii_digit()) || time.len() != 19 {
+            return None;
+        }
+        let time = DateTime::parse(time)?;
+        Some(Timestamp(time.to_seconds() as u64 * 1000 + millis.parse::<u64>().ok()?))
+    }
+}
+
+/// "2026-10-19T12:34:56.789"
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let t = self.date_time();
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}",
+            t.year,
+            t.month,
+            t.day,
+            t.hour,
+            t.minute,
+            t.second,
+            self.millis()
+        )
+    }
+}
+
+/// Wall clock time from the milliseconds since boot. Synchronized to the RTC
+/// regularly, the millisecond count drifts and wraps after 49 days.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WallClock {
+    /// Time at `millis` 0
+    boot: u64,
+}
+
+impl WallClock {
+    /// The time was `now` plus `millis_in_second` at `millis` ms after boot
+    pub fn new(now: DateTime, millis_in_second: u16, millis: u32) -> Self {
+        let now = now.to_seconds() as u64 * 1000 + millis_in_second as u64;
+        Self {
+            boot: now.saturating_sub(millis as u64),
+        }
+    }
+
+    /// Time at `millis` ms after boot
+    pub fn at(&self, millis: u32) -> Timestamp {
+        Timestamp(self.boot + millis as u64)
+    }
+}
+
+/// Milliseconds of the day, or since boot without a wall clock
+pub fn clock_millis(wall_clock: Option<WallClock>, millis: u32) -> u32 {
+    match wall_clock {
+        Some(clock) => (clock.at(millis).0 % (SECONDS_PER_DAY as u64 * 1000)) as u32,
+        None => millis,
+    }
+}
+
+/// Seconds for the status bar, see `clock_millis`
+pub fn clock_seconds(wall_clock: Option<WallClock>, millis: u32) -> u32 {
+    clock_millis(wall_clock, millis) / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> DateTime {
+        DateTime { year, month, day, hour, minute, second }
+    }
+
+    #[test]
+    fn seconds_round_trip() {
+        assert_eq!(DateTime::from_seconds(0), time(2000, 1, 1, 0, 0, 0));
+        // every day of the range, at the last second of the day
+        let last = time(2099, 12, 31, 23, 59, 59).to_seconds();
+        for seconds in (SECONDS_PER_DAY - 1..=last).step_by(SECONDS_PER_DAY as usize) {
+            let t = DateTime::from_seconds(seconds);
+            assert!(t.is_valid(), "{}", t);
+            assert_eq!((t.hour, t.minute, t.second), (23, 59, 59));
+            assert_eq!(t.to_seconds(), seconds);
+        }
+    }
+
+    #[test]
+    fn rollovers() {
+        let next = |t: DateTime| DateTime::from_seconds(t.to_seconds() + 1);
+        assert_eq!(next(time(2026, 10, 19, 12, 34, 59)), time(2026, 10, 19, 12, 35, 0));
+        assert_eq!(next(time(2026, 10, 31, 23, 59, 59)), time(2026, 11, 1, 0, 0, 0));
+        assert_eq!(next(time(2026, 12, 31, 23, 59, 59)), time(2027, 1, 1, 0, 0, 0));
+        // leap years, 2000 is one as a multiple of 400
+        assert_eq!(next(time(2000, 2, 28, 23, 59, 59)), time(2000, 2, 29, 0, 0, 0));
+        assert_eq!(next(time(2024, 2, 29, 23, 59, 59)), time(2024, 3, 1, 0, 0, 0));
+        assert_eq!(next(time(2025, 2, 28, 23, 59, 59)), time(2025, 3, 1, 0, 0, 0));
+        assert!(!is_leap_year(2100));
+    }
+
+    #[test]
+    fn weekdays() {
+        assert_eq!(time(2000, 1, 1, 0, 0, 0).weekday(), 6);
+        assert_eq!(time(2000, 1, 2, 23, 59, 59).weekday(), 7);
+        assert_eq!(time(2000, 1, 3, 0, 0, 0).weekday(), 1);
+        assert_eq!(time(2026, 10, 19, 12, 0, 0).weekday(), 1);
+        assert_eq!(time(2099, 12, 31, 0, 0, 0).weekday(), 4);
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(DateTime::parse("2026-10-19 12:34:56"), Some(time(2026, 10, 19, 12, 34, 56)));
+        assert_eq!(DateTime::parse(" 2026-10-19T12:34 "), Some(time(2026, 10, 19, 12, 34, 0)));
+        assert_eq!(DateTime::parse("2024-02-29 00:00"), Some(time(2024, 2, 29, 0, 0, 0)));
+        for text in [
+            "2025-02-29 00:00",
+            "2026-10-19 24:00",
+            "2026-10-19 12:60",
+            "2026-10-19 12:34:60",
+            "2026-13-01 00:00",
+            "2026-00-01 00:00",
+            "2026-10-00 00:00",
+            "1999-12-31 23:59",
+            "2100-01-01 00:00",
+            "2026/10/19 12:34",
+            "2026-10-19_12:34",
+            "2026-10-19 12:34:5",
+            "2026-1-19 12:34:56",
+            "2026-10-19 +2:34",
+            "2026-10-19 12:34:56.789",
+            "2026-10-19 12:3é",
+            "",
+        ] {
+            assert_eq!(DateTime::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn rtc_registers() {
+        let t = time(2026, 10, 19, 12, 34, 56);
+        let (tr, dr) = t.to_bcd();
+        assert_eq!(tr, 0x12_34_56);
+        // year 26, Monday, October 19th
+        assert_eq!(dr, 0x26 << 16 | 1 << 13 | 0x10 << 8 | 0x19);
+        assert_eq!(DateTime::from_bcd(tr, dr), Some(t));
+        let t = time(2099, 12, 31, 23, 59, 59);
+        assert_eq!(DateTime::from_bcd(t.to_bcd().0, t.to_bcd().1), Some(t));
+        // the reset value of RTC_DR has no valid day
+        assert_eq!(DateTime::from_bcd(0, 0), None);
+        assert_eq!(DateTime::from_bcd(0x24_00_00, 0x2101), None);
+    }
+
+    #[test]
+    fn timestamps() {
+        let t = Timestamp(time(2026, 10, 19, 12, 34, 56).to_seconds() as u64 * 1000 + 789);
+        assert_eq!(format!("{}", t), "2026-10-19T12:34:56.789");
+        assert_eq!(Timestamp::parse("2026-10-19T12:34:56.789"), Some(t));
+        assert_eq!(format!("{}", Timestamp(7)), "2000-01-01T00:00:00.007");
+        // the last millisecond of the year
+        let t = Timestamp(time(2027, 1, 1, 0, 0, 0).to_seconds() as u64 * 1000 - 1);
+        assert_eq!(format!("{}", t), "2026-12-31T23:59:59.999");
+        assert_eq!(format!("{}", Timestamp(t.0 + 1)), "2027-01-01T00:00:00.000");
+
+        let bad = [
+            "2026-10-19T12:34:56",
+            "2026-10-19T12:34:56.78",
+            "2026-10-19T12:34.789",
+            "2026-10-19T12:34:56.+78",
+        ];
+        for text in bad {
+            assert_eq!(Timestamp::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn wall_clock() {
+        let now = time(2026, 10, 19, 23, 59, 59);
+        // set at 10 s after boot, 500 ms into the second
+        let clock = WallClock::new(now, 500, 10_000);
+        assert_eq!(format!("{}", clock.at(10_000)), "2026-10-19T23:59:59.500");
+        assert_eq!(format!("{}", clock.at(10_499)), "2026-10-19T23:59:59.999");
+        assert_eq!(format!("{}", clock.at(10_500)), "2026-10-20T00:00:00.000");
+        assert_eq!(format!("{}", clock.at(0)), "2026-10-19T23:59:49.500");
+        // the millisecond count wraps after 49 days
+        assert_eq!(format!("{}", clock.at(u32::MAX)), "2026-12-08T17:02:36.795");
+
+        // boot can't be before the start of the range, the time is late then
+        let clock = WallClock::new(time(2000, 1, 1, 0, 0, 1), 0, 5000);
+        assert_eq!(clock.at(0), Timestamp(0));
+        assert_eq!(clock.at(5000), Timestamp(5000));
+    }
+
+    #[test]
+    fn status_bar_clock() {
+        assert_eq!(clock_seconds(None, 100 * 3600 * 1000 + 999), 100 * 3600);
+        let clock = WallClock::new(time(2026, 10, 19, 23, 59, 59), 0, 0);
+        assert_eq!(clock_seconds(Some(clock), 0), SECONDS_PER_DAY - 1);
+        // a new day starts at 0
+        assert_eq!(clock_millis(Some(clock), 1000), 0);
+        assert_eq!(clock_millis(Some(clock), 1001), 1);
+    }
+}
@@ -0,0 +1,986 @@
+//! Persistent device configuration
+//!
+//! The configuration is stored as a record in one of two flash pages. Records
+//! are appended to the active page, so a page is only erased once it is full,
+//! and the other page keeps the previous configuration until the new one is
+//! written. At boot the valid record with the highest sequence number wins.
+//!
+//! Record layout, little endian:
+//!
+//! | offset | size | content                          |
+//! |--------|------|----------------------------------|
+//! | 0      | 2    | magic `0xC0F1`                   |
+//! | 2      | 2    | config version                   |
+//! | 4      | 4    | sequence number                  |
+//! | 8      | 2    | payload length                   |
+//! | 10     | 2    | reserved (0)                     |
+//! | 12     | n    | payload                          |
+//! | 508    | 4    | CRC-32 of bytes 0 - 507          |
+//!
+//! Fields are only ever appended to the payload. A record of an older version is
+//! migrated by reading the fields it has and using defaults for the rest.
+
+use arrayvec::ArrayString;
+
+use crate::bridge::Format;
+use crate::burn_in;
+use crate::crc::crc32;
+use crate::font::FontSize;
+use crate::framer::PacketCrc;
+use crate::layout::Layout;
+use crate::line::{DataBits, LineConfig, Parity, StopBits};
+use crate::macros::{Delays, LineEnding, Macro, MAX_MACROS};
+use crate::oled::Rotation;
+use crate::plot::MAX_PATTERN;
+use crate::template::{Template, MAX_TEMPLATE};
+use crate::wrap::WrapMode;
+
+/// Version of the payload written by this firmware
+pub const CONFIG_VERSION: u16 = 13;
+
+/// Bytes per record, flash pages hold a whole number of records
+pub const RECORD_LEN: usize = 512;
+/// Bytes per flash page
+pub const PAGE_LEN: usize = 2048;
+const RECORDS_PER_PAGE: usize = PAGE_LEN / RECORD_LEN;
+
+const MAGIC: u16 = 0xC0F1;
+const HEADER_LEN: usize = 12;
+const CRC_OFFSET: usize = RECORD_LEN - 4;
+/// Longest payload that fits in a record
+pub const MAX_PAYLOAD: usize = CRC_OFFSET - HEADER_LEN;
+
+/// Capture UART settings
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UartConfig {
+    pub baudrate: u32,
+    /// Idle time after which the receiver timeout fires
+    pub rx_timeout_us: u32,
+    pub line: LineConfig,
+}
+
+/// How received bytes are split into lines or packets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Framing {
+    /// New line on '\n'
+    Lines = 0,
+    /// New line on '\n' and when the line is idle for `rx_timeout_us`
+    Idle = 1,
+    /// SLIP packets, shown as hex dump
+    Slip = 2,
+    /// COBS packets, shown as hex dump
+    Cobs = 3,
+}
+
+/// How received data is shown
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum View {
+    /// Text lines
+    Text = 0,
+    /// Modbus RTU transactions, frames are split on the idle gap
+    Modbus = 1,
+    /// NMEA sentences, the ones with a bad checksum inverted
+    Nmea = 2,
+    /// GPS summary from the NMEA sentences
+    Gps = 3,
+    /// Binary packets decoded with the packet template
+    Template = 4,
+    /// Graph of a number picked from each line with `plot_pattern`
+    Plot = 5,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayConfig {
+    pub rotation: Rotation,
+    /// Render rate of the terminal
+    pub refresh_hz: u8,
+    pub contrast: u8,
+    /// Pixel rows scrolled per render, 0 scrolls a line at once
+    pub smooth_scroll: u8,
+    /// Status bar and panes
+    pub layout: Layout,
+    /// Terminal font
+    pub font: FontSize,
+    /// What happens to lines longer than the screen
+    pub wrap: WrapMode,
+    /// Received lines start with the time, see `clock`
+    pub timestamps: bool,
+    /// Pre-charge voltage level of the OLED (0 - 31)
+    pub pre_charge: u8,
+    /// Move the image a few rows now and then, see `burn_in`
+    pub pixel_shift: bool,
+    /// Dim the display after this many seconds without activity, 0 = never
+    pub dim_after_s: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerConfig {
+    /// Turn the display off after this many seconds without activity, 0 = never
+    pub display_timeout_s: u16,
+}
+
+/// What is shown of the received data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilterConfig {
+    /// Show overrun, framing and noise errors as 'O', 'F' and 'N' and
+    /// bytes with a parity error inverted
+    pub show_errors: bool,
+    /// Drop control characters other than '\n', '\r' and '\t'
+    pub printable_only: bool,
+}
+
+/// Sending to the target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TxConfig {
+    pub macros: [Macro; MAX_MACROS],
+    pub delays: Delays,
+    /// Show sent bytes in the terminal
+    pub echo: bool,
+}
+
+/// Forwarding between the capture UART and the debug UART
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BridgeConfig {
+    pub enabled: bool,
+    /// Start records with the channel, see `bridge`
+    pub tags: bool,
+    /// Start records with the time since boot
+    pub timestamps: bool,
+}
+
+impl BridgeConfig {
+    pub fn format(&self) -> Format {
+        Format {
+            tags: self.tags,
+            timestamps: self.timestamps,
+        }
+    }
+}
+
+/// Device configuration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    pub uart: UartConfig,
+    pub framing: Framing,
+    /// Checksum at the end of SLIP and COBS packets
+    pub packet_crc: PacketCrc,
+    pub view: View,
+    pub display: DisplayConfig,
+    pub power: PowerConfig,
+    pub filter: FilterConfig,
+    /// Packet template text for the template view, see `template`
+    pub template: ArrayString<[u8; MAX_TEMPLATE]>,
+    /// Which number of a line the plot view shows, see `plot::Pattern`
+    pub plot_pattern: ArrayString<[u8; MAX_PATTERN]>,
+    pub tx: TxConfig,
+    pub bridge: BridgeConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            uart: UartConfig {
+                baudrate: 115_200,
+                rx_timeout_us: 25_000,
+                line: LineConfig::default(),
+            },
+            framing: Framing::Lines,
+            packet_crc: PacketCrc::None,
+            view: View::Text,
+            display: DisplayConfig {
+                rotation: Rotation::Rotate180,
+                refresh_hz: 10,
+                contrast: 0x7F,
+                smooth_scroll: 0,
+                layout: Layout::Full,
+                font: FontSize::Medium,
+                wrap: WrapMode::Char,
+                timestamps: false,
+                pre_charge: 0x04,
+                pixel_shift: true,
+                dim_after_s: 300,
+            },
+            power: PowerConfig {
+                display_timeout_s: 0,
+            },
+            filter: FilterConfig {
+                show_errors: true,
+                printable_only: false,
+            },
+            template: ArrayString::new(),
+            plot_pattern: ArrayString::new(),
+            tx: TxConfig {
+                macros: [Macro::empty(); MAX_MACROS],
+                delays: Delays { byte_ms: 0, line_ms: 0 },
+                echo: true,
+            },
+            bridge: BridgeConfig {
+                enabled: false,
+                tags: false,
+                timestamps: false,
+            },
+        }
+    }
+}
+
+impl Config {
+    /// Display settings of the burn-in protection
+    pub fn burn_in(&self) -> burn_in::Settings {
+        burn_in::Settings {
+            contrast: self.display.contrast,
+            pre_charge: self.display.pre_charge,
+            pixel_shift: self.display.pixel_shift,
+            dim_after_s: self.display.dim_after_s,
+            off_after_s: self.power.display_timeout_s,
+        }
+    }
+
+    /// Serialize the payload at `CONFIG_VERSION`. Returns the payload length.
+    pub fn serialize(&self, buf: &mut [u8]) -> Option<usize> {
+        let mut w = Writer::new(buf);
+
+        // version 1
+        w.u32(self.uart.baudrate)?;
+        w.u32(self.uart.rx_timeout_us)?;
+        w.u8(self.framing as u8)?;
+        w.u8(self.display.rotation as u8)?;
+        w.u8(self.display.refresh_hz)?;
+        w.u8(self.display.contrast)?;
+        w.u8(self.display.smooth_scroll)?;
+        w.u16(self.power.display_timeout_s)?;
+        w.bool(self.filter.show_errors)?;
+        w.bool(self.filter.printable_only)?;
+
+        // version 2
+        let line = &self.uart.line;
+        w.u8(line.data_bits as u8)?;
+        w.u8(line.parity as u8)?;
+        w.u8(line.stop_bits as u8)?;
+        w.bool(line.rx_invert)?;
+        w.bool(line.msb_first)?;
+        w.bool(line.swap)?;
+
+        // version 3
+        w.u8(self.view as u8)?;
+
+        // version 4
+        w.u8(self.packet_crc as u8)?;
+
+        // version 5
+        w.u8(self.template.len() as u8)?;
+        w.bytes(self.template.as_bytes())?;
+
+        // version 6
+        w.u8(self.plot_pattern.len() as u8)?;
+        w.bytes(self.plot_pattern.as_bytes())?;
+
+        // version 7
+        w.u8(self.display.layout as u8)?;
+
+        // version 8
+        w.u8(self.display.font as u8)?;
+
+        // version 9
+        w.u8(self.display.wrap as u8)?;
+
+        // version 10
+        for m in self.tx.macros.iter() {
+            w.u8(m.ending as u8)?;
+            w.u8(m.text.len() as u8)?;
+            w.bytes(m.text.as_bytes())?;
+        }
+        w.u16(self.tx.delays.byte_ms)?;
+        w.u16(self.tx.delays.line_ms)?;
+        w.bool(self.tx.echo)?;
+
+        // version 11
+        w.bool(self.bridge.enabled)?;
+        w.bool(self.bridge.tags)?;
+        w.bool(self.bridge.timestamps)?;
+
+        // version 12
+        w.bool(self.display.timestamps)?;
+
+        // version 13
+        w.u8(self.display.pre_charge)?;
+        w.bool(self.display.pixel_shift)?;
+        w.u16(self.display.dim_after_s)?;
+
+        Some(w.len)
+    }
+
+    /// Deserialize a payload written at `version`, migrating older versions.
+    /// Returns None for unknown versions and invalid values.
+    pub fn deserialize(version: u16, payload: &[u8]) -> Option<Self> {
+        if version == 0 || version > CONFIG_VERSION {
+            return None;
+        }
+
+        let mut config = Config::default();
+        let mut r = Reader::new(payload);
+
+        config.uart.baudrate = r.u32()?;
+        config.uart.rx_timeout_us = r.u32()?;
+        config.framing = match r.u8()? {
+            0 => Framing::Lines,
+            1 => Framing::Idle,
+            2 => Framing::Slip,
+            3 => Framing::Cobs,
+            _ => return None,
+        };
+        config.display.rotation = match r.u8()? {
+            0 => Rotation::Rotate0,
+            1 => Rotation::Rotate180,
+            _ => return None,
+        };
+        config.display.refresh_hz = r.u8()?;
+        config.display.contrast = r.u8()?;
+        config.display.smooth_scroll = r.u8()?;
+        config.power.display_timeout_s = r.u16()?;
+        config.filter.show_errors = r.bool()?;
+        config.filter.printable_only = r.bool()?;
+
+        // version 1 only had 8N1
+        if version >= 2 {
+            let line = &mut config.uart.line;
+            line.data_bits = DataBits::from_bits(r.u8()?)?;
+            line.parity = Parity::from_u8(r.u8()?)?;
+            line.stop_bits = StopBits::from_u8(r.u8()?)?;
+            line.rx_invert = r.bool()?;
+            line.msb_first = r.bool()?;
+            line.swap = r.bool()?;
+        }
+
+        if version >= 3 {
+            config.view = match r.u8()? {
+                0 => View::Text,
+                1 => View::Modbus,
+                2 => View::Nmea,
+                3 => View::Gps,
+                4 => View::Template,
+                5 => View::Plot,
+                _ => return None,
+            };
+        }
+
+        if version >= 4 {
+            config.packet_crc = PacketCrc::from_u8(r.u8()?)?;
+        }
+
+        if version >= 5 {
+            let len = r.u8()? as usize;
+            let text = core::str::from_utf8(r.take(len)?).ok()?;
+            config.template = ArrayString::from(text).ok()?;
+        }
+
+        if version >= 6 {
+            let len = r.u8()? as usize;
+            let text = core::str::from_utf8(r.take(len)?).ok()?;
+            config.plot_pattern = ArrayString::from(text).ok()?;
+        }
+
+        if version >= 7 {
+            config.display.layout = Layout::from_u8(r.u8()?)?;
+        }
+
+        if version >= 8 {
+            config.display.font = FontSize::from_u8(r.u8()?)?;
+        }
+
+        if version >= 9 {
+            config.display.wrap = WrapMode::from_u8(r.u8()?)?;
+        }
+
+        if version >= 10 {
+            for m in config.tx.macros.iter_mut() {
+                m.ending = LineEnding::from_u8(r.u8()?)?;
+                let len = r.u8()? as usize;
+                let text = core::str::from_utf8(r.take(len)?).ok()?;
+                m.text = ArrayString::from(text).ok()?;
+            }
+            config.tx.delays.byte_ms = r.u16()?;
+            config.tx.delays.line_ms = r.u16()?;
+            config.tx.echo = r.bool()?;
+        }
+
+        if version >= 11 {
+            config.bridge.enabled = r.bool()?;
+            config.bridge.tags = r.bool()?;
+            config.bridge.timestamps = r.bool()?;
+        }
+
+        if version >= 12 {
+            config.display.timestamps = r.bool()?;
+        }
+
+        if version >= 13 {
+            config.display.pre_charge = r.u8()?;
+            config.display.pixel_shift = r.bool()?;
+            config.display.dim_after_s = r.u16()?;
+        }
+
+        if config.is_valid() {
+            Some(config)
+        } else {
+            None
+        }
+    }
+
+    /// Receiver timeout in bit times. In the Modbus view this is the 3.5
+    /// character gap between frames.
+    pub fn rx_timeout_bits(&self) -> u32 {
+        let baudrate = self.uart.baudrate as u64;
+        let us = match self.view {
+            View::Text | View::Nmea | View::Gps | View::Template | View::Plot => self.uart.rx_timeout_us as u64,
+            // fixed gap above 19200 baud
+            View::Modbus if baudrate > 19_200 => 1750,
+            View::Modbus => {
+                let line = &self.uart.line;
+                let stop = if line.stop_bits == StopBits::Stop2 { 2 } else { 1 };
+                let char_bits = 1 + line.word_length() as u32 + stop;
+                return char_bits * 7 / 2;
+            }
+        };
+        ((us * baudrate / 1_000_000) as u32).min(0xFF_FFFF)
+    }
+
+    /// All values are in range
+    pub fn is_valid(&self) -> bool {
+        self.uart.line.validate().is_ok()
+            && self.uart.baudrate >= 300
+            && self.uart.baudrate <= 4_000_000
+            && self.display.refresh_hz >= 1
+            && self.display.refresh_hz <= 100
+            && self.display.pre_charge <= 31
+            && Template::parse(&self.template).is_ok()
+            && self.tx.macros.iter().all(|m| m.bytes().is_ok())
+    }
+}
+
+/// Little endian writer for the payload
+struct Writer<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> Option<()> {
+        let end = self.len + bytes.len();
+        self.buf.get_mut(self.len..end)?.copy_from_slice(bytes);
+        self.len = end;
+        Some(())
+    }
+
+    fn u8(&mut self, v: u8) -> Option<()> {
+        self.bytes(&[v])
+    }
+
+    fn bool(&mut self, v: bool) -> Option<()> {
+        self.u8(v as u8)
+    }
+
+    fn u16(&mut self, v: u16) -> Option<()> {
+        self.bytes(&v.to_le_bytes())
+    }
+
+    fn u32(&mut self, v: u32) -> Option<()> {
+        self.bytes(&v.to_le_bytes())
+    }
+}
+
+/// Little endian reader for the payload
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.buf.len() < n {
+            return None;
+        }
+        let (bytes, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn bool(&mut self) -> Option<bool> {
+        match self.u8()? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.take(4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+/// Two flash pages reserved for the configuration
+pub trait ConfigFlash {
+    type Error;
+
+    /// Read from page 0 or 1
+    fn read(&mut self, page: usize, offset: usize, buf: &mut [u8]);
+
+    /// Erase page 0 or 1 to 0xFF
+    fn erase(&mut self, page: usize) -> Result<(), Self::Error>;
+
+    /// Program erased flash. `offset` and the length of `data` are multiples of 8.
+    fn program(&mut self, page: usize, offset: usize, data: &[u8]) -> Result<(), Self::Error>;
+}
+
+/// Where a configuration was loaded from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoadStatus {
+    /// Valid record at `CONFIG_VERSION`
+    Loaded,
+    /// Valid record of an older version, it is rewritten at the current version
+    Migrated(u16),
+    /// Valid record of an older version that could not be rewritten, it is
+    /// migrated again on the next load
+    NotRewritten(u16),
+    /// No valid record, defaults are used
+    Defaults,
+}
+
+/// Reads and writes `Config` records in flash
+pub struct ConfigStore<F> {
+    flash: F,
+    /// Page and record the next write goes to
+    page: usize,
+    record: usize,
+    sequence: u32,
+}
+
+impl<F> ConfigStore<F>
+where
+    F: ConfigFlash,
+{
+    pub fn new(flash: F) -> Self {
+        Self {
+            flash,
+            page: 0,
+            record: 0,
+            sequence: 0,
+        }
+    }
+
+    /// Load the newest valid configuration, falling back to defaults.
+    /// Migrated configurations are saved again at the current version.
+    pub fn load(&mut self) -> (Config, LoadStatus) {
+        // the newest record is kept while the others are read into the other buffer
+        let mut bufs = [[0u8; RECORD_LEN]; 2];
+        let mut scratch = 0;
+        let mut newest: Option<(usize, usize, Header)> = None;
+
+        for page in 0..2 {
+            for record in 0..RECORDS_PER_PAGE {
+                self.flash.read(page, record * RECORD_LEN, &mut bufs[scratch]);
+                if let Some(header) = Header::check(&bufs[scratch]) {
+                    if newest.as_ref().is_none_or(|(_, _, newest)| header.sequence > newest.sequence) {
+                        newest = Some((page, record, header));
+                        scratch ^= 1;
+                    }
+                }
+            }
+        }
+
+        let (page, record, header) = match newest {
+            Some(newest) => newest,
+            None => {
+                // nothing valid, start over on a clean page
+                self.page = 0;
+                self.record = RECORDS_PER_PAGE;
+                self.sequence = 0;
+                return (Config::default(), LoadStatus::Defaults);
+            }
+        };
+        self.page = page;
+        self.record = record + 1;
+        self.sequence = header.sequence;
+
+        let payload = &bufs[scratch ^ 1][HEADER_LEN..HEADER_LEN + header.len];
+        match Config::deserialize(header.version, payload) {
+            Some(config) if header.version == CONFIG_VERSION => (config, LoadStatus::Loaded),
+            // a worn or locked page keeps the settings, only the rewrite is lost
+            Some(config) => match self.save(&config) {
+                Ok(()) => (config, LoadStatus::Migrated(header.version)),
+                Err(_) => (config, LoadStatus::NotRewritten(header.version)),
+            },
+            None => (Config::default(), LoadStatus::Defaults),
+        }
+    }
+
+    /// Append `config` as a new record, erasing the other page when the
+    /// active one is full
+    pub fn save(&mut self, config: &Config) -> Result<(), F::Error> {
+        let mut buf = [0u8; RECORD_LEN];
+        let len = config
+            .serialize(&mut buf[HEADER_LEN..CRC_OFFSET])
+            .expect("config does not fit in a record");
+
+        self.sequence = self.sequence.wrapping_add(1);
+        buf[0..2].copy_from_slice(&MAGIC.to_le_bytes());
+        buf[2..4].copy_from_slice(&CONFIG_VERSION.to_le_bytes());
+        buf[4..8].copy_from_slice(&self.sequence.to_le_bytes());
+        buf[8..10].copy_from_slice(&(len as u16).to_le_bytes());
+        let crc = crc32(&buf[..CRC_OFFSET]);
+        buf[CRC_OFFSET..].copy_from_slice(&crc.to_le_bytes());
+
+        // skip records that were partially written before a reset
+        while self.record < RECORDS_PER_PAGE && !self.is_erased(self.page, self.record) {
+            self.record += 1;
+        }
+        if self.record >= RECORDS_PER_PAGE {
+            self.page ^= 1;
+            self.record = 0;
+            self.flash.erase(self.page)?;
+        }
+
+        self.flash.program(self.page, self.record * RECORD_LEN, &buf)?;
+        self.record += 1;
+        Ok(())
+    }
+
+    /// Give back the flash
+    pub fn release(self) -> F {
+        self.flash
+    }
+
+    fn is_erased(&mut self, page: usize, record: usize) -> bool {
+        let mut buf = [0u8; RECORD_LEN];
+        self.flash.read(page, record * RECORD_LEN, &mut buf);
+        buf.iter().all(|&b| b == 0xFF)
+    }
+}
+
+struct Header {
+    version: u16,
+    sequence: u32,
+    len: usize,
+}
+
+impl Header {
+    /// Header of a record with a valid magic, length and CRC
+    fn check(record: &[u8; RECORD_LEN]) -> Option<Self> {
+        let field = |at: usize| u16::from_le_bytes([record[at], record[at + 1]]);
+
+        if field(0) != MAGIC {
+            return None;
+        }
+        let len = field(8) as usize;
+        if len > MAX_PAYLOAD {
+            return None;
+        }
+        let mut crc = [0; 4];
+        crc.copy_from_slice(&record[CRC_OFFSET..]);
+        if u32::from_le_bytes(crc) != crc32(&record[..CRC_OFFSET]) {
+            return None;
+        }
+
+        let mut sequence = [0; 4];
+        sequence.copy_from_slice(&record[4..8]);
+        Some(Self {
+            version: field(2),
+            sequence: u32::from_le_bytes(sequence),
+            len,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The two pages in RAM, counting reads and erases
+    struct RamFlash {
+        pages: [[u8; PAGE_LEN]; 2],
+        reads: u32,
+        erases: [u32; 2],
+        /// Erasing and programming fail
+        locked: bool,
+    }
+
+    impl RamFlash {
+        fn new() -> Self {
+            Self {
+                pages: [[0xFF; PAGE_LEN]; 2],
+                reads: 0,
+                erases: [0; 2],
+                locked: false,
+            }
+        }
+    }
+
+    /// Flash that is locked
+    #[derive(Debug)]
+    struct Locked;
+
+    impl ConfigFlash for RamFlash {
+        type Error = Locked;
+
+        fn read(&mut self, page: usize, offset: usize, buf: &mut [u8]) {
+            buf.copy_from_slice(&self.pages[page][offset..offset + buf.len()]);
+            self.reads += 1;
+        }
+
+        fn erase(&mut self, page: usize) -> Result<(), Locked> {
+            if self.locked {
+                return Err(Locked);
+            }
+            self.pages[page] = [0xFF; PAGE_LEN];
+            self.erases[page] += 1;
+            Ok(())
+        }
+
+        fn program(&mut self, page: usize, offset: usize, data: &[u8]) -> Result<(), Locked> {
+            if self.locked {
+                return Err(Locked);
+            }
+            for (cell, &byte) in self.pages[page][offset..].iter_mut().zip(data) {
+                assert_eq!(*cell, 0xFF, "programming flash that isn't erased");
+                *cell = byte;
+            }
+            Ok(())
+        }
+    }
+
+    fn config(baudrate: u32) -> Config {
+        let mut config = Config::default();
+        config.uart.baudrate = baudrate;
+        config
+    }
+
+    /// Flash after saving `baudrates` to an empty store
+    fn saved(baudrates: &[u32]) -> RamFlash {
+        let mut store = ConfigStore::new(RamFlash::new());
+        store.load();
+        for &baudrate in baudrates {
+            store.save(&config(baudrate)).unwrap();
+        }
+        store.release()
+    }
+
+    fn load(flash: RamFlash) -> (Config, LoadStatus) {
+        ConfigStore::new(flash).load()
+    }
+
+    #[test]
+    fn empty_flash_gives_defaults() {
+        assert_eq!(load(RamFlash::new()), (Config::default(), LoadStatus::Defaults));
+    }
+
+    #[test]
+    fn newest_record_wins() {
+        let (config, status) = load(saved(&[9600, 19_200, 57_600]));
+        assert_eq!(status, LoadStatus::Loaded);
+        assert_eq!(config.uart.baudrate, 57_600);
+    }
+
+    #[test]
+    fn corrupt_record_falls_back_to_the_previous_one() {
+        let mut flash = saved(&[9600, 19_200]);
+        // a bit flipped in the payload of the second record, an empty store
+        // starts on page 1
+        flash.pages[1][RECORD_LEN + HEADER_LEN] ^= 0x01;
+        assert_eq!(load(flash).0.uart.baudrate, 9600);
+    }
+
+    #[test]
+    fn torn_write_is_skipped() {
+        let mut flash = saved(&[9600]);
+        // a reset while writing the second record left only its header
+        let torn = raw_record(CONFIG_VERSION, 2, &[]);
+        flash.pages[1][RECORD_LEN..RECORD_LEN + HEADER_LEN].copy_from_slice(&torn[..HEADER_LEN]);
+
+        let mut store = ConfigStore::new(flash);
+        assert_eq!(store.load().0.uart.baudrate, 9600);
+        store.save(&config(38_400)).unwrap();
+        let flash = store.release();
+        let header = Header::check(&record(&flash, 1, 2)).unwrap();
+        assert_eq!(header.sequence, 2);
+        assert_eq!(load(flash).0.uart.baudrate, 38_400);
+    }
+
+    #[test]
+    fn full_page_wraps_to_the_other_page() {
+        let baudrates: Vec<u32> = (1..=RECORDS_PER_PAGE as u32 + 1).map(|i| i * 9600).collect();
+        let flash = saved(&baudrates);
+        // the old page keeps its records until the new one fills up
+        assert_eq!(flash.erases, [1, 1]);
+        assert!(Header::check(&record(&flash, 1, RECORDS_PER_PAGE - 1)).is_some());
+        assert_eq!(load(flash).0.uart.baudrate, *baudrates.last().unwrap());
+    }
+
+    #[test]
+    fn pages_wear_evenly() {
+        let baudrates: Vec<u32> = (0..RECORDS_PER_PAGE as u32 * 6).map(|i| 9600 + i).collect();
+        let flash = saved(&baudrates);
+        // one erase per page full, alternating
+        assert_eq!(flash.erases, [3, 3]);
+        assert_eq!(load(flash).0.uart.baudrate, *baudrates.last().unwrap());
+    }
+
+    #[test]
+    fn store_continues_after_reload() {
+        let mut store = ConfigStore::new(saved(&[9600; RECORDS_PER_PAGE]));
+        store.load();
+        store.save(&config(115_200)).unwrap();
+        let flash = store.release();
+        assert_eq!(flash.erases, [1, 1]);
+        assert_eq!(load(flash).0.uart.baudrate, 115_200);
+    }
+
+    /// Flash with a version 1 record: 8N1, no view, defaults for everything
+    /// added later
+    fn version_1() -> RamFlash {
+        let mut payload = Vec::new();
+        payload.extend_from_slice(&9600u32.to_le_bytes());
+        payload.extend_from_slice(&1000u32.to_le_bytes());
+        payload.extend_from_slice(&[Framing::Slip as u8, Rotation::Rotate0 as u8, 20, 0x40, 2]);
+        payload.extend_from_slice(&60u16.to_le_bytes());
+        payload.extend_from_slice(&[0, 1]);
+
+        let mut flash = RamFlash::new();
+        flash.pages[1][..RECORD_LEN].copy_from_slice(&raw_record(1, 7, &payload));
+        flash
+    }
+
+    #[test]
+    fn old_version_is_migrated_and_rewritten() {
+        let mut store = ConfigStore::new(version_1());
+        let (config, status) = store.load();
+        assert_eq!(status, LoadStatus::Migrated(1));
+        assert_eq!(config.uart.baudrate, 9600);
+        assert_eq!(config.framing, Framing::Slip);
+        assert_eq!(config.display.rotation, Rotation::Rotate0);
+        assert_eq!(config.power.display_timeout_s, 60);
+        assert!(config.filter.printable_only);
+        assert_eq!(config.view, View::Text);
+        assert_eq!(config.display.dim_after_s, Config::default().display.dim_after_s);
+
+        // written behind the old record at the current version
+        let flash = store.release();
+        let header = Header::check(&record(&flash, 1, 1)).unwrap();
+        assert_eq!((header.version, header.sequence), (CONFIG_VERSION, 8));
+        assert_eq!(load(flash), (config, LoadStatus::Loaded));
+    }
+
+    #[test]
+    fn migration_is_kept_when_it_cannot_be_rewritten() {
+        let mut flash = version_1();
+        flash.locked = true;
+        let (config, status) = load(flash);
+        assert_eq!(status, LoadStatus::NotRewritten(1));
+        assert_eq!(config.uart.baudrate, 9600);
+        assert_eq!(config.framing, Framing::Slip);
+    }
+
+    #[test]
+    fn records_are_read_once() {
+        let mut store = ConfigStore::new(saved(&[9600, 19_200, 57_600]));
+        let before = store.flash.reads;
+        assert_eq!(store.load().0.uart.baudrate, 57_600);
+        assert_eq!(store.flash.reads - before, 2 * RECORDS_PER_PAGE as u32);
+    }
+
+    #[test]
+    fn unknown_version_gives_defaults() {
+        let mut payload = [0u8; MAX_PAYLOAD];
+        let len = config(9600).serialize(&mut payload).unwrap();
+        let mut flash = RamFlash::new();
+        flash.pages[0][..RECORD_LEN].copy_from_slice(&raw_record(CONFIG_VERSION + 1, 1, &payload[..len]));
+        assert_eq!(load(flash), (Config::default(), LoadStatus::Defaults));
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        let mut buf = [0u8; MAX_PAYLOAD];
+        let len = config(100).serialize(&mut buf).unwrap();
+        assert_eq!(Config::deserialize(CONFIG_VERSION, &buf[..len]), None);
+
+        let len = config(9600).serialize(&mut buf).unwrap();
+        assert!(Config::deserialize(CONFIG_VERSION, &buf[..len]).is_some());
+        // truncated payload
+        assert_eq!(Config::deserialize(CONFIG_VERSION, &buf[..len - 1]), None);
+        // framing past the last one
+        buf[8] = 4;
+        assert_eq!(Config::deserialize(CONFIG_VERSION, &buf[..len]), None);
+    }
+
+    #[test]
+    fn every_field_round_trips() {
+        let mut config = config(921_600);
+        config.uart.line.parity = Parity::Even;
+        config.framing = Framing::Cobs;
+        config.packet_crc = PacketCrc::Crc32;
+        config.view = View::Plot;
+        config.template = ArrayString::from("sync:AA55 t:i16@2/10").unwrap();
+        config.plot_pattern = ArrayString::from("T=").unwrap();
+        config.display.layout = Layout::SplitPlot;
+        config.display.font = FontSize::Small;
+        config.display.wrap = WrapMode::Off;
+        config.display.timestamps = true;
+        config.display.pixel_shift = false;
+        config.display.dim_after_s = 12;
+        config.tx.macros[1].text = ArrayString::from("AT").unwrap();
+        config.tx.macros[1].ending = LineEnding::CrLf;
+        config.tx.echo = false;
+        config.bridge.enabled = true;
+
+        let mut buf = [0u8; MAX_PAYLOAD];
+        let len = config.serialize(&mut buf).unwrap();
+        assert_eq!(Config::deserialize(CONFIG_VERSION, &buf[..len]), Some(config));
+    }
+
+    #[test]
+    fn modbus_gap_is_three_and_a_half_characters() {
+        let mut config = config(9600);
+        config.view = View::Modbus;
+        // start, 8 data and stop bit
+        assert_eq!(config.rx_timeout_bits(), 10 * 7 / 2);
+        config.uart.line.parity = Parity::Even;
+        config.uart.line.stop_bits = StopBits::Stop2;
+        assert_eq!(config.rx_timeout_bits(), 12 * 7 / 2);
+        // fixed 1750 us above 19200 baud
+        config.uart.baudrate = 115_200;
+        assert_eq!(config.rx_timeout_bits(), 201);
+        config.view = View::Text;
+        assert_eq!(config.rx_timeout_bits(), 2880);
+    }
+
+    fn record(flash: &RamFlash, page: usize, record: usize) -> [u8; RECORD_LEN] {
+        let mut buf = [0; RECORD_LEN];
+        buf.copy_from_slice(&flash.pages[page][record * RECORD_LEN..(record + 1) * RECORD_LEN]);
+        buf
+    }
+
+    fn raw_record(version: u16, sequence: u32, payload: &[u8]) -> [u8; RECORD_LEN] {
+        let mut buf = [0u8; RECORD_LEN];
+        buf[0..2].copy_from_slice(&MAGIC.to_le_bytes());
+        buf[2..4].copy_from_slice(&version.to_le_bytes());
+        buf[4..8].copy_from_slice(&sequence.to_le_bytes());
+        buf[8..10].copy_from_slice(&(payload.len() as u16).to_le_bytes());
+        buf[HEADER_LEN..HEADER_LEN + payload.len()].copy_from_slice(payload);
+        let crc = crc32(&buf[..CRC_OFFSET]);
+        buf[CRC_OFFSET..].copy_from_slice(&crc.to_le_bytes());
+        buf
+    }
+}
@@ -0,0 +1,268 @@
+//! Commands on the debug port
+//!
+//! The debug UART (USART3 on PB8/PB9, 115200 8N1) takes one command per line:
+//!
+//! - `help`
+//! - `template`: show the packet template
+//! - `template <text>`: set and store the packet template, see `template`
+//! - `template -`: remove the packet template
+//! - `plot`: show the pattern of the plot view
+//! - `plot <pattern>`: set and store the pattern, see `plot::Pattern`
+//! - `plot -`: plot the first number of each line
+//! - `macros`: show the transmit macros and delays
+//! - `macro <n> <ending> <text>`: set and store macro n (1 - 4), the ending is
+//!   `none`, `cr`, `lf` or `crlf`, see `macros` for the text
+//! - `macro <n> -`: remove macro n
+//! - `txdelay <byte ms> <line ms>`: set and store the transmit delays
+//! - `time`: show the date and time of the RTC
+//! - `time <YYYY-MM-DD HH:MM[:SS]>`: set the RTC
+//! - `display`: show the display settings
+//! - `contrast <0-255>`: set and store the contrast
+//! - `precharge <0-31>`: set and store the pre-charge voltage level
+//! - `shift <on|off>`: move the image now and then, see `burn_in`
+//! - `dim <s>`: dim after s seconds without activity, the status bar after s seconds
+//!   without a press or turn, 0 never
+//! - `saver <s>`: turn the display off after s seconds without activity, 0 never
+
+use crate::clock::DateTime;
+use crate::macros::{Delays, LineEnding, MAX_MACROS};
+
+/// Longest command line
+pub const MAX_LINE: usize = 160;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command<'a> {
+    Help,
+    ShowTemplate,
+    /// Empty to remove the template
+    SetTemplate(&'a str),
+    ShowPlot,
+    /// Empty to plot the first number
+    SetPlot(&'a str),
+    ShowMacros,
+    /// Macro index from 0, empty text to remove it
+    SetMacro(usize, LineEnding, &'a str),
+    SetDelays(Delays),
+    ShowTime,
+    SetTime(DateTime),
+    ShowDisplay,
+    Contrast(u8),
+    PreChargeVoltage(u8),
+    SetPixelShift(bool),
+    /// Seconds, 0 never dims
+    SetDim(u16),
+    /// Seconds, 0 never turns the display off
+    SetSaver(u16),
+}
+
+/// Parse a command line, None for unknown commands
+pub fn parse(line: &str) -> Option<Command<'_>> {
+    let line = line.trim();
+    let (command, argument) = match line.find(' ') {
+        Some(i) => (&line[..i], line[i + 1..].trim()),
+        None => (line, ""),
+    };
+
+    match (command, argument) {
+        ("help", "") => Some(Command::Help),
+        ("template", "") => Some(Command::ShowTemplate),
+        ("template", "-") => Some(Command::SetTemplate("")),
+        ("template", text) => Some(Command::SetTemplate(text)),
+        ("plot", "") => Some(Command::ShowPlot),
+        ("plot", "-") => Some(Command::SetPlot("")),
+        ("plot", pattern) => Some(Command::SetPlot(pattern)),
+        ("macros", "") => Some(Command::ShowMacros),
+        ("macro", argument) => parse_macro(argument),
+        ("txdelay", argument) => {
+            let mut values = argument.split(' ').filter(|v| !v.is_empty());
+            let byte_ms = values.next()?.parse().ok()?;
+            let line_ms = values.next()?.parse().ok()?;
+            if values.next().is_some() {
+                return None;
+            }
+            Some(Command::SetDelays(Delays { byte_ms, line_ms }))
+        }
+        ("time", "") => Some(Command::ShowTime),
+        ("time", time) => Some(Command::SetTime(DateTime::parse(time)?)),
+        ("display", "") => Some(Command::ShowDisplay),
+        ("contrast", value) => Some(Command::Contrast(value.parse().ok()?)),
+        ("precharge", value) => match value.parse().ok()? {
+            level @ 0..=31 => Some(Command::PreChargeVoltage(level)),
+            _ => None,
+        },
+        ("shift", "on") => Some(Command::SetPixelShift(true)),
+        ("shift", "off") => Some(Command::SetPixelShift(false)),
+        ("dim", seconds) => Some(Command::SetDim(seconds.parse().ok()?)),
+        ("saver", seconds) => Some(Command::SetSaver(seconds.parse().ok()?)),
+        _ => None,
+    }
+}
+
+/// "<n> <ending> <text>" or "<n> -"
+fn parse_macro(argument: &str) -> Option<Command<'_>> {
+    let (number, rest) = split_word(argument)?;
+    let index = match number.parse::<usize>().ok()? {
+        n @ 1..=MAX_MACROS => n - 1,
+        _ => return None,
+    };
+    if rest == "-" {
+        return Some(Command::SetMacro(index, LineEnding::None, ""));
+    }
+    let (ending, text) = split_word(rest)?;
+    let ending = LineEnding::parse(ending)?;
+    if text.is_empty() {
+        return None;
+    }
+    Some(Command::SetMacro(index, ending, text))
+}
+
+/// First word and the trimmed rest
+fn split_word(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    match text.find(' ') {
+        Some(i) => Some((&text[..i], text[i + 1..].trim_start())),
+        None if !text.is_empty() => Some((text, "")),
+        None => None,
+    }
+}
+
+pub const HELP: &str = "commands:\n\
+    \x20 template            show the packet template\n\
+    \x20 template <text>     set the packet template\n\
+    \x20 template -          remove the packet template\n\
+    \x20 plot                show the plot pattern\n\
+    \x20 plot <pattern>      set the plot pattern\n\
+    \x20 plot -              plot the first number\n\
+    \x20 macros              show the transmit macros\n\
+    \x20 macro <n> <ending> <text>\n\
+    \x20                     set macro n, ending none/cr/lf/crlf\n\
+    \x20 macro <n> -         remove macro n\n\
+    \x20 txdelay <byte ms> <line ms>\n\
+    \x20                     set the transmit delays\n\
+    \x20 time                show the date and time\n\
+    \x20 time <YYYY-MM-DD HH:MM[:SS]>\n\
+    \x20                     set the date and time\n\
+    \x20 display             show the display settings\n\
+    \x20 contrast <0-255>    set the contrast\n\
+    \x20 precharge <0-31>    set the pre-charge voltage\n\
+    \x20 shift <on|off>      move the image against burn-in\n\
+    \x20 dim <s>             dim after s seconds idle, 0 never\n\
+    \x20 saver <s>           display off after s seconds idle, 0 never\n";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simple_commands() {
+        assert_eq!(parse("help"), Some(Command::Help));
+        assert_eq!(parse("  help \r"), Some(Command::Help));
+        assert_eq!(parse("template"), Some(Command::ShowTemplate));
+        assert_eq!(parse("plot   "), Some(Command::ShowPlot));
+        assert_eq!(parse("macros"), Some(Command::ShowMacros));
+        assert_eq!(parse("time"), Some(Command::ShowTime));
+        assert_eq!(parse("display"), Some(Command::ShowDisplay));
+    }
+
+    #[test]
+    fn unknown_commands() {
+        let lines = ["", "   ", "HELP", "help me", "macros 1", "display on", "helpx", "shift", "shift maybe"];
+        for line in lines {
+            assert_eq!(parse(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn text_arguments() {
+        assert_eq!(parse("template t:x@0 u16le:v@1"), Some(Command::SetTemplate("t:x@0 u16le:v@1")));
+        // spaces inside are kept, around are not
+        assert_eq!(parse("template   a  b  "), Some(Command::SetTemplate("a  b")));
+        assert_eq!(parse("template -"), Some(Command::SetTemplate("")));
+        assert_eq!(parse("plot temp=*"), Some(Command::SetPlot("temp=*")));
+        assert_eq!(parse("plot -"), Some(Command::SetPlot("")));
+    }
+
+    #[test]
+    fn macros() {
+        assert_eq!(parse("macro 1 crlf AT+GMR"), Some(Command::SetMacro(0, LineEnding::CrLf, "AT+GMR")));
+        let hex = Command::SetMacro(3, LineEnding::None, "hex:01 03");
+        assert_eq!(parse("macro 4 none hex:01 03"), Some(hex));
+        assert_eq!(parse("macro  2   lf  a b"), Some(Command::SetMacro(1, LineEnding::Lf, "a b")));
+        assert_eq!(parse("macro 3 -"), Some(Command::SetMacro(2, LineEnding::None, "")));
+        for line in [
+            "macro",
+            "macro 0 cr x",
+            "macro 5 cr x",
+            "macro x cr x",
+            "macro 1",
+            "macro 1 cr",
+            "macro 1 crlf ",
+            "macro 1 CR x",
+            "macro 1 tab x",
+        ] {
+            assert_eq!(parse(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn transmit_delays() {
+        assert_eq!(parse("txdelay 2 100"), Some(Command::SetDelays(Delays { byte_ms: 2, line_ms: 100 })));
+        assert_eq!(parse("txdelay  0   0 "), Some(Command::SetDelays(Delays { byte_ms: 0, line_ms: 0 })));
+        let lines = [
+            "txdelay",
+            "txdelay 2",
+            "txdelay 2 100 3",
+            "txdelay -1 0",
+            "txdelay 2 x",
+            "txdelay 70000 0",
+        ];
+        for line in lines {
+            assert_eq!(parse(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn set_time() {
+        let time = DateTime { year: 2026, month: 10, day: 19, hour: 12, minute: 34, second: 0 };
+        assert_eq!(parse("time 2026-10-19 12:34"), Some(Command::SetTime(time)));
+        assert_eq!(parse("time 2026-10-19T12:34:00"), Some(Command::SetTime(time)));
+        assert_eq!(parse("time 2026-02-30 12:34"), None);
+        assert_eq!(parse("time now"), None);
+    }
+
+    #[test]
+    fn display_settings() {
+        assert_eq!(parse("contrast 0"), Some(Command::Contrast(0)));
+        assert_eq!(parse("contrast 255"), Some(Command::Contrast(255)));
+        assert_eq!(parse("precharge 31"), Some(Command::PreChargeVoltage(31)));
+        assert_eq!(parse("shift on"), Some(Command::SetPixelShift(true)));
+        assert_eq!(parse("shift off"), Some(Command::SetPixelShift(false)));
+        assert_eq!(parse("dim 0"), Some(Command::SetDim(0)));
+        assert_eq!(parse("saver 65535"), Some(Command::SetSaver(65535)));
+        let lines = [
+            "contrast",
+            "contrast 256",
+            "contrast -1",
+            "precharge 32",
+            "dim 65536",
+            "saver 1.5",
+            "dim x",
+        ];
+        for line in lines {
+            assert_eq!(parse(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn help_lists_every_command() {
+        let commands = [
+            "template", "plot", "macros", "macro", "txdelay", "time", "display", "contrast", "precharge",
+            "shift", "dim", "saver",
+        ];
+        for command in commands {
+            let listed = HELP.lines().any(|line| line.trim_start().split(' ').next() == Some(command));
+            assert!(listed, "{}", command);
+        }
+        assert!(HELP.lines().all(|line| line.len() <= 80));
+    }
+}
@@ -0,0 +1,52 @@
+//! Checksums
+
+/// CRC-32 (IEEE 802.3, as used by zlib and Ethernet)
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+/// CRC-16/MODBUS (reflected 0x8005, init 0xFFFF), sent low byte first
+pub fn crc16_modbus(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &b in data {
+        crc ^= b as u16;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xA001 & mask);
+        }
+    }
+    crc
+}
+
+/// CRC-16/CCITT-FALSE (0x1021, init 0xFFFF, not reflected), as in the remote
+/// control protocol
+pub use protocol::crc::crc16_ccitt;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check values of the CRC catalogue, over "123456789"
+    #[test]
+    fn check_values() {
+        let check = b"123456789";
+        assert_eq!(crc32(check), 0xCBF4_3926);
+        assert_eq!(crc16_modbus(check), 0x4B37);
+        assert_eq!(crc16_ccitt(check), 0x29B1);
+    }
+
+    #[test]
+    fn empty_input_is_the_init_value() {
+        assert_eq!(crc32(&[]), 0);
+        assert_eq!(crc16_modbus(&[]), 0xFFFF);
+        assert_eq!(crc16_ccitt(&[]), 0xFFFF);
+    }
+}
@@ -0,0 +1,241 @@
+//! `display-interface` on top of the DMA driven SSD1362 interface
+//!
+//! The OLED driver sends commands and rows through `WriteOnlyDataCommand`.
+//! Commands are copied into the transfer queue of `DmaSpiInterface`, data into
+//! one of two buffers, so the next row is copied while the previous one is still
+//! on the bus. A call returns once its last transfer has started, the bus is only
+//! waited for when a buffer is needed again. The transfers are polled, so the task
+//! that renders waits for the bus then.
+
+use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+use embedded_hal::digital::v2::OutputPin;
+
+use crate::error::Error;
+use crate::interface::{AsyncDisplayInterface, DmaSpiInterface, TxDma, MAX_BATCH};
+use crate::oled::ROW_BYTES;
+
+/// Bytes in one data transfer, a display row
+pub const CHUNK_LEN: usize = ROW_BYTES;
+
+pub struct DmaDisplay<DMA, DC> {
+    iface: DmaSpiInterface<DMA, DC>,
+    buffers: &'static mut [[u8; CHUNK_LEN]; 2],
+    /// Buffer filled next
+    next: usize,
+}
+
+impl<DMA, DC, DmaE, PinE> DmaDisplay<DMA, DC>
+where
+    DMA: TxDma<Error = DmaE>,
+    DC: OutputPin<Error = PinE>,
+{
+    pub fn new(iface: DmaSpiInterface<DMA, DC>, buffers: &'static mut [[u8; CHUNK_LEN]; 2]) -> Self {
+        Self {
+            iface,
+            buffers,
+            next: 0,
+        }
+    }
+
+    /// Wait until every transfer but the last one completed and the last one
+    /// started
+    fn settle(&mut self) -> Result<(), DisplayError> {
+        loop {
+            match self.iface.poll() {
+                Ok(()) => return Ok(()),
+                Err(nb::Error::WouldBlock) if self.iface.pending() <= 1 => return Ok(()),
+                Err(nb::Error::WouldBlock) => {}
+                Err(nb::Error::Other(err)) => return Err(display_error(err)),
+            }
+        }
+    }
+
+    /// Send the first `len` bytes of buffer `next`
+    fn send_buffer(&mut self, len: usize) -> Result<(), DisplayError> {
+        let buffer = &self.buffers[self.next][..len];
+        // Safety: the buffer is written again once it is `next` again. By then
+        // the transfers of the other buffer started, `settle` only returns when
+        // everything before them completed.
+        let data = unsafe { core::slice::from_raw_parts(buffer.as_ptr(), buffer.len()) };
+        self.iface.queue_data(data).map_err(nb_error)?;
+        self.next = 1 - self.next;
+        self.settle()
+    }
+}
+
+impl<DMA, DC, DmaE, PinE> WriteOnlyDataCommand for DmaDisplay<DMA, DC>
+where
+    DMA: TxDma<Error = DmaE>,
+    DC: OutputPin<Error = PinE>,
+{
+    fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+        let cmds = match cmd {
+            DataFormat::U8(cmds) => cmds,
+            _ => return Err(DisplayError::DataFormatNotImplemented),
+        };
+        for batch in cmds.chunks(MAX_BATCH) {
+            self.iface.queue_commands(batch).map_err(nb_error)?;
+            self.settle()?;
+        }
+        Ok(())
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        match buf {
+            DataFormat::U8(data) => {
+                for chunk in data.chunks(CHUNK_LEN) {
+                    self.buffers[self.next][..chunk.len()].copy_from_slice(chunk);
+                    self.send_buffer(chunk.len())?;
+                }
+            }
+            DataFormat::U8Iter(data) => loop {
+                let mut len = 0;
+                for (byte, value) in self.buffers[self.next].iter_mut().zip(&mut *data) {
+                    *byte = value;
+                    len += 1;
+                }
+                if len == 0 {
+                    break;
+                }
+                self.send_buffer(len)?;
+            },
+            _ => return Err(DisplayError::DataFormatNotImplemented),
+        }
+        Ok(())
+    }
+}
+
+fn display_error<DmaE, PinE>(err: Error<DmaE, PinE>) -> DisplayError {
+    match err {
+        Error::Pin(_) => DisplayError::DCError,
+        Error::Comm(_) | Error::TooManyCommands => DisplayError::BusWriteError,
+    }
+}
+
+/// `settle` made room in the queue, so it doesn't block
+fn nb_error<DmaE, PinE>(err: nb::Error<Error<DmaE, PinE>>) -> DisplayError {
+    match err {
+        nb::Error::Other(err) => display_error(err),
+        nb::Error::WouldBlock => DisplayError::BusWriteError,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::RefCell;
+    use core::convert::Infallible;
+    use std::rc::Rc;
+
+    #[derive(Default)]
+    struct Bus {
+        dc_high: bool,
+        /// Transfer in flight: D/C, memory and `is_done` calls until it completes
+        transfer: Option<(bool, *const u8, usize, u32)>,
+        /// Bytes with D/C, read from memory when the transfer completes like the
+        /// DMA reads them while it goes
+        sent: Vec<(bool, u8)>,
+    }
+
+    struct MockDma(Rc<RefCell<Bus>>);
+
+    impl TxDma for MockDma {
+        type Error = Infallible;
+
+        unsafe fn start(&mut self, ptr: *const u8, len: usize) {
+            let mut bus = self.0.borrow_mut();
+            bus.transfer = Some((bus.dc_high, ptr, len, 3));
+        }
+
+        fn is_done(&mut self) -> Result<bool, Infallible> {
+            let mut bus = self.0.borrow_mut();
+            match bus.transfer {
+                Some((dc, ptr, len, 0)) => {
+                    let bytes = unsafe { core::slice::from_raw_parts(ptr, len) };
+                    bus.sent.extend(bytes.iter().map(|&b| (dc, b)));
+                    bus.transfer = None;
+                    Ok(true)
+                }
+                Some((dc, ptr, len, polls)) => {
+                    bus.transfer = Some((dc, ptr, len, polls - 1));
+                    Ok(false)
+                }
+                None => Ok(true),
+            }
+        }
+    }
+
+    struct MockDc(Rc<RefCell<Bus>>);
+
+    impl OutputPin for MockDc {
+        type Error = Infallible;
+
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().dc_high = false;
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.borrow_mut().dc_high = true;
+            Ok(())
+        }
+    }
+
+    fn display() -> (DmaDisplay<MockDma, MockDc>, Rc<RefCell<Bus>>) {
+        let bus = Rc::new(RefCell::new(Bus::default()));
+        let cmds = Box::leak(Box::new([0; MAX_BATCH]));
+        let iface = DmaSpiInterface::new(MockDma(bus.clone()), MockDc(bus.clone()), cmds);
+        let buffers = Box::leak(Box::new([[0; CHUNK_LEN]; 2]));
+        (DmaDisplay::new(iface, buffers), bus)
+    }
+
+    /// Let the last transfer complete
+    fn finish(display: &mut DmaDisplay<MockDma, MockDc>) {
+        while display.iface.poll().is_err() {}
+    }
+
+    #[test]
+    fn rows_are_sent_whole() {
+        let (mut display, bus) = display();
+        let rows: Vec<u8> = (0..CHUNK_LEN * 3).map(|i| i as u8 ^ (i / CHUNK_LEN) as u8).collect();
+        display.send_commands(DataFormat::U8(&[0x15, 0x00, 0x7F])).unwrap();
+        display.send_commands(DataFormat::U8(&[0x75, 0x00, 0x02])).unwrap();
+        display.send_data(DataFormat::U8(&rows)).unwrap();
+        display.send_commands(DataFormat::U8(&[0xA1, 0x10])).unwrap();
+        finish(&mut display);
+
+        let mut expected = vec![(false, 0x15), (false, 0x00), (false, 0x7F), (false, 0x75), (false, 0x00), (false, 0x02)];
+        expected.extend(rows.iter().map(|&b| (true, b)));
+        expected.extend([(false, 0xA1), (false, 0x10)].iter());
+        assert_eq!(bus.borrow().sent, expected);
+    }
+
+    #[test]
+    fn long_command_lists_are_split() {
+        let (mut display, bus) = display();
+        let cmds: Vec<u8> = (0..20).collect();
+        display.send_commands(DataFormat::U8(&cmds)).unwrap();
+        finish(&mut display);
+        assert_eq!(bus.borrow().sent, cmds.iter().map(|&b| (false, b)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn iterator_data() {
+        let (mut display, bus) = display();
+        let len = CHUNK_LEN * 2 + 5;
+        display.send_data(DataFormat::U8Iter(&mut (0..len).map(|i| i as u8))).unwrap();
+        finish(&mut display);
+        let sent = &bus.borrow().sent;
+        assert_eq!(sent.len(), len);
+        assert!(sent.iter().enumerate().all(|(i, &(dc, b))| dc && b == i as u8));
+    }
+
+    #[test]
+    fn other_formats_are_rejected() {
+        let (mut display, _bus) = display();
+        assert!(matches!(
+            display.send_data(DataFormat::U16(&[0x1234])),
+            Err(DisplayError::DataFormatNotImplemented)
+        ));
+    }
+}
@@ -0,0 +1,131 @@
+//! Flash pages holding the configuration
+//!
+//! The last two 2K pages of flash are kept out of the program by `memory.x`.
+
+use core::ptr;
+
+use stm32g0xx_hal::stm32::FLASH;
+
+use crate::config::{ConfigFlash, PAGE_LEN};
+
+/// Page number of the first configuration page
+const FIRST_PAGE: usize = 62;
+const FLASH_BASE: usize = 0x0800_0000;
+
+const KEY1: u32 = 0x4567_0123;
+const KEY2: u32 = 0xCDEF_89AB;
+
+/// Error flags in FLASH_SR
+const SR_ERRORS: u32 = 0xC3FA;
+const SR_EOP: u32 = 1 << 0;
+
+/// Flash errors
+#[derive(Debug)]
+pub enum FlashError {
+    /// Error flags set in FLASH_SR after an operation
+    Operation(u32),
+    /// Offset or length not double word aligned
+    Alignment,
+}
+
+/// The two configuration pages
+pub struct ConfigPages {
+    flash: FLASH,
+}
+
+impl ConfigPages {
+    pub fn new(flash: FLASH) -> Self {
+        Self { flash }
+    }
+
+    fn address(page: usize, offset: usize) -> usize {
+        FLASH_BASE + (FIRST_PAGE + page) * PAGE_LEN + offset
+    }
+
+    fn unlock(&mut self) {
+        if self.flash.cr.read().lock().bit_is_set() {
+            self.flash.keyr.write(|w| unsafe { w.keyr().bits(KEY1) });
+            self.flash.keyr.write(|w| unsafe { w.keyr().bits(KEY2) });
+        }
+    }
+
+    fn lock(&mut self) {
+        self.flash.cr.modify(|_, w| w.lock().set_bit());
+    }
+
+    fn wait(&mut self) -> Result<(), FlashError> {
+        while self.flash.sr.read().bsy().bit_is_set() {}
+
+        let sr = self.flash.sr.read().bits();
+        // flags are cleared by writing 1
+        self.flash.sr.write(|w| unsafe { w.bits(sr & (SR_ERRORS | SR_EOP)) });
+        if sr & SR_ERRORS != 0 {
+            Err(FlashError::Operation(sr & SR_ERRORS))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn erase_page(&mut self, page: usize) -> Result<(), FlashError> {
+        self.wait()?;
+        self.flash.cr.modify(|_, w| unsafe {
+            w.per().set_bit().pnb().bits((FIRST_PAGE + page) as u8)
+        });
+        self.flash.cr.modify(|_, w| w.strt().set_bit());
+        let result = self.wait();
+        self.flash.cr.modify(|_, w| w.per().clear_bit());
+        result
+    }
+
+    fn program_double_words(&mut self, address: usize, data: &[u8]) -> Result<(), FlashError> {
+        self.wait()?;
+        self.flash.cr.modify(|_, w| w.pg().set_bit());
+
+        let mut result = Ok(());
+        for (i, chunk) in data.chunks(8).enumerate() {
+            let low = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            let high = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]);
+            let word = (address + i * 8) as *mut u32;
+            // both words of a double word are written back to back
+            unsafe {
+                ptr::write_volatile(word, low);
+                ptr::write_volatile(word.add(1), high);
+            }
+            result = self.wait();
+            if result.is_err() {
+                break;
+            }
+        }
+
+        self.flash.cr.modify(|_, w| w.pg().clear_bit());
+        result
+    }
+}
+
+impl ConfigFlash for ConfigPages {
+    type Error = FlashError;
+
+    fn read(&mut self, page: usize, offset: usize, buf: &mut [u8]) {
+        let address = Self::address(page, offset) as *const u8;
+        for (i, b) in buf.iter_mut().enumerate() {
+            *b = unsafe { ptr::read_volatile(address.add(i)) };
+        }
+    }
+
+    fn erase(&mut self, page: usize) -> Result<(), FlashError> {
+        self.unlock();
+        let result = self.erase_page(page);
+        self.lock();
+        result
+    }
+
+    fn program(&mut self, page: usize, offset: usize, data: &[u8]) -> Result<(), FlashError> {
+        if offset % 8 != 0 || data.len() % 8 != 0 {
+            return Err(FlashError::Alignment);
+        }
+        self.unlock();
+        let result = self.program_double_words(Self::address(page, offset), data);
+        self.lock();
+        result
+    }
+}
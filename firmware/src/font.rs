@@ -0,0 +1,429 @@
+//! Bitmap fonts for the terminal
+//!
+//! Glyphs cover printable ASCII (0x20 - 0x7E). Every glyph is stored as one byte
+//! per pixel row, with the leftmost pixel in the MSB.
+
+/// Monospaced bitmap font
+pub struct Font {
+    /// Character cell width in pixels (max 8)
+    pub width: u8,
+    /// Character cell height in pixels
+    pub height: u8,
+    glyphs: &'static [u8],
+}
+
+impl Font {
+    /// Pixel row `y` of the glyph for `c`. Characters without a glyph are drawn as '?'
+    pub fn glyph_row(&self, c: u8, y: u8) -> u8 {
+        let index = match c {
+            0x20..=0x7E => c - 0x20,
+            _ => b'?' - 0x20,
+        };
+        self.glyphs[index as usize * self.height as usize + y as usize]
+    }
+}
+
+/// Fonts to choose from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FontSize {
+    /// 4x6, 64x10 characters
+    Small = 0,
+    /// 6x8, 42x8 characters
+    Medium = 1,
+    /// 8x16, 32x4 characters
+    Large = 2,
+}
+
+impl FontSize {
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(FontSize::Small),
+            1 => Some(FontSize::Medium),
+            2 => Some(FontSize::Large),
+            _ => None,
+        }
+    }
+
+    pub fn font(self) -> &'static Font {
+        match self {
+            FontSize::Small => &FONT_4X6,
+            FontSize::Medium => &FONT_6X8,
+            FontSize::Large => &FONT_8X16,
+        }
+    }
+}
+
+/// 4x6 font, 64x10 characters on the 256x64 display
+pub static FONT_4X6: Font = Font {
+    width: 4,
+    height: 6,
+    glyphs: &GLYPHS_4X6,
+};
+
+/// 6x8 font, 42x8 characters on the 256x64 display
+pub static FONT_6X8: Font = Font {
+    width: 6,
+    height: 8,
+    glyphs: &GLYPHS_6X8,
+};
+
+/// 8x16 font, 32x4 characters on the 256x64 display
+pub static FONT_8X16: Font = Font {
+    width: 8,
+    height: 16,
+    glyphs: &GLYPHS_8X16,
+};
+
+const GLYPHS_6X8: [u8; 95 * 8] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // ' '
+    0x00, 0x20, 0x20, 0x20, 0x20, 0x00, 0x20, 0x00,  // '!'
+    0x00, 0x50, 0x50, 0x50, 0x00, 0x00, 0x00, 0x00,  // '"'
+    0x50, 0x50, 0xF8, 0x50, 0xF8, 0x50, 0x50, 0x00,  // '#'
+    0x20, 0x70, 0xA0, 0x70, 0x28, 0x70, 0x20, 0x00,  // '$'
+    0x00, 0x40, 0x50, 0x20, 0x50, 0x10, 0x00, 0x00,  // '%'
+    0x40, 0xA0, 0xA0, 0x40, 0xA0, 0xA0, 0x50, 0x00,  // '&'
+    0x00, 0x20, 0x20, 0x20, 0x00, 0x00, 0x00, 0x00,  // '''
+    0x00, 0x20, 0x40, 0x40, 0x40, 0x40, 0x20, 0x00,  // '('
+    0x00, 0x40, 0x20, 0x20, 0x20, 0x20, 0x40, 0x00,  // ')'
+    0x00, 0x00, 0x90, 0x60, 0xF0, 0x60, 0x90, 0x00,  // '*'
+    0x00, 0x00, 0x20, 0x20, 0xF8, 0x20, 0x20, 0x00,  // '+'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x30, 0x20, 0x40,  // ','
+    0x00, 0x00, 0x00, 0x00, 0xF0, 0x00, 0x00, 0x00,  // '-'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x70, 0x20,  // '.'
+    0x00, 0x10, 0x10, 0x20, 0x40, 0x80, 0x80, 0x00,  // '/'
+    0x00, 0x20, 0x50, 0x50, 0x50, 0x50, 0x20, 0x00,  // '0'
+    0x00, 0x20, 0x60, 0x20, 0x20, 0x20, 0x70, 0x00,  // '1'
+    0x00, 0x60, 0x90, 0x10, 0x60, 0x80, 0xF0, 0x00,  // '2'
+    0x00, 0xF0, 0x20, 0x60, 0x10, 0x90, 0x60, 0x00,  // '3'
+    0x00, 0x20, 0x60, 0xA0, 0xF0, 0x20, 0x20, 0x00,  // '4'
+    0x00, 0xF0, 0x80, 0xE0, 0x10, 0x90, 0x60, 0x00,  // '5'
+    0x00, 0x60, 0x80, 0xE0, 0x90, 0x90, 0x60, 0x00,  // '6'
+    0x00, 0xF0, 0x10, 0x20, 0x20, 0x40, 0x40, 0x00,  // '7'
+    0x00, 0x60, 0x90, 0x60, 0x90, 0x90, 0x60, 0x00,  // '8'
+    0x00, 0x60, 0x90, 0x90, 0x70, 0x10, 0x60, 0x00,  // '9'
+    0x00, 0x00, 0x60, 0x60, 0x00, 0x60, 0x60, 0x00,  // ':'
+    0x00, 0x00, 0x30, 0x30, 0x00, 0x30, 0x20, 0x40,  // ';'
+    0x00, 0x10, 0x20, 0x40, 0x40, 0x20, 0x10, 0x00,  // '<'
+    0x00, 0x00, 0x00, 0xF0, 0x00, 0xF0, 0x00, 0x00,  // '='
+    0x00, 0x40, 0x20, 0x10, 0x10, 0x20, 0x40, 0x00,  // '>'
+    0x00, 0x20, 0x50, 0x10, 0x20, 0x00, 0x20, 0x00,  // '?'
+    0x30, 0x48, 0x98, 0xA8, 0xA8, 0x90, 0x40, 0x30,  // '@'
+    0x00, 0x60, 0x90, 0x90, 0xF0, 0x90, 0x90, 0x00,  // 'A'
+    0x00, 0xE0, 0x90, 0xE0, 0x90, 0x90, 0xE0, 0x00,  // 'B'
+    0x00, 0x60, 0x90, 0x80, 0x80, 0x90, 0x60, 0x00,  // 'C'
+    0x00, 0xE0, 0x90, 0x90, 0x90, 0x90, 0xE0, 0x00,  // 'D'
+    0x00, 0xF0, 0x80, 0xE0, 0x80, 0x80, 0xF0, 0x00,  // 'E'
+    0x00, 0xF0, 0x80, 0xE0, 0x80, 0x80, 0x80, 0x00,  // 'F'
+    0x00, 0x60, 0x90, 0x80, 0xB0, 0x90, 0x60, 0x00,  // 'G'
+    0x00, 0x90, 0x90, 0xF0, 0x90, 0x90, 0x90, 0x00,  // 'H'
+    0x00, 0x70, 0x20, 0x20, 0x20, 0x20, 0x70, 0x00,  // 'I'
+    0x00, 0x70, 0x20, 0x20, 0x20, 0xA0, 0x40, 0x00,  // 'J'
+    0x00, 0x90, 0xA0, 0xC0, 0xA0, 0xA0, 0x90, 0x00,  // 'K'
+    0x00, 0x80, 0x80, 0x80, 0x80, 0x80, 0xF0, 0x00,  // 'L'
+    0x00, 0x90, 0xF0, 0xF0, 0x90, 0x90, 0x90, 0x00,  // 'M'
+    0x00, 0x90, 0xD0, 0xF0, 0xB0, 0xB0, 0x90, 0x00,  // 'N'
+    0x00, 0x60, 0x90, 0x90, 0x90, 0x90, 0x60, 0x00,  // 'O'
+    0x00, 0xE0, 0x90, 0x90, 0xE0, 0x80, 0x80, 0x00,  // 'P'
+    0x00, 0x60, 0x90, 0x90, 0xD0, 0xB0, 0x60, 0x10,  // 'Q'
+    0x00, 0xE0, 0x90, 0x90, 0xE0, 0x90, 0x90, 0x00,  // 'R'
+    0x00, 0x60, 0x90, 0x40, 0x20, 0x90, 0x60, 0x00,  // 'S'
+    0x00, 0x70, 0x20, 0x20, 0x20, 0x20, 0x20, 0x00,  // 'T'
+    0x00, 0x90, 0x90, 0x90, 0x90, 0x90, 0x60, 0x00,  // 'U'
+    0x00, 0x90, 0x90, 0x90, 0x90, 0x60, 0x60, 0x00,  // 'V'
+    0x00, 0x90, 0x90, 0x90, 0xF0, 0xF0, 0x90, 0x00,  // 'W'
+    0x00, 0x90, 0x90, 0x60, 0x60, 0x90, 0x90, 0x00,  // 'X'
+    0x00, 0x88, 0x88, 0x50, 0x20, 0x20, 0x20, 0x00,  // 'Y'
+    0x00, 0xF0, 0x10, 0x20, 0x40, 0x80, 0xF0, 0x00,  // 'Z'
+    0x00, 0x70, 0x40, 0x40, 0x40, 0x40, 0x70, 0x00,  // '['
+    0x00, 0x80, 0x80, 0x40, 0x20, 0x10, 0x10, 0x00,  // '\'
+    0x00, 0x70, 0x10, 0x10, 0x10, 0x10, 0x70, 0x00,  // ']'
+    0x00, 0x20, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00,  // '^'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF0,  // '_'
+    0x00, 0x40, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00,  // '`'
+    0x00, 0x00, 0x00, 0x70, 0x90, 0x90, 0x70, 0x00,  // 'a'
+    0x00, 0x80, 0x80, 0xE0, 0x90, 0x90, 0xE0, 0x00,  // 'b'
+    0x00, 0x00, 0x00, 0x30, 0x40, 0x40, 0x30, 0x00,  // 'c'
+    0x00, 0x10, 0x10, 0x70, 0x90, 0x90, 0x70, 0x00,  // 'd'
+    0x00, 0x00, 0x00, 0x60, 0xB0, 0xC0, 0x60, 0x00,  // 'e'
+    0x00, 0x20, 0x50, 0x40, 0xE0, 0x40, 0x40, 0x00,  // 'f'
+    0x00, 0x00, 0x00, 0x60, 0x90, 0x70, 0x10, 0x60,  // 'g'
+    0x00, 0x80, 0x80, 0xE0, 0x90, 0x90, 0x90, 0x00,  // 'h'
+    0x00, 0x20, 0x00, 0x60, 0x20, 0x20, 0x70, 0x00,  // 'i'
+    0x00, 0x10, 0x00, 0x10, 0x10, 0x10, 0x50, 0x20,  // 'j'
+    0x00, 0x80, 0x80, 0x90, 0xE0, 0x90, 0x90, 0x00,  // 'k'
+    0x00, 0x60, 0x20, 0x20, 0x20, 0x20, 0x70, 0x00,  // 'l'
+    0x00, 0x00, 0x00, 0xD0, 0xA8, 0xA8, 0xA8, 0x00,  // 'm'
+    0x00, 0x00, 0x00, 0xE0, 0x90, 0x90, 0x90, 0x00,  // 'n'
+    0x00, 0x00, 0x00, 0x60, 0x90, 0x90, 0x60, 0x00,  // 'o'
+    0x00, 0x00, 0x00, 0xE0, 0x90, 0xE0, 0x80, 0x80,  // 'p'
+    0x00, 0x00, 0x00, 0x70, 0x90, 0x70, 0x10, 0x10,  // 'q'
+    0x00, 0x00, 0x00, 0xA0, 0xD0, 0x80, 0x80, 0x00,  // 'r'
+    0x00, 0x00, 0x00, 0x30, 0x60, 0x10, 0x60, 0x00,  // 's'
+    0x00, 0x40, 0x40, 0xE0, 0x40, 0x50, 0x20, 0x00,  // 't'
+    0x00, 0x00, 0x00, 0x90, 0x90, 0x90, 0x70, 0x00,  // 'u'
+    0x00, 0x00, 0x00, 0x50, 0x50, 0x50, 0x20, 0x00,  // 'v'
+    0x00, 0x00, 0x00, 0x88, 0xA8, 0xA8, 0x50, 0x00,  // 'w'
+    0x00, 0x00, 0x00, 0x90, 0x60, 0x60, 0x90, 0x00,  // 'x'
+    0x00, 0x00, 0x00, 0x90, 0x90, 0x70, 0x90, 0x60,  // 'y'
+    0x00, 0x00, 0x00, 0xF0, 0x20, 0x40, 0xF0, 0x00,  // 'z'
+    0x30, 0x40, 0x20, 0xC0, 0x20, 0x40, 0x30, 0x00,  // '{'
+    0x00, 0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x00,  // '|'
+    0xC0, 0x20, 0x40, 0x30, 0x40, 0x20, 0xC0, 0x00,  // '}'
+    0x00, 0x50, 0xA0, 0x00, 0x00, 0x00, 0x00, 0x00,  // '~'
+];
+
+// X11 misc-fixed 4x6, as bundled with embedded-graphics
+const GLYPHS_4X6: [u8; 95 * 6] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // ' '
+    0x40, 0x40, 0x40, 0x00, 0x40, 0x00,  // '!'
+    0xA0, 0xA0, 0x00, 0x00, 0x00, 0x00,  // '"'
+    0xA0, 0xF0, 0xA0, 0xF0, 0xA0, 0x00,  // '#'
+    0x40, 0xE0, 0xC0, 0x20, 0xE0, 0x40,  // '$'
+    0x80, 0x20, 0x40, 0x80, 0x20, 0x00,  // '%'
+    0x40, 0xA0, 0x40, 0xA0, 0x50, 0x00,  // '&'
+    0x40, 0x40, 0x00, 0x00, 0x00, 0x00,  // '''
+    0x20, 0x40, 0x40, 0x40, 0x40, 0x20,  // '('
+    0x80, 0x40, 0x40, 0x40, 0x40, 0x80,  // ')'
+    0xA0, 0x40, 0xE0, 0x40, 0xA0, 0x00,  // '*'
+    0x40, 0x40, 0xE0, 0x40, 0x40, 0x00,  // '+'
+    0x00, 0x00, 0x00, 0x00, 0x40, 0x80,  // ','
+    0x00, 0x00, 0xE0, 0x00, 0x00, 0x00,  // '-'
+    0x00, 0x00, 0x00, 0x00, 0x40, 0x00,  // '.'
+    0x20, 0x20, 0x40, 0x80, 0x80, 0x00,  // '/'
+    0x40, 0xA0, 0xE0, 0xA0, 0x40, 0x00,  // '0'
+    0x40, 0xC0, 0x40, 0x40, 0xE0, 0x00,  // '1'
+    0x40, 0xA0, 0x20, 0x40, 0xE0, 0x00,  // '2'
+    0xE0, 0x20, 0x40, 0x20, 0xC0, 0x00,  // '3'
+    0xA0, 0xA0, 0xE0, 0x20, 0x20, 0x00,  // '4'
+    0xE0, 0x80, 0xC0, 0x20, 0xC0, 0x00,  // '5'
+    0x60, 0x80, 0xC0, 0xA0, 0x40, 0x00,  // '6'
+    0xE0, 0x20, 0x40, 0x80, 0x80, 0x00,  // '7'
+    0x60, 0xA0, 0x40, 0xA0, 0xC0, 0x00,  // '8'
+    0x40, 0xA0, 0x60, 0x20, 0xC0, 0x00,  // '9'
+    0x00, 0x40, 0x00, 0x00, 0x40, 0x00,  // ':'
+    0x00, 0x40, 0x00, 0x00, 0x40, 0x80,  // ';'
+    0x20, 0x40, 0x80, 0x40, 0x20, 0x00,  // '<'
+    0x00, 0xE0, 0x00, 0xE0, 0x00, 0x00,  // '='
+    0x80, 0x40, 0x20, 0x40, 0x80, 0x00,  // '>'
+    0xC0, 0x20, 0x40, 0x00, 0x40, 0x00,  // '?'
+    0x60, 0xA0, 0xA0, 0x80, 0x60, 0x00,  // '@'
+    0x40, 0xA0, 0xE0, 0xA0, 0xA0, 0x00,  // 'A'
+    0xC0, 0xA0, 0xC0, 0xA0, 0xC0, 0x00,  // 'B'
+    0x40, 0xA0, 0x80, 0xA0, 0x40, 0x00,  // 'C'
+    0xC0, 0xA0, 0xA0, 0xA0, 0xC0, 0x00,  // 'D'
+    0xE0, 0x80, 0xC0, 0x80, 0xE0, 0x00,  // 'E'
+    0xE0, 0x80, 0xC0, 0x80, 0x80, 0x00,  // 'F'
+    0x60, 0x80, 0xA0, 0xA0, 0x60, 0x00,  // 'G'
+    0xA0, 0xA0, 0xE0, 0xA0, 0xA0, 0x00,  // 'H'
+    0xE0, 0x40, 0x40, 0x40, 0xE0, 0x00,  // 'I'
+    0x20, 0x20, 0x20, 0xA0, 0x40, 0x00,  // 'J'
+    0xA0, 0xA0, 0xC0, 0xA0, 0xA0, 0x00,  // 'K'
+    0x80, 0x80, 0x80, 0x80, 0xE0, 0x00,  // 'L'
+    0xA0, 0xE0, 0xE0, 0xA0, 0xA0, 0x00,  // 'M'
+    0x20, 0xA0, 0xE0, 0xA0, 0x80, 0x00,  // 'N'
+    0x40, 0xA0, 0xA0, 0xA0, 0x40, 0x00,  // 'O'
+    0xC0, 0xA0, 0xC0, 0x80, 0x80, 0x00,  // 'P'
+    0x40, 0xA0, 0xA0, 0xA0, 0x40, 0x20,  // 'Q'
+    0xC0, 0xA0, 0xC0, 0xA0, 0xA0, 0x00,  // 'R'
+    0x60, 0x80, 0x40, 0x20, 0xC0, 0x00,  // 'S'
+    0xE0, 0x40, 0x40, 0x40, 0x40, 0x00,  // 'T'
+    0xA0, 0xA0, 0xA0, 0xA0, 0xE0, 0x00,  // 'U'
+    0xA0, 0xA0, 0xA0, 0xE0, 0x40, 0x00,  // 'V'
+    0xA0, 0xA0, 0xE0, 0xE0, 0xA0, 0x00,  // 'W'
+    0xA0, 0xA0, 0x40, 0xA0, 0xA0, 0x00,  // 'X'
+    0xA0, 0xA0, 0x40, 0x40, 0x40, 0x00,  // 'Y'
+    0xE0, 0x20, 0x40, 0x80, 0xE0, 0x00,  // 'Z'
+    0x60, 0x40, 0x40, 0x40, 0x60, 0x00,  // '['
+    0x80, 0x80, 0x40, 0x20, 0x20, 0x00,  // '\'
+    0xC0, 0x40, 0x40, 0x40, 0xC0, 0x00,  // ']'
+    0x40, 0xA0, 0x00, 0x00, 0x00, 0x00,  // '^'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0xE0,  // '_'
+    0x40, 0x20, 0x00, 0x00, 0x00, 0x00,  // '`'
+    0x00, 0x60, 0xA0, 0xA0, 0x60, 0x00,  // 'a'
+    0x80, 0xC0, 0xA0, 0xA0, 0xC0, 0x00,  // 'b'
+    0x00, 0x60, 0x80, 0x80, 0x60, 0x00,  // 'c'
+    0x20, 0x60, 0xA0, 0xA0, 0x60, 0x00,  // 'd'
+    0x00, 0x40, 0xA0, 0xC0, 0x60, 0x00,  // 'e'
+    0x20, 0x40, 0xE0, 0x40, 0x40, 0x00,  // 'f'
+    0x00, 0x60, 0xA0, 0x60, 0x20, 0xC0,  // 'g'
+    0x80, 0xC0, 0xA0, 0xA0, 0xA0, 0x00,  // 'h'
+    0x40, 0x00, 0xC0, 0x40, 0xE0, 0x00,  // 'i'
+    0x20, 0x00, 0x20, 0x20, 0x20, 0xC0,  // 'j'
+    0x80, 0xA0, 0xC0, 0xA0, 0xA0, 0x00,  // 'k'
+    0xC0, 0x40, 0x40, 0x40, 0xE0, 0x00,  // 'l'
+    0x00, 0xA0, 0xE0, 0xA0, 0xA0, 0x00,  // 'm'
+    0x00, 0xC0, 0xA0, 0xA0, 0xA0, 0x00,  // 'n'
+    0x00, 0x40, 0xA0, 0xA0, 0x40, 0x00,  // 'o'
+    0x00, 0xC0, 0xA0, 0xC0, 0x80, 0x80,  // 'p'
+    0x00, 0x60, 0xA0, 0xA0, 0x60, 0x20,  // 'q'
+    0x00, 0xA0, 0xC0, 0x80, 0x80, 0x00,  // 'r'
+    0x00, 0x60, 0xC0, 0x20, 0xC0, 0x00,  // 's'
+    0x40, 0xE0, 0x40, 0x40, 0x20, 0x00,  // 't'
+    0x00, 0xA0, 0xA0, 0xA0, 0x60, 0x00,  // 'u'
+    0x00, 0xA0, 0xA0, 0xA0, 0x40, 0x00,  // 'v'
+    0x00, 0xA0, 0xA0, 0xE0, 0xA0, 0x00,  // 'w'
+    0x00, 0xA0, 0x40, 0x40, 0xA0, 0x00,  // 'x'
+    0x00, 0xA0, 0xA0, 0x60, 0x20, 0xC0,  // 'y'
+    0x00, 0xE0, 0x20, 0x40, 0xE0, 0x00,  // 'z'
+    0x20, 0x40, 0xC0, 0x40, 0x40, 0x20,  // '{'
+    0x40, 0x40, 0x40, 0x40, 0x40, 0x00,  // '|'
+    0x80, 0x40, 0x60, 0x40, 0x40, 0x80,  // '}'
+    0x50, 0xA0, 0x00, 0x00, 0x00, 0x00,  // '~'
+];
+
+// X11 misc-fixed 8x13 with a blank row above and two below
+const GLYPHS_8X16: [u8; 95 * 16] = [
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // ' '
+    0x00, 0x00, 0x00, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00,  // '!'
+    0x00, 0x00, 0x00, 0x24, 0x24, 0x24, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // '"'
+    0x00, 0x00, 0x00, 0x00, 0x24, 0x24, 0x7E, 0x24, 0x7E, 0x24, 0x24, 0x00, 0x00, 0x00, 0x00, 0x00,  // '#'
+    0x00, 0x00, 0x00, 0x10, 0x3C, 0x50, 0x50, 0x38, 0x14, 0x14, 0x78, 0x10, 0x00, 0x00, 0x00, 0x00,  // '$'
+    0x00, 0x00, 0x00, 0x22, 0x52, 0x24, 0x08, 0x08, 0x10, 0x24, 0x2A, 0x44, 0x00, 0x00, 0x00, 0x00,  // '%'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x30, 0x48, 0x48, 0x30, 0x4A, 0x44, 0x3A, 0x00, 0x00, 0x00, 0x00,  // '&'
+    0x00, 0x00, 0x00, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // '''
+    0x00, 0x00, 0x00, 0x04, 0x08, 0x08, 0x10, 0x10, 0x10, 0x08, 0x08, 0x04, 0x00, 0x00, 0x00, 0x00,  // '('
+    0x00, 0x00, 0x00, 0x20, 0x10, 0x10, 0x08, 0x08, 0x08, 0x10, 0x10, 0x20, 0x00, 0x00, 0x00, 0x00,  // ')'
+    0x00, 0x00, 0x00, 0x24, 0x18, 0x7E, 0x18, 0x24, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // '*'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x10, 0x7C, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // '+'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x38, 0x30, 0x40, 0x00, 0x00, 0x00,  // ','
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // '-'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x38, 0x10, 0x00, 0x00, 0x00,  // '.'
+    0x00, 0x00, 0x00, 0x02, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x80, 0x00, 0x00, 0x00, 0x00,  // '/'
+    0x00, 0x00, 0x00, 0x18, 0x24, 0x42, 0x42, 0x42, 0x42, 0x42, 0x24, 0x18, 0x00, 0x00, 0x00, 0x00,  // '0'
+    0x00, 0x00, 0x00, 0x10, 0x30, 0x50, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7C, 0x00, 0x00, 0x00, 0x00,  // '1'
+    0x00, 0x00, 0x00, 0x3C, 0x42, 0x42, 0x02, 0x04, 0x18, 0x20, 0x40, 0x7E, 0x00, 0x00, 0x00, 0x00,  // '2'
+    0x00, 0x00, 0x00, 0x7E, 0x02, 0x04, 0x08, 0x1C, 0x02, 0x02, 0x42, 0x3C, 0x00, 0x00, 0x00, 0x00,  // '3'
+    0x00, 0x00, 0x00, 0x04, 0x0C, 0x14, 0x24, 0x44, 0x44, 0x7E, 0x04, 0x04, 0x00, 0x00, 0x00, 0x00,  // '4'
+    0x00, 0x00, 0x00, 0x7E, 0x40, 0x40, 0x5C, 0x62, 0x02, 0x02, 0x42, 0x3C, 0x00, 0x00, 0x00, 0x00,  // '5'
+    0x00, 0x00, 0x00, 0x1C, 0x20, 0x40, 0x40, 0x5C, 0x62, 0x42, 0x42, 0x3C, 0x00, 0x00, 0x00, 0x00,  // '6'
+    0x00, 0x00, 0x00, 0x7E, 0x02, 0x04, 0x08, 0x08, 0x10, 0x10, 0x20, 0x20, 0x00, 0x00, 0x00, 0x00,  // '7'
+    0x00, 0x00, 0x00, 0x3C, 0x42, 0x42, 0x42, 0x3C, 0x42, 0x42, 0x42, 0x3C, 0x00, 0x00, 0x00, 0x00,  // '8'
+    0x00, 0x00, 0x00, 0x3C, 0x42, 0x42, 0x46, 0x3A, 0x02, 0x02, 0x04, 0x38, 0x00, 0x00, 0x00, 0x00,  // '9'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x38, 0x10, 0x00, 0x00, 0x10, 0x38, 0x10, 0x00, 0x00, 0x00,  // ':'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x38, 0x10, 0x00, 0x00, 0x38, 0x30, 0x40, 0x00, 0x00, 0x00,  // ';'
+    0x00, 0x00, 0x00, 0x02, 0x04, 0x08, 0x10, 0x20, 0x10, 0x08, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00,  // '<'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7E, 0x00, 0x00, 0x7E, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // '='
+    0x00, 0x00, 0x00, 0x40, 0x20, 0x10, 0x08, 0x04, 0x08, 0x10, 0x20, 0x40, 0x00, 0x00, 0x00, 0x00,  // '>'
+    0x00, 0x00, 0x00, 0x3C, 0x42, 0x42, 0x02, 0x04, 0x08, 0x08, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00,  // '?'
+    0x00, 0x00, 0x00, 0x3C, 0x42, 0x42, 0x4E, 0x52, 0x56, 0x4A, 0x40, 0x3C, 0x00, 0x00, 0x00, 0x00,  // '@'
+    0x00, 0x00, 0x00, 0x18, 0x24, 0x42, 0x42, 0x42, 0x7E, 0x42, 0x42, 0x42, 0x00, 0x00, 0x00, 0x00,  // 'A'
+    0x00, 0x00, 0x00, 0x78, 0x44, 0x42, 0x44, 0x78, 0x44, 0x42, 0x44, 0x78, 0x00, 0x00, 0x00, 0x00,  // 'B'
+    0x00, 0x00, 0x00, 0x3C, 0x42, 0x40, 0x40, 0x40, 0x40, 0x40, 0x42, 0x3C, 0x00, 0x00, 0x00, 0x00,  // 'C'
+    0x00, 0x00, 0x00, 0x78, 0x44, 0x42, 0x42, 0x42, 0x42, 0x42, 0x44, 0x78, 0x00, 0x00, 0x00, 0x00,  // 'D'
+    0x00, 0x00, 0x00, 0x7E, 0x40, 0x40, 0x40, 0x78, 0x40, 0x40, 0x40, 0x7E, 0x00, 0x00, 0x00, 0x00,  // 'E'
+    0x00, 0x00, 0x00, 0x7E, 0x40, 0x40, 0x40, 0x78, 0x40, 0x40, 0x40, 0x40, 0x00, 0x00, 0x00, 0x00,  // 'F'
+    0x00, 0x00, 0x00, 0x3C, 0x42, 0x40, 0x40, 0x40, 0x4E, 0x42, 0x46, 0x3A, 0x00, 0x00, 0x00, 0x00,  // 'G'
+    0x00, 0x00, 0x00, 0x42, 0x42, 0x42, 0x42, 0x7E, 0x42, 0x42, 0x42, 0x42, 0x00, 0x00, 0x00, 0x00,  // 'H'
+    0x00, 0x00, 0x00, 0x7C, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7C, 0x00, 0x00, 0x00, 0x00,  // 'I'
+    0x00, 0x00, 0x00, 0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x44, 0x38, 0x00, 0x00, 0x00, 0x00,  // 'J'
+    0x00, 0x00, 0x00, 0x42, 0x44, 0x48, 0x50, 0x60, 0x50, 0x48, 0x44, 0x42, 0x00, 0x00, 0x00, 0x00,  // 'K'
+    0x00, 0x00, 0x00, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x40, 0x7E, 0x00, 0x00, 0x00, 0x00,  // 'L'
+    0x00, 0x00, 0x00, 0x82, 0x82, 0xC6, 0xAA, 0x92, 0x92, 0x82, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00,  // 'M'
+    0x00, 0x00, 0x00, 0x42, 0x42, 0x62, 0x52, 0x4A, 0x46, 0x42, 0x42, 0x42, 0x00, 0x00, 0x00, 0x00,  // 'N'
+    0x00, 0x00, 0x00, 0x3C, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x3C, 0x00, 0x00, 0x00, 0x00,  // 'O'
+    0x00, 0x00, 0x00, 0x7C, 0x42, 0x42, 0x42, 0x7C, 0x40, 0x40, 0x40, 0x40, 0x00, 0x00, 0x00, 0x00,  // 'P'
+    0x00, 0x00, 0x00, 0x3C, 0x42, 0x42, 0x42, 0x42, 0x42, 0x52, 0x4A, 0x3C, 0x02, 0x00, 0x00, 0x00,  // 'Q'
+    0x00, 0x00, 0x00, 0x7C, 0x42, 0x42, 0x42, 0x7C, 0x50, 0x48, 0x44, 0x42, 0x00, 0x00, 0x00, 0x00,  // 'R'
+    0x00, 0x00, 0x00, 0x3C, 0x42, 0x40, 0x40, 0x3C, 0x02, 0x02, 0x42, 0x3C, 0x00, 0x00, 0x00, 0x00,  // 'S'
+    0x00, 0x00, 0x00, 0xFE, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00,  // 'T'
+    0x00, 0x00, 0x00, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x42, 0x3C, 0x00, 0x00, 0x00, 0x00,  // 'U'
+    0x00, 0x00, 0x00, 0x82, 0x82, 0x44, 0x44, 0x44, 0x28, 0x28, 0x28, 0x10, 0x00, 0x00, 0x00, 0x00,  // 'V'
+    0x00, 0x00, 0x00, 0x82, 0x82, 0x82, 0x82, 0x92, 0x92, 0x92, 0xAA, 0x44, 0x00, 0x00, 0x00, 0x00,  // 'W'
+    0x00, 0x00, 0x00, 0x82, 0x82, 0x44, 0x28, 0x10, 0x28, 0x44, 0x82, 0x82, 0x00, 0x00, 0x00, 0x00,  // 'X'
+    0x00, 0x00, 0x00, 0x82, 0x82, 0x44, 0x28, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00,  // 'Y'
+    0x00, 0x00, 0x00, 0x7E, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x40, 0x7E, 0x00, 0x00, 0x00, 0x00,  // 'Z'
+    0x00, 0x00, 0x00, 0x3C, 0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x20, 0x3C, 0x00, 0x00, 0x00, 0x00,  // '['
+    0x00, 0x00, 0x00, 0x80, 0x80, 0x40, 0x20, 0x10, 0x08, 0x04, 0x02, 0x02, 0x00, 0x00, 0x00, 0x00,  // '\'
+    0x00, 0x00, 0x00, 0x78, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x08, 0x78, 0x00, 0x00, 0x00, 0x00,  // ']'
+    0x00, 0x00, 0x00, 0x10, 0x28, 0x44, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // '^'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFE, 0x00, 0x00, 0x00,  // '_'
+    0x00, 0x00, 0x10, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // '`'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x3C, 0x02, 0x3E, 0x42, 0x46, 0x3A, 0x00, 0x00, 0x00, 0x00,  // 'a'
+    0x00, 0x00, 0x00, 0x40, 0x40, 0x40, 0x5C, 0x62, 0x42, 0x42, 0x62, 0x5C, 0x00, 0x00, 0x00, 0x00,  // 'b'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x3C, 0x42, 0x40, 0x40, 0x42, 0x3C, 0x00, 0x00, 0x00, 0x00,  // 'c'
+    0x00, 0x00, 0x00, 0x02, 0x02, 0x02, 0x3A, 0x46, 0x42, 0x42, 0x46, 0x3A, 0x00, 0x00, 0x00, 0x00,  // 'd'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x3C, 0x42, 0x7E, 0x40, 0x42, 0x3C, 0x00, 0x00, 0x00, 0x00,  // 'e'
+    0x00, 0x00, 0x00, 0x1C, 0x22, 0x20, 0x20, 0x7C, 0x20, 0x20, 0x20, 0x20, 0x00, 0x00, 0x00, 0x00,  // 'f'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x3A, 0x44, 0x44, 0x38, 0x40, 0x3C, 0x42, 0x3C, 0x00, 0x00,  // 'g'
+    0x00, 0x00, 0x00, 0x40, 0x40, 0x40, 0x5C, 0x62, 0x42, 0x42, 0x42, 0x42, 0x00, 0x00, 0x00, 0x00,  // 'h'
+    0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x30, 0x10, 0x10, 0x10, 0x10, 0x7C, 0x00, 0x00, 0x00, 0x00,  // 'i'
+    0x00, 0x00, 0x00, 0x00, 0x04, 0x00, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x44, 0x44, 0x38, 0x00, 0x00,  // 'j'
+    0x00, 0x00, 0x00, 0x40, 0x40, 0x40, 0x44, 0x48, 0x70, 0x48, 0x44, 0x42, 0x00, 0x00, 0x00, 0x00,  // 'k'
+    0x00, 0x00, 0x00, 0x30, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x7C, 0x00, 0x00, 0x00, 0x00,  // 'l'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xEC, 0x92, 0x92, 0x92, 0x92, 0x82, 0x00, 0x00, 0x00, 0x00,  // 'm'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x5C, 0x62, 0x42, 0x42, 0x42, 0x42, 0x00, 0x00, 0x00, 0x00,  // 'n'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x3C, 0x42, 0x42, 0x42, 0x42, 0x3C, 0x00, 0x00, 0x00, 0x00,  // 'o'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x5C, 0x62, 0x42, 0x62, 0x5C, 0x40, 0x40, 0x40, 0x00, 0x00,  // 'p'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x3A, 0x46, 0x42, 0x46, 0x3A, 0x02, 0x02, 0x02, 0x00, 0x00,  // 'q'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x5C, 0x22, 0x20, 0x20, 0x20, 0x20, 0x00, 0x00, 0x00, 0x00,  // 'r'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x3C, 0x42, 0x30, 0x0C, 0x42, 0x3C, 0x00, 0x00, 0x00, 0x00,  // 's'
+    0x00, 0x00, 0x00, 0x00, 0x20, 0x20, 0x7C, 0x20, 0x20, 0x20, 0x22, 0x1C, 0x00, 0x00, 0x00, 0x00,  // 't'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x44, 0x44, 0x44, 0x44, 0x44, 0x3A, 0x00, 0x00, 0x00, 0x00,  // 'u'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x44, 0x44, 0x44, 0x28, 0x28, 0x10, 0x00, 0x00, 0x00, 0x00,  // 'v'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x82, 0x82, 0x92, 0x92, 0xAA, 0x44, 0x00, 0x00, 0x00, 0x00,  // 'w'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x42, 0x24, 0x18, 0x18, 0x24, 0x42, 0x00, 0x00, 0x00, 0x00,  // 'x'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x42, 0x42, 0x42, 0x46, 0x3A, 0x02, 0x42, 0x3C, 0x00, 0x00,  // 'y'
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7E, 0x04, 0x08, 0x10, 0x20, 0x7E, 0x00, 0x00, 0x00, 0x00,  // 'z'
+    0x00, 0x00, 0x00, 0x0E, 0x10, 0x10, 0x08, 0x30, 0x08, 0x10, 0x10, 0x0E, 0x00, 0x00, 0x00, 0x00,  // '{'
+    0x00, 0x00, 0x00, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x00, 0x00, 0x00, 0x00,  // '|'
+    0x00, 0x00, 0x00, 0x70, 0x08, 0x08, 0x10, 0x0C, 0x10, 0x08, 0x08, 0x70, 0x00, 0x00, 0x00, 0x00,  // '}'
+    0x00, 0x00, 0x00, 0x24, 0x54, 0x48, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,  // '~'
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static FONTS: [&Font; 3] = [&FONT_4X6, &FONT_6X8, &FONT_8X16];
+
+    fn glyph(font: &Font, c: u8) -> Vec<u8> {
+        (0..font.height).map(|y| font.glyph_row(c, y)).collect()
+    }
+
+    #[test]
+    fn glyphs_fit_their_cell() {
+        for font in FONTS.iter() {
+            let outside = (0xFF >> font.width) as u8;
+            for c in 0x20..=0x7E {
+                let rows = glyph(font, c);
+                assert!(rows.iter().all(|row| row & outside == 0), "'{}' {}x{}", c as char, font.width, font.height);
+            }
+        }
+    }
+
+    #[test]
+    fn printable_characters_are_drawn() {
+        for font in FONTS.iter() {
+            assert!(glyph(font, b' ').iter().all(|&row| row == 0));
+            for c in 0x21..=0x7E {
+                let rows = glyph(font, c);
+                assert!(rows.iter().any(|&row| row != 0), "'{}' {}x{}", c as char, font.width, font.height);
+            }
+            // glyphs are in ASCII order
+            assert_ne!(glyph(font, b'A'), glyph(font, b'B'));
+            assert_ne!(glyph(font, b'~'), glyph(font, b'?'));
+        }
+    }
+
+    #[test]
+    fn other_characters_are_question_marks() {
+        for font in FONTS.iter() {
+            let question = glyph(font, b'?');
+            for &c in [0x00, b'\t', 0x1F, 0x7F, 0x80, 0xFF].iter() {
+                assert_eq!(glyph(font, c), question);
+            }
+        }
+    }
+
+    #[test]
+    fn sizes() {
+        for v in 0..3 {
+            let font = FontSize::from_u8(v).unwrap().font();
+            assert_eq!(FontSize::from_u8(v).map(|size| size as u8), Some(v));
+            assert!(font.width <= 8);
+        }
+        assert_eq!(FontSize::from_u8(3), None);
+        assert_eq!((FontSize::Small.font().width, FontSize::Small.font().height), (4, 6));
+        assert_eq!((FontSize::Large.font().width, FontSize::Large.font().height), (8, 16));
+    }
+}
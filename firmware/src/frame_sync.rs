@@ -0,0 +1,116 @@
+//! Rendering in step with the panel refresh
+//!
+//! The OLED pulses `FRAME_SYNC` (PA15) at the start of every panel frame. Lines
+//! drawn while the panel scans the same rows show up half old and half new, so a
+//! frame made by the timer is held until the next sync edge and pushed right
+//! after it, a whole panel frame ahead of the scan. Without edges, the panel not
+//! connected or not driving the pin, frames are pushed by the timer right away.
+
+/// Sync is lost without an edge for this long, a few frames at the slowest
+/// panel refresh
+pub const SYNC_TIMEOUT_MS: u32 = 50;
+
+pub struct FrameSync {
+    /// Time of the last sync edge
+    last_edge_ms: Option<u32>,
+    /// A frame waits for the next edge
+    pending: bool,
+}
+
+impl FrameSync {
+    pub const fn new() -> Self {
+        Self {
+            last_edge_ms: None,
+            pending: false,
+        }
+    }
+
+    /// Sync edges came in lately
+    pub fn is_synced(&self, now_ms: u32) -> bool {
+        self.last_edge_ms
+            .is_some_and(|edge| now_ms.wrapping_sub(edge) < SYNC_TIMEOUT_MS)
+    }
+
+    /// A new frame is ready at `now_ms`, returns true when it is pushed right
+    /// away, false when it waits for the next edge
+    pub fn frame(&mut self, now_ms: u32) -> bool {
+        let synced = self.is_synced(now_ms);
+        self.pending = synced;
+        !synced
+    }
+
+    /// Sync edge at `now_ms`, returns true when a frame is to be pushed now
+    pub fn edge(&mut self, now_ms: u32) -> bool {
+        self.last_edge_ms = Some(now_ms);
+        core::mem::replace(&mut self.pending, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn without_edges_frames_go_right_away() {
+        let mut sync = FrameSync::new();
+        assert!(!sync.is_synced(0));
+        assert!(sync.frame(0));
+        assert!(sync.frame(1_000_000));
+    }
+
+    #[test]
+    fn frames_wait_for_the_next_edge() {
+        let mut sync = FrameSync::new();
+        // no frame waiting
+        assert!(!sync.edge(0));
+        assert!(sync.is_synced(0));
+        assert!(!sync.frame(10));
+        assert!(sync.edge(16));
+        // pushed once
+        assert!(!sync.edge(33));
+    }
+
+    #[test]
+    fn newer_frames_replace_the_waiting_one() {
+        let mut sync = FrameSync::new();
+        sync.edge(0);
+        assert!(!sync.frame(5));
+        assert!(!sync.frame(10));
+        assert!(sync.edge(16));
+        assert!(!sync.edge(33));
+    }
+
+    #[test]
+    fn sync_is_lost_without_edges() {
+        let mut sync = FrameSync::new();
+        sync.edge(100);
+        assert!(sync.is_synced(100 + SYNC_TIMEOUT_MS - 1));
+        assert!(!sync.is_synced(100 + SYNC_TIMEOUT_MS));
+        // frames go right away, nothing waits for the edges when they are back
+        assert!(sync.frame(100 + SYNC_TIMEOUT_MS));
+        assert!(!sync.edge(200));
+        assert!(!sync.frame(210));
+        assert!(sync.edge(216));
+    }
+
+    #[test]
+    fn waiting_frame_goes_when_sync_is_lost() {
+        let mut sync = FrameSync::new();
+        sync.edge(0);
+        assert!(!sync.frame(10));
+        // the panel stopped, the timer pushes the next frame itself
+        assert!(sync.frame(SYNC_TIMEOUT_MS));
+        // and a late edge has nothing to push
+        assert!(!sync.edge(SYNC_TIMEOUT_MS + 1));
+    }
+
+    #[test]
+    fn millisecond_count_wraps() {
+        let mut sync = FrameSync::new();
+        sync.edge(u32::MAX - 5);
+        assert!(sync.is_synced(10));
+        assert!(!sync.is_synced(SYNC_TIMEOUT_MS - 6));
+        assert!(!sync.frame(10));
+        assert!(sync.edge(11));
+    }
+}
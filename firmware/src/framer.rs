@@ -0,0 +1,439 @@
+//! Packet framing for binary links
+//!
+//! Streaming SLIP (RFC 1055) and COBS decoders. Bytes are pushed one at a time,
+//! so packets can arrive in chunks of any size. A decoder hands out a packet when
+//! its end delimiter comes in; the packet stays valid until the next push.
+
+use core::fmt;
+
+use protocol::cobs;
+
+use crate::crc::{crc16_ccitt, crc32};
+
+/// Longest packet, longer packets are dropped
+pub const MAX_PACKET: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PacketError {
+    /// More than `MAX_PACKET` bytes
+    TooLong,
+    /// SLIP escape byte not followed by an escaped END or ESC
+    Escape,
+    /// COBS packet ended inside a block
+    Truncated,
+}
+
+/// Checksum at the end of a packet, sent low byte first
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PacketCrc {
+    None = 0,
+    /// CRC-16/CCITT-FALSE
+    Crc16 = 1,
+    /// CRC-32 (IEEE)
+    Crc32 = 2,
+}
+
+impl PacketCrc {
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(PacketCrc::None),
+            1 => Some(PacketCrc::Crc16),
+            2 => Some(PacketCrc::Crc32),
+            _ => None,
+        }
+    }
+
+    fn len(self) -> usize {
+        match self {
+            PacketCrc::None => 0,
+            PacketCrc::Crc16 => 2,
+            PacketCrc::Crc32 => 4,
+        }
+    }
+
+    /// Split `packet` into payload and the result of the CRC check, None without CRC
+    pub fn check(self, packet: &[u8]) -> (&[u8], Option<bool>) {
+        if self == PacketCrc::None {
+            return (packet, None);
+        }
+        if packet.len() < self.len() {
+            return (packet, Some(false));
+        }
+
+        let (payload, crc) = packet.split_at(packet.len() - self.len());
+        let ok = match self {
+            PacketCrc::Crc16 => crc16_ccitt(payload) == u16::from_le_bytes([crc[0], crc[1]]),
+            PacketCrc::Crc32 => {
+                crc32(payload) == u32::from_le_bytes([crc[0], crc[1], crc[2], crc[3]])
+            }
+            PacketCrc::None => true,
+        };
+        (payload, Some(ok))
+    }
+}
+
+/// Packet collected by a decoder
+struct Buffer {
+    data: [u8; MAX_PACKET],
+    len: usize,
+    error: Option<PacketError>,
+    /// The packet was handed out, start over on the next byte
+    done: bool,
+}
+
+impl Buffer {
+    const fn new() -> Self {
+        Self {
+            data: [0; MAX_PACKET],
+            len: 0,
+            error: None,
+            done: false,
+        }
+    }
+
+    fn start(&mut self) {
+        if self.done {
+            self.len = 0;
+            self.error = None;
+            self.done = false;
+        }
+    }
+
+    fn push(&mut self, b: u8) {
+        if self.len == MAX_PACKET {
+            self.error = self.error.or(Some(PacketError::TooLong));
+            return;
+        }
+        self.data[self.len] = b;
+        self.len += 1;
+    }
+
+    fn fail(&mut self, error: PacketError) {
+        self.error = self.error.or(Some(error));
+    }
+
+    /// End of the packet. Empty packets are skipped.
+    fn finish(&mut self) -> Option<Result<&[u8], PacketError>> {
+        self.done = true;
+        match self.error {
+            Some(error) => Some(Err(error)),
+            None if self.len == 0 => None,
+            None => Some(Ok(&self.data[..self.len])),
+        }
+    }
+}
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+pub struct Slip {
+    buf: Buffer,
+    escaped: bool,
+}
+
+impl Slip {
+    pub const fn new() -> Self {
+        Self {
+            buf: Buffer::new(),
+            escaped: false,
+        }
+    }
+
+    pub fn push(&mut self, b: u8) -> Option<Result<&[u8], PacketError>> {
+        self.buf.start();
+
+        if b == SLIP_END {
+            if self.escaped {
+                self.buf.fail(PacketError::Escape);
+            }
+            self.escaped = false;
+            return self.buf.finish();
+        }
+
+        if self.escaped {
+            self.escaped = false;
+            match b {
+                SLIP_ESC_END => self.buf.push(SLIP_END),
+                SLIP_ESC_ESC => self.buf.push(SLIP_ESC),
+                _ => self.buf.fail(PacketError::Escape),
+            }
+        } else if b == SLIP_ESC {
+            self.escaped = true;
+        } else {
+            self.buf.push(b);
+        }
+        None
+    }
+}
+
+/// Packets end at a zero byte, the blocks are decoded as in the remote control
+/// protocol
+pub struct Cobs {
+    buf: Buffer,
+    blocks: cobs::Blocks,
+}
+
+impl Cobs {
+    pub const fn new() -> Self {
+        Self {
+            buf: Buffer::new(),
+            blocks: cobs::Blocks::new(),
+        }
+    }
+
+    pub fn push(&mut self, b: u8) -> Option<Result<&[u8], PacketError>> {
+        self.buf.start();
+
+        if b == 0 {
+            if self.blocks.end().is_err() {
+                self.buf.fail(PacketError::Truncated);
+            }
+            return self.buf.finish();
+        }
+
+        if let Some(b) = self.blocks.push(b) {
+            self.buf.push(b);
+        }
+        None
+    }
+}
+
+/// A packet to show as hex dump
+pub struct Packet<'a> {
+    /// Packet number
+    pub number: u32,
+    pub data: Result<&'a [u8], PacketError>,
+    pub crc: PacketCrc,
+}
+
+/// Bytes per line of the hex dump
+const DUMP_BYTES: usize = 12;
+
+/// A header line like "#12 16B crc ok", followed by hex dump lines
+impl fmt::Display for Packet<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{} ", self.number)?;
+        let data = match self.data {
+            Ok(data) => data,
+            Err(PacketError::TooLong) => return writeln!(f, "too long"),
+            Err(PacketError::Escape) => return writeln!(f, "bad escape"),
+            Err(PacketError::Truncated) => return writeln!(f, "truncated"),
+        };
+
+        let (payload, crc) = self.crc.check(data);
+        write!(f, "{}B", payload.len())?;
+        match crc {
+            Some(true) => writeln!(f, " crc ok")?,
+            Some(false) => writeln!(f, " CRC BAD")?,
+            None => writeln!(f)?,
+        }
+
+        for (i, chunk) in payload.chunks(DUMP_BYTES).enumerate() {
+            write!(f, "{:03X}:", i * DUMP_BYTES)?;
+            for b in chunk {
+                write!(f, " {:02X}", b)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slip_encode(payload: &[u8]) -> Vec<u8> {
+        let mut encoded = vec![SLIP_END];
+        for &b in payload {
+            match b {
+                SLIP_END => encoded.extend_from_slice(&[SLIP_ESC, SLIP_ESC_END]),
+                SLIP_ESC => encoded.extend_from_slice(&[SLIP_ESC, SLIP_ESC_ESC]),
+                b => encoded.push(b),
+            }
+        }
+        encoded.push(SLIP_END);
+        encoded
+    }
+
+    fn cobs_encode(payload: &[u8]) -> Vec<u8> {
+        // delimiter and the first code byte
+        let mut encoded = vec![0, 0];
+        let mut code_at = 1;
+        for &b in payload {
+            if b != 0 {
+                encoded.push(b);
+            }
+            if b == 0 || encoded.len() - code_at == 0xFF {
+                encoded[code_at] = (encoded.len() - code_at) as u8;
+                code_at = encoded.len();
+                encoded.push(0);
+            }
+        }
+        encoded[code_at] = (encoded.len() - code_at) as u8;
+        encoded.push(0);
+        encoded
+    }
+
+    /// Packets and errors decoded from `bytes`
+    fn decode<F>(bytes: &[u8], mut push: F) -> Vec<Result<Vec<u8>, PacketError>>
+    where
+        F: FnMut(u8) -> Option<Result<Vec<u8>, PacketError>>,
+    {
+        bytes.iter().filter_map(|&b| push(b)).collect()
+    }
+
+    fn slip(bytes: &[u8]) -> Vec<Result<Vec<u8>, PacketError>> {
+        let mut slip = Slip::new();
+        decode(bytes, |b| slip.push(b).map(|p| p.map(<[u8]>::to_vec)))
+    }
+
+    fn cobs(bytes: &[u8]) -> Vec<Result<Vec<u8>, PacketError>> {
+        let mut cobs = Cobs::new();
+        decode(bytes, |b| cobs.push(b).map(|p| p.map(<[u8]>::to_vec)))
+    }
+
+    /// Payloads of every length up to `MAX_PACKET`, heavy on delimiters and escapes
+    fn payloads() -> Vec<Vec<u8>> {
+        let mut seed = 0x1234_5678u32;
+        let mut next = move || {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            let special = [0x00, SLIP_END, SLIP_ESC, SLIP_ESC_END, SLIP_ESC_ESC, 0xFF];
+            match seed >> 29 {
+                0..=3 => special[(seed >> 8) as usize % special.len()],
+                _ => (seed >> 16) as u8,
+            }
+        };
+        (1..=MAX_PACKET).map(|len| (0..len).map(|_| next()).collect()).collect()
+    }
+
+    #[test]
+    fn slip_escapes_at_the_ends() {
+        let payload = [SLIP_END, 1, 2, SLIP_ESC];
+        assert_eq!(slip_encode(&payload), [0xC0, 0xDB, 0xDC, 1, 2, 0xDB, 0xDD, 0xC0]);
+        assert_eq!(slip(&slip_encode(&payload)), [Ok(payload.to_vec())]);
+        assert_eq!(slip(&[0xC0, 0xDB, 0xDC, 0xC0]), [Ok(vec![SLIP_END])]);
+    }
+
+    #[test]
+    fn slip_bad_escapes() {
+        // escape right before the end, the next packet is fine
+        assert_eq!(slip(&[0xC0, 1, 0xDB, 0xC0, 2, 0xC0]), [Err(PacketError::Escape), Ok(vec![2])]);
+        // escape of a byte that isn't escaped
+        assert_eq!(slip(&[1, 0xDB, 0x41, 2, 0xC0]), [Err(PacketError::Escape)]);
+        // a packet ending in ESC ESC_ESC is fine
+        assert_eq!(slip(&[1, 0xDB, 0xDD, 0xC0]), [Ok(vec![1, SLIP_ESC])]);
+    }
+
+    #[test]
+    fn empty_packets_are_skipped() {
+        assert_eq!(slip(&[0xC0, 0xC0, 0xC0, 7, 0xC0, 0xC0]), [Ok(vec![7])]);
+        assert_eq!(cobs(&[0, 0, 0x02, 7, 0, 0]), [Ok(vec![7])]);
+    }
+
+    #[test]
+    fn cobs_vectors() {
+        // from the COBS paper and its usual examples
+        let vectors: [(&[u8], &[u8]); 4] = [
+            (&[0x00], &[0x01, 0x01, 0x00]),
+            (&[0x00, 0x00], &[0x01, 0x01, 0x01, 0x00]),
+            (&[0x11, 0x22, 0x00, 0x33], &[0x03, 0x11, 0x22, 0x02, 0x33, 0x00]),
+            (&[0x11, 0x00, 0x00, 0x00], &[0x02, 0x11, 0x01, 0x01, 0x01, 0x00]),
+        ];
+        for (payload, encoded) in vectors.iter() {
+            assert_eq!(&cobs_encode(payload)[1..], *encoded);
+            assert_eq!(cobs(encoded), [Ok(payload.to_vec())]);
+        }
+    }
+
+    #[test]
+    fn cobs_full_blocks() {
+        // 254 non-zero bytes fill a block without a zero after it
+        let payload: Vec<u8> = (1..=0xFE).collect();
+        let encoded = cobs_encode(&payload);
+        assert_eq!(encoded[1], 0xFF);
+        assert_eq!(encoded.len(), payload.len() + 4);
+        assert_eq!(cobs(&encoded), [Ok(payload.clone())]);
+
+        // a zero right after a full block
+        let mut payload = payload;
+        payload.extend_from_slice(&[0, 5]);
+        assert_eq!(cobs(&cobs_encode(&payload)), [Ok(payload)]);
+    }
+
+    #[test]
+    fn cobs_truncated() {
+        assert_eq!(
+            cobs(&[0x05, 0x11, 0x22, 0x00, 0x02, 0x33, 0x00]),
+            [Err(PacketError::Truncated), Ok(vec![0x33])]
+        );
+        // bytes lost inside a block run the next packet into it
+        assert_eq!(cobs(&[0x05, 0x11, 0x22, 0x03, 0x33, 0x44, 0x00]), [Err(PacketError::Truncated)]);
+    }
+
+    #[test]
+    fn too_long() {
+        let payload = vec![0x55; MAX_PACKET];
+        assert_eq!(slip(&slip_encode(&payload)), [Ok(payload.clone())]);
+        assert_eq!(cobs(&cobs_encode(&payload)), [Ok(payload.clone())]);
+
+        let mut long = payload;
+        long.push(0x55);
+        let mut stream = slip_encode(&long);
+        stream.extend(slip_encode(&[1]));
+        assert_eq!(slip(&stream), [Err(PacketError::TooLong), Ok(vec![1])]);
+        let mut stream = cobs_encode(&long);
+        stream.extend(cobs_encode(&[1]));
+        assert_eq!(cobs(&stream), [Err(PacketError::TooLong), Ok(vec![1])]);
+    }
+
+    /// Back to back packets of any content come out as they went in
+    #[test]
+    fn round_trip() {
+        let payloads = payloads();
+        let expected: Vec<_> = payloads.iter().cloned().map(Ok).collect();
+        let slip_stream: Vec<u8> = payloads.iter().flat_map(|p| slip_encode(p)).collect();
+        assert_eq!(slip(&slip_stream), expected);
+        let cobs_stream: Vec<u8> = payloads.iter().flat_map(|p| cobs_encode(p)).collect();
+        assert_eq!(cobs(&cobs_stream), expected);
+    }
+
+    #[test]
+    fn crc_check() {
+        let mut packet = b"123456789".to_vec();
+        packet.extend_from_slice(&0x29B1u16.to_le_bytes());
+        assert_eq!(PacketCrc::Crc16.check(&packet), (&b"123456789"[..], Some(true)));
+        packet[0] ^= 1;
+        assert_eq!(PacketCrc::Crc16.check(&packet).1, Some(false));
+
+        let mut packet = b"123456789".to_vec();
+        packet.extend_from_slice(&0xCBF4_3926u32.to_le_bytes());
+        assert_eq!(PacketCrc::Crc32.check(&packet), (&b"123456789"[..], Some(true)));
+        // shorter than the CRC
+        assert_eq!(PacketCrc::Crc32.check(&[1, 2, 3]), (&[1, 2, 3][..], Some(false)));
+        assert_eq!(PacketCrc::None.check(&[1]), (&[1][..], None));
+    }
+
+    #[test]
+    fn hex_dump() {
+        let mut data: Vec<u8> = (0..14).collect();
+        data.extend_from_slice(&crc16_ccitt(&data).to_le_bytes());
+        let packet = Packet {
+            number: 12,
+            data: Ok(&data),
+            crc: PacketCrc::Crc16,
+        };
+        assert_eq!(
+            format!("{}", packet),
+            "#12 14B crc ok\n000: 00 01 02 03 04 05 06 07 08 09 0A 0B\n00C: 0C 0D\n"
+        );
+        let packet = Packet {
+            number: 13,
+            data: Err(PacketError::Escape),
+            crc: PacketCrc::None,
+        };
+        assert_eq!(format!("{}", packet), "#13 bad escape\n");
+    }
+}
@@ -0,0 +1,370 @@
+//! Screen layouts with a status bar and panes
+//!
+//! The full screen layout is the hardware scrolling terminal. The other layouts
+//! are drawn on a canvas: a status bar at the top and one or two panes below it.
+//! Every pane is drawn on a target cropped to its region, so it draws from (0, 0).
+//! A cropped target doesn't clip, text is clipped to the target by `draw_text`
+//! and graphs stay within the size of their target.
+
+use core::fmt::{self, Write};
+
+use arrayvec::ArrayString;
+use embedded_graphics::{
+    pixelcolor::{Gray4, GrayColor},
+    prelude::*,
+    primitives::{PrimitiveStyle, Rectangle},
+};
+
+use crate::font::Font;
+use crate::line::LineConfig;
+
+/// Height of the status bar in pixels
+pub const STATUS_HEIGHT: u32 = 8;
+const STATUS_BACKGROUND: u8 = 0x3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Layout {
+    /// Scrolling text on the whole screen
+    Full = 0,
+    /// Status bar above the view
+    Status = 1,
+    /// Status bar, received text above a plot
+    SplitPlot = 2,
+}
+
+impl Layout {
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(Layout::Full),
+            1 => Some(Layout::Status),
+            2 => Some(Layout::SplitPlot),
+            _ => None,
+        }
+    }
+
+    /// Regions of the status bar and panes on a screen of `size`
+    pub fn regions(self, size: Size) -> Regions {
+        let screen = Rectangle::new(Point::zero(), size);
+        let status = Rectangle::new(Point::zero(), Size::new(size.width, STATUS_HEIGHT));
+        let below = Rectangle::new(
+            Point::new(0, STATUS_HEIGHT as i32),
+            Size::new(size.width, size.height.saturating_sub(STATUS_HEIGHT)),
+        );
+
+        match self {
+            Layout::Full => Regions { status: None, top: screen, bottom: None },
+            Layout::Status => Regions { status: Some(status), top: below, bottom: None },
+            Layout::SplitPlot => {
+                let top = Size::new(size.width, below.size.height / 2);
+                let bottom = Size::new(size.width, below.size.height - top.height);
+                Regions {
+                    status: Some(status),
+                    top: Rectangle::new(below.top_left, top),
+                    bottom: Some(Rectangle::new(below.top_left + Point::new(0, top.height as i32), bottom)),
+                }
+            }
+        }
+    }
+}
+
+/// Where the parts of a layout go
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Regions {
+    pub status: Option<Rectangle>,
+    pub top: Rectangle,
+    pub bottom: Option<Rectangle>,
+}
+
+/// Receive errors since boot
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ErrorCounts {
+    pub overrun: u32,
+    pub framing: u32,
+    pub noise: u32,
+    pub parity: u32,
+}
+
+impl ErrorCounts {
+    pub fn total(&self) -> u32 {
+        self.overrun + self.framing + self.noise + self.parity
+    }
+}
+
+/// Content of the status bar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Status {
+    pub baudrate: u32,
+    pub line: LineConfig,
+    /// Name of the shown input, like "RX1"
+    pub channel: &'static str,
+    /// Battery voltage, None when unknown
+    pub battery_mv: Option<u16>,
+    pub recording: bool,
+    pub errors: ErrorCounts,
+    /// Seconds since boot, or of the day once the RTC is set
+    pub clock_s: u32,
+}
+
+impl Status {
+    /// "115200 8N1 RX1 E0", the error counts once there are errors
+    fn write_left<W: Write>(&self, w: &mut W) -> fmt::Result {
+        write!(w, "{} {} {}", self.baudrate, self.line, self.channel)?;
+        let errors = &self.errors;
+        if errors.total() == 0 {
+            w.write_str(" E0")
+        } else {
+            write!(w, " O{} F{} N{} P{}", errors.overrun, errors.framing, errors.noise, errors.parity)
+        }
+    }
+
+    /// Battery, recording state and clock
+    fn write_right<W: Write>(&self, w: &mut W) -> fmt::Result {
+        if let Some(mv) = self.battery_mv {
+            write!(w, "{}.{:02}V ", mv / 1000, mv % 1000 / 10)?;
+        }
+        if self.recording {
+            w.write_str("REC ")?;
+        }
+        let s = self.clock_s;
+        write!(w, "{:02}:{:02}:{:02}", s / 3600 % 100, s / 60 % 60, s % 60)
+    }
+
+    /// Draw the status bar on a target the size of its region
+    pub fn draw<D>(&self, target: &mut D, font: &Font) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray4>,
+    {
+        let area = target.bounding_box();
+        area.into_styled(PrimitiveStyle::with_fill(Gray4::new(STATUS_BACKGROUND)))
+            .draw(target)?;
+
+        let mut left = ArrayString::<[u8; 64]>::new();
+        self.write_left(&mut left).ok();
+        let mut right = ArrayString::<[u8; 32]>::new();
+        self.write_right(&mut right).ok();
+
+        draw_text(target, font, Point::zero(), left.as_bytes(), 0)?;
+
+        // the right part goes over the left one when they don't fit
+        let width = (right.len() * font.width as usize) as u32;
+        let right_area = Rectangle::new(
+            Point::new(area.size.width as i32 - width as i32, 0),
+            Size::new(width, area.size.height),
+        );
+        right_area
+            .into_styled(PrimitiveStyle::with_fill(Gray4::new(STATUS_BACKGROUND)))
+            .draw(target)?;
+        draw_text(target, font, right_area.top_left, right.as_bytes(), 0)
+    }
+}
+
+/// Draw `text` with its top left corner at `origin`. Characters with their bit set
+/// in `inverse` are drawn dark on a lit cell, other backgrounds are left as they are.
+/// Pixels outside of the target are left out.
+pub fn draw_text<D>(
+    target: &mut D,
+    font: &Font,
+    origin: Point,
+    text: &[u8],
+    inverse: u128,
+) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Gray4>,
+{
+    let width = font.width as i32;
+    let area = target.bounding_box();
+    let pixels = text.iter().enumerate().flat_map(move |(column, &c)| {
+        let inverted = column < 128 && inverse & (1 << column) != 0;
+        let x0 = origin.x + column as i32 * width;
+        (0..font.height).flat_map(move |y| {
+            let bits = font.glyph_row(c, y);
+            (0..font.width).filter_map(move |bit| {
+                let lit = bits & (0x80 >> bit) != 0;
+                let point = Point::new(x0 + bit as i32, origin.y + y as i32);
+                if !area.contains(point) {
+                    return None;
+                }
+                match (lit, inverted) {
+                    (true, false) => Some(Pixel(point, Gray4::WHITE)),
+                    (false, true) => Some(Pixel(point, Gray4::WHITE)),
+                    (true, true) => Some(Pixel(point, Gray4::BLACK)),
+                    (false, false) => None,
+                }
+            })
+        })
+    });
+    target.draw_iter(pixels)
+}
+
+/// Draw text lines bottom up on a target the size of the pane, `line(0)` is the
+/// bottom line. The top line is cut off when the pane is not a whole number of
+/// lines high.
+pub fn draw_lines<'a, D, F>(target: &mut D, font: &Font, line: F) -> Result<(), D::Error>
+where
+    D: DrawTarget<Color = Gray4>,
+    F: Fn(usize) -> Option<(&'a [u8], u128)>,
+{
+    let height = target.bounding_box().size.height as i32;
+    let mut y = height - font.height as i32;
+    let mut n = 0;
+    while y > -(font.height as i32) {
+        let (text, inverse) = match line(n) {
+            Some(line) => line,
+            None => break,
+        };
+        draw_text(target, font, Point::new(0, y), text, inverse)?;
+        y -= font.height as i32;
+        n += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::FONT_6X8;
+    use core::convert::Infallible;
+
+    /// Pixel levels of a screen
+    struct Screen {
+        size: Size,
+        pixels: Vec<u8>,
+    }
+
+    impl Screen {
+        fn new() -> Self {
+            Self {
+                size: Size::new(256, 64),
+                pixels: vec![0; 256 * 64],
+            }
+        }
+
+        /// Smallest rectangle around the pixels that aren't black
+        fn drawn(&self) -> Rectangle {
+            let lit = (0..self.pixels.len()).filter(|&i| self.pixels[i] != 0);
+            let points: Vec<Point> = lit.map(|i| Point::new(i as i32 % 256, i as i32 / 256)).collect();
+            let min = points.iter().fold(Point::new(i32::MAX, i32::MAX), |a, p| a.component_min(*p));
+            let max = points.iter().fold(Point::new(i32::MIN, i32::MIN), |a, p| a.component_max(*p));
+            Rectangle::with_corners(min, max)
+        }
+    }
+
+    impl OriginDimensions for Screen {
+        fn size(&self) -> Size {
+            self.size
+        }
+    }
+
+    impl DrawTarget for Screen {
+        type Color = Gray4;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Infallible>
+        where
+            I: IntoIterator<Item = Pixel<Gray4>>,
+        {
+            for Pixel(point, color) in pixels {
+                assert!(self.bounding_box().contains(point), "{:?} off the screen", point);
+                self.pixels[point.y as usize * 256 + point.x as usize] = color.luma();
+            }
+            Ok(())
+        }
+    }
+
+    fn status() -> Status {
+        Status {
+            baudrate: 115_200,
+            line: LineConfig::default(),
+            channel: "RX1",
+            battery_mv: Some(3_705),
+            recording: true,
+            errors: ErrorCounts::default(),
+            clock_s: 36 * 3600 + 5 * 60 + 9,
+        }
+    }
+
+    #[test]
+    fn regions() {
+        let size = Size::new(256, 64);
+        let full = Layout::Full.regions(size);
+        assert_eq!((full.status, full.top, full.bottom), (None, Rectangle::new(Point::zero(), size), None));
+
+        let status = Layout::Status.regions(size);
+        assert_eq!(status.status, Some(Rectangle::new(Point::zero(), Size::new(256, 8))));
+        assert_eq!(status.top, Rectangle::new(Point::new(0, 8), Size::new(256, 56)));
+
+        // odd heights go to the bottom pane
+        let split = Layout::SplitPlot.regions(Size::new(256, 63));
+        assert_eq!(split.top, Rectangle::new(Point::new(0, 8), Size::new(256, 27)));
+        assert_eq!(split.bottom, Some(Rectangle::new(Point::new(0, 35), Size::new(256, 28))));
+
+        // a screen lower than the status bar
+        assert_eq!(Layout::Status.regions(Size::new(256, 4)).top.size, Size::new(256, 0));
+    }
+
+    #[test]
+    fn status_text() {
+        let mut text = String::new();
+        status().write_left(&mut text).unwrap();
+        text.push('|');
+        status().write_right(&mut text).unwrap();
+        assert_eq!(text, "115200 8N1 RX1 E0|3.70V REC 36:05:09");
+
+        let mut status = Status {
+            battery_mv: None,
+            recording: false,
+            errors: ErrorCounts { overrun: 1, framing: 2, noise: 0, parity: 4 },
+            clock_s: 100 * 3600,
+            ..status()
+        };
+        status.line.parity = crate::line::Parity::Even;
+        let mut text = String::new();
+        status.write_left(&mut text).unwrap();
+        text.push('|');
+        status.write_right(&mut text).unwrap();
+        assert_eq!(text, "115200 8E1 RX1 O1 F2 N0 P4|00:00:00");
+    }
+
+    #[test]
+    fn status_bar_stays_in_its_region() {
+        let mut screen = Screen::new();
+        let regions = Layout::Status.regions(screen.size());
+        let status_area = regions.status.unwrap();
+        status().draw(&mut screen.cropped(&status_area), &FONT_6X8).unwrap();
+        assert_eq!(screen.drawn(), status_area);
+        // the clock is right aligned
+        let right_column = (0..8).map(|y| screen.pixels[y * 256 + 255]).collect::<Vec<_>>();
+        assert!(right_column.iter().all(|&level| level == STATUS_BACKGROUND || level == 15));
+    }
+
+    #[test]
+    fn panes_are_clipped() {
+        let mut screen = Screen::new();
+        let regions = Layout::SplitPlot.regions(screen.size());
+        let top = regions.top;
+        let long = [b'#'; 60];
+        draw_lines(&mut screen.cropped(&top), &FONT_6X8, |n| match n {
+            // wider than the pane
+            0 => Some((&long[..], 0)),
+            1 | 2 => Some((b"line", 0)),
+            // inverted, so its top rows are lit
+            3 => Some((b"cut off", !0)),
+            _ => None,
+        })
+        .unwrap();
+        let drawn = screen.drawn();
+        assert!(top.contains(drawn.top_left), "{:?} in {:?}", drawn, top);
+        assert!(top.contains(drawn.bottom_right().unwrap()), "{:?} in {:?}", drawn, top);
+        // 28 rows: three lines and half of the fourth
+        assert_eq!(drawn.top_left.y, top.top_left.y);
+        assert_eq!(drawn.bottom_right().unwrap().x, 255);
+    }
+
+    #[test]
+    fn inverse_text() {
+        let mut screen = Screen::new();
+        draw_text(&mut screen, &FONT_6X8, Point::new(6, 0), b"  ", 0b10).unwrap();
+        // only the second space is a lit cell
+        assert_eq!(screen.drawn(), Rectangle::new(Point::new(12, 0), Size::new(6, 8)));
+    }
+}
@@ -0,0 +1,261 @@
+//! UART line parameters
+//!
+//! Describes the character framing of the capture UART and maps it onto the
+//! STM32G0 USART. The USART counts the parity bit as part of the word, so a frame
+//! of data bits plus parity must be 7, 8 or 9 bits long.
+
+use core::fmt;
+
+/// Data bits per character, without parity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DataBits {
+    Bits6 = 6,
+    Bits7 = 7,
+    Bits8 = 8,
+    Bits9 = 9,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Parity {
+    None,
+    Even,
+    Odd,
+}
+
+/// Stop bits, the values are the USART_CR2 STOP field
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StopBits {
+    Stop1 = 0b00,
+    Stop0_5 = 0b01,
+    Stop2 = 0b10,
+    Stop1_5 = 0b11,
+}
+
+/// Why a `LineConfig` can not be used
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineError {
+    /// Data bits plus parity bit is not 7, 8 or 9 bits
+    WordLength,
+}
+
+/// Character framing of the capture UART
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LineConfig {
+    pub data_bits: DataBits,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    /// Idle low instead of idle high on RX
+    pub rx_invert: bool,
+    /// Send and receive the most significant bit first
+    pub msb_first: bool,
+    /// Swap the TX and RX pins
+    pub swap: bool,
+}
+
+impl Default for LineConfig {
+    /// 8N1
+    fn default() -> Self {
+        Self {
+            data_bits: DataBits::Bits8,
+            parity: Parity::None,
+            stop_bits: StopBits::Stop1,
+            rx_invert: false,
+            msb_first: false,
+            swap: false,
+        }
+    }
+}
+
+/// USART_CR1 bits
+pub const CR1_M0: u32 = 1 << 12;
+pub const CR1_PCE: u32 = 1 << 10;
+pub const CR1_PS: u32 = 1 << 9;
+pub const CR1_M1: u32 = 1 << 28;
+pub const CR1_MASK: u32 = CR1_M0 | CR1_M1 | CR1_PCE | CR1_PS;
+
+/// USART_CR2 bits
+pub const CR2_STOP_SHIFT: u32 = 12;
+pub const CR2_SWAP: u32 = 1 << 15;
+pub const CR2_RXINV: u32 = 1 << 16;
+pub const CR2_MSBFIRST: u32 = 1 << 19;
+pub const CR2_MASK: u32 = (0b11 << CR2_STOP_SHIFT) | CR2_SWAP | CR2_RXINV | CR2_MSBFIRST;
+
+impl LineConfig {
+    pub fn validate(&self) -> Result<(), LineError> {
+        match self.word_length() {
+            7..=9 => Ok(()),
+            _ => Err(LineError::WordLength),
+        }
+    }
+
+    /// Bits per word as seen by the USART, including parity
+    pub fn word_length(&self) -> u8 {
+        self.data_bits as u8 + (self.parity != Parity::None) as u8
+    }
+
+    /// Mask for the data bits of a received word
+    pub fn data_mask(&self) -> u16 {
+        (1 << self.data_bits as u16) - 1
+    }
+
+    /// USART_CR1 bits for this config, within `CR1_MASK`
+    pub fn cr1(&self) -> Result<u32, LineError> {
+        let m = match self.word_length() {
+            7 => CR1_M1,
+            8 => 0,
+            9 => CR1_M0,
+            _ => return Err(LineError::WordLength),
+        };
+        let parity = match self.parity {
+            Parity::None => 0,
+            Parity::Even => CR1_PCE,
+            Parity::Odd => CR1_PCE | CR1_PS,
+        };
+        Ok(m | parity)
+    }
+
+    /// USART_CR2 bits for this config, within `CR2_MASK`
+    pub fn cr2(&self) -> u32 {
+        let mut cr2 = (self.stop_bits as u32) << CR2_STOP_SHIFT;
+        if self.swap {
+            cr2 |= CR2_SWAP;
+        }
+        if self.rx_invert {
+            cr2 |= CR2_RXINV;
+        }
+        if self.msb_first {
+            cr2 |= CR2_MSBFIRST;
+        }
+        cr2
+    }
+}
+
+/// Short notation like "8N1" or "7E1.5"
+impl fmt::Display for LineConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parity = match self.parity {
+            Parity::None => 'N',
+            Parity::Even => 'E',
+            Parity::Odd => 'O',
+        };
+        let stop = match self.stop_bits {
+            StopBits::Stop1 => "1",
+            StopBits::Stop0_5 => "0.5",
+            StopBits::Stop2 => "2",
+            StopBits::Stop1_5 => "1.5",
+        };
+        write!(f, "{}{}{}", self.data_bits as u8, parity, stop)
+    }
+}
+
+impl DataBits {
+    pub fn from_bits(bits: u8) -> Option<Self> {
+        match bits {
+            6 => Some(DataBits::Bits6),
+            7 => Some(DataBits::Bits7),
+            8 => Some(DataBits::Bits8),
+            9 => Some(DataBits::Bits9),
+            _ => None,
+        }
+    }
+}
+
+impl Parity {
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(Parity::None),
+            1 => Some(Parity::Even),
+            2 => Some(Parity::Odd),
+            _ => None,
+        }
+    }
+}
+
+impl StopBits {
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0b00 => Some(StopBits::Stop1),
+            0b01 => Some(StopBits::Stop0_5),
+            0b10 => Some(StopBits::Stop2),
+            0b11 => Some(StopBits::Stop1_5),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(data_bits: DataBits, parity: Parity, stop_bits: StopBits) -> LineConfig {
+        LineConfig {
+            data_bits,
+            parity,
+            stop_bits,
+            ..LineConfig::default()
+        }
+    }
+
+    #[test]
+    fn word_lengths() {
+        let cases = [
+            (DataBits::Bits6, Parity::None, Err(LineError::WordLength)),
+            (DataBits::Bits6, Parity::Even, Ok(CR1_M1 | CR1_PCE)),
+            (DataBits::Bits7, Parity::None, Ok(CR1_M1)),
+            (DataBits::Bits7, Parity::Even, Ok(CR1_PCE)),
+            (DataBits::Bits8, Parity::None, Ok(0)),
+            (DataBits::Bits8, Parity::Odd, Ok(CR1_M0 | CR1_PCE | CR1_PS)),
+            (DataBits::Bits9, Parity::None, Ok(CR1_M0)),
+            (DataBits::Bits9, Parity::Even, Err(LineError::WordLength)),
+        ];
+        for &(data_bits, parity, cr1) in cases.iter() {
+            let config = line(data_bits, parity, StopBits::Stop1);
+            assert_eq!(config.cr1(), cr1, "{}", config);
+            assert_eq!(config.validate(), cr1.map(|_| ()), "{}", config);
+            assert_eq!(config.cr1().unwrap_or(0) & !CR1_MASK, 0);
+        }
+    }
+
+    #[test]
+    fn data_mask_leaves_out_parity() {
+        assert_eq!(line(DataBits::Bits7, Parity::Even, StopBits::Stop1).data_mask(), 0x7F);
+        assert_eq!(line(DataBits::Bits8, Parity::Even, StopBits::Stop1).data_mask(), 0xFF);
+        assert_eq!(line(DataBits::Bits9, Parity::None, StopBits::Stop1).data_mask(), 0x1FF);
+    }
+
+    #[test]
+    fn cr2_options() {
+        assert_eq!(LineConfig::default().cr2(), 0);
+        let config = LineConfig {
+            stop_bits: StopBits::Stop1_5,
+            rx_invert: true,
+            msb_first: true,
+            swap: true,
+            ..LineConfig::default()
+        };
+        assert_eq!(config.cr2(), CR2_MASK);
+        assert_eq!(line(DataBits::Bits8, Parity::None, StopBits::Stop2).cr2(), 0b10 << CR2_STOP_SHIFT);
+    }
+
+    #[test]
+    fn notation() {
+        assert_eq!(format!("{}", LineConfig::default()), "8N1");
+        assert_eq!(format!("{}", line(DataBits::Bits7, Parity::Even, StopBits::Stop1_5)), "7E1.5");
+        assert_eq!(format!("{}", line(DataBits::Bits8, Parity::Odd, StopBits::Stop0_5)), "8O0.5");
+    }
+
+    #[test]
+    fn stored_values_round_trip() {
+        for bits in 0..=255 {
+            if let Some(data_bits) = DataBits::from_bits(bits) {
+                assert_eq!(data_bits as u8, bits);
+            } else {
+                assert!(!(6..=9).contains(&bits));
+            }
+        }
+        for v in 0..=255 {
+            assert_eq!(Parity::from_u8(v).map(|p| p as u8), Some(v).filter(|&v| v < 3));
+            assert_eq!(StopBits::from_u8(v).map(|s| s as u8), Some(v).filter(|&v| v < 4));
+        }
+    }
+}
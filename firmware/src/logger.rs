@@ -0,0 +1,646 @@
+//! What the RTIC tasks do, over the board traits
+//!
+//! The tasks in `main` hand their resources to the types here: `Receiver` takes
+//! the received bytes of `uart_buffer` and `frame_end` to the views, `Controls`
+//! the encoder and the button of `encoder_b` and `button` to the menu, the
+//! markers and the search. Resources shared with other tasks come as
+//! `rtic::Mutex`, the board only through the traits of `board`, so the host tests
+//! run the same code with plain references and the mock board.
+
+use core::fmt::{self, Write};
+
+use arrayvec::{ArrayString, ArrayVec};
+use embedded_hal::serial;
+
+use crate::board::{Calendar, CaptureLine, DisplaySink, InputEvent, Storage};
+use crate::bridge;
+use crate::burn_in::Protection;
+use crate::clock::{self, DateTime, WallClock};
+use crate::config::{Config, ConfigStore, Framing, View};
+use crate::framer::{Cobs, Packet, Slip};
+use crate::macros::Sender;
+use crate::markers::{Browser, Markers};
+use crate::menu::{Action, Menu};
+use crate::modbus;
+use crate::nmea;
+use crate::plot::{Pattern, Plot};
+use crate::search::Search;
+use crate::template::{self, Template};
+use crate::terminal::Terminal;
+use crate::trigger::Trigger;
+// `rtic::Mutex` in the firmware, a stand-in for plain references in the simulator
+use crate::Mutex;
+
+/// Longest received line, longer ones are cut off
+pub const MAX_LINE: usize = 1024;
+
+/// Received data that only `uart_buffer` and `frame_end` keep
+pub struct Input {
+    /// Line being received
+    line: ArrayString<[u8; MAX_LINE]>,
+    /// Positions in `line` of bytes with a parity error
+    errors: ArrayVec<[usize; 32]>,
+    /// Frame collected for the Modbus view
+    frame: ArrayVec<[u8; modbus::MAX_FRAME]>,
+    modbus: modbus::Decoder,
+    slip: Slip,
+    cobs: Cobs,
+    /// Packets received with SLIP or COBS framing
+    packets: u32,
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Self {
+            line: ArrayString::new(),
+            errors: ArrayVec::new(),
+            frame: ArrayVec::new(),
+            modbus: modbus::Decoder::new(),
+            slip: Slip::new(),
+            cobs: Cobs::new(),
+            packets: 0,
+        }
+    }
+
+    /// Decode the frame collected in the Modbus view, on the idle gap
+    pub fn frame_end<D, T>(&mut self, mut terminal: T)
+    where
+        D: DisplaySink,
+        T: Mutex<T = Terminal<D>>,
+    {
+        if self.frame.is_empty() {
+            return;
+        }
+        if let Some(event) = self.modbus.frame(&self.frame) {
+            terminal.lock(|terminal| writeln!(terminal, "{}", event).ok());
+        }
+        self.frame.clear();
+    }
+}
+
+/// Received bytes on their way to the view, with the resources of `uart_buffer`
+pub struct Receiver<'a, T, G, P, C, W, B> {
+    pub terminal: T,
+    pub input: &'a mut Input,
+    pub gps: G,
+    /// Parsed `config.template`
+    pub template: &'a Template,
+    pub template_decoder: &'a mut template::Decoder,
+    pub plot: P,
+    pub config: C,
+    pub trigger: &'a mut Trigger,
+    pub wall_clock: W,
+    pub burn_in: B,
+}
+
+impl<D, T, G, P, C, W, B> Receiver<'_, T, G, P, C, W, B>
+where
+    D: DisplaySink,
+    T: Mutex<T = Terminal<D>>,
+    G: Mutex<T = nmea::Summary>,
+    P: Mutex<T = Plot>,
+    C: Mutex<T = Config>,
+    W: Mutex<T = Option<WallClock>>,
+    B: Mutex<T = Protection>,
+{
+    /// Take byte `b` received at `now_ms`, `parity_error` when it is to be shown
+    /// as receive error
+    pub fn byte(&mut self, b: u8, parity_error: bool, now_ms: u32) {
+        if self.trigger.is_holding() {
+            return;
+        }
+        self.burn_in.lock(|burn_in| burn_in.activity(now_ms));
+
+        let input = &mut *self.input;
+        let (view, framing, packet_crc) = self
+            .config
+            .lock(|config| (config.view, config.framing, config.packet_crc));
+        if view == View::Modbus {
+            // a frame that is too long fails the CRC check
+            input.frame.try_push(b).ok();
+            return;
+        }
+
+        let packet = match framing {
+            Framing::Slip => input.slip.push(b),
+            Framing::Cobs => input.cobs.push(b),
+            Framing::Lines | Framing::Idle => None,
+        };
+        if framing == Framing::Slip || framing == Framing::Cobs {
+            if let Some(data) = packet {
+                input.packets += 1;
+                match (view, data) {
+                    (View::Template, Ok(data)) => {
+                        let result = self.template.decode(data);
+                        self.terminal.lock(|terminal| match result {
+                            Ok(frame) => writeln!(terminal, "{}", frame).ok(),
+                            Err(err) => writeln!(terminal, "{}", err).ok(),
+                        });
+                    }
+                    _ => {
+                        let packet = Packet {
+                            number: input.packets,
+                            data,
+                            crc: packet_crc,
+                        };
+                        self.terminal
+                            .lock(|terminal| write!(terminal, "{}", packet).ok());
+                    }
+                }
+            }
+            return;
+        }
+
+        if view == View::Template {
+            if let Some(result) = self.template_decoder.push(self.template, b) {
+                self.terminal.lock(|terminal| match result {
+                    Ok(frame) => writeln!(terminal, "{}", frame).ok(),
+                    Err(err) => writeln!(terminal, "{}", err).ok(),
+                });
+            }
+            return;
+        }
+
+        let position = input.line.len();
+        let printable_only = self.config.lock(|config| config.filter.printable_only);
+        if printable_only && b.is_ascii_control() && !matches!(b, b'\n' | b'\r' | b'\t') {
+            return;
+        }
+        if input.line.try_push(b as char).is_err() {
+            return;
+        }
+        if parity_error {
+            input.errors.try_push(position).ok();
+        }
+        if b != b'\n' {
+            return;
+        }
+
+        let line = input.line.as_bytes();
+        let mut bad_sentence = false;
+        if view == View::Nmea || view == View::Gps {
+            let result = self.gps.lock(|gps| gps.update(line));
+            bad_sentence = matches!(result, Err(err) if err.is_bad());
+        }
+        if view == View::Plot {
+            let value = self
+                .config
+                .lock(|config| Pattern::new(&config.plot_pattern).extract(line));
+            if let Some(value) = value {
+                self.plot.lock(|plot| plot.push(value));
+            }
+        }
+        if view != View::Gps {
+            let timestamps = self.config.lock(|config| config.display.timestamps);
+            let s = clock::clock_seconds(self.wall_clock.lock(|clock| *clock), now_ms);
+            let errors = &input.errors;
+            self.terminal.lock(|terminal| {
+                if timestamps {
+                    write!(
+                        terminal,
+                        "{:02}:{:02}:{:02} ",
+                        s / 3600 % 100,
+                        s / 60 % 60,
+                        s % 60
+                    )
+                    .ok();
+                }
+                for (i, &b) in line.iter().enumerate() {
+                    terminal.write_flagged(b, bad_sentence || errors.contains(&i));
+                }
+            });
+        }
+        self.trigger.check(line);
+        input.line.clear();
+        input.errors.clear();
+    }
+}
+
+/// Take an encoder step: the menu gets it while it is open, otherwise it is kept
+/// in `steps` for the timer to pan or browse
+pub fn turn(menu: &mut Menu, steps: &mut i32, burn_in: &mut Protection, step: i32, now_ms: u32) {
+    // turning wakes the display up first
+    if burn_in.input(now_ms) {
+        return;
+    }
+    if menu.is_open() {
+        menu.turn(step);
+    } else {
+        *steps += step;
+    }
+}
+
+/// Encoder and button, with the resources of `button`
+pub struct Controls<'a, W, L, F, M, C, T, S, K, N, Q, R, A, B, E> {
+    /// Debug port, for messages and the bridge
+    pub debug: &'a mut W,
+    pub line: &'a mut L,
+    pub menu: M,
+    pub config: C,
+    pub config_store: &'a mut ConfigStore<F>,
+    pub terminal: T,
+    /// Macro being sent
+    pub sender: S,
+    pub bridge: &'a mut bridge::Encoder,
+    pub markers: K,
+    pub browser: N,
+    pub search: Q,
+    pub rtc: R,
+    pub wall_clock: A,
+    pub burn_in: B,
+    /// Encoder steps while the menu is closed
+    pub encoder_steps: E,
+}
+
+impl<D, W, L, F, M, C, T, S, K, N, Q, R, A, B, E>
+    Controls<'_, W, L, F, M, C, T, S, K, N, Q, R, A, B, E>
+where
+    D: DisplaySink,
+    W: serial::Write<u8>,
+    L: CaptureLine,
+    F: Storage,
+    M: Mutex<T = Menu>,
+    C: Mutex<T = Config>,
+    T: Mutex<T = Terminal<D>>,
+    S: Mutex<T = Option<Sender>>,
+    K: Mutex<T = Markers>,
+    N: Mutex<T = Browser>,
+    Q: Mutex<T = Search>,
+    R: Mutex,
+    R::T: Calendar,
+    A: Mutex<T = Option<WallClock>>,
+    B: Mutex<T = Protection>,
+    E: Mutex<T = i32>,
+{
+    /// Take `event` made at `now_ms`
+    pub fn input(&mut self, event: InputEvent, now_ms: u32) {
+        match event {
+            InputEvent::Turn(step) => {
+                let (burn_in, encoder_steps) = (&mut self.burn_in, &mut self.encoder_steps);
+                self.menu.lock(|menu| {
+                    encoder_steps.lock(|steps| {
+                        burn_in.lock(|burn_in| turn(menu, steps, burn_in, step, now_ms))
+                    })
+                });
+            }
+            InputEvent::Press => self.press(now_ms),
+        }
+    }
+
+    fn press(&mut self, now_ms: u32) {
+        // a press wakes the display up first
+        if self.burn_in.lock(|burn_in| burn_in.input(now_ms)) {
+            return;
+        }
+
+        // a press while browsing the markers or the matches goes back to the live view
+        let browsing = self.browser.lock(|browser| {
+            let browsing = browser.selected().is_some();
+            browser.live();
+            browsing
+        });
+        let searching = self.search.lock(|search| {
+            let searching = search.is_active();
+            search.stop();
+            searching
+        });
+        if browsing || searching {
+            return;
+        }
+
+        let current = self.config.lock(|config| *config);
+        let now = self
+            .rtc
+            .lock(|rtc| rtc.now())
+            .map_or_else(DateTime::default, |(time, _)| time);
+        let action = self.menu.lock(|menu| {
+            if menu.is_open() {
+                menu.press()
+            } else {
+                menu.open(current, now)
+            }
+        });
+        let wall_clock = self.wall_clock.lock(|clock| *clock);
+        self.bridge.set_wall_clock(wall_clock);
+
+        let (debug, bridge) = (&mut *self.debug, &mut *self.bridge);
+        let mut out = |b| {
+            nb::block!(debug.write(b)).ok();
+        };
+        match action {
+            Action::Apply => {
+                let new_config = self.menu.lock(|menu| *menu.config());
+                if self.line.configure(&new_config).is_err() {
+                    return;
+                }
+                let display = new_config.display;
+                if display.font != current.display.font || display.wrap != current.display.wrap {
+                    self.terminal
+                        .lock(|terminal| {
+                            terminal.set_font(display.font.font())?;
+                            terminal.set_wrap(display.wrap)
+                        })
+                        .unwrap();
+                }
+                bridge.set_format(new_config.bridge.format(), &mut out);
+                self.config.lock(|config| *config = new_config);
+                if self.config_store.save(&new_config).is_err() {
+                    writeln!(Text(&mut out), "config: save failed").ok();
+                }
+            }
+            Action::Send(m) => match m.bytes() {
+                Ok(data) => {
+                    if current.bridge.enabled {
+                        for &b in data.iter() {
+                            bridge.push(bridge::Channel::Tx, b, now_ms, &mut out);
+                        }
+                    }
+                    let delays = current.tx.delays;
+                    // a macro still being sent is replaced
+                    self.sender
+                        .lock(|sender| *sender = Some(Sender::new(data, delays)));
+                }
+                Err(err) => {
+                    writeln!(Text(&mut out), "macro: {}", err).ok();
+                }
+            },
+            Action::Mark => {
+                let time_of_day = clock::clock_millis(wall_clock, now_ms);
+                let marker = self.markers.lock(|markers| markers.add(time_of_day));
+                let mut text = ArrayString::<[u8; 40]>::new();
+                write!(text, "{}", marker).ok();
+                self.terminal
+                    .lock(|terminal| terminal.write_marker(marker.number, &text));
+                if current.bridge.enabled {
+                    for b in text.bytes().chain(Some(b'\n')) {
+                        bridge.push(bridge::Channel::Mark, b, now_ms, &mut out);
+                    }
+                } else {
+                    writeln!(Text(&mut out), "{}", text).ok();
+                }
+            }
+            Action::Find(term) => self.search.lock(|search| search.start(&term)),
+            Action::SetClock(time) => {
+                self.rtc.lock(|rtc| rtc.set(time));
+                let clock = Some(WallClock::new(time, 0, now_ms));
+                self.wall_clock.lock(|wall_clock| *wall_clock = clock);
+            }
+            Action::None | Action::Redraw => {}
+        }
+    }
+}
+
+/// Text written byte by byte
+struct Text<F>(F);
+
+impl<F: FnMut(u8)> Write for Text<F> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        s.bytes().for_each(&mut self.0);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::mock::{
+        MockCalendar, MockClock, MockDebug, MockDisplay, MockFlash, MockInputs, MockLine, MockUart,
+    };
+    use crate::board::{CaptureUart, InputEvents};
+    use embedded_hal::blocking::delay::DelayMs;
+
+    /// The resources of the tasks
+    struct Logger {
+        terminal: Terminal<MockDisplay>,
+        input: Input,
+        gps: nmea::Summary,
+        template: Template,
+        template_decoder: template::Decoder,
+        plot: Plot,
+        config: Config,
+        trigger: Trigger,
+        wall_clock: Option<WallClock>,
+        burn_in: Protection,
+        debug: MockDebug,
+        line: MockLine,
+        menu: Menu,
+        config_store: ConfigStore<MockFlash>,
+        sender: Option<Sender>,
+        bridge: bridge::Encoder,
+        markers: Markers,
+        browser: Browser,
+        search: Search,
+        rtc: MockCalendar,
+        encoder_steps: i32,
+    }
+
+    impl Logger {
+        fn new(config: Config) -> Self {
+            Self {
+                terminal: Terminal::new(MockDisplay::default()),
+                input: Input::new(),
+                gps: nmea::Summary::new(),
+                template: Template::empty(),
+                template_decoder: template::Decoder::new(),
+                plot: Plot::new(),
+                config,
+                trigger: Trigger::new(),
+                wall_clock: None,
+                burn_in: Protection::new(),
+                debug: MockDebug::new(),
+                line: MockLine::default(),
+                menu: Menu::new(),
+                config_store: ConfigStore::new(MockFlash::new()),
+                sender: None,
+                bridge: bridge::Encoder::new(config.bridge.format()),
+                markers: Markers::new(),
+                browser: Browser::new(),
+                search: Search::new(),
+                rtc: MockCalendar(None),
+                encoder_steps: 0,
+            }
+        }
+
+        /// `uart_buffer`
+        fn receive(&mut self, b: u8, now_ms: u32) {
+            Receiver {
+                terminal: &mut self.terminal,
+                input: &mut self.input,
+                gps: &mut self.gps,
+                template: &self.template,
+                template_decoder: &mut self.template_decoder,
+                plot: &mut self.plot,
+                config: &mut self.config,
+                trigger: &mut self.trigger,
+                wall_clock: &mut self.wall_clock,
+                burn_in: &mut self.burn_in,
+            }
+            .byte(b, false, now_ms);
+        }
+
+        /// `encoder_b` and `button`
+        fn input(&mut self, event: InputEvent, now_ms: u32) {
+            Controls {
+                debug: &mut self.debug,
+                line: &mut self.line,
+                menu: &mut self.menu,
+                config: &mut self.config,
+                config_store: &mut self.config_store,
+                terminal: &mut self.terminal,
+                sender: &mut self.sender,
+                bridge: &mut self.bridge,
+                markers: &mut self.markers,
+                browser: &mut self.browser,
+                search: &mut self.search,
+                rtc: &mut self.rtc,
+                wall_clock: &mut self.wall_clock,
+                burn_in: &mut self.burn_in,
+                encoder_steps: &mut self.encoder_steps,
+            }
+            .input(event, now_ms);
+        }
+
+        /// Receive `data` on the capture UART, looped back from TX1
+        fn uart(&mut self, uart: &mut MockUart, data: &[u8], now_ms: u32) {
+            for &b in data {
+                uart.write(b).unwrap();
+                let b = uart.read().unwrap();
+                self.receive(b, now_ms);
+            }
+        }
+
+        /// Take the inputs until `until_ms`, polled every 10 ms
+        fn run(&mut self, clock: &MockClock, inputs: &mut MockInputs, until_ms: u32) {
+            let mut delay = clock;
+            while clock.now() < until_ms {
+                while let Some(event) = inputs.poll() {
+                    self.input(event, clock.now());
+                }
+                delay.delay_ms(10);
+            }
+        }
+
+        /// Text of the line `line` lines above the bottom line
+        fn line(&self, line: usize) -> &str {
+            core::str::from_utf8(self.terminal.line(line).unwrap().0).unwrap()
+        }
+    }
+
+    #[test]
+    fn received_lines_go_to_the_terminal() {
+        let mut logger = Logger::new(Config::default());
+        let mut uart = MockUart::new(true);
+        logger.uart(&mut uart, b"boot ok\r\n", 0);
+        logger.uart(&mut uart, b"T=21", 0);
+        // a line shows up once it is complete
+        assert_eq!(logger.line(1), "boot ok");
+        logger.uart(&mut uart, b".5\n", 0);
+        assert_eq!(logger.line(1), "T=21.5");
+        assert_eq!(logger.line(2), "boot ok");
+    }
+
+    #[test]
+    fn received_lines_with_timestamps_and_filter() {
+        let mut config = Config::default();
+        config.display.timestamps = true;
+        config.filter.printable_only = true;
+        let mut logger = Logger::new(config);
+        let mut uart = MockUart::new(true);
+        logger.uart(&mut uart, b"a\x07b\n", 3_723_000);
+        assert_eq!(logger.line(1), "01:02:03 ab");
+    }
+
+    #[test]
+    fn trigger_holds_the_lines() {
+        let mut logger = Logger::new(Config::default());
+        let mut uart = MockUart::new(true);
+        assert!(logger.trigger.arm(b"ERR"));
+        logger.uart(&mut uart, b"ok\nERROR 3\nafter\n", 0);
+        assert_eq!(logger.line(1), "ERROR 3");
+        assert_eq!(logger.line(2), "ok");
+        assert!(logger.trigger.is_holding());
+    }
+
+    #[test]
+    fn modbus_frames_end_on_the_gap() {
+        let mut logger = Logger::new(Config {
+            view: View::Modbus,
+            ..Config::default()
+        });
+        let mut uart = MockUart::new(true);
+        // example of the Modbus application protocol specification
+        logger.uart(
+            &mut uart,
+            &[0x11, 0x03, 0x00, 0x6B, 0x00, 0x03, 0x76, 0x87],
+            0,
+        );
+        logger.input.frame_end(&mut logger.terminal);
+        let response = [
+            0x11, 0x03, 0x06, 0x02, 0x2B, 0x00, 0x00, 0x00, 0x64, 0xC8, 0xBA,
+        ];
+        logger.uart(&mut uart, &response, 0);
+        assert_eq!(logger.line(1), "");
+        logger.input.frame_end(&mut logger.terminal);
+        assert_eq!(logger.line(1), "11 RD HR 006B+3 = 022B 0000 0064");
+        // nothing more on a gap without bytes
+        logger.input.frame_end(&mut logger.terminal);
+        assert_eq!(logger.line(2), "");
+    }
+
+    #[test]
+    fn two_presses_drop_a_marker() {
+        let clock = MockClock::new();
+        let mut inputs = MockInputs::new(&clock, &[100..150, 300..350, 700..750], &[(600, -1)]);
+        let mut logger = Logger::new(Config::default());
+        logger.run(&clock, &mut inputs, 500);
+
+        assert!(!logger.menu.is_open());
+        assert_eq!(logger.line(1), "=== MARK 1 @ 00:00:00.300 ===");
+        assert_eq!(logger.terminal.find_marker(1), Some(1));
+        assert_eq!(&logger.debug.sent[..], b"=== MARK 1 @ 00:00:00.300 ===\n");
+
+        // turning with the menu closed is left to the timer, which browses the
+        // markers, a press goes back to the live view
+        logger.run(&clock, &mut inputs, 650);
+        assert_eq!(logger.encoder_steps, -1);
+        logger
+            .browser
+            .turn(core::mem::replace(&mut logger.encoder_steps, 0), 1);
+        assert_eq!(logger.browser.selected(), Some(0));
+        logger.run(&clock, &mut inputs, 800);
+        assert_eq!(logger.browser.selected(), None);
+        assert!(!logger.menu.is_open());
+    }
+
+    #[test]
+    fn applied_settings_are_stored() {
+        let clock = MockClock::new();
+        // open, edit the baud rate one step down and exit
+        let held = [0..50, 200..250, 400..450, 600..650];
+        let turns = [(100, 2), (300, -1), (500, -3)];
+        let mut inputs = MockInputs::new(&clock, &held, &turns);
+        let mut logger = Logger::new(Config::default());
+        logger.run(&clock, &mut inputs, 700);
+
+        assert!(!logger.menu.is_open());
+        assert_eq!(logger.config.uart.baudrate, 57600);
+        assert_eq!(logger.line.uart.unwrap().baudrate, 57600);
+        let (stored, _) = logger.config_store.load();
+        assert_eq!(stored, logger.config);
+        assert!(logger.debug.sent.is_empty());
+    }
+
+    #[test]
+    fn unsupported_line_is_not_applied() {
+        let mut config = Config::default();
+        config.uart.line.data_bits = crate::line::DataBits::Bits6;
+        let mut logger = Logger::new(Config::default());
+        logger.menu.open(config, DateTime::default());
+        logger.menu.turn(-1);
+        // the menu refuses to exit with it
+        logger.input(InputEvent::Press, 0);
+        assert!(logger.menu.is_open());
+        assert_eq!(logger.line.uart, None);
+        assert_eq!(logger.config_store.load().0, Config::default());
+    }
+}
@@ -0,0 +1,330 @@
+//! Transmit macros
+//!
+//! Stored strings sent to the target on the capture UART TX. A macro is text with
+//! the escapes `\r`, `\n`, `\t`, `\\` and `\xHH`, or hex bytes after `hex:`, like
+//! `hex:01 03 00 00 00 0A C5 CD`. Its line ending is sent after it.
+//!
+//! `Sender` paces the bytes of a macro: after every byte it waits the byte delay,
+//! after the end of a line the line delay instead.
+
+use core::fmt;
+
+use arrayvec::{ArrayString, ArrayVec};
+
+/// Stored macros
+pub const MAX_MACROS: usize = 4;
+/// Longest macro text
+pub const MAX_MACRO: usize = 48;
+/// Most bytes sent for a macro, including the line ending
+pub const MAX_SEND: usize = MAX_MACRO + 2;
+
+const HEX_PREFIX: &str = "hex:";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    None = 0,
+    Cr = 1,
+    Lf = 2,
+    CrLf = 3,
+}
+
+impl LineEnding {
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(LineEnding::None),
+            1 => Some(LineEnding::Cr),
+            2 => Some(LineEnding::Lf),
+            3 => Some(LineEnding::CrLf),
+            _ => None,
+        }
+    }
+
+    /// Ending named "none", "cr", "lf" or "crlf"
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(LineEnding::None),
+            "cr" => Some(LineEnding::Cr),
+            "lf" => Some(LineEnding::Lf),
+            "crlf" => Some(LineEnding::CrLf),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::None => "none",
+            LineEnding::Cr => "cr",
+            LineEnding::Lf => "lf",
+            LineEnding::CrLf => "crlf",
+        }
+    }
+
+    fn bytes(self) -> &'static [u8] {
+        match self {
+            LineEnding::None => b"",
+            LineEnding::Cr => b"\r",
+            LineEnding::Lf => b"\n",
+            LineEnding::CrLf => b"\r\n",
+        }
+    }
+}
+
+/// Invalid macro text, with the byte offset of the problem
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MacroError {
+    Escape(usize),
+    Hex(usize),
+}
+
+impl fmt::Display for MacroError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MacroError::Escape(at) => write!(f, "bad escape at {}", at),
+            MacroError::Hex(at) => write!(f, "bad hex byte at {}", at),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Macro {
+    pub text: ArrayString<[u8; MAX_MACRO]>,
+    pub ending: LineEnding,
+}
+
+impl Macro {
+    pub fn empty() -> Self {
+        Self {
+            text: ArrayString::new(),
+            ending: LineEnding::None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// The bytes to send, line ending included
+    pub fn bytes(&self) -> Result<ArrayVec<[u8; MAX_SEND]>, MacroError> {
+        let mut out = ArrayVec::new();
+        let text = self.text.as_bytes();
+        if self.text.starts_with(HEX_PREFIX) {
+            hex(text, HEX_PREFIX.len(), &mut out)?;
+        } else {
+            unescape(text, &mut out)?;
+        }
+        // the text is at most MAX_MACRO bytes and never gets longer
+        out.try_extend_from_slice(self.ending.bytes()).ok();
+        Ok(out)
+    }
+}
+
+fn unescape(text: &[u8], out: &mut ArrayVec<[u8; MAX_SEND]>) -> Result<(), MacroError> {
+    let mut i = 0;
+    while i < text.len() {
+        let b = if text[i] == b'\\' {
+            let escape = i;
+            i += 1;
+            match text.get(i) {
+                Some(b'r') => b'\r',
+                Some(b'n') => b'\n',
+                Some(b't') => b'\t',
+                Some(b'\\') => b'\\',
+                Some(b'x') => {
+                    let byte = text
+                        .get(i + 1..i + 3)
+                        .and_then(hex_byte)
+                        .ok_or(MacroError::Escape(escape))?;
+                    i += 2;
+                    byte
+                }
+                _ => return Err(MacroError::Escape(escape)),
+            }
+        } else {
+            text[i]
+        };
+        out.push(b);
+        i += 1;
+    }
+    Ok(())
+}
+
+/// Hex bytes from `start` on, optionally separated by spaces
+fn hex(text: &[u8], start: usize, out: &mut ArrayVec<[u8; MAX_SEND]>) -> Result<(), MacroError> {
+    let mut i = start;
+    while i < text.len() {
+        if text[i] == b' ' {
+            i += 1;
+            continue;
+        }
+        let byte = text.get(i..i + 2).and_then(hex_byte).ok_or(MacroError::Hex(i))?;
+        out.push(byte);
+        i += 2;
+    }
+    Ok(())
+}
+
+fn hex_byte(digits: &[u8]) -> Option<u8> {
+    let digit = |c: u8| (c as char).to_digit(16);
+    Some((digit(digits[0])? * 16 + digit(digits[1])?) as u8)
+}
+
+/// Pauses while sending, in milliseconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Delays {
+    /// After every byte
+    pub byte_ms: u16,
+    /// After '\n', or a '\r' that is not followed by '\n'
+    pub line_ms: u16,
+}
+
+/// Sends the bytes of a macro with the delays in between
+pub struct Sender {
+    data: ArrayVec<[u8; MAX_SEND]>,
+    sent: usize,
+    delays: Delays,
+    /// Milliseconds until the next byte is due
+    wait_ms: u16,
+}
+
+impl Sender {
+    pub fn new(data: ArrayVec<[u8; MAX_SEND]>, delays: Delays) -> Self {
+        Self {
+            data,
+            sent: 0,
+            delays,
+            wait_ms: 0,
+        }
+    }
+
+    /// The byte to send now, None while waiting and when done
+    pub fn pending(&self) -> Option<u8> {
+        if self.wait_ms > 0 {
+            return None;
+        }
+        self.data.get(self.sent).copied()
+    }
+
+    /// The pending byte was sent
+    pub fn advance(&mut self) {
+        let b = self.data[self.sent];
+        self.sent += 1;
+        let line_end = b == b'\n' || (b == b'\r' && self.data.get(self.sent) != Some(&b'\n'));
+        self.wait_ms = if line_end { self.delays.line_ms } else { self.delays.byte_ms };
+    }
+
+    /// One millisecond passed
+    pub fn tick(&mut self) {
+        self.wait_ms = self.wait_ms.saturating_sub(1);
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.sent == self.data.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(text: &str, ending: LineEnding) -> Result<Vec<u8>, MacroError> {
+        let m = Macro {
+            text: ArrayString::from(text).unwrap(),
+            ending,
+        };
+        m.bytes().map(|bytes| bytes.to_vec())
+    }
+
+    /// Times in milliseconds the bytes go out, sending whenever a byte is due
+    fn schedule(data: &[u8], delays: Delays) -> Vec<(u32, u8)> {
+        let mut sender = Sender::new(data.iter().copied().collect(), delays);
+        let mut sent = Vec::new();
+        let mut ms = 0;
+        while !sender.is_done() {
+            if let Some(b) = sender.pending() {
+                sent.push((ms, b));
+                sender.advance();
+            } else {
+                sender.tick();
+                ms += 1;
+            }
+        }
+        sent
+    }
+
+    #[test]
+    fn text_with_escapes() {
+        assert_eq!(bytes(r"AT\r\n", LineEnding::None), Ok(b"AT\r\n".to_vec()));
+        assert_eq!(bytes(r"a\tb\\c\x00\xfF", LineEnding::Lf), Ok(b"a\tb\\c\x00\xFF\n".to_vec()));
+        assert_eq!(bytes("", LineEnding::CrLf), Ok(b"\r\n".to_vec()));
+    }
+
+    #[test]
+    fn bad_escapes() {
+        assert_eq!(bytes(r"ab\q", LineEnding::None), Err(MacroError::Escape(2)));
+        // at the end of the text
+        assert_eq!(bytes(r"ab\", LineEnding::None), Err(MacroError::Escape(2)));
+        assert_eq!(bytes(r"\x4", LineEnding::None), Err(MacroError::Escape(0)));
+        assert_eq!(bytes(r"x\x4g", LineEnding::None), Err(MacroError::Escape(1)));
+        assert_eq!(format!("{}", MacroError::Escape(2)), "bad escape at 2");
+    }
+
+    #[test]
+    fn hex_bytes() {
+        assert_eq!(
+            bytes("hex:01 03 00 00 00 0A C5 CD", LineEnding::None),
+            Ok(vec![0x01, 0x03, 0x00, 0x00, 0x00, 0x0A, 0xC5, 0xCD])
+        );
+        assert_eq!(bytes("hex:  aBcd ", LineEnding::Cr), Ok(vec![0xAB, 0xCD, b'\r']));
+        assert_eq!(bytes("hex:", LineEnding::None), Ok(vec![]));
+        // odd digit and a digit split by a space
+        assert_eq!(bytes("hex:01 2", LineEnding::None), Err(MacroError::Hex(7)));
+        assert_eq!(bytes("hex:0 1", LineEnding::None), Err(MacroError::Hex(4)));
+        assert_eq!(bytes("hex:zz", LineEnding::None), Err(MacroError::Hex(4)));
+        // only the prefix makes it hex
+        assert_eq!(bytes("HEX:01", LineEnding::None), Ok(b"HEX:01".to_vec()));
+    }
+
+    #[test]
+    fn longest_macro_fits_with_its_ending() {
+        let text = "x".repeat(MAX_MACRO);
+        let sent = bytes(&text, LineEnding::CrLf).unwrap();
+        assert_eq!(sent.len(), MAX_SEND);
+        assert_eq!(&sent[MAX_MACRO..], b"\r\n");
+    }
+
+    #[test]
+    fn endings() {
+        for v in 0..4 {
+            let ending = LineEnding::from_u8(v).unwrap();
+            assert_eq!(ending as u8, v);
+            assert_eq!(LineEnding::parse(ending.name()), Some(ending));
+        }
+        assert_eq!(LineEnding::from_u8(4), None);
+        assert_eq!(LineEnding::parse("CRLF"), None);
+    }
+
+    #[test]
+    fn no_delays_send_at_once() {
+        let delays = Delays { byte_ms: 0, line_ms: 0 };
+        assert_eq!(schedule(b"ab\n", delays), [(0, b'a'), (0, b'b'), (0, b'\n')]);
+    }
+
+    #[test]
+    fn byte_and_line_delays() {
+        let delays = Delays { byte_ms: 2, line_ms: 10 };
+        assert_eq!(
+            schedule(b"a\r\nb\rc\n", delays),
+            [(0, b'a'), (2, b'\r'), (4, b'\n'), (14, b'b'), (16, b'\r'), (26, b'c'), (28, b'\n')]
+        );
+    }
+
+    #[test]
+    fn line_delay_only() {
+        let delays = Delays { byte_ms: 0, line_ms: 5 };
+        assert_eq!(schedule(b"ab\ncd", delays), [(0, b'a'), (0, b'b'), (0, b'\n'), (5, b'c'), (5, b'd')]);
+        // nothing to send
+        let sender = Sender::new(ArrayVec::new(), delays);
+        assert!(sender.is_done());
+        assert_eq!(sender.pending(), None);
+    }
+}
@@ -6,22 +6,93 @@ use core::fmt::Write;
 // pick a panicking behavior
 extern crate panic_halt; // you can put a breakpoint on `rust_begin_unwind` to catch panics
 
-use display_interface_spi::SPIInterface;
-use arrayvec::ArrayString;
+use arrayvec::{ArrayString, ArrayVec};
 
+use embedded_graphics::{pixelcolor::{Gray4, GrayColor}, draw_target::{DrawTarget, DrawTargetExt}, geometry::OriginDimensions, prelude::Point};
 use embedded_hal as hal;
+use protocol::{ErrorCode, Filter, Status as LinkStatus};
+use rtic::Mutex;
 use hal::digital::v2::OutputPin;
 
 mod encoder;
-use encoder::{Encoder, Channel};
+use encoder::Channel;
+
+mod board;
+use board::{BatterySensor, CaptureLine, InputEvent, Led, StatusLeds};
+mod bridge;
+mod burn_in;
+use burn_in::{Dimmed, Protection};
+mod canvas;
+#[allow(dead_code)]
+#[path = "../../oled_test/src/command.rs"]
+mod command;
+use canvas::Canvas;
+mod clock;
+use clock::WallClock;
+
+mod config;
+use config::{Config, ConfigStore, Framing, View};
+
+mod console;
+use console::Command;
+
+mod crc;
+mod flash;
+mod framer;
+
+#[path = "../../oled_test/src/dma.rs"]
+mod dma;
+mod dma_display;
+use dma_display::DmaDisplay;
+// shared with oled_test, which uses the blocking interface
+#[allow(dead_code)]
+#[path = "../../oled_test/src/error.rs"]
+mod error;
+mod font;
+mod frame_sync;
+use frame_sync::FrameSync;
+#[allow(dead_code)]
+#[path = "../../oled_test/src/interface.rs"]
+mod interface;
+use interface::DmaSpiInterface;
+mod layout;
+use layout::{ErrorCounts, Layout, Status};
+mod line;
+mod logger;
+use logger::{Controls, Input, Receiver};
+mod macros;
+use macros::{Macro, Sender};
+mod markers;
+use markers::{Browser, Markers};
+mod menu;
+use menu::Menu;
+mod modbus;
+mod nmea;
+mod oled;
+mod plot;
+use plot::Plot;
+mod rtc;
+use rtc::Rtc;
+mod scroll;
+mod search;
+mod selftest;
+use search::Search;
+mod template;
+use template::Template;
+mod terminal;
+mod trigger;
+use trigger::Trigger;
+mod usart;
+mod wrap;
+use wrap::WrapMode;
 
 use nb;
 
 use stm32g0xx_hal::{
     prelude::*,
-    stm32::{self, SPI1, EXTI, TIM15},
+    stm32::{self, EXTI, TIM15},
     spi,
-    serial::{self, FullConfig, FifoThreshold, Error as SerialError},
+    serial::{BasicConfig, FullConfig, FifoThreshold, Error as SerialError},
     gpio,
     timer::Timer,
     exti::Event,
@@ -29,43 +100,177 @@ use stm32g0xx_hal::{
     delay::Delay
 };
 
-use ssd1362::{self, display::DisplayRotation, terminal, Font6x8};
-
-type Terminal = terminal::TerminalView<SPIInterface<
-    spi::Spi<
-        SPI1,
-        (
-            gpio::gpiob::PB3<gpio::Input<gpio::Floating>>,
-            gpio::gpioa::PA6<gpio::Input<gpio::Floating>>,
-            gpio::gpiob::PB5<gpio::Input<gpio::Floating>>
-        )
-    >,
-    gpio::gpiob::PB7<gpio::Output<gpio::PushPull>>,
-    gpio::gpiob::PB4<gpio::Output<gpio::PushPull>>
-    >,
-    Font6x8
-    >;
-
-type Enc = Encoder<
-    gpio::gpiob::PB<gpio::Input<gpio::PushPull>>,
-    gpio::gpiob::PB<gpio::Input<gpio::PushPull>>
-    >;
+type Terminal = terminal::Terminal<board::Display>;
+
+/// The logger as the remote control sees it, with the resources of `remote_request`
+struct Remote<'a, C, L, T, V> {
+    config: C,
+    config_store: &'a mut ConfigStore<board::Flash>,
+    line: L,
+    terminal: T,
+    canvas: V,
+    trigger: &'a mut Trigger,
+}
+
+impl<C, L, T, V> Remote<'_, C, L, T, V>
+where
+    C: Mutex<T = Config>,
+{
+    /// Use and store `new_config`
+    fn apply(&mut self, new_config: Config) -> Result<(), ErrorCode> {
+        self.config.lock(|config| *config = new_config);
+        self.config_store.save(&new_config).map_err(|_| ErrorCode::SaveFailed)
+    }
+}
+
+impl<C, L, T, V> protocol::Device for Remote<'_, C, L, T, V>
+where
+    C: Mutex<T = Config>,
+    L: CaptureLine,
+    T: Mutex<T = Terminal>,
+    V: Mutex<T = Canvas>,
+{
+    fn screen_size(&self) -> (u16, u8) {
+        (oled::WIDTH, oled::HEIGHT)
+    }
+
+    fn status(&mut self) -> LinkStatus {
+        let current = self.config.lock(|config| *config);
+        LinkStatus {
+            baudrate: current.uart.baudrate,
+            filter: Filter {
+                show_errors: current.filter.show_errors,
+                printable_only: current.filter.printable_only,
+            },
+            trigger: self.trigger.state(),
+        }
+    }
+
+    fn set_baudrate(&mut self, baudrate: u32) -> Result<(), ErrorCode> {
+        let mut new_config = self.config.lock(|config| *config);
+        new_config.uart.baudrate = baudrate;
+        if !new_config.is_valid() {
+            return Err(ErrorCode::BadValue);
+        }
+        self.line.configure(&new_config).map_err(|_| ErrorCode::BadValue)?;
+        self.apply(new_config)
+    }
+
+    fn set_filter(&mut self, filter: Filter) -> Result<(), ErrorCode> {
+        let mut new_config = self.config.lock(|config| *config);
+        new_config.filter.show_errors = filter.show_errors;
+        new_config.filter.printable_only = filter.printable_only;
+        self.apply(new_config)
+    }
+
+    fn clear_screen(&mut self) {
+        self.terminal.lock(|terminal| terminal.clear());
+        self.trigger.release();
+    }
+
+    fn arm_trigger(&mut self, pattern: &[u8]) -> Result<(), ErrorCode> {
+        if self.trigger.arm(pattern) {
+            Ok(())
+        } else {
+            Err(ErrorCode::BadValue)
+        }
+    }
+
+    fn read_screen(&mut self, first_row: u8, data: &mut [u8]) {
+        let rows = first_row..first_row + (data.len() / oled::ROW_BYTES) as u8;
+        let canvas = &mut self.canvas;
+        self.terminal.lock(|terminal| canvas.lock(|canvas| {
+            // the canvas holds the screen when it is shown, otherwise it is free
+            // until the next render
+            if !terminal.is_canvas_shown() {
+                canvas.clear(Gray4::BLACK).ok();
+                terminal.draw(canvas);
+            }
+            data.copy_from_slice(canvas.rows(rows));
+        }));
+    }
+}
 
 #[rtic::app(device = stm32g0xx_hal::stm32)]
 const APP: () = {
 
     struct Resources {
         terminal: Terminal,
-        led_r: gpio::gpiob::PB0<gpio::Output<gpio::PushPull>>,
-        led_g: gpio::gpioa::PA7<gpio::Output<gpio::PushPull>>,
+        leds: board::Leds,
         timer: Timer<stm32::TIM1>,
         exti: EXTI,
-        encoder: Enc,
-        tx: serial::Tx<stm32::USART1, FullConfig>,
-        rx: serial::Rx<stm32::USART1, FullConfig>,
-        uart_in_buffer: ArrayString::<[u8; 1024]>,
-        debug_pin1: gpio::gpiob::PB8<gpio::Output<gpio::PushPull>>,
-        debug_pin2: gpio::gpiob::PB9<gpio::Output<gpio::PushPull>>,
+        encoder: board::Encoder,
+        tx: board::CaptureTx,
+        rx: board::CaptureRx,
+        /// Second capture input, only passed on to the bridge
+        rx2: board::Capture2Rx,
+        /// Received data of `uart_buffer` and `frame_end`
+        input: Input,
+        gps: nmea::Summary,
+        /// Parsed `config.template`
+        template: Template,
+        template_decoder: template::Decoder,
+        #[init(Plot::new())]
+        plot: Plot,
+        /// Frame buffer of the plot view and the layouts with a status bar
+        #[init(Canvas::new())]
+        canvas: Canvas,
+        /// Receive errors for the status bar
+        #[init(ErrorCounts { overrun: 0, framing: 0, noise: 0, parity: 0 })]
+        error_counts: ErrorCounts,
+        /// Timer interrupts since boot
+        #[init(0)]
+        ticks: u32,
+        #[init(FrameSync::new())]
+        frame_sync: FrameSync,
+        /// Frames that could not be pushed to the OLED
+        #[init(0)]
+        render_errors: u32,
+        #[init(Protection::new())]
+        burn_in: Protection,
+        /// Encoder steps while the menu is closed, taken by `timer` to pan cut off
+        /// lines or to browse the markers
+        #[init(0)]
+        encoder_steps: i32,
+        /// Millisecond ticks
+        ms_timer: Timer<stm32::TIM16>,
+        /// Milliseconds since boot
+        #[init(0)]
+        millis: u32,
+        /// Macro being sent
+        #[init(None)]
+        sender: Option<Sender>,
+        /// Captured bytes out of the debug port in bridge mode
+        bridge: bridge::Encoder,
+        /// Captured bytes that did not fit in the `bridge_out` queue
+        #[init(0)]
+        bridge_lost: u32,
+        battery: board::Battery,
+        #[init(None)]
+        battery_mv: Option<u16>,
+        /// USART1 kernel clock in Hz
+        uart_clock: u32,
+        config: Config,
+        config_store: ConfigStore<board::Flash>,
+        menu: Menu,
+        debug_tx: board::DebugTx,
+        debug_rx: board::DebugRx,
+        /// Command line received on the debug port
+        debug_line: ArrayString::<[u8; console::MAX_LINE]>,
+        /// Remote control requests received on the debug port
+        #[init(protocol::cobs::Decoder::new())]
+        remote_in: protocol::cobs::Decoder,
+        trigger: Trigger,
+        #[init(Markers::new())]
+        markers: Markers,
+        /// Marker shown from the scrollback
+        #[init(Browser::new())]
+        browser: Browser,
+        /// Search in the scrollback
+        search: Search,
+        rtc: Rtc,
+        /// Time of the RTC, None until it is set
+        wall_clock: Option<WallClock>,
         debug_pin3: gpio::gpioa::PA11<gpio::Output<gpio::PushPull>>,
         debug_pin4: gpio::gpioa::PA12<gpio::Output<gpio::PushPull>>,
         delay: Delay<TIM15>
@@ -73,6 +278,10 @@ const APP: () = {
 
     #[init(spawn = [startup])]
     fn init(cx: init::Context) -> init::LateResources {
+        // rows on their way to the OLED, see `dma_display`
+        static mut DISPLAY_BUFFERS: [[u8; dma_display::CHUNK_LEN]; 2] = [[0; dma_display::CHUNK_LEN]; 2];
+        // the command batch on its way, the terminal is moved into the resources
+        static mut DISPLAY_COMMANDS: [u8; interface::MAX_BATCH] = [0; interface::MAX_BATCH];
 
         let dp = stm32::Peripherals::take().expect("cannot take peripherals");
 
@@ -82,6 +291,9 @@ const APP: () = {
 
         let mut delay = dp.TIM15.delay(&mut rcc);
 
+        let mut config_store = ConfigStore::new(board::Flash::new(dp.FLASH));
+        let (config, config_status) = config_store.load();
+
         let gpioa = dp.GPIOA.split(&mut rcc);
         let gpiob = dp.GPIOB.split(&mut rcc);
         let _gpioc = dp.GPIOC.split(&mut rcc);
@@ -89,20 +301,28 @@ const APP: () = {
 
 
         // let mut led = gpioa.pa5.into_push_pull_output();
-        let mut led_g = gpioa.pa7.into_push_pull_output();
-        let led_r = gpiob.pb0.into_push_pull_output();
+        let mut leds = board::Leds {
+            red: gpiob.pb0.into_push_pull_output(),
+            green: gpioa.pa7.into_push_pull_output(),
+        };
         let mut en_16v = gpioa.pa1.into_push_pull_output();
 
         let mut exti = dp.EXTI;
 
-        let encoder = {
+        let mut encoder = {
             let encoder_a = gpiob.pb1.listen( gpio::SignalEdge::Rising, &mut exti).downgrade();
             let encoder_b = gpiob.pb2.listen(gpio::SignalEdge::Rising, &mut exti).downgrade();
-            Encoder::new(encoder_a, encoder_b)
+            encoder::Encoder::new(encoder_a, encoder_b)
         };
 
+        let mut battery = board::Battery::new(dp.ADC.constrain(&mut rcc), gpioa.pa4.into_analog());
+
+        // held at boot for the self-test
         let btn = gpioa.pa8.into_pull_up_input();
-        btn.listen(gpio::SignalEdge::Falling, &mut exti);
+        let self_test = btn.is_low().unwrap();
+
+        // FRAME_SYNC of the OLED, see `frame_sync`
+        gpioa.pa15.listen(gpio::SignalEdge::Rising, &mut exti);
 
         let mut cs = gpiob.pb4.into_push_pull_output(); // blue 13
         cs.set_high().unwrap();
@@ -117,23 +337,40 @@ const APP: () = {
 
         let mut usart = dp.USART1.usart( gpioa.pa9, gpioa.pa10,
             FullConfig::default()
-                .baudrate(115200.bps())
+                .baudrate(config.uart.baudrate.bps())
                 .fifo_enable()
                 .rx_fifo_threshold(FifoThreshold::FIFO_4_BYTES)
                 .rx_fifo_enable_interrupt()
-                .receiver_timeout_us(25_000),
+                .receiver_timeout_us(config.uart.rx_timeout_us),
+            &mut rcc).unwrap();
+        // only RX2 on PA3 is used, set up before `usart::configure` sets its line
+        let usart2 = dp.USART2.usart(gpioa.pa2, gpioa.pa3,
+            FullConfig::default().baudrate(config.uart.baudrate.bps()),
             &mut rcc).unwrap();
+        let (_, mut rx2) = usart2.split();
+        rx2.listen();
+        let uart_clock = rcc.clocks.apb_clk.0;
+        usart::configure(&config.uart, config.rx_timeout_bits(), uart_clock).unwrap();
 
         writeln!(usart, "Hello SerialLogger\n").unwrap();
+        writeln!(usart, "config: {:?}", config_status).unwrap();
+
+        let rtc = Rtc::new(dp.RTC);
+        let now = rtc.now();
+        match now {
+            Some((time, _)) => writeln!(usart, "rtc: {} {}", rtc.source().name(), time).unwrap(),
+            None => writeln!(usart, "rtc: {} not set", rtc.source().name()).unwrap(),
+        }
+        let wall_clock = now.map(|(time, ms)| WallClock::new(time, ms, 0));
 
-        led_g.set_high().unwrap();
+        leds.set(Led::Green, true);
         delay.delay(2500.ms());
-        led_g.set_low().unwrap();
+        leds.set(Led::Green, false);
 
         let sck = gpiob.pb3; // yellow 10
         let miso = gpioa.pa6; //not used
         let mosi = gpiob.pb5; // green 9
-        let spi = dp.SPI1.spi(
+        let _spi = dp.SPI1.spi(
             (sck, miso, mosi),
             spi::MODE_0,
             10.mhz(),
@@ -151,12 +388,17 @@ const APP: () = {
         writeln!(usart, "Turn on VCC!").unwrap();
         en_16v.set_high().unwrap();
 
-        let interface = display_interface_spi::SPIInterface::new(spi, dc, cs);
-        let display = ssd1362::display::Display::new(interface, DisplayRotation::Rotate180);
+        // the OLED is the only device on SPI1, it stays selected
+        cs.set_low().unwrap();
+        let dma = dma::Spi1TxDma::new(dp.DMA, dp.DMAMUX);
+        let interface = DmaDisplay::new(DmaSpiInterface::new(dma, dc, DISPLAY_COMMANDS), DISPLAY_BUFFERS);
         writeln!(usart, "create terminal..").unwrap();
-        let font = terminal::Font6x8 {};
-        let mut terminal = terminal::TerminalView::new(display, font);
-        terminal.init().unwrap();
+        let mut terminal = terminal::Terminal::new(interface);
+        terminal.init(config.display.rotation).unwrap();
+        terminal.contrast(config.display.contrast).unwrap();
+        terminal.smooth_scroll(config.display.smooth_scroll);
+        terminal.set_font(config.display.font.font()).unwrap();
+        terminal.set_wrap(config.display.wrap).unwrap();
 
         writeln!(usart, "Display init done!").unwrap();
         writeln!(terminal, "Display init done!").unwrap();
@@ -164,18 +406,42 @@ const APP: () = {
 
         terminal.render().unwrap();
         let mut timer = dp.TIM1.timer(&mut rcc);
-        timer.start(10.hz());
+        timer.start((config.display.refresh_hz as u32).hz());
         timer.listen();
 
-        let (tx, rx) = usart.split();
+        let mut ms_timer = dp.TIM16.timer(&mut rcc);
+        ms_timer.start(1.khz());
+        ms_timer.listen();
+
+        let (mut tx, mut rx) = usart.split();
 
         // rx.listen();
 
-        let mut debug_pin1 = gpiob.pb8.into_push_pull_output();
-        debug_pin1.set_low().unwrap();
+        let debug_uart = dp.USART3.usart(gpiob.pb8, gpiob.pb9,
+            BasicConfig::default().baudrate(115_200.bps()),
+            &mut rcc).unwrap();
+        let (mut debug_tx, mut debug_rx) = debug_uart.split();
+        debug_rx.listen();
+
+        if self_test {
+            let mut board = selftest::Board {
+                terminal: &mut terminal,
+                debug: &mut debug_tx,
+                uart: board::Capture { tx: &mut tx, rx: &mut rx },
+                leds: &mut leds,
+                inputs: board::PolledInputs { exti: &mut exti, encoder: &mut encoder, button: &btn },
+                battery: &mut battery,
+                delay: &mut delay,
+                config_store: &mut config_store,
+                config,
+            };
+            selftest::run(&mut board);
+            selftest::wait_for_start(&mut board);
+            rx.clear_timeout();
+        }
+        btn.listen(gpio::SignalEdge::Falling, &mut exti);
 
-        let mut debug_pin2 = gpiob.pb9.into_push_pull_output();
-        debug_pin2.set_low().unwrap();
+        let template = Template::parse(&config.template).unwrap_or(Template::empty());
 
         let mut debug_pin3 = gpioa.pa11.into_push_pull_output();
         debug_pin3.set_low().unwrap();
@@ -187,16 +453,31 @@ const APP: () = {
 
         init::LateResources {
             terminal,
-            led_r,
-            led_g,
+            leds,
             timer,
+            ms_timer,
             exti,
             encoder,
             tx,
             rx,
-            uart_in_buffer: ArrayString::new(),
-            debug_pin1,
-            debug_pin2,
+            rx2,
+            input: Input::new(),
+            gps: nmea::Summary::new(),
+            template,
+            template_decoder: template::Decoder::new(),
+            bridge: bridge::Encoder::new(config.bridge.format()),
+            uart_clock,
+            config,
+            config_store,
+            menu: Menu::new(),
+            debug_tx,
+            debug_rx,
+            debug_line: ArrayString::new(),
+            trigger: Trigger::new(),
+            search: Search::new(),
+            rtc,
+            wall_clock,
+            battery,
             debug_pin3,
             debug_pin4,
             delay
@@ -207,16 +488,166 @@ const APP: () = {
     fn startup(_cx: startup::Context) {
     }
 
-    #[task(binds=TIM1_BRK_UP_TRG_COM, resources = [timer, terminal, debug_pin3], priority = 3, spawn = [])]
+    #[task(binds=TIM1_BRK_UP_TRG_COM, resources = [timer, terminal, menu, config, gps, plot, canvas, error_counts, ticks, frame_sync, burn_in, encoder_steps, markers, browser, search, rtc, wall_clock, millis, battery, battery_mv, debug_pin3, render_errors], priority = 3, spawn = [bridge_idle])]
     fn timer(cx: timer::Context) {
         let timer::Resources {
             timer,
             terminal,
+            mut menu,
+            mut config,
+            gps,
+            plot,
+            canvas,
+            mut error_counts,
+            ticks,
+            frame_sync,
+            mut burn_in,
+            mut encoder_steps,
+            markers,
+            browser,
+            search,
+            rtc,
+            wall_clock,
+            mut millis,
+            battery,
+            battery_mv,
             debug_pin3,
+            render_errors,
         } = cx.resources;
 
         debug_pin3.set_high().unwrap();
-        terminal.render().unwrap();
+        let current = config.lock(|config| *config);
+        let view = current.view;
+        let gps_view = view == View::Gps;
+        let layout = current.display.layout;
+        let refresh_hz = current.display.refresh_hz as u32;
+        if current.bridge.enabled {
+            cx.spawn.bridge_idle().ok();
+        }
+
+        *ticks = ticks.wrapping_add(1);
+        let now_ms = millis.lock(|millis| *millis);
+        if *ticks % refresh_hz == 0 {
+            *battery_mv = battery.battery_mv();
+            // the millisecond count drifts from the RTC
+            *wall_clock = rtc.now().map(|(time, ms)| WallClock::new(time, ms, now_ms));
+        }
+        let (panel, static_divider) = burn_in.lock(|burn_in| {
+            (burn_in.update(now_ms, current.burn_in()), burn_in.static_divider(now_ms, current.burn_in()))
+        });
+        if let Some(panel) = panel {
+            terminal.contrast(panel.contrast).unwrap();
+            terminal.pre_charge_voltage(panel.pre_charge).unwrap();
+            terminal.display_offset(panel.offset).unwrap();
+            terminal.on(panel.on).unwrap();
+        }
+
+        let steps = encoder_steps.lock(|steps| core::mem::replace(steps, 0));
+        let scrollback = layout == Layout::Full && !gps_view && view != View::Plot;
+        if !scrollback {
+            search.stop();
+        }
+        // line of the found match or the browsed marker above the bottom line
+        let browsing = if search.is_active() {
+            search.step(steps, &*terminal);
+            Some(match search.found() {
+                Some(found) => terminal.line_number().wrapping_sub(found.line) as usize,
+                // the newest lines with "Not found"
+                None => 0,
+            })
+        } else if scrollback && current.display.wrap != WrapMode::Off {
+            // markers whose lines are no longer kept can't be shown
+            let kept = markers
+                .iter()
+                .take_while(|marker| terminal.find_marker(marker.number).is_some())
+                .count();
+            browser.turn(steps, kept);
+            browser
+                .selected()
+                .and_then(|n| markers.newest(n))
+                .and_then(|marker| terminal.find_marker(marker.number))
+        } else {
+            terminal.pan(steps);
+            browser.live();
+            None
+        };
+
+        menu.lock(|menu| {
+            let lines = terminal.page_lines();
+            if menu.is_open() {
+                let first = menu.first_row(lines);
+                for row in 0..lines {
+                    let mut text = ArrayString::<[u8; 64]>::new();
+                    menu.write_row(first + row, &mut text).ok();
+                    terminal.set_page_line(row, &text, menu.is_selected(first + row));
+                }
+            } else if gps_view {
+                for row in 0..lines {
+                    let mut text = ArrayString::<[u8; 64]>::new();
+                    gps.write_row(row, &mut text).ok();
+                    terminal.set_page_line(row, &text, false);
+                }
+            } else if let Some(line) = browsing {
+                terminal.set_page_history(line, search.columns());
+                if search.is_active() && search.found().is_none() {
+                    let mut text = ArrayString::<[u8; 64]>::new();
+                    write!(text, "Not found: {}", search.term()).ok();
+                    terminal.set_page_line(lines - 1, &text, true);
+                }
+            }
+
+            if menu.is_open() {
+                terminal.show_page(true);
+            } else if layout != Layout::Full {
+                let status = Status {
+                    baudrate: current.uart.baudrate,
+                    line: current.uart.line,
+                    channel: "RX1",
+                    battery_mv: *battery_mv,
+                    recording: false,
+                    errors: error_counts.lock(|counts| *counts),
+                    clock_s: clock::clock_seconds(*wall_clock, now_ms),
+                };
+                let font = terminal.font();
+                let regions = layout.regions(canvas.size());
+
+                canvas.clear(Gray4::BLACK).ok();
+                if let Some(area) = regions.status {
+                    let mut status_bar = canvas.cropped(&area);
+                    status.draw(&mut Dimmed::new(&mut status_bar, static_divider), &font::FONT_6X8).ok();
+                }
+                let top = &mut canvas.cropped(&regions.top);
+                match view {
+                    View::Plot if layout == Layout::Status => plot.draw(top).ok(),
+                    View::Gps => {
+                        for row in 0..nmea::SUMMARY_ROWS {
+                            let mut text = ArrayString::<[u8; 64]>::new();
+                            gps.write_row(row, &mut text).ok();
+                            let y = (row * font.height as usize) as i32;
+                            layout::draw_text(top, font, Point::new(0, y), text.as_bytes(), 0).ok();
+                        }
+                        Some(())
+                    }
+                    _ => layout::draw_lines(top, font, |n| terminal.line(n)).ok(),
+                };
+                if let Some(area) = regions.bottom {
+                    plot.draw(&mut canvas.cropped(&area)).ok();
+                }
+                terminal.show_canvas(canvas).unwrap();
+            } else if view == View::Plot {
+                if plot.take_changed() || !terminal.is_canvas_shown() {
+                    canvas.clear(Gray4::BLACK).ok();
+                    plot.draw(canvas).ok();
+                    terminal.show_canvas(canvas).unwrap();
+                }
+            } else {
+                terminal.show_page(gps_view || browsing.is_some());
+            }
+        });
+        // see `render` for failed frames
+        if frame_sync.frame(now_ms) && terminal.render().is_err() {
+            *render_errors += 1;
+        }
         debug_pin3.set_low().unwrap();
 
         timer.clear_irq();
@@ -237,109 +668,522 @@ const APP: () = {
         }
     }
 
-    #[task(binds=EXTI2_3, resources = [exti, encoder, tx], priority = 4, spawn = [])]
+    #[task(binds=EXTI2_3, resources = [exti, encoder, menu, encoder_steps, burn_in, millis], priority = 4, spawn = [])]
     fn encoder_b(cx: encoder_b::Context) {
         let encoder_b::Resources {
             exti,
             encoder,
-            tx
+            menu,
+            encoder_steps,
+            burn_in,
+            millis,
         } = cx.resources;
 
         if exti.is_pending(Event::GPIO2, gpio::SignalEdge::Rising) {
-            let (position, _step) = encoder.update(Channel::B);
+            let (_position, step) = encoder.update(Channel::B);
             exti.unpend(Event::GPIO2);
-            writeln!(tx, "encoder b: {}", position).unwrap();
+            // one B edge per detent
+            logger::turn(menu, encoder_steps, burn_in, step, *millis);
         }
     }
 
-    #[task(binds=EXTI4_15, resources = [tx, exti], priority = 1, spawn = [])]
-    fn button(cx: button::Context) {
-        let button::Resources {
-            mut tx,
+    /// Frame sync edges and the button share the interrupt
+    #[task(binds=EXTI4_15, resources = [exti, frame_sync, millis], priority = 3, spawn = [button, render])]
+    fn exti4_15(cx: exti4_15::Context) {
+        let exti4_15::Resources {
             mut exti,
+            frame_sync,
+            mut millis,
         } = cx.resources;
 
-        exti.lock(|exti| {
-            if exti.is_pending(Event::GPIO8, gpio::SignalEdge::Falling) {
+        let (sync, pressed) = exti.lock(|exti| {
+            let sync = exti.is_pending(Event::GPIO15, gpio::SignalEdge::Rising);
+            if sync {
+                exti.unpend(Event::GPIO15);
+            }
+            let pressed = exti.is_pending(Event::GPIO8, gpio::SignalEdge::Falling);
+            if pressed {
                 exti.unpend(Event::GPIO8);
             }
+            (sync, pressed)
         });
-        tx.lock(|tx| writeln!(tx, " <== button ==>").unwrap());
+
+        // when `render` is still queued it pushes the latest frame anyway
+        if sync && frame_sync.edge(millis.lock(|millis| *millis)) {
+            cx.spawn.render().ok();
+        }
+        if pressed {
+            cx.spawn.button().ok();
+        }
     }
 
-    #[task(priority = 1, resources=[terminal, uart_in_buffer], capacity = 100)]
-    fn uart_buffer(cx: uart_buffer::Context, byte: u8) { //Result<u8, nb::Error<serial::Error>>) {
+    /// Push the frame held for a sync edge, out of `exti4_15` so the button and
+    /// the edges are not held up by the transfer
+    #[task(priority = 2, resources = [terminal, render_errors])]
+    fn render(cx: render::Context) {
+        let render::Resources {
+            mut terminal,
+            mut render_errors,
+        } = cx.resources;
 
-        let uart_buffer::Resources {
+        // the frame is dropped, the lines it did not draw are still dirty and go
+        // out with the next one
+        if terminal.lock(|terminal| terminal.render()).is_err() {
+            render_errors.lock(|errors| *errors += 1);
+        }
+    }
+
+    #[task(priority = 1, resources = [debug_tx, rx, menu, config, config_store, uart_clock, terminal, sender, bridge, millis, markers, browser, search, rtc, wall_clock, burn_in, encoder_steps])]
+    fn button(cx: button::Context) {
+        let button::Resources {
+            debug_tx,
+            rx,
+            menu,
+            config,
+            config_store,
+            uart_clock,
+            terminal,
+            sender,
+            bridge,
+            mut millis,
+            markers,
+            browser,
+            search,
+            rtc,
+            wall_clock,
+            burn_in,
+            encoder_steps,
+        } = cx.resources;
+
+        let now_ms = millis.lock(|millis| *millis);
+        let mut controls = Controls {
+            debug: debug_tx,
+            line: &mut board::Line { rx, clock: *uart_clock },
+            menu,
+            config,
+            config_store,
+            terminal,
+            sender,
+            bridge,
+            markers,
+            browser,
+            search,
+            rtc,
+            wall_clock,
+            burn_in,
+            encoder_steps,
+        };
+        controls.input(InputEvent::Press, now_ms);
+    }
+
+    /// Count milliseconds and send the pending bytes of a macro
+    #[task(binds = TIM16, resources = [ms_timer, millis, sender, tx, terminal, config], priority = 2)]
+    fn ms_tick(cx: ms_tick::Context) {
+        let ms_tick::Resources {
+            ms_timer,
+            mut millis,
+            sender,
+            tx,
             mut terminal,
-            uart_in_buffer
+            mut config,
         } = cx.resources;
 
-        let b = byte;
-        match uart_in_buffer.try_push(b as char) {
-            Ok(_n)  => {},
-            Err(_buffer_error) => {
-                // uart_in_buffer.clear();
-                return;
+        ms_timer.clear_irq();
+        millis.lock(|millis| *millis = millis.wrapping_add(1));
+
+        let done = match sender {
+            Some(sender) => {
+                let echo = config.lock(|config| config.tx.echo);
+                sender.tick();
+                while let Some(b) = sender.pending() {
+                    if tx.write(b).is_err() {
+                        // TX is busy, try again on the next tick
+                        break;
+                    }
+                    sender.advance();
+                    if echo {
+                        terminal.lock(|terminal| terminal.write_byte(b));
+                    }
+                }
+                sender.is_done()
             }
+            None => true,
+        };
+        if done {
+            *sender = None;
         }
+    }
 
-        if b == b'\n' {
-            let string = &uart_in_buffer[0..uart_in_buffer.len()];
-            terminal.lock(|terminal| terminal.write_string(string).unwrap());
-            uart_in_buffer.clear();
+    #[task(priority = 1, resources=[terminal, input, gps, template, template_decoder, plot, config, trigger, millis, wall_clock, burn_in], capacity = 100)]
+    fn uart_buffer(cx: uart_buffer::Context, byte: u8, parity_error: bool) {
+        let uart_buffer::Resources {
+            terminal,
+            input,
+            gps,
+            template,
+            template_decoder,
+            plot,
+            config,
+            trigger,
+            mut millis,
+            wall_clock,
+            burn_in,
+        } = cx.resources;
 
-        }
+        let now_ms = millis.lock(|millis| *millis);
+        let mut receiver = Receiver {
+            terminal,
+            input,
+            gps,
+            template,
+            template_decoder,
+            plot,
+            config,
+            trigger,
+            wall_clock,
+            burn_in,
+        };
+        receiver.byte(byte, parity_error, now_ms);
     }
 
-    #[task(binds = USART1, resources = [rx, led_r, debug_pin1,debug_pin2], priority = 4, spawn=[uart_buffer])]
+    /// Decode the frame collected in the Modbus view, spawned on the idle gap
+    #[task(priority = 1, resources=[terminal, input], capacity = 4)]
+    fn frame_end(cx: frame_end::Context) {
+        let frame_end::Resources {
+            terminal,
+            input,
+        } = cx.resources;
+
+        input.frame_end(terminal);
+    }
+
+    #[task(binds = USART1, resources = [rx, leds, config, error_counts, millis, bridge_lost], priority = 4, spawn=[uart_buffer, frame_end, bridge_out])]
     fn usart_in(cx: usart_in::Context) {
+        // last byte passed on, to not end a line twice
+        static mut LAST: u8 = b'\n';
+        // the byte after a parity error is the one with the wrong parity
+        static mut PARITY_ERROR: bool = false;
 
         let usart_in::Resources {
             rx,
-            led_r,
-            debug_pin1,
-            debug_pin2
+            leds,
+            config,
+            error_counts,
+            millis,
+            bridge_lost,
         } = cx.resources;
 
-        debug_pin1.set_high().unwrap();
-
         loop {
             match rx.read() {
                 Err(nb::Error::WouldBlock) => {
                     break;
                 },
                 Err(nb::Error::Other(err)) => {
-                    led_r.set_high().unwrap();
-                    match err {
+                    leds.set(Led::Red, true);
+                    let marker = match err {
                         SerialError::Overrun => {
-                            cx.spawn.uart_buffer('O' as u8).ok();
-                            debug_pin2.set_high().unwrap();
+                            error_counts.overrun += 1;
+                            'O'
                         },
                         SerialError::Framing => {
-                            cx.spawn.uart_buffer('F' as u8).ok();
-                        }
+                            error_counts.framing += 1;
+                            'F'
+                        },
                         SerialError::Noise => {
-                            cx.spawn.uart_buffer('N' as u8).ok();
+                            error_counts.noise += 1;
+                            'N'
                         },
                         SerialError::Parity => {
-                            cx.spawn.uart_buffer('P' as u8).ok();
+                            error_counts.parity += 1;
+                            *PARITY_ERROR = true;
+                            continue;
                         }
+                    };
+                    if config.filter.show_errors {
+                        cx.spawn.uart_buffer(marker as u8, false).ok();
+                        cx.spawn.uart_buffer('\n' as u8, false).ok();
+                        *LAST = b'\n';
                     }
-                    cx.spawn.uart_buffer('\n' as u8).ok();
                 },
                 Ok(byte) => {
-                    cx.spawn.uart_buffer(byte).ok();
+                    let byte = byte & config.uart.line.data_mask() as u8;
+                    let parity_error = core::mem::replace(PARITY_ERROR, false);
+                    cx.spawn.uart_buffer(byte, parity_error && config.filter.show_errors).ok();
+                    if config.bridge.enabled
+                        && cx.spawn.bridge_out(bridge::Channel::Rx1, byte, *millis).is_err()
+                    {
+                        *bridge_lost += 1;
+                    }
+                    *LAST = byte;
                 },
             }
         }
 
         if rx.timeout_lapsed() {
             rx.clear_timeout();
+            if config.view == View::Modbus {
+                cx.spawn.frame_end().ok();
+            } else if config.framing == Framing::Idle && config.view != View::Template && *LAST != b'\n' {
+                cx.spawn.uart_buffer(b'\n', false).ok();
+                *LAST = b'\n';
+            }
+        }
+    }
+
+    /// Pass the bytes of the second capture input on to the bridge
+    #[task(binds = USART2, resources = [rx2, config, millis, bridge_lost], priority = 4, spawn = [bridge_out])]
+    fn usart2_in(cx: usart2_in::Context) {
+        let usart2_in::Resources {
+            rx2,
+            config,
+            millis,
+            bridge_lost,
+        } = cx.resources;
+
+        loop {
+            match rx2.read() {
+                Err(nb::Error::WouldBlock) => break,
+                // the status bar counts the errors of RX1 only
+                Err(nb::Error::Other(_)) => {},
+                Ok(byte) if config.bridge.enabled => {
+                    let byte = byte & config.uart.line.data_mask() as u8;
+                    if cx.spawn.bridge_out(bridge::Channel::Rx2, byte, *millis).is_err() {
+                        *bridge_lost += 1;
+                    }
+                },
+                Ok(_) => {},
+            }
+        }
+    }
+
+    /// Collect command lines and remote control requests on the debug port, in
+    /// bridge mode pass the input on to the target
+    #[task(binds = USART3_4, resources = [debug_rx, debug_line, remote_in, config, tx], priority = 2, spawn = [debug_command, remote_request])]
+    fn debug_in(cx: debug_in::Context) {
+        let debug_in::Resources {
+            debug_rx,
+            debug_line,
+            remote_in,
+            mut config,
+            tx,
+        } = cx.resources;
+
+        let bridge = config.lock(|config| config.bridge.enabled);
+        loop {
+            match debug_rx.read() {
+                Err(nb::Error::WouldBlock) => break,
+                Ok(byte) if bridge => {
+                    nb::block!(tx.write(byte)).ok();
+                },
+                // a damaged command fails to parse, a damaged request its CRC check
+                Err(nb::Error::Other(_)) => {},
+                Ok(byte) if byte == 0 || remote_in.is_receiving() => {
+                    if let Some(Ok(packet)) = remote_in.push(byte) {
+                        let mut request = ArrayVec::new();
+                        // the decoder takes at most MAX_PACKET bytes
+                        request.try_extend_from_slice(packet).ok();
+                        cx.spawn.remote_request(request).ok();
+                    }
+                },
+                Ok(b'\n') => {
+                    cx.spawn.debug_command(*debug_line).ok();
+                    debug_line.clear();
+                },
+                Ok(b'\r') => {},
+                Ok(byte) => {
+                    // a line that is too long is cut off
+                    debug_line.try_push(byte as char).ok();
+                },
+            }
+        }
+    }
+
+    /// Send a captured byte out of the debug port, after the number of bytes
+    /// dropped before it
+    #[task(priority = 1, resources = [bridge, debug_tx, wall_clock, bridge_lost], capacity = 100)]
+    fn bridge_out(cx: bridge_out::Context, channel: bridge::Channel, byte: u8, time_ms: u32) {
+        // `bridge_lost` when last reported
+        static mut REPORTED: u32 = 0;
+
+        let bridge_out::Resources {
+            bridge,
+            debug_tx,
+            mut wall_clock,
+            mut bridge_lost,
+        } = cx.resources;
+
+        let mut out = |b| {
+            nb::block!(debug_tx.write(b)).ok();
+        };
+        bridge.set_wall_clock(wall_clock.lock(|clock| *clock));
+        let lost = bridge_lost.lock(|lost| *lost);
+        bridge.lost(lost.wrapping_sub(*REPORTED), time_ms, &mut out);
+        *REPORTED = lost;
+        bridge.push(channel, byte, time_ms, &mut out);
+    }
+
+    /// End a cut off bridge record when no bytes came in for a while
+    #[task(priority = 1, resources = [bridge, debug_tx])]
+    fn bridge_idle(cx: bridge_idle::Context) {
+        let bridge_idle::Resources {
+            bridge,
+            debug_tx,
+        } = cx.resources;
+
+        bridge.idle(&mut |b| {
+            nb::block!(debug_tx.write(b)).ok();
+        });
+    }
+
+    /// Answer a remote control request from the debug port
+    #[task(priority = 1, resources = [debug_tx, config, config_store, rx, uart_clock, terminal, canvas, trigger])]
+    fn remote_request(cx: remote_request::Context, packet: ArrayVec::<[u8; protocol::MAX_PACKET]>) {
+        let remote_request::Resources {
+            debug_tx,
+            config,
+            config_store,
+            rx,
+            uart_clock,
+            terminal,
+            canvas,
+            trigger,
+        } = cx.resources;
+
+        let mut device = Remote {
+            config,
+            config_store,
+            line: board::Line { rx, clock: *uart_clock },
+            terminal,
+            canvas,
+            trigger,
+        };
+        protocol::serve(&mut device, &packet, &mut |b| {
+            nb::block!(debug_tx.write(b)).ok();
+        });
+    }
+
+    /// Run a command from the debug port
+    #[task(priority = 1, resources = [debug_tx, config, config_store, template, template_decoder, plot, rtc, wall_clock, millis, render_errors], capacity = 2)]
+    fn debug_command(cx: debug_command::Context, line: ArrayString::<[u8; console::MAX_LINE]>) {
+        let debug_command::Resources {
+            debug_tx,
+            mut config,
+            config_store,
+            template,
+            template_decoder,
+            mut plot,
+            mut rtc,
+            mut wall_clock,
+            mut millis,
+            mut render_errors,
+        } = cx.resources;
+
+        let command = match console::parse(&line) {
+            Some(command) => command,
+            None => {
+                writeln!(debug_tx, "unknown command, try help").ok();
+                return;
+            }
+        };
+
+        let mut new_config = config.lock(|config| *config);
+        match command {
+            Command::Help => {
+                debug_tx.write_str(console::HELP).ok();
+                return;
+            }
+            Command::ShowTemplate => {
+                writeln!(debug_tx, "template: {}", new_config.template).ok();
+                return;
+            }
+            Command::SetTemplate(text) => {
+                let parsed = match Template::parse(text) {
+                    Ok(parsed) => parsed,
+                    Err(err) => {
+                        writeln!(debug_tx, "error: {}", err).ok();
+                        return;
+                    }
+                };
+                // parse checked the length
+                new_config.template = ArrayString::from(text).unwrap();
+                *template = parsed;
+                template_decoder.reset();
+            }
+            Command::ShowPlot => {
+                writeln!(debug_tx, "plot: {}", new_config.plot_pattern).ok();
+                return;
+            }
+            Command::SetPlot(pattern) => {
+                new_config.plot_pattern = match ArrayString::from(pattern) {
+                    Ok(pattern) => pattern,
+                    Err(_) => {
+                        writeln!(debug_tx, "error: pattern too long").ok();
+                        return;
+                    }
+                };
+                plot.lock(|plot| plot.clear());
+            }
+            Command::ShowMacros => {
+                let tx = &new_config.tx;
+                for (i, m) in tx.macros.iter().enumerate() {
+                    writeln!(debug_tx, "macro {} {} {}", i + 1, m.ending.name(), m.text).ok();
+                }
+                writeln!(debug_tx, "txdelay {} {}", tx.delays.byte_ms, tx.delays.line_ms).ok();
+                return;
+            }
+            Command::SetMacro(index, ending, text) => {
+                let text = match ArrayString::from(text) {
+                    Ok(text) => text,
+                    Err(_) => {
+                        writeln!(debug_tx, "error: macro too long").ok();
+                        return;
+                    }
+                };
+                let m = Macro { text, ending };
+                if let Err(err) = m.bytes() {
+                    writeln!(debug_tx, "error: {}", err).ok();
+                    return;
+                }
+                new_config.tx.macros[index] = m;
+            }
+            Command::SetDelays(delays) => new_config.tx.delays = delays,
+            Command::ShowTime => {
+                let (source, now) = rtc.lock(|rtc| (rtc.source(), rtc.now()));
+                match now {
+                    Some((time, _)) => writeln!(debug_tx, "time: {} ({})", time, source.name()).ok(),
+                    None => writeln!(debug_tx, "time: not set ({})", source.name()).ok(),
+                };
+                return;
+            }
+            Command::ShowDisplay => {
+                let display = &new_config.display;
+                writeln!(debug_tx, "contrast {}", display.contrast).ok();
+                writeln!(debug_tx, "precharge {}", display.pre_charge).ok();
+                writeln!(debug_tx, "shift {}", if display.pixel_shift { "on" } else { "off" }).ok();
+                writeln!(debug_tx, "dim {}", display.dim_after_s).ok();
+                writeln!(debug_tx, "saver {}", new_config.power.display_timeout_s).ok();
+                writeln!(debug_tx, "render errors {}", render_errors.lock(|errors| *errors)).ok();
+                return;
+            }
+            Command::Contrast(contrast) => new_config.display.contrast = contrast,
+            Command::PreChargeVoltage(level) => new_config.display.pre_charge = level,
+            Command::SetPixelShift(on) => new_config.display.pixel_shift = on,
+            Command::SetDim(seconds) => new_config.display.dim_after_s = seconds,
+            Command::SetSaver(seconds) => new_config.power.display_timeout_s = seconds,
+            Command::SetTime(time) => {
+                rtc.lock(|rtc| rtc.set(time));
+                let now_ms = millis.lock(|millis| *millis);
+                wall_clock.lock(|clock| *clock = Some(WallClock::new(time, 0, now_ms)));
+                writeln!(debug_tx, "ok").ok();
+                return;
+            }
         }
+        config.lock(|config| *config = new_config);
 
-        debug_pin1.set_low().unwrap();
-        debug_pin2.set_low().unwrap();
+        match config_store.save(&new_config) {
+            Ok(()) => writeln!(debug_tx, "ok").ok(),
+            Err(_) => writeln!(debug_tx, "error: save failed").ok(),
+        };
     }
 
 
@@ -347,6 +1191,7 @@ const APP: () = {
      extern "C" {
         fn SPI2();
         fn I2C1();
+        fn I2C2();
     }
 
 };
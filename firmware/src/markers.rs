@@ -0,0 +1,211 @@
+//! Session markers
+//!
+//! "Add marker" in the menu drops a numbered marker line into the terminal and
+//! out of the debug port, like `=== MARK 3 @ 00:12:34.567 ===` with the time
+//! since boot. With the menu closed the encoder jumps between the markers still
+//! in the scrollback, newest first, and back to the live view.
+
+use core::fmt;
+
+/// Markers remembered, older ones are forgotten
+pub const MAX_MARKERS: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Marker {
+    /// Counts from 1 since boot
+    pub number: u16,
+    /// Since boot, or of the day once the RTC is set
+    pub time_ms: u32,
+}
+
+/// "=== MARK 3 @ 00:12:34.567 ==="
+impl fmt::Display for Marker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = self.time_ms;
+        let s = ms / 1000;
+        write!(
+            f,
+            "=== MARK {} @ {:02}:{:02}:{:02}.{:03} ===",
+            self.number,
+            s / 3600,
+            s / 60 % 60,
+            s % 60,
+            ms % 1000
+        )
+    }
+}
+
+/// The latest markers
+pub struct Markers {
+    list: [Marker; MAX_MARKERS],
+    /// Index in `list` of the next marker
+    next: usize,
+    len: usize,
+    number: u16,
+}
+
+impl Markers {
+    pub const fn new() -> Self {
+        Self {
+            list: [Marker { number: 0, time_ms: 0 }; MAX_MARKERS],
+            next: 0,
+            len: 0,
+            number: 0,
+        }
+    }
+
+    /// Add the next marker, dropped at `time_ms`
+    pub fn add(&mut self, time_ms: u32) -> Marker {
+        self.number = self.number.wrapping_add(1).max(1);
+        let marker = Marker {
+            number: self.number,
+            time_ms,
+        };
+        self.list[self.next] = marker;
+        self.next = (self.next + 1) % MAX_MARKERS;
+        self.len = (self.len + 1).min(MAX_MARKERS);
+        marker
+    }
+
+    /// Marker `n` counted from the newest one
+    pub fn newest(&self, n: usize) -> Option<Marker> {
+        if n >= self.len {
+            return None;
+        }
+        Some(self.list[(self.next + MAX_MARKERS - 1 - n) % MAX_MARKERS])
+    }
+
+    /// Markers newest first
+    pub fn iter(&self) -> impl Iterator<Item = Marker> + '_ {
+        (0..self.len).filter_map(move |n| self.newest(n))
+    }
+}
+
+/// Which marker is shown while browsing the scrollback
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Browser {
+    /// Counted from the newest marker, None shows the live view
+    selected: Option<usize>,
+}
+
+impl Browser {
+    pub const fn new() -> Self {
+        Self { selected: None }
+    }
+
+    /// The shown marker counted from the newest one, None for the live view
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    /// Go back to the live view
+    pub fn live(&mut self) {
+        self.selected = None;
+    }
+
+    /// Move `steps` markers, negative steps go back to older markers. Only the
+    /// `available` newest markers are still in the scrollback; turning past the
+    /// newest one goes to the live view, past the oldest one stays there.
+    pub fn turn(&mut self, steps: i32, available: usize) {
+        if available == 0 {
+            self.selected = None;
+            return;
+        }
+        // the live view is one step newer than the newest marker
+        let position = self.selected.map_or(-1, |n| n.min(available - 1) as i32);
+        let position = (position - steps).min(available as i32 - 1);
+        self.selected = if position < 0 { None } else { Some(position as usize) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(markers: &Markers) -> Vec<u16> {
+        markers.iter().map(|marker| marker.number).collect()
+    }
+
+    #[test]
+    fn marker_line() {
+        let marker = Marker { number: 3, time_ms: 754_567 };
+        assert_eq!(format!("{}", marker), "=== MARK 3 @ 00:12:34.567 ===");
+        let marker = Marker { number: 1, time_ms: 0 };
+        assert_eq!(format!("{}", marker), "=== MARK 1 @ 00:00:00.000 ===");
+        // the millisecond count wraps after 49 days
+        let marker = Marker { number: 65535, time_ms: u32::MAX };
+        assert_eq!(format!("{}", marker), "=== MARK 65535 @ 1193:02:47.295 ===");
+    }
+
+    #[test]
+    fn newest_first() {
+        let mut markers = Markers::new();
+        assert_eq!(markers.newest(0), None);
+        assert_eq!(markers.add(100), Marker { number: 1, time_ms: 100 });
+        markers.add(200);
+        assert_eq!(numbers(&markers), [2, 1]);
+        assert_eq!(markers.newest(1), Some(Marker { number: 1, time_ms: 100 }));
+        assert_eq!(markers.newest(2), None);
+    }
+
+    #[test]
+    fn old_markers_are_forgotten() {
+        let mut markers = Markers::new();
+        for time in 0..MAX_MARKERS as u32 + 3 {
+            markers.add(time);
+        }
+        let expected: Vec<u16> = (4..=MAX_MARKERS as u16 + 3).rev().collect();
+        assert_eq!(numbers(&markers), expected);
+        assert_eq!(markers.newest(MAX_MARKERS - 1).map(|m| m.number), Some(4));
+        assert_eq!(markers.newest(MAX_MARKERS), None);
+    }
+
+    #[test]
+    fn numbers_skip_zero_when_wrapping() {
+        let mut markers = Markers::new();
+        markers.number = u16::MAX - 1;
+        assert_eq!(markers.add(0).number, u16::MAX);
+        assert_eq!(markers.add(0).number, 1);
+    }
+
+    #[test]
+    fn browsing() {
+        let mut browser = Browser::new();
+        assert_eq!(browser.selected(), None);
+        // nothing to browse
+        browser.turn(-1, 0);
+        assert_eq!(browser.selected(), None);
+
+        browser.turn(-1, 3);
+        assert_eq!(browser.selected(), Some(0));
+        browser.turn(-5, 3);
+        // stays at the oldest
+        assert_eq!(browser.selected(), Some(2));
+        browser.turn(1, 3);
+        assert_eq!(browser.selected(), Some(1));
+        browser.turn(2, 3);
+        assert_eq!(browser.selected(), None);
+        browser.turn(3, 3);
+        assert_eq!(browser.selected(), None);
+
+        browser.turn(-2, 3);
+        browser.live();
+        assert_eq!(browser.selected(), None);
+    }
+
+    #[test]
+    fn markers_leaving_the_scrollback() {
+        let mut browser = Browser::new();
+        browser.turn(-3, 3);
+        assert_eq!(browser.selected(), Some(2));
+        // the oldest one scrolled out, one step newer shows the newest left
+        browser.turn(1, 1);
+        assert_eq!(browser.selected(), None);
+        browser.turn(-3, 3);
+        browser.turn(0, 2);
+        assert_eq!(browser.selected(), Some(1));
+        // all gone
+        browser.turn(0, 0);
+        assert_eq!(browser.selected(), None);
+    }
+}
@@ -0,0 +1,854 @@
+//! Settings menu
+//!
+//! Opened with the button. Turning the encoder moves through the items, the
+//! button starts and stops editing the selected item, where turning changes its
+//! value. "Exit" closes the menu and hands back the changed settings.
+//!
+//! "Send" opens the list of transmit macros, the button sends the selected one
+//! until "Back" is chosen.
+//!
+//! "Set clock" opens the date and time fields, "Set" hands them over to be set
+//! on the RTC.
+//!
+//! "Add marker" is the first item, so two presses drop a marker (see `markers`).
+//! It closes the menu without applying changed settings, like "Find" once its
+//! search term is entered (see `search`).
+
+use core::fmt::{self, Write};
+
+use crate::clock::{days_in_month, DateTime};
+use crate::config::{BridgeConfig, Config, Framing, View};
+use crate::font::FontSize;
+use crate::framer::PacketCrc;
+use crate::layout::Layout;
+use crate::line::{DataBits, LineError, Parity, StopBits};
+use crate::macros::{Macro, MAX_MACROS};
+use crate::search::{Entry, Term};
+use crate::wrap::WrapMode;
+
+/// Contrast change per encoder step
+const CONTRAST_STEP: i32 = 16;
+/// Lowest contrast in the menu, a black screen would look broken
+const MIN_CONTRAST: i32 = 0x0F;
+
+/// Baud rates to choose from
+const BAUDRATES: [u32; 13] = [
+    1200, 2400, 4800, 9600, 19200, 38400, 57600, 115_200, 230_400, 460_800, 921_600, 1_000_000,
+    2_000_000,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Item {
+    Mark,
+    Find,
+    Baudrate,
+    DataBits,
+    Parity,
+    StopBits,
+    RxInvert,
+    MsbFirst,
+    Swap,
+    View,
+    Layout,
+    Font,
+    Wrap,
+    Timestamps,
+    Clock,
+    Contrast,
+    PixelShift,
+    Framing,
+    PacketCrc,
+    Send,
+    Echo,
+    Bridge,
+    Exit,
+}
+
+const ITEMS: [Item; 23] = [
+    Item::Mark,
+    Item::Find,
+    Item::Baudrate,
+    Item::DataBits,
+    Item::Parity,
+    Item::StopBits,
+    Item::RxInvert,
+    Item::MsbFirst,
+    Item::Swap,
+    Item::View,
+    Item::Layout,
+    Item::Font,
+    Item::Wrap,
+    Item::Timestamps,
+    Item::Clock,
+    Item::Contrast,
+    Item::PixelShift,
+    Item::Framing,
+    Item::PacketCrc,
+    Item::Send,
+    Item::Echo,
+    Item::Bridge,
+    Item::Exit,
+];
+
+/// What the caller has to do after an input
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    None,
+    /// The menu text changed
+    Redraw,
+    /// The menu closed, apply and store the settings of `Menu::config`
+    Apply,
+    /// Send a macro to the target
+    Send(Macro),
+    /// The menu closed, drop a marker
+    Mark,
+    /// The menu closed, search the kept lines
+    Find(Term),
+    /// The menu closed, set the RTC
+    SetClock(DateTime),
+}
+
+/// Rows of the clock fields: year, month, day, hour, minute, "Set" and "Back"
+const CLOCK_ROWS: usize = 7;
+const CLOCK_SET: usize = 5;
+const CLOCK_BACK: usize = 6;
+
+pub struct Menu {
+    open: bool,
+    selected: usize,
+    editing: bool,
+    /// The macro list is shown instead of the items
+    sending: bool,
+    /// Selected macro, `MAX_MACROS` is "Back"
+    selected_macro: usize,
+    /// The search term entry is shown instead of the items
+    finding: bool,
+    /// Keeps the last search term
+    entry: Entry,
+    /// The clock fields are shown instead of the items
+    setting_clock: bool,
+    selected_field: usize,
+    clock: DateTime,
+    config: Config,
+    /// Shown on the exit line when the settings can not be applied
+    error: Option<LineError>,
+}
+
+impl Menu {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            selected: 0,
+            editing: false,
+            sending: false,
+            selected_macro: 0,
+            finding: false,
+            entry: Entry::new(),
+            setting_clock: false,
+            selected_field: 0,
+            clock: DateTime::default(),
+            config: Config::default(),
+            error: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// The settings as edited
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Open the menu to edit `config`, "Set clock" starts at `now`
+    pub fn open(&mut self, config: Config, now: DateTime) -> Action {
+        self.open = true;
+        self.selected = 0;
+        self.editing = false;
+        self.sending = false;
+        self.finding = false;
+        self.setting_clock = false;
+        self.clock = DateTime { second: 0, ..now };
+        self.config = config;
+        self.error = None;
+        Action::Redraw
+    }
+
+    /// Button pressed while the menu is open
+    pub fn press(&mut self) -> Action {
+        if !self.open {
+            return Action::None;
+        }
+
+        if self.sending {
+            return match self.config.tx.macros.get(self.selected_macro) {
+                Some(m) if m.is_empty() => Action::None,
+                Some(&m) => Action::Send(m),
+                None => {
+                    self.sending = false;
+                    Action::Redraw
+                }
+            };
+        }
+        if self.finding {
+            if !self.entry.press() {
+                return Action::Redraw;
+            }
+            self.finding = false;
+            self.open = false;
+            return Action::Find(Term::from(self.entry.text()).unwrap_or_default());
+        }
+        if self.setting_clock {
+            return match self.selected_field {
+                CLOCK_SET => {
+                    self.setting_clock = false;
+                    self.open = false;
+                    Action::SetClock(self.clock)
+                }
+                CLOCK_BACK => {
+                    self.setting_clock = false;
+                    Action::Redraw
+                }
+                _ => {
+                    self.editing = !self.editing;
+                    Action::Redraw
+                }
+            };
+        }
+
+        match ITEMS[self.selected] {
+            Item::Exit => {}
+            Item::Mark => {
+                self.open = false;
+                return Action::Mark;
+            }
+            Item::Find => {
+                self.finding = true;
+                return Action::Redraw;
+            }
+            Item::Clock => {
+                self.setting_clock = true;
+                self.selected_field = 0;
+                return Action::Redraw;
+            }
+            Item::Send => {
+                self.sending = true;
+                self.selected_macro = 0;
+                return Action::Redraw;
+            }
+            _ => {
+                self.editing = !self.editing;
+                return Action::Redraw;
+            }
+        }
+
+        match self.config.uart.line.validate() {
+            Ok(()) => {
+                self.open = false;
+                Action::Apply
+            }
+            Err(err) => {
+                self.error = Some(err);
+                Action::Redraw
+            }
+        }
+    }
+
+    /// Encoder turned by `step` detents
+    pub fn turn(&mut self, step: i32) -> Action {
+        if !self.open || step == 0 {
+            return Action::None;
+        }
+
+        if self.sending {
+            self.selected_macro = wrap(self.selected_macro, step, MAX_MACROS + 1);
+            return Action::Redraw;
+        }
+        if self.finding {
+            self.entry.turn(step);
+            return Action::Redraw;
+        }
+        if self.setting_clock {
+            if self.editing {
+                self.turn_clock(step);
+            } else {
+                self.selected_field = wrap(self.selected_field, step, CLOCK_ROWS);
+            }
+            return Action::Redraw;
+        }
+        if !self.editing {
+            self.selected = wrap(self.selected, step, ITEMS.len());
+            return Action::Redraw;
+        }
+
+        let uart = &mut self.config.uart;
+        let line = &mut uart.line;
+        match ITEMS[self.selected] {
+            Item::Baudrate => {
+                let index = BAUDRATES
+                    .iter()
+                    .position(|&b| b >= uart.baudrate)
+                    .unwrap_or(BAUDRATES.len() - 1);
+                uart.baudrate = BAUDRATES[wrap(index, step, BAUDRATES.len())];
+            }
+            Item::DataBits => {
+                const BITS: [DataBits; 4] =
+                    [DataBits::Bits6, DataBits::Bits7, DataBits::Bits8, DataBits::Bits9];
+                line.data_bits = cycle(&BITS, line.data_bits, step);
+            }
+            Item::Parity => {
+                const PARITY: [Parity; 3] = [Parity::None, Parity::Even, Parity::Odd];
+                line.parity = cycle(&PARITY, line.parity, step);
+            }
+            Item::StopBits => {
+                const STOP: [StopBits; 4] =
+                    [StopBits::Stop0_5, StopBits::Stop1, StopBits::Stop1_5, StopBits::Stop2];
+                line.stop_bits = cycle(&STOP, line.stop_bits, step);
+            }
+            Item::RxInvert => line.rx_invert = !line.rx_invert,
+            Item::MsbFirst => line.msb_first = !line.msb_first,
+            Item::Swap => line.swap = !line.swap,
+            Item::View => {
+                const VIEWS: [View; 6] = [
+                    View::Text,
+                    View::Modbus,
+                    View::Nmea,
+                    View::Gps,
+                    View::Template,
+                    View::Plot,
+                ];
+                self.config.view = cycle(&VIEWS, self.config.view, step);
+            }
+            Item::Layout => {
+                const LAYOUTS: [Layout; 3] = [Layout::Full, Layout::Status, Layout::SplitPlot];
+                let display = &mut self.config.display;
+                display.layout = cycle(&LAYOUTS, display.layout, step);
+            }
+            Item::Font => {
+                const FONTS: [FontSize; 3] = [FontSize::Small, FontSize::Medium, FontSize::Large];
+                let display = &mut self.config.display;
+                display.font = cycle(&FONTS, display.font, step);
+            }
+            Item::Wrap => {
+                const WRAP: [WrapMode; 3] = [WrapMode::Char, WrapMode::Word, WrapMode::Off];
+                let display = &mut self.config.display;
+                display.wrap = cycle(&WRAP, display.wrap, step);
+            }
+            Item::Framing => {
+                const FRAMING: [Framing; 4] =
+                    [Framing::Lines, Framing::Idle, Framing::Slip, Framing::Cobs];
+                self.config.framing = cycle(&FRAMING, self.config.framing, step);
+            }
+            Item::PacketCrc => {
+                const CRC: [PacketCrc; 3] = [PacketCrc::None, PacketCrc::Crc16, PacketCrc::Crc32];
+                self.config.packet_crc = cycle(&CRC, self.config.packet_crc, step);
+            }
+            Item::Timestamps => {
+                let display = &mut self.config.display;
+                display.timestamps = !display.timestamps;
+            }
+            Item::Contrast => {
+                let display = &mut self.config.display;
+                let contrast = display.contrast as i32 + step * CONTRAST_STEP;
+                display.contrast = contrast.clamp(MIN_CONTRAST, 0xFF) as u8;
+            }
+            Item::PixelShift => {
+                let display = &mut self.config.display;
+                display.pixel_shift = !display.pixel_shift;
+            }
+            Item::Echo => {
+                let tx = &mut self.config.tx;
+                tx.echo = !tx.echo;
+            }
+            Item::Bridge => {
+                const fn bridge(enabled: bool, tags: bool, timestamps: bool) -> BridgeConfig {
+                    BridgeConfig { enabled, tags, timestamps }
+                }
+                const BRIDGE: [BridgeConfig; 5] = [
+                    bridge(false, false, false),
+                    bridge(true, false, false),
+                    bridge(true, true, false),
+                    bridge(true, false, true),
+                    bridge(true, true, true),
+                ];
+                self.config.bridge = cycle(&BRIDGE, self.config.bridge, step);
+            }
+            Item::Mark | Item::Find | Item::Clock | Item::Send | Item::Exit => {}
+        }
+        self.error = None;
+        Action::Redraw
+    }
+
+    /// Change the edited clock field by `step`
+    fn turn_clock(&mut self, step: i32) {
+        let clock = &mut self.clock;
+        match self.selected_field {
+            0 => clock.year = (2000 + wrap(clock.year as usize - 2000, step, 100)) as u16,
+            1 => clock.month = 1 + wrap(clock.month as usize - 1, step, 12) as u8,
+            2 => {
+                let days = days_in_month(clock.year, clock.month) as usize;
+                clock.day = 1 + wrap(clock.day as usize - 1, step, days) as u8;
+            }
+            3 => clock.hour = wrap(clock.hour as usize, step, 24) as u8,
+            4 => clock.minute = wrap(clock.minute as usize, step, 60) as u8,
+            _ => {}
+        }
+        clock.day = clock.day.min(days_in_month(clock.year, clock.month));
+    }
+
+    /// Number of lines of menu text
+    pub fn rows(&self) -> usize {
+        if self.sending {
+            MAX_MACROS + 1
+        } else if self.finding {
+            2
+        } else if self.setting_clock {
+            CLOCK_ROWS
+        } else {
+            ITEMS.len()
+        }
+    }
+
+    /// First item shown when `lines` lines fit on the screen, keeping the selection visible
+    pub fn first_row(&self, lines: usize) -> usize {
+        (self.selection() + 1).saturating_sub(lines)
+    }
+
+    /// Is `row` the selected item
+    pub fn is_selected(&self, row: usize) -> bool {
+        row == self.selection()
+    }
+
+    fn selection(&self) -> usize {
+        if self.sending {
+            self.selected_macro
+        } else if self.finding {
+            // the wheel
+            1
+        } else if self.setting_clock {
+            self.selected_field
+        } else {
+            self.selected
+        }
+    }
+
+    /// Write the text of menu line `row`
+    pub fn write_row<W: Write>(&self, row: usize, w: &mut W) -> fmt::Result {
+        if self.sending {
+            return match self.config.tx.macros.get(row) {
+                Some(m) if m.is_empty() => write!(w, "{} -", row + 1),
+                Some(m) => write!(w, "{} {} +{}", row + 1, m.text, m.ending.name()),
+                None if row == MAX_MACROS => w.write_str("Back"),
+                None => Ok(()),
+            };
+        }
+        if self.finding {
+            return match row {
+                0 => write!(w, "Find: {}_", self.entry.text()),
+                1 => self.entry.write_wheel(w),
+                _ => Ok(()),
+            };
+        }
+        if self.setting_clock {
+            let (open, close) = if self.editing && row == self.selected_field {
+                ('<', '>')
+            } else {
+                (' ', ' ')
+            };
+            let clock = &self.clock;
+            return match row {
+                0 => write!(w, "Year         {}{}{}", open, clock.year, close),
+                1 => write!(w, "Month        {}{}{}", open, clock.month, close),
+                2 => write!(w, "Day          {}{}{}", open, clock.day, close),
+                3 => write!(w, "Hour         {}{:02}{}", open, clock.hour, close),
+                4 => write!(w, "Minute       {}{:02}{}", open, clock.minute, close),
+                CLOCK_SET => write!(w, "Set {}", clock),
+                CLOCK_BACK => w.write_str("Back"),
+                _ => Ok(()),
+            };
+        }
+
+        let item = match ITEMS.get(row) {
+            Some(&item) => item,
+            None => return Ok(()),
+        };
+        let (open, close) = if self.editing && row == self.selected {
+            ('<', '>')
+        } else {
+            (' ', ' ')
+        };
+        let uart = &self.config.uart;
+        let line = &uart.line;
+
+        match item {
+            Item::Mark => w.write_str("Add marker"),
+            Item::Find => w.write_str("Find ..."),
+            Item::Baudrate => write!(w, "Baud rate    {}{}{}", open, uart.baudrate, close),
+            Item::DataBits => write!(w, "Data bits    {}{}{}", open, line.data_bits as u8, close),
+            Item::Parity => {
+                let parity = match line.parity {
+                    Parity::None => "none",
+                    Parity::Even => "even",
+                    Parity::Odd => "odd",
+                };
+                write!(w, "Parity       {}{}{}", open, parity, close)
+            }
+            Item::StopBits => {
+                let stop = match line.stop_bits {
+                    StopBits::Stop0_5 => "0.5",
+                    StopBits::Stop1 => "1",
+                    StopBits::Stop1_5 => "1.5",
+                    StopBits::Stop2 => "2",
+                };
+                write!(w, "Stop bits    {}{}{}", open, stop, close)
+            }
+            Item::RxInvert => write!(w, "RX inverted  {}{}{}", open, on_off(line.rx_invert), close),
+            Item::MsbFirst => write!(w, "MSB first    {}{}{}", open, on_off(line.msb_first), close),
+            Item::Swap => write!(w, "Swap TX/RX   {}{}{}", open, on_off(line.swap), close),
+            Item::View => {
+                let view = match self.config.view {
+                    View::Text => "text",
+                    View::Modbus => "modbus",
+                    View::Nmea => "nmea",
+                    View::Gps => "gps summary",
+                    View::Template => "template",
+                    View::Plot => "plot",
+                };
+                write!(w, "View         {}{}{}", open, view, close)
+            }
+            Item::Layout => {
+                let layout = match self.config.display.layout {
+                    Layout::Full => "full screen",
+                    Layout::Status => "status bar",
+                    Layout::SplitPlot => "text + plot",
+                };
+                write!(w, "Layout       {}{}{}", open, layout, close)
+            }
+            Item::Font => {
+                let font = match self.config.display.font {
+                    FontSize::Small => "4x6 (64x10)",
+                    FontSize::Medium => "6x8 (42x8)",
+                    FontSize::Large => "8x16 (32x4)",
+                };
+                write!(w, "Font         {}{}{}", open, font, close)
+            }
+            Item::Wrap => {
+                let wrap = match self.config.display.wrap {
+                    WrapMode::Char => "wrap",
+                    WrapMode::Word => "word wrap",
+                    WrapMode::Off => "cut off, pan",
+                };
+                write!(w, "Long lines   {}{}{}", open, wrap, close)
+            }
+            Item::Timestamps => {
+                write!(w, "Timestamps   {}{}{}", open, on_off(self.config.display.timestamps), close)
+            }
+            Item::Clock => w.write_str("Set clock ..."),
+            Item::Contrast => write!(w, "Contrast     {}{}{}", open, self.config.display.contrast, close),
+            Item::PixelShift => {
+                write!(w, "Pixel shift  {}{}{}", open, on_off(self.config.display.pixel_shift), close)
+            }
+            Item::Framing => {
+                let framing = match self.config.framing {
+                    Framing::Lines => "lines",
+                    Framing::Idle => "lines+idle",
+                    Framing::Slip => "SLIP",
+                    Framing::Cobs => "COBS",
+                };
+                write!(w, "Framing      {}{}{}", open, framing, close)
+            }
+            Item::PacketCrc => {
+                let crc = match self.config.packet_crc {
+                    PacketCrc::None => "none",
+                    PacketCrc::Crc16 => "CRC-16",
+                    PacketCrc::Crc32 => "CRC-32",
+                };
+                write!(w, "Packet CRC   {}{}{}", open, crc, close)
+            }
+            Item::Send => w.write_str("Send macros ..."),
+            Item::Echo => write!(w, "TX echo      {}{}{}", open, on_off(self.config.tx.echo), close),
+            Item::Bridge => {
+                let bridge = &self.config.bridge;
+                let mode = match (bridge.enabled, bridge.tags, bridge.timestamps) {
+                    (false, _, _) => "off",
+                    (true, false, false) => "raw",
+                    (true, true, false) => "tags",
+                    (true, false, true) => "time",
+                    (true, true, true) => "tags+time",
+                };
+                write!(w, "Bridge       {}{}{}", open, mode, close)
+            }
+            Item::Exit => match self.error {
+                Some(LineError::WordLength) => write!(w, "Exit: {} not supported", line),
+                None => write!(w, "Exit ({} {})", uart.baudrate, line),
+            },
+        }
+    }
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+/// `index` moved by `step`, wrapping around at `len`
+fn wrap(index: usize, step: i32, len: usize) -> usize {
+    (index as i32 + step).rem_euclid(len as i32) as usize
+}
+
+/// The value `step` places after `current` in `values`
+fn cycle<T: Copy + PartialEq>(values: &[T], current: T, step: i32) -> T {
+    let index = values.iter().position(|&v| v == current).unwrap_or(0);
+    values[wrap(index, step, values.len())]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrayvec::ArrayString;
+
+    fn now() -> DateTime {
+        DateTime { year: 2024, month: 2, day: 29, hour: 23, minute: 59, second: 30 }
+    }
+
+    fn opened(config: Config) -> Menu {
+        let mut menu = Menu::new();
+        assert_eq!(menu.open(config, now()), Action::Redraw);
+        menu
+    }
+
+    /// Move the selection onto `item`
+    fn select(menu: &mut Menu, item: Item) {
+        let index = ITEMS.iter().position(|&i| i == item).unwrap();
+        menu.turn(index as i32 - menu.selected as i32);
+        assert!(menu.is_selected(index));
+    }
+
+    /// Edit `item` by turning `steps` one step at a time
+    fn edit(menu: &mut Menu, item: Item, steps: &[i32]) {
+        select(menu, item);
+        menu.press();
+        for &step in steps {
+            assert_eq!(menu.turn(step), Action::Redraw);
+        }
+        menu.press();
+    }
+
+    fn row(menu: &Menu, row: usize) -> String {
+        let mut text = String::new();
+        menu.write_row(row, &mut text).unwrap();
+        text
+    }
+
+    #[test]
+    fn closed_menu_takes_no_input() {
+        let mut menu = Menu::new();
+        assert_eq!(menu.press(), Action::None);
+        assert_eq!(menu.turn(1), Action::None);
+        let mut menu = opened(Config::default());
+        assert_eq!(menu.turn(0), Action::None);
+    }
+
+    #[test]
+    fn items_wrap_around() {
+        let mut menu = opened(Config::default());
+        assert!(menu.is_selected(0));
+        assert_eq!(row(&menu, 0), "Add marker");
+        assert_eq!(menu.turn(-1), Action::Redraw);
+        assert!(menu.is_selected(ITEMS.len() - 1));
+        assert_eq!(row(&menu, ITEMS.len() - 1), "Exit (115200 8N1)");
+        // the selection stays on the last of 8 lines shown
+        assert_eq!(menu.first_row(8), ITEMS.len() - 8);
+        menu.turn(2);
+        assert!(menu.is_selected(1));
+        assert_eq!(menu.first_row(8), 0);
+        assert_eq!(menu.rows(), ITEMS.len());
+        assert_eq!(row(&menu, ITEMS.len()), "");
+    }
+
+    #[test]
+    fn values_cycle() {
+        let mut menu = opened(Config::default());
+        select(&mut menu, Item::Baudrate);
+        menu.press();
+        assert_eq!(row(&menu, 2), "Baud rate    <115200>");
+        menu.turn(1);
+        assert_eq!(menu.config().uart.baudrate, 230_400);
+        menu.press();
+        assert_eq!(row(&menu, 2), "Baud rate     230400 ");
+
+        // off the end of the list and back
+        edit(&mut menu, Item::Baudrate, &[5]);
+        assert_eq!(menu.config().uart.baudrate, 1200);
+        edit(&mut menu, Item::Baudrate, &[-1]);
+        assert_eq!(menu.config().uart.baudrate, 2_000_000);
+
+        edit(&mut menu, Item::Parity, &[1, 1, 1, 1]);
+        assert_eq!(menu.config().uart.line.parity, Parity::Even);
+        edit(&mut menu, Item::Bridge, &[-1]);
+        assert_eq!(row(&menu, 21), "Bridge        tags+time ");
+        edit(&mut menu, Item::Echo, &[1, 1, 1]);
+        assert!(!menu.config().tx.echo);
+    }
+
+    #[test]
+    fn contrast_limits() {
+        let mut config = Config::default();
+        config.display.contrast = 0x20;
+        let mut menu = opened(config);
+        edit(&mut menu, Item::Contrast, &[-1]);
+        assert_eq!(menu.config().display.contrast, 0x10);
+        // never black
+        edit(&mut menu, Item::Contrast, &[-1]);
+        assert_eq!(menu.config().display.contrast, MIN_CONTRAST as u8);
+        edit(&mut menu, Item::Contrast, &[-100]);
+        assert_eq!(menu.config().display.contrast, MIN_CONTRAST as u8);
+        edit(&mut menu, Item::Contrast, &[100]);
+        assert_eq!(menu.config().display.contrast, 0xFF);
+    }
+
+    #[test]
+    fn exit_applies_the_changes() {
+        let mut menu = opened(Config::default());
+        edit(&mut menu, Item::View, &[1]);
+        edit(&mut menu, Item::Font, &[1]);
+        select(&mut menu, Item::Exit);
+        assert_eq!(menu.press(), Action::Apply);
+        assert!(!menu.is_open());
+        assert_eq!(menu.config().view, View::Modbus);
+        assert_eq!(menu.config().display.font, FontSize::Large);
+
+        // opening again starts over from the given settings
+        menu.open(Config::default(), now());
+        assert_eq!(menu.config().view, View::Text);
+        assert!(menu.is_selected(0));
+    }
+
+    #[test]
+    fn unsupported_line_is_not_applied() {
+        let mut menu = opened(Config::default());
+        // 6 data bits without parity
+        edit(&mut menu, Item::DataBits, &[-1, -1]);
+        select(&mut menu, Item::Exit);
+        assert_eq!(menu.press(), Action::Redraw);
+        assert!(menu.is_open());
+        assert_eq!(row(&menu, ITEMS.len() - 1), "Exit: 6N1 not supported");
+        // a change clears the error, with parity the word has 7 bits
+        edit(&mut menu, Item::Parity, &[1]);
+        assert_eq!(row(&menu, ITEMS.len() - 1), "Exit (115200 6E1)");
+        select(&mut menu, Item::Exit);
+        assert_eq!(menu.press(), Action::Apply);
+    }
+
+    #[test]
+    fn mark_closes_the_menu() {
+        let mut menu = opened(Config::default());
+        assert_eq!(menu.press(), Action::Mark);
+        assert!(!menu.is_open());
+    }
+
+    #[test]
+    fn send_and_back() {
+        let mut config = Config::default();
+        config.tx.macros[1].text = ArrayString::from("AT").unwrap();
+        let macro_1 = config.tx.macros[1];
+        let mut menu = opened(config);
+        select(&mut menu, Item::Send);
+        assert_eq!(menu.press(), Action::Redraw);
+        assert_eq!(menu.rows(), MAX_MACROS + 1);
+        // empty macros are not sent
+        assert_eq!(menu.press(), Action::None);
+        menu.turn(1);
+        assert_eq!(menu.press(), Action::Send(macro_1));
+        assert!(menu.is_open());
+        menu.turn(-2);
+        assert_eq!(row(&menu, MAX_MACROS), "Back");
+        assert_eq!(menu.press(), Action::Redraw);
+        assert_eq!(menu.rows(), ITEMS.len());
+        assert!(menu.is_selected(ITEMS.iter().position(|&i| i == Item::Send).unwrap()));
+    }
+
+    #[test]
+    fn clock_fields() {
+        let mut menu = opened(Config::default());
+        select(&mut menu, Item::Clock);
+        menu.press();
+        assert_eq!(menu.rows(), CLOCK_ROWS);
+        // seconds start at 0
+        assert_eq!(row(&menu, CLOCK_SET), "Set 2024-02-29 23:59:00");
+        // one year on, the 29th is not in February
+        menu.press();
+        menu.turn(1);
+        menu.press();
+        assert_eq!(row(&menu, 0), "Year          2025 ");
+        assert_eq!(row(&menu, 2), "Day           28 ");
+        menu.turn(4);
+        menu.press();
+        menu.turn(1);
+        menu.press();
+        menu.turn(1);
+        let set = DateTime { year: 2025, month: 2, day: 28, hour: 23, minute: 0, second: 0 };
+        assert_eq!(menu.press(), Action::SetClock(set));
+        assert!(!menu.is_open());
+
+        // "Back" leaves the RTC alone
+        let mut menu = opened(Config::default());
+        select(&mut menu, Item::Clock);
+        menu.press();
+        menu.turn(-1);
+        assert_eq!(row(&menu, CLOCK_BACK), "Back");
+        assert_eq!(menu.press(), Action::Redraw);
+        assert!(menu.is_open());
+        assert_eq!(menu.rows(), ITEMS.len());
+    }
+
+    #[test]
+    fn find_hands_over_the_term() {
+        let mut menu = opened(Config::default());
+        select(&mut menu, Item::Find);
+        menu.press();
+        assert_eq!(menu.rows(), 2);
+        assert!(menu.is_selected(1));
+        // the wheel starts on 'a'
+        menu.press();
+        menu.turn(1);
+        menu.press();
+        assert_eq!(row(&menu, 0), "Find: ab_");
+        menu.turn(-4);
+        assert_eq!(menu.press(), Action::Find(Term::from("ab").unwrap()));
+        assert!(!menu.is_open());
+    }
+
+    #[test]
+    fn find_deletes_and_keeps_the_term() {
+        let mut menu = opened(Config::default());
+        select(&mut menu, Item::Find);
+        menu.press();
+        menu.press();
+        assert_eq!(row(&menu, 0), "Find: a_");
+        menu.turn(-2);
+        assert_eq!(row(&menu, 1), " \"  @  ok [del] spc  a  b ");
+        menu.press();
+        assert_eq!(row(&menu, 0), "Find: _");
+        // deleting from an empty term does nothing, ok hands over the empty term
+        menu.press();
+        menu.turn(-1);
+        assert_eq!(menu.press(), Action::Find(Term::new()));
+
+        // the term is kept for the next search
+        let mut menu = opened(Config::default());
+        select(&mut menu, Item::Find);
+        menu.press();
+        menu.press();
+        menu.turn(-3);
+        assert_eq!(menu.press(), Action::Find(Term::from("a").unwrap()));
+        menu.open(Config::default(), now());
+        select(&mut menu, Item::Find);
+        menu.press();
+        assert_eq!(row(&menu, 0), "Find: a_");
+    }
+}
@@ -0,0 +1,453 @@
+//! Modbus RTU decoder
+//!
+//! Frames are delimited by the idle gap on the line (see `Config::rx_timeout_bits`)
+//! and handed to `Decoder::frame` whole. Requests and responses can't be told
+//! apart from the frame alone, so a frame that fits the pending request is taken
+//! as its response and anything else as a new request.
+
+use core::fmt;
+
+use crate::crc::crc16_modbus;
+
+/// Longest RTU frame: address, function, 252 bytes of data and the CRC
+pub const MAX_FRAME: usize = 256;
+
+/// Register values shown per transaction
+const VALUES: usize = 4;
+
+/// Why a frame was not decoded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameError {
+    /// Less than address, function and CRC
+    TooShort,
+    Crc,
+}
+
+/// Data model table a function works on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Table {
+    Coils,
+    DiscreteInputs,
+    HoldingRegisters,
+    InputRegisters,
+}
+
+impl Table {
+    fn name(self) -> &'static str {
+        match self {
+            Table::Coils => "CO",
+            Table::DiscreteInputs => "DI",
+            Table::HoldingRegisters => "HR",
+            Table::InputRegisters => "IR",
+        }
+    }
+
+    /// Data bytes holding `count` items
+    fn bytes(self, count: u16) -> usize {
+        match self {
+            Table::Coils | Table::DiscreteInputs => (count as usize).div_ceil(8),
+            Table::HoldingRegisters | Table::InputRegisters => count as usize * 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation {
+    /// Functions 1 - 4
+    Read { table: Table, start: u16, count: u16 },
+    /// Functions 5 and 6
+    WriteSingle { table: Table, start: u16, value: u16 },
+    /// Functions 15 and 16
+    WriteMultiple { table: Table, start: u16, count: u16 },
+    /// Any other function, not decoded
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Request {
+    /// Slave address, 0 is broadcast
+    pub address: u8,
+    pub function: u8,
+    pub operation: Operation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Response {
+    /// Read data, the first values are kept. Registers are 16 bit, coils
+    /// and inputs are kept per byte.
+    Read { values: [u16; VALUES], len: usize, more: bool },
+    /// Write acknowledged
+    Written,
+    /// Exception code
+    Exception(u8),
+    /// Response to a function that is not decoded
+    Other,
+}
+
+/// Result of a frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Event {
+    Transaction(Request, Response),
+    /// Request to address 0, there is no response
+    Broadcast(Request),
+    /// A new request came in before the response to this one
+    NoResponse(Request),
+    /// Valid frame that is neither a response nor a request
+    Unknown { address: u8, function: u8 },
+    Invalid(FrameError),
+}
+
+/// Pairs requests with their responses
+pub struct Decoder {
+    pending: Option<Request>,
+}
+
+impl Decoder {
+    pub const fn new() -> Self {
+        Self { pending: None }
+    }
+
+    /// Forget the pending request, e.g. after changing the line settings
+    pub fn reset(&mut self) {
+        self.pending = None;
+    }
+
+    /// Decode one frame, CRC included
+    pub fn frame(&mut self, frame: &[u8]) -> Option<Event> {
+        let (address, function, data) = match split(frame) {
+            Ok(parts) => parts,
+            Err(err) => return Some(Event::Invalid(err)),
+        };
+
+        if let Some(request) = self.pending {
+            if address == request.address {
+                if let Some(response) = response(&request, function, data) {
+                    self.pending = None;
+                    return Some(Event::Transaction(request, response));
+                }
+            }
+        }
+
+        let request = match parse_request(address, function, data) {
+            Some(request) => request,
+            None => return Some(Event::Unknown { address, function }),
+        };
+
+        if address == 0 {
+            return Some(Event::Broadcast(request));
+        }
+        self.pending
+            .replace(request)
+            .map(Event::NoResponse)
+    }
+}
+
+/// Check the CRC and split into address, function and data
+fn split(frame: &[u8]) -> Result<(u8, u8, &[u8]), FrameError> {
+    if frame.len() < 4 {
+        return Err(FrameError::TooShort);
+    }
+    let (content, crc) = frame.split_at(frame.len() - 2);
+    if crc16_modbus(content) != u16::from_le_bytes([crc[0], crc[1]]) {
+        return Err(FrameError::Crc);
+    }
+    Ok((content[0], content[1], &content[2..]))
+}
+
+fn be16(data: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes([data[at], data[at + 1]])
+}
+
+fn parse_request(address: u8, function: u8, data: &[u8]) -> Option<Request> {
+    let table = match function {
+        1 | 5 | 15 => Table::Coils,
+        2 => Table::DiscreteInputs,
+        3 | 6 | 16 => Table::HoldingRegisters,
+        4 => Table::InputRegisters,
+        0x01..=0x7F => {
+            return Some(Request {
+                address,
+                function,
+                operation: Operation::Other,
+            })
+        }
+        _ => return None,
+    };
+
+    let operation = match function {
+        1..=4 if data.len() == 4 => Operation::Read {
+            table,
+            start: be16(data, 0),
+            count: be16(data, 2),
+        },
+        5 | 6 if data.len() == 4 => Operation::WriteSingle {
+            table,
+            start: be16(data, 0),
+            value: be16(data, 2),
+        },
+        15 | 16 if data.len() >= 5 && data[4] as usize == data.len() - 5 => {
+            Operation::WriteMultiple {
+                table,
+                start: be16(data, 0),
+                count: be16(data, 2),
+            }
+        }
+        _ => return None,
+    };
+
+    Some(Request {
+        address,
+        function,
+        operation,
+    })
+}
+
+/// Decode the frame as response to `request`
+fn response(request: &Request, function: u8, data: &[u8]) -> Option<Response> {
+    if function == request.function | 0x80 && data.len() == 1 {
+        return Some(Response::Exception(data[0]));
+    }
+    if function != request.function {
+        return None;
+    }
+
+    match request.operation {
+        Operation::Read { table, count, .. } => {
+            let bytes = table.bytes(count);
+            if data.len() != bytes + 1 || data[0] as usize != bytes {
+                return None;
+            }
+            let data = &data[1..];
+            let mut values = [0; VALUES];
+            let len = match table {
+                Table::HoldingRegisters | Table::InputRegisters => {
+                    for (value, chunk) in values.iter_mut().zip(data.chunks(2)) {
+                        *value = be16(chunk, 0);
+                    }
+                    data.len() / 2
+                }
+                Table::Coils | Table::DiscreteInputs => {
+                    for (value, &b) in values.iter_mut().zip(data) {
+                        *value = b as u16;
+                    }
+                    data.len()
+                }
+            };
+            Some(Response::Read {
+                values,
+                len: len.min(VALUES),
+                more: len > VALUES,
+            })
+        }
+        Operation::WriteSingle { start, value, .. } => {
+            let echo = data.len() == 4 && be16(data, 0) == start && be16(data, 2) == value;
+            if echo {
+                Some(Response::Written)
+            } else {
+                None
+            }
+        }
+        Operation::WriteMultiple { start, count, .. } => {
+            let ack = data.len() == 4 && be16(data, 0) == start && be16(data, 2) == count;
+            if ack {
+                Some(Response::Written)
+            } else {
+                None
+            }
+        }
+        Operation::Other => Some(Response::Other),
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:02X} ", self.address)?;
+        match self.operation {
+            Operation::Read { table, start, count } => {
+                write!(f, "RD {} {:04X}+{}", table.name(), start, count)
+            }
+            Operation::WriteSingle { table, start, value } => {
+                write!(f, "WR {} {:04X}={:04X}", table.name(), start, value)
+            }
+            Operation::WriteMultiple { table, start, count } => {
+                write!(f, "WR {} {:04X}+{}", table.name(), start, count)
+            }
+            Operation::Other => write!(f, "F{:02X}", self.function),
+        }
+    }
+}
+
+/// One compact line per event
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Transaction(request, response) => {
+                write!(f, "{}", request)?;
+                match response {
+                    Response::Read { values, len, more } => {
+                        f.write_str(" =")?;
+                        for value in &values[..*len] {
+                            write!(f, " {:04X}", value)?;
+                        }
+                        if *more {
+                            f.write_str(" ..")?;
+                        }
+                        Ok(())
+                    }
+                    Response::Written | Response::Other => f.write_str(" ok"),
+                    Response::Exception(code) => {
+                        write!(f, " EXC {} {}", code, exception_name(*code))
+                    }
+                }
+            }
+            Event::Broadcast(request) => write!(f, "{} bcast", request),
+            Event::NoResponse(request) => write!(f, "{} no response", request),
+            Event::Unknown { address, function } => {
+                write!(f, "{:02X} F{:02X} unknown", address, function)
+            }
+            Event::Invalid(FrameError::TooShort) => f.write_str("-- short frame"),
+            Event::Invalid(FrameError::Crc) => f.write_str("-- CRC error"),
+        }
+    }
+}
+
+fn exception_name(code: u8) -> &'static str {
+    match code {
+        1 => "function",
+        2 => "address",
+        3 => "value",
+        4 => "failure",
+        5 => "ack",
+        6 => "busy",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        text.split_whitespace()
+            .map(|b| u8::from_str_radix(b, 16).unwrap())
+            .collect()
+    }
+
+    /// `text` with its CRC appended
+    fn frame(text: &str) -> Vec<u8> {
+        let mut frame = hex(text);
+        let crc = crc16_modbus(&frame);
+        frame.extend_from_slice(&crc.to_le_bytes());
+        frame
+    }
+
+    fn line(event: Option<Event>) -> String {
+        format!("{}", event.unwrap())
+    }
+
+    #[test]
+    fn read_holding_registers() {
+        // example of the Modbus application protocol specification
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.frame(&hex("11 03 00 6B 00 03 76 87")), None);
+        let event = decoder.frame(&hex("11 03 06 02 2B 00 00 00 64 C8 BA"));
+        assert_eq!(line(event), "11 RD HR 006B+3 = 022B 0000 0064");
+    }
+
+    #[test]
+    fn long_reads_keep_the_first_values() {
+        let mut decoder = Decoder::new();
+        decoder.frame(&frame("01 04 00 00 00 05"));
+        let event = decoder.frame(&frame("01 04 0A 00 01 00 02 00 03 00 04 00 05"));
+        assert_eq!(line(event), "01 RD IR 0000+5 = 0001 0002 0003 0004 ..");
+    }
+
+    #[test]
+    fn coils_are_counted_in_bytes() {
+        let mut decoder = Decoder::new();
+        decoder.frame(&frame("05 01 00 13 00 0A"));
+        // 10 coils take 2 bytes, one byte is not a response
+        assert!(matches!(
+            decoder.frame(&frame("05 01 01 CD")),
+            Some(Event::Unknown { .. })
+        ));
+        let event = decoder.frame(&frame("05 01 02 CD 01"));
+        assert_eq!(line(event), "05 RD CO 0013+10 = 00CD 0001");
+    }
+
+    #[test]
+    fn writes_are_acknowledged() {
+        let mut decoder = Decoder::new();
+        decoder.frame(&frame("02 06 00 01 00 03"));
+        assert_eq!(line(decoder.frame(&frame("02 06 00 01 00 03"))), "02 WR HR 0001=0003 ok");
+
+        decoder.frame(&frame("02 10 00 01 00 02 04 00 0A 01 02"));
+        assert_eq!(line(decoder.frame(&frame("02 10 00 01 00 02"))), "02 WR HR 0001+2 ok");
+    }
+
+    #[test]
+    fn exception_response() {
+        let mut decoder = Decoder::new();
+        decoder.frame(&frame("01 03 00 00 00 0A"));
+        let event = decoder.frame(&hex("01 83 02 C0 F1"));
+        assert_eq!(line(event), "01 RD HR 0000+10 EXC 2 address");
+    }
+
+    #[test]
+    fn response_from_another_slave_is_a_new_request() {
+        let mut decoder = Decoder::new();
+        decoder.frame(&frame("01 03 00 00 00 01"));
+        let event = decoder.frame(&frame("02 03 00 00 00 01"));
+        assert_eq!(line(event), "01 RD HR 0000+1 no response");
+        let event = decoder.frame(&frame("02 03 02 12 34"));
+        assert_eq!(line(event), "02 RD HR 0000+1 = 1234");
+    }
+
+    #[test]
+    fn broadcast_has_no_response() {
+        let mut decoder = Decoder::new();
+        assert_eq!(line(decoder.frame(&frame("00 06 00 10 00 01"))), "00 WR HR 0010=0001 bcast");
+        // nothing is pending
+        assert_eq!(decoder.frame(&frame("01 03 00 00 00 01")), None);
+    }
+
+    #[test]
+    fn bad_frames() {
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.frame(&hex("11 03 76")), Some(Event::Invalid(FrameError::TooShort)));
+        assert_eq!(
+            decoder.frame(&hex("11 03 00 6B 00 03 76 88")),
+            Some(Event::Invalid(FrameError::Crc))
+        );
+        // CRC sent high byte first
+        assert_eq!(
+            decoder.frame(&hex("11 03 00 6B 00 03 87 76")),
+            Some(Event::Invalid(FrameError::Crc))
+        );
+        // byte count of a write multiple that doesn't match its data
+        assert_eq!(
+            line(decoder.frame(&frame("01 10 00 01 00 02 05 00 0A 01 02"))),
+            "01 F10 unknown"
+        );
+        // exception codes are not requests
+        assert_eq!(line(decoder.frame(&frame("01 83 02"))), "01 F83 unknown");
+    }
+
+    #[test]
+    fn other_functions_pass_through() {
+        let mut decoder = Decoder::new();
+        decoder.frame(&frame("01 2B 0E 01 00"));
+        assert_eq!(line(decoder.frame(&frame("01 2B 0E 01 01"))), "01 F2B ok");
+    }
+
+    #[test]
+    fn reset_forgets_the_request() {
+        let mut decoder = Decoder::new();
+        decoder.frame(&frame("01 03 00 00 00 01"));
+        decoder.reset();
+        assert!(matches!(
+            decoder.frame(&frame("01 03 02 12 34")),
+            Some(Event::Unknown { address: 1, function: 3 })
+        ));
+    }
+}
@@ -0,0 +1,459 @@
+//! NMEA 0183 sentences
+//!
+//! Checks the `*hh` checksum of received sentences and collects GGA, RMC and GSV
+//! data into a `Summary` of the GPS state. Works on the received line in place.
+
+use core::fmt::{self, Write};
+
+/// Why a line was not used
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// Does not start with '$' or '!'
+    NotASentence,
+    /// No `*hh` at the end
+    MissingChecksum,
+    Checksum,
+    /// A field of a known sentence could not be parsed
+    Format,
+}
+
+impl Error {
+    /// Sentence that is damaged, as opposed to a line that is not NMEA
+    pub fn is_bad(self) -> bool {
+        self != Error::NotASentence
+    }
+}
+
+/// Check a line and return the sentence between '$' and '*'.
+/// Trailing '\r' and '\n' are ignored.
+pub fn check(line: &[u8]) -> Result<&[u8], Error> {
+    let mut line = line;
+    while let [rest @ .., b'\r'] | [rest @ .., b'\n'] = line {
+        line = rest;
+    }
+
+    match line.first() {
+        Some(b'$') | Some(b'!') => {}
+        _ => return Err(Error::NotASentence),
+    }
+    if line.len() < 4 || line[line.len() - 3] != b'*' {
+        return Err(Error::MissingChecksum);
+    }
+
+    let body = &line[1..line.len() - 3];
+    let expected = hex(line[line.len() - 2])? << 4 | hex(line[line.len() - 1])?;
+    let sum = body.iter().fold(0, |sum, &b| sum ^ b);
+    if sum == expected {
+        Ok(body)
+    } else {
+        Err(Error::Checksum)
+    }
+}
+
+fn hex(c: u8) -> Result<u8, Error> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        _ => Err(Error::MissingChecksum),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Time {
+    pub hours: u8,
+    pub minutes: u8,
+    pub seconds: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+/// GPS state collected from the sentences
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Summary {
+    pub time: Option<Time>,
+    pub date: Option<Date>,
+    /// GGA fix quality, 0 is no fix
+    pub quality: u8,
+    /// Satellites used for the fix
+    pub satellites: u8,
+    /// Satellites in view
+    pub in_view: u8,
+    /// Latitude in millionths of a degree, north positive
+    pub latitude: Option<i32>,
+    /// Longitude in millionths of a degree, east positive
+    pub longitude: Option<i32>,
+    /// Altitude above mean sea level in decimeters
+    pub altitude_dm: Option<i32>,
+    /// Sentences with a valid checksum
+    pub sentences: u32,
+    /// Sentences with a bad checksum or content
+    pub bad: u32,
+}
+
+/// Lines of text written by `Summary::write_row`
+pub const SUMMARY_ROWS: usize = 6;
+
+impl Summary {
+    pub const fn new() -> Self {
+        Self {
+            time: None,
+            date: None,
+            quality: 0,
+            satellites: 0,
+            in_view: 0,
+            latitude: None,
+            longitude: None,
+            altitude_dm: None,
+            sentences: 0,
+            bad: 0,
+        }
+    }
+
+    /// Check a received line and update the state from it
+    pub fn update(&mut self, line: &[u8]) -> Result<(), Error> {
+        let result = check(line).and_then(|body| self.parse(body));
+        match result {
+            Ok(()) => self.sentences += 1,
+            Err(err) if err.is_bad() => self.bad += 1,
+            Err(_) => {}
+        }
+        result
+    }
+
+    fn parse(&mut self, body: &[u8]) -> Result<(), Error> {
+        let mut fields = body.split(|&b| b == b',');
+        let address = fields.next().unwrap_or(&[]);
+        // talker (GP, GN, ...) followed by the sentence type
+        let kind = address.get(2..).unwrap_or(&[]);
+
+        match kind {
+            b"GGA" => {
+                let time = fields.next().ok_or(Error::Format)?;
+                let latitude = coordinate(&mut fields, b'N', b'S')?;
+                let longitude = coordinate(&mut fields, b'E', b'W')?;
+                let quality = number(fields.next())?;
+                let satellites = number(fields.next())?;
+                let _hdop = fields.next();
+                let altitude = fields.next().ok_or(Error::Format)?;
+
+                self.time = parse_time(time)?.or(self.time);
+                self.latitude = latitude;
+                self.longitude = longitude;
+                self.quality = quality.unwrap_or(0) as u8;
+                self.satellites = satellites.unwrap_or(0) as u8;
+                self.altitude_dm = decimal(altitude, 1)?.map(|dm| dm as i32);
+            }
+            b"RMC" => {
+                let time = fields.next().ok_or(Error::Format)?;
+                let _status = fields.next();
+                let latitude = coordinate(&mut fields, b'N', b'S')?;
+                let longitude = coordinate(&mut fields, b'E', b'W')?;
+                let _speed = fields.next();
+                let _course = fields.next();
+                let date = fields.next().ok_or(Error::Format)?;
+
+                self.time = parse_time(time)?.or(self.time);
+                self.date = parse_date(date)?.or(self.date);
+                if latitude.is_some() {
+                    self.latitude = latitude;
+                    self.longitude = longitude;
+                }
+            }
+            b"GSV" => {
+                let _messages = fields.next();
+                let _index = fields.next();
+                self.in_view = number(fields.next())?.unwrap_or(0) as u8;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Write line `row` of the summary screen
+    pub fn write_row<W: Write>(&self, row: usize, w: &mut W) -> fmt::Result {
+        match row {
+            0 => {
+                let fix = match self.quality {
+                    0 => "none",
+                    1 => "GPS",
+                    2 => "DGPS",
+                    4 => "RTK",
+                    5 => "float RTK",
+                    6 => "estimated",
+                    _ => "other",
+                };
+                write!(w, "Fix  {:<9} sats {}/{}", fix, self.satellites, self.in_view)
+            }
+            1 => {
+                w.write_str("UTC  ")?;
+                match self.date {
+                    Some(d) => write!(w, "{:04}-{:02}-{:02} ", d.year, d.month, d.day)?,
+                    None => w.write_str("----------- ")?,
+                }
+                match self.time {
+                    Some(t) => write!(w, "{:02}:{:02}:{:02}", t.hours, t.minutes, t.seconds),
+                    None => w.write_str("--:--:--"),
+                }
+            }
+            2 => {
+                w.write_str("Lat  ")?;
+                write_angle(w, self.latitude, 'N', 'S')
+            }
+            3 => {
+                w.write_str("Lon  ")?;
+                write_angle(w, self.longitude, 'E', 'W')
+            }
+            4 => match self.altitude_dm {
+                Some(dm) => {
+                    let sign = if dm < 0 { "-" } else { "" };
+                    let dm = dm.abs();
+                    write!(w, "Alt  {}{}.{} m", sign, dm / 10, dm % 10)
+                }
+                None => w.write_str("Alt  --"),
+            },
+            5 => write!(w, "Sentences {}  bad {}", self.sentences, self.bad),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn write_angle<W: Write>(w: &mut W, micro: Option<i32>, pos: char, neg: char) -> fmt::Result {
+    match micro {
+        Some(micro) => {
+            let hemisphere = if micro < 0 { neg } else { pos };
+            let micro = (micro as i64).abs();
+            write!(w, "{}.{:06} {}", micro / 1_000_000, micro % 1_000_000, hemisphere)
+        }
+        None => w.write_str("--"),
+    }
+}
+
+/// Unsigned integer field, None when empty
+fn number(field: Option<&[u8]>) -> Result<Option<u32>, Error> {
+    let field = field.ok_or(Error::Format)?;
+    if field.is_empty() {
+        return Ok(None);
+    }
+    let mut value = 0u32;
+    for &c in field {
+        if !c.is_ascii_digit() {
+            return Err(Error::Format);
+        }
+        value = value
+            .checked_mul(10)
+            .and_then(|v| v.checked_add((c - b'0') as u32))
+            .ok_or(Error::Format)?;
+    }
+    Ok(Some(value))
+}
+
+/// Decimal field like "-12.345" scaled to `decimals` decimals, None when empty
+fn decimal(field: &[u8], decimals: u32) -> Result<Option<i64>, Error> {
+    if field.is_empty() {
+        return Ok(None);
+    }
+    let (negative, field) = match field.split_first() {
+        Some((b'-', rest)) => (true, rest),
+        _ => (false, field),
+    };
+    let mut parts = field.splitn(2, |&b| b == b'.');
+    let integer = number(parts.next())?.unwrap_or(0);
+    let fraction = parts.next().unwrap_or(&[]);
+
+    let mut value = integer as i64;
+    for i in 0..decimals as usize {
+        let digit = match fraction.get(i) {
+            Some(c) if c.is_ascii_digit() => (c - b'0') as i64,
+            Some(_) => return Err(Error::Format),
+            None => 0,
+        };
+        value = value * 10 + digit;
+    }
+    Ok(Some(if negative { -value } else { value }))
+}
+
+/// `ddmm.mmmm` (or `dddmm.mmmm`) and hemisphere fields as millionths of a degree
+fn coordinate<'a, I>(fields: &mut I, pos: u8, neg: u8) -> Result<Option<i32>, Error>
+where
+    I: Iterator<Item = &'a [u8]>,
+{
+    let value = fields.next().ok_or(Error::Format)?;
+    let hemisphere = fields.next().ok_or(Error::Format)?;
+    // minutes in millionths
+    let minutes = match decimal(value, 6)? {
+        Some(minutes) => minutes,
+        None => return Ok(None),
+    };
+
+    let degrees = minutes / 100_000_000;
+    let minutes = minutes % 100_000_000;
+    let micro = degrees * 1_000_000 + minutes / 60;
+
+    match hemisphere {
+        [c] if *c == pos => Ok(Some(micro as i32)),
+        [c] if *c == neg => Ok(Some(-micro as i32)),
+        _ => Err(Error::Format),
+    }
+}
+
+/// `hhmmss.ss`, None when empty
+fn parse_time(field: &[u8]) -> Result<Option<Time>, Error> {
+    if field.is_empty() {
+        return Ok(None);
+    }
+    let digits = two_digits(field, 3)?;
+    Ok(Some(Time {
+        hours: digits[0],
+        minutes: digits[1],
+        seconds: digits[2],
+    }))
+}
+
+/// `ddmmyy`, None when empty
+fn parse_date(field: &[u8]) -> Result<Option<Date>, Error> {
+    if field.is_empty() {
+        return Ok(None);
+    }
+    let digits = two_digits(field, 3)?;
+    let century = if digits[2] < 80 { 2000 } else { 1900 };
+    Ok(Some(Date {
+        day: digits[0],
+        month: digits[1],
+        year: century + digits[2] as u16,
+    }))
+}
+
+/// The first `n` (max 3) pairs of digits
+fn two_digits(field: &[u8], n: usize) -> Result<[u8; 3], Error> {
+    let mut values = [0; 3];
+    for (i, value) in values.iter_mut().enumerate().take(n) {
+        let pair = field.get(i * 2..i * 2 + 2).ok_or(Error::Format)?;
+        *value = number(Some(pair))?.ok_or(Error::Format)? as u8;
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GGA: &[u8] = b"$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47\r\n";
+    const RMC: &[u8] = b"$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A";
+    const GSV: &[u8] = b"$GPGSV,2,1,08,01,40,083,46,02,17,308,41,12,07,344,39,14,22,228,45*75";
+    /// u-blox before the first fix
+    const GGA_NO_FIX: &[u8] = b"$GPGGA,,,,,,0,00,99.99,,,,,,*48";
+
+    fn rows(summary: &Summary) -> Vec<String> {
+        (0..SUMMARY_ROWS)
+            .map(|row| {
+                let mut text = String::new();
+                summary.write_row(row, &mut text).unwrap();
+                text
+            })
+            .collect()
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(check(RMC), Ok(&RMC[1..RMC.len() - 3]));
+        assert!(check(GGA).is_ok());
+        assert!(check(GSV).is_ok());
+        // lower case hex
+        assert!(check(b"!AIVDM,1,1,,A,14eG;o@034o8sd<L9i:a;WF>062D,0*7d").is_ok());
+    }
+
+    #[test]
+    fn bad_checksums() {
+        // one character changed
+        assert_eq!(
+            check(b"$GPGGA,123519,4807.038,N,01131.000,E,1,09,0.9,545.4,M,46.9,M,,*47"),
+            Err(Error::Checksum)
+        );
+        assert_eq!(check(b"$GPRMC,123519*00"), Err(Error::Checksum));
+        assert_eq!(check(b"$GPRMC,123519*6G"), Err(Error::MissingChecksum));
+        assert_eq!(check(b"$GPRMC,123519"), Err(Error::MissingChecksum));
+        assert_eq!(check(b"$*"), Err(Error::MissingChecksum));
+        // the checksum covers nothing
+        assert_eq!(check(b"$*00"), Ok(&b""[..]));
+        assert_eq!(check(b"GPRMC,123519*6A"), Err(Error::NotASentence));
+        assert_eq!(check(b"\r\n"), Err(Error::NotASentence));
+    }
+
+    #[test]
+    fn summary_of_a_fix() {
+        let mut summary = Summary::new();
+        for sentence in [GGA, RMC, GSV].iter() {
+            summary.update(sentence).unwrap();
+        }
+        assert_eq!(summary.latitude, Some(48_117_300));
+        assert_eq!(summary.longitude, Some(11_516_666));
+        assert_eq!(
+            rows(&summary),
+            [
+                "Fix  GPS       sats 8/8",
+                "UTC  1994-03-23 12:35:19",
+                "Lat  48.117300 N",
+                "Lon  11.516666 E",
+                "Alt  545.4 m",
+                "Sentences 3  bad 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn southern_and_western_hemisphere() {
+        let mut summary = Summary::new();
+        summary
+            .update(b"$GNRMC,235959.00,A,3352.1280,S,15112.3456,W,0.0,,311299,,,A*6F")
+            .unwrap();
+        summary
+            .update(b"$GPGGA,001500.00,0000.0000,N,00000.0000,W,1,04,1.0,-12.3,M,,,,*31")
+            .unwrap();
+        assert_eq!(summary.date, Some(Date { year: 1999, month: 12, day: 31 }));
+        assert_eq!(rows(&summary)[1], "UTC  1999-12-31 00:15:00");
+        assert_eq!(rows(&summary)[2], "Lat  0.000000 N");
+        assert_eq!(rows(&summary)[4], "Alt  -12.3 m");
+
+        summary
+            .update(b"$GNRMC,235959.00,A,3352.1280,S,15112.3456,W,0.0,,311299,,,A*6F")
+            .unwrap();
+        assert_eq!(summary.latitude, Some(-33_868_800));
+        assert_eq!(summary.longitude, Some(-151_205_760));
+        assert_eq!(rows(&summary)[3], "Lon  151.205760 W");
+    }
+
+    #[test]
+    fn no_fix_keeps_the_time() {
+        let mut summary = Summary::new();
+        summary.update(GGA).unwrap();
+        summary.update(GGA_NO_FIX).unwrap();
+        assert_eq!(summary.time, Some(Time { hours: 12, minutes: 35, seconds: 19 }));
+        assert_eq!(
+            rows(&summary)[..5],
+            ["Fix  none      sats 0/0", "UTC  ----------- 12:35:19", "Lat  --", "Lon  --", "Alt  --"]
+        );
+    }
+
+    #[test]
+    fn bad_sentences_are_counted() {
+        let mut summary = Summary::new();
+        assert_eq!(summary.update(b"$GPGGA,123519*00"), Err(Error::Checksum));
+        // valid checksum, but the hemisphere is not N or S
+        assert_eq!(
+            summary.update(b"$GPGGA,123519,4807.038,X,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*51"),
+            Err(Error::Format)
+        );
+        // plain text is not counted
+        assert_eq!(summary.update(b"booting..."), Err(Error::NotASentence));
+        // sentences that are not decoded still count
+        summary.update(b"$GPTXT,01,01,02,ANTSTATUS=OK*3B").unwrap();
+        assert_eq!((summary.sentences, summary.bad), (1, 2));
+        assert_eq!(summary.latitude, None);
+    }
+}
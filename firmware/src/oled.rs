@@ -0,0 +1,221 @@
+//! SSD1362 driver on top of `display-interface`
+//!
+//! Display RAM is 256x64 pixels, 4 bit gray scale, two pixels per byte.
+//! The left pixel of a pair is in the high nibble. Commands are encoded by
+//! `Command` of `oled_test`.
+
+use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+
+use crate::command::{Command, RemapConfig, VcomhLevel};
+pub use crate::command::DisplayMode;
+
+/// Width in pixels
+pub const WIDTH: u16 = 256;
+/// Height in pixels
+pub const HEIGHT: u8 = 64;
+/// Bytes in one row of display RAM
+pub const ROW_BYTES: usize = WIDTH as usize / 2;
+
+/// Display orientation
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(dead_code)]
+pub enum Rotation {
+    Rotate0,
+    Rotate180,
+}
+
+/// SSD1362 OLED
+pub struct Oled<DI> {
+    iface: DI,
+}
+
+impl<DI> Oled<DI>
+where
+    DI: WriteOnlyDataCommand,
+{
+    pub fn new(iface: DI) -> Self {
+        Self { iface }
+    }
+
+    /// Configure the controller, clear the display RAM and turn the panel on.
+    /// VCC (16V) must be enabled before calling this.
+    pub fn init(&mut self, rotation: Rotation) -> Result<(), DisplayError> {
+        let remap = RemapConfig {
+            column_remap: rotation == Rotation::Rotate0,
+            nibble_remap: true,
+            com_remap: rotation == Rotation::Rotate180,
+            seg_split_odd_even: true,
+            ..RemapConfig::default()
+        };
+
+        self.command(Command::CommandLock(false))?;
+        self.on(false)?;
+        self.command(Command::Remap(remap))?;
+        self.command(Command::StartLine(0))?;
+        self.command(Command::DisplayOffset(0))?;
+        self.display_mode(DisplayMode::Normal)?;
+        // 64 rows
+        self.command(Command::Multiplex(0x3F))?;
+        self.contrast(0x7F)?;
+        self.command(Command::InternalVDD(true))?;
+        self.command(Command::InternalIREF(true))?;
+        self.command(Command::PhaseLength(2, 2))?;
+        self.command(Command::DisplayClockDiv(0xA, 0))?;
+        self.command(Command::PreChargePeriod(4))?;
+        self.command(Command::DefaultGrayScale())?;
+        self.pre_charge_voltage(0x04)?;
+        self.command(Command::PreChargeCapacitor(true))?;
+        self.command(Command::VcomhDeselect(VcomhLevel::V082))?;
+
+        self.clear()?;
+        self.on(true)
+    }
+
+    /// Turn the panel on or off. RAM content is kept.
+    pub fn on(&mut self, on: bool) -> Result<(), DisplayError> {
+        self.command(Command::DisplayOn(on))
+    }
+
+    pub fn display_mode(&mut self, mode: DisplayMode) -> Result<(), DisplayError> {
+        self.command(Command::Mode(mode))
+    }
+
+    /// Contrast current, default 0x7F
+    pub fn contrast(&mut self, contrast: u8) -> Result<(), DisplayError> {
+        self.command(Command::Contrast(contrast))
+    }
+
+    /// Pre-charge voltage level (0 - 31), default 0x04
+    pub fn pre_charge_voltage(&mut self, level: u8) -> Result<(), DisplayError> {
+        self.command(Command::PreChargeVoltage(level & 0x1F))
+    }
+
+    /// Shift the whole image vertically by `rows` (0 - 63), wrapping around
+    pub fn display_offset(&mut self, rows: u8) -> Result<(), DisplayError> {
+        self.command(Command::DisplayOffset(rows % HEIGHT))
+    }
+
+    /// Set the RAM row shown at the top of the screen (0 - 63)
+    pub fn start_line(&mut self, line: u8) -> Result<(), DisplayError> {
+        self.command(Command::StartLine(line % HEIGHT))
+    }
+
+    /// Write whole RAM rows starting at row `first`.
+    /// `data` holds `ROW_BYTES` bytes per row.
+    pub fn write_rows(&mut self, first: u8, data: &[u8]) -> Result<(), DisplayError> {
+        let rows = (data.len() / ROW_BYTES) as u8;
+        if rows == 0 {
+            return Ok(());
+        }
+        self.command(Command::ColumnAddress(0, (ROW_BYTES - 1) as u8))?;
+        self.command(Command::RowAddress(first, first + rows - 1))?;
+        self.iface.send_data(DataFormat::U8(data))
+    }
+
+    /// Clear the whole display RAM
+    pub fn clear(&mut self) -> Result<(), DisplayError> {
+        let blank = [0u8; ROW_BYTES];
+        for row in 0..HEIGHT {
+            self.write_rows(row, &blank)?;
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, command: Command) -> Result<(), DisplayError> {
+        let encoded = command.encode().ok_or(DisplayError::OutOfBoundsError)?;
+        self.iface.send_commands(DataFormat::U8(encoded.as_bytes()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Commands and the number of data bytes sent
+    #[derive(Default)]
+    struct Recorder {
+        commands: Vec<Vec<u8>>,
+        data: usize,
+    }
+
+    impl WriteOnlyDataCommand for Recorder {
+        fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+            match cmd {
+                DataFormat::U8(bytes) => self.commands.push(bytes.to_vec()),
+                _ => return Err(DisplayError::DataFormatNotImplemented),
+            }
+            Ok(())
+        }
+
+        fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+            match buf {
+                DataFormat::U8(bytes) => self.data += bytes.len(),
+                _ => return Err(DisplayError::DataFormatNotImplemented),
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn init_sequence() {
+        let mut oled = Oled::new(Recorder::default());
+        oled.init(Rotation::Rotate180).unwrap();
+        let commands = &oled.iface.commands;
+        let setup: Vec<Vec<u8>> = [
+            &[0xFD, 0x12][..],
+            &[0xAE],
+            &[0xA0, 0x52],
+            &[0xA1, 0x00],
+            &[0xA2, 0x00],
+            &[0xA4],
+            &[0xA8, 0x3F],
+            &[0x81, 0x7F],
+            &[0xAB, 0x01],
+            &[0xAD, 0x9E],
+            &[0xB1, 0x22],
+            &[0xB3, 0xA0],
+            &[0xB6, 0x04],
+            &[0xB9],
+            &[0xBC, 0x04],
+            &[0xBD, 0x01],
+            &[0xBE, 0x05],
+        ]
+        .iter()
+        .map(|c| c.to_vec())
+        .collect();
+        assert_eq!(commands[..setup.len()], setup[..]);
+        // RAM cleared row by row, then the panel turned on
+        assert_eq!(commands[setup.len()..].len(), HEIGHT as usize * 2 + 1);
+        assert_eq!(commands.last().unwrap(), &vec![0xAF]);
+        assert_eq!(oled.iface.data, ROW_BYTES * HEIGHT as usize);
+    }
+
+    #[test]
+    fn rotation_remap() {
+        let mut oled = Oled::new(Recorder::default());
+        oled.init(Rotation::Rotate0).unwrap();
+        assert_eq!(oled.iface.commands[2], vec![0xA0, 0x43]);
+    }
+
+    #[test]
+    fn rows_and_lines_wrap() {
+        let mut oled = Oled::new(Recorder::default());
+        oled.start_line(HEIGHT + 3).unwrap();
+        oled.display_offset(HEIGHT - 1).unwrap();
+        oled.pre_charge_voltage(0xFF).unwrap();
+        oled.write_rows(62, &[0; ROW_BYTES * 2]).unwrap();
+        assert_eq!(
+            oled.iface.commands,
+            vec![vec![0xA1, 3], vec![0xA2, 63], vec![0xBC, 0x1F], vec![0x15, 0x00, 0x7F], vec![0x75, 62, 63]]
+        );
+    }
+
+    #[test]
+    fn rows_past_the_ram_are_an_error() {
+        let mut oled = Oled::new(Recorder::default());
+        assert!(matches!(
+            oled.write_rows(63, &[0; ROW_BYTES * 2]),
+            Err(DisplayError::OutOfBoundsError)
+        ));
+    }
+}
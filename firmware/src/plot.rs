@@ -0,0 +1,495 @@
+//! Numeric values from text lines, plotted as a scrolling graph
+//!
+//! A `Pattern` picks a number out of each received line, a `Plot` keeps the last
+//! values and draws them with the current, minimum, maximum and average value.
+//! The vertical scale follows the values on screen.
+//!
+//! Values are kept in thousandths, more decimals are cut off.
+
+use core::fmt::Write;
+
+use arrayvec::ArrayString;
+use embedded_graphics::{
+    mono_font::{ascii::FONT_5X8, MonoTextStyle},
+    pixelcolor::{Gray4, GrayColor},
+    prelude::*,
+    primitives::{Line, PrimitiveStyle},
+    text::{Baseline, Text},
+};
+
+use crate::oled::WIDTH;
+
+/// Longest pattern text
+pub const MAX_PATTERN: usize = 32;
+/// Values on screen, one per pixel column
+pub const SAMPLES: usize = WIDTH as usize;
+
+/// Top row of the graph, the readouts are above it
+const GRAPH_TOP: i32 = 10;
+const GRID_LEVEL: u8 = 0x3;
+
+/// Which number of a line to plot
+///
+/// - empty: the first number in the line
+/// - a key like `rpm`: the number after `rpm=` or `rpm:`
+/// - a pattern with `#` for the number, `?` for any character and `*` for any
+///   text, like `T*: # C`. It can match anywhere in the line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pattern<'a> {
+    text: &'a str,
+}
+
+/// A number in thousandths and the decimals it was written with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Value {
+    pub milli: i32,
+    pub decimals: u8,
+}
+
+impl<'a> Pattern<'a> {
+    pub fn new(text: &'a str) -> Self {
+        Self { text }
+    }
+
+    /// The number in `line`, None when the line does not match
+    pub fn extract(&self, line: &[u8]) -> Option<Value> {
+        let pattern = self.text.as_bytes();
+        if pattern.is_empty() {
+            return (0..line.len()).find_map(|i| number(&line[i..]).map(|(value, _)| value));
+        }
+        if pattern.contains(&b'#') {
+            return (0..line.len()).find_map(|i| matches(pattern, &line[i..]));
+        }
+
+        // key=value
+        (0..line.len()).find_map(|i| {
+            let rest = line[i..].strip_prefix(pattern)?;
+            if i > 0 && is_word(line[i - 1]) {
+                return None;
+            }
+            let rest = skip_spaces(rest);
+            let rest = match rest.split_first() {
+                Some((b'=', rest)) | Some((b':', rest)) => rest,
+                _ => return None,
+            };
+            number(skip_spaces(rest)).map(|(value, _)| value)
+        })
+    }
+}
+
+/// Match `pattern` at the start of `text` and return the number at the first `#`
+fn matches(pattern: &[u8], text: &[u8]) -> Option<Value> {
+    let mut value = None;
+    if match_here(pattern, text, &mut value) {
+        value
+    } else {
+        None
+    }
+}
+
+fn match_here(pattern: &[u8], text: &[u8], value: &mut Option<Value>) -> bool {
+    match pattern.split_first() {
+        // the rest of the line is ignored
+        None => true,
+        Some((b'*', rest)) => (0..=text.len()).any(|i| match_here(rest, &text[i..], value)),
+        Some((b'?', rest)) => !text.is_empty() && match_here(rest, &text[1..], value),
+        Some((b'#', rest)) => match number(skip_spaces(text)) {
+            Some((number, len)) => {
+                let skipped = text.len() - skip_spaces(text).len();
+                if match_here(rest, &text[skipped + len..], value) {
+                    *value = Some(number);
+                    true
+                } else {
+                    false
+                }
+            }
+            None => false,
+        },
+        Some((c, rest)) => text.first() == Some(c) && match_here(rest, &text[1..], value),
+    }
+}
+
+fn is_word(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || c == b'_'
+}
+
+fn skip_spaces(text: &[u8]) -> &[u8] {
+    let spaces = text.iter().take_while(|&&c| c == b' ').count();
+    &text[spaces..]
+}
+
+/// Number like "-12.345" at the start of `text` and its length in bytes
+fn number(text: &[u8]) -> Option<(Value, usize)> {
+    let (negative, start) = match text.first() {
+        Some(b'-') => (true, 1),
+        Some(b'+') => (false, 1),
+        _ => (false, 0),
+    };
+
+    let mut milli = 0i64;
+    let mut digits = 0;
+    let mut decimals = 0u8;
+    let mut point = false;
+    let mut len = start;
+    for &c in &text[start..] {
+        match c {
+            b'0'..=b'9' => {
+                digits += 1;
+                if !point {
+                    milli = milli * 10 + (c - b'0') as i64 * 1000;
+                } else if decimals < 3 {
+                    decimals += 1;
+                    milli += (c - b'0') as i64 * 10i64.pow(3 - decimals as u32);
+                }
+            }
+            b'.' if !point => point = true,
+            _ => break,
+        }
+        if milli > i32::MAX as i64 {
+            return None;
+        }
+        len += 1;
+    }
+    if digits == 0 {
+        return None;
+    }
+    // a trailing point is not part of the number
+    if text[len - 1] == b'.' {
+        len -= 1;
+    }
+
+    let milli = if negative { -milli } else { milli } as i32;
+    Some((Value { milli, decimals }, len))
+}
+
+/// Current, minimum, maximum and average value on screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub last: i32,
+    pub min: i32,
+    pub max: i32,
+    pub avg: i32,
+}
+
+pub struct Plot {
+    samples: [i32; SAMPLES],
+    /// Index of the oldest sample
+    first: usize,
+    len: usize,
+    /// Most decimals of the values, used for the readouts
+    decimals: u8,
+    changed: bool,
+}
+
+impl Plot {
+    pub const fn new() -> Self {
+        Self {
+            samples: [0; SAMPLES],
+            first: 0,
+            len: 0,
+            decimals: 0,
+            changed: true,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.first = 0;
+        self.len = 0;
+        self.decimals = 0;
+        self.changed = true;
+    }
+
+    pub fn push(&mut self, value: Value) {
+        if self.len == SAMPLES {
+            self.samples[self.first] = value.milli;
+            self.first = (self.first + 1) % SAMPLES;
+        } else {
+            self.samples[(self.first + self.len) % SAMPLES] = value.milli;
+            self.len += 1;
+        }
+        self.decimals = self.decimals.max(value.decimals);
+        self.changed = true;
+    }
+
+    /// True once after values were added
+    pub fn take_changed(&mut self) -> bool {
+        core::mem::replace(&mut self.changed, false)
+    }
+
+    /// Values on screen, oldest first
+    pub fn samples(&self) -> impl Iterator<Item = i32> + '_ {
+        (0..self.len).map(move |i| self.samples[(self.first + i) % SAMPLES])
+    }
+
+    pub fn stats(&self) -> Option<Stats> {
+        let last = self.samples().last()?;
+        let mut stats = Stats { last, min: last, max: last, avg: 0 };
+        let mut sum = 0i64;
+        for value in self.samples() {
+            stats.min = stats.min.min(value);
+            stats.max = stats.max.max(value);
+            sum += value as i64;
+        }
+        stats.avg = (sum / self.len as i64) as i32;
+        Some(stats)
+    }
+
+    /// Draw the readouts and the graph on a cleared target. The newest values
+    /// that fit in its width are shown.
+    pub fn draw<D>(&self, target: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Gray4>,
+    {
+        let text_style = MonoTextStyle::new(&FONT_5X8, Gray4::WHITE);
+        let grid = PrimitiveStyle::with_stroke(Gray4::new(GRID_LEVEL), 1);
+        let stroke = PrimitiveStyle::with_stroke(Gray4::WHITE, 1);
+
+        let stats = match self.stats() {
+            Some(stats) => stats,
+            None => {
+                Text::with_baseline("no values", Point::zero(), text_style, Baseline::Top)
+                    .draw(target)?;
+                return Ok(());
+            }
+        };
+
+        let mut text = ArrayString::<[u8; 64]>::new();
+        self.write_value(&mut text, stats.last);
+        for (label, value) in [("lo", stats.min), ("hi", stats.max), ("avg", stats.avg)].iter() {
+            write!(text, "  {} ", label).ok();
+            self.write_value(&mut text, *value);
+        }
+        Text::with_baseline(&text, Point::zero(), text_style, Baseline::Top).draw(target)?;
+
+        let size = target.bounding_box().size;
+        let right = size.width as i32 - 1;
+        let bottom = size.height as i32 - 1;
+        for &y in [GRAPH_TOP, bottom].iter() {
+            Line::new(Point::new(0, y), Point::new(right, y))
+                .into_styled(grid)
+                .draw(target)?;
+        }
+
+        let (lo, hi) = scale(stats.min, stats.max);
+        let shown = self.len.min(size.width as usize);
+        let x0 = size.width as i32 - shown as i32;
+        let mut previous: Option<Point> = None;
+        for (i, value) in self.samples().skip(self.len - shown).enumerate() {
+            let y = y_position(value, lo, hi, GRAPH_TOP + 1, bottom - 1);
+            let point = Point::new(x0 + i as i32, y);
+            let start = previous.unwrap_or(point);
+            Line::new(start, point).into_styled(stroke).draw(target)?;
+            previous = Some(point);
+        }
+        Ok(())
+    }
+
+    /// Thousandths with the decimals of the values
+    fn write_value<W: Write>(&self, w: &mut W, milli: i32) {
+        let sign = if milli < 0 { "-" } else { "" };
+        let milli = (milli as i64).abs();
+        let decimals = self.decimals as usize;
+        if decimals == 0 {
+            write!(w, "{}{}", sign, milli / 1000).ok();
+        } else {
+            let fraction = (milli % 1000) / 10i64.pow(3 - decimals as u32);
+            write!(w, "{}{}.{:0width$}", sign, milli / 1000, fraction, width = decimals).ok();
+        }
+    }
+}
+
+/// Range of the vertical axis for values from `min` to `max`, a flat line ends up
+/// in the middle
+pub fn scale(min: i32, max: i32) -> (i32, i32) {
+    if min == max {
+        (min.saturating_sub(1), max.saturating_add(1))
+    } else {
+        (min, max)
+    }
+}
+
+/// Pixel row of `value` on an axis from `lo` at row `bottom` to `hi` at row `top`
+pub fn y_position(value: i32, lo: i32, hi: i32, top: i32, bottom: i32) -> i32 {
+    let height = (bottom - top).max(0) as i64;
+    let offset = (value as i64 - lo as i64) * height / (hi as i64 - lo as i64);
+    bottom - offset as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+
+    fn value(milli: i32, decimals: u8) -> Option<Value> {
+        Some(Value { milli, decimals })
+    }
+
+    /// Pixels as characters: ' ' off, '-' the grid level, '#' on.
+    /// Drawing off the target is clipped like on `Canvas`.
+    struct Ascii {
+        size: Size,
+        rows: Vec<Vec<char>>,
+    }
+
+    impl Ascii {
+        fn new(width: u32, height: u32) -> Self {
+            Self {
+                size: Size::new(width, height),
+                rows: vec![vec![' '; width as usize]; height as usize],
+            }
+        }
+
+        /// Rows from `top` down
+        fn below(&self, top: i32) -> Vec<String> {
+            self.rows[top as usize..].iter().map(|row| row.iter().collect()).collect()
+        }
+    }
+
+    impl OriginDimensions for Ascii {
+        fn size(&self) -> Size {
+            self.size
+        }
+    }
+
+    impl DrawTarget for Ascii {
+        type Color = Gray4;
+        type Error = Infallible;
+
+        fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Infallible>
+        where
+            I: IntoIterator<Item = Pixel<Gray4>>,
+        {
+            for Pixel(point, color) in pixels {
+                let c = match color.luma() {
+                    0 => ' ',
+                    GRID_LEVEL => '-',
+                    15 => '#',
+                    _ => '?',
+                };
+                // the readouts are wider than the target
+                if self.bounding_box().contains(point) {
+                    self.rows[point.y as usize][point.x as usize] = c;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn first_number() {
+        let pattern = Pattern::new("");
+        assert_eq!(pattern.extract(b"temp=23.4 rpm=1200"), value(23_400, 1));
+        assert_eq!(pattern.extract(b"t -1.2345"), value(-1_234, 3));
+        assert_eq!(pattern.extract(b"v+7."), value(7_000, 0));
+        assert_eq!(pattern.extract(b"x=.5"), value(500, 1));
+        assert_eq!(pattern.extract(b"no digits - . +"), None);
+    }
+
+    #[test]
+    fn numbers_past_the_range_are_skipped() {
+        let pattern = Pattern::new("");
+        assert_eq!(pattern.extract(b"2147483.647"), value(i32::MAX, 3));
+        // too large, the first number that fits is taken
+        assert_eq!(pattern.extract(b"2147484"), value(147_484_000, 0));
+        assert_eq!(pattern.extract(b"-2147483.647"), value(-i32::MAX, 3));
+    }
+
+    #[test]
+    fn key() {
+        let pattern = Pattern::new("rpm");
+        assert_eq!(pattern.extract(b"temp=23.4 rpm=1200"), value(1_200_000, 0));
+        assert_eq!(pattern.extract(b"rpm : -3"), value(-3_000, 0));
+        // part of a longer word
+        assert_eq!(pattern.extract(b"maxrpm=9 rpm=5"), value(5_000, 0));
+        assert_eq!(pattern.extract(b"rpm 1200"), None);
+        assert_eq!(pattern.extract(b"rpm="), None);
+    }
+
+    #[test]
+    fn wildcards() {
+        let pattern = Pattern::new("T*: # C");
+        assert_eq!(pattern.extract(b"[12] T2 sensor:  21.50 C"), value(21_500, 2));
+        assert_eq!(pattern.extract(b"T2: 21.5 F"), None);
+        assert_eq!(Pattern::new("a?#").extract(b"ab12 ax-3"), value(12_000, 0));
+        assert_eq!(Pattern::new("#/#").extract(b"ratio 3/4"), value(3_000, 0));
+        // the number must be followed by what comes after '#'
+        assert_eq!(Pattern::new("# ms").extract(b"took 12 s, then 40 ms"), value(40_000, 0));
+    }
+
+    #[test]
+    fn ring_keeps_the_newest_values() {
+        let mut plot = Plot::new();
+        assert_eq!(plot.stats(), None);
+        for i in 0..SAMPLES as i32 + 3 {
+            plot.push(Value { milli: i, decimals: 0 });
+        }
+        assert_eq!(plot.samples().count(), SAMPLES);
+        assert_eq!(plot.samples().next(), Some(3));
+        let stats = plot.stats().unwrap();
+        assert_eq!((stats.last, stats.min, stats.max), (SAMPLES as i32 + 2, 3, SAMPLES as i32 + 2));
+        assert_eq!(stats.avg, (3 + SAMPLES as i32 + 2) / 2);
+        assert!(plot.take_changed());
+        assert!(!plot.take_changed());
+    }
+
+    #[test]
+    fn scale_and_positions() {
+        assert_eq!(scale(5, 5), (4, 6));
+        assert_eq!(scale(i32::MAX, i32::MAX), (i32::MAX - 1, i32::MAX));
+        assert_eq!(y_position(4, 4, 6, 11, 62), 62);
+        assert_eq!(y_position(5, 4, 6, 11, 62), 37);
+        assert_eq!(y_position(6, 4, 6, 11, 62), 11);
+        // the whole i32 range
+        assert_eq!(y_position(i32::MIN, i32::MIN, i32::MAX, 11, 62), 62);
+        assert_eq!(y_position(i32::MAX, i32::MIN, i32::MAX, 11, 62), 11);
+    }
+
+    #[test]
+    fn readouts() {
+        let mut plot = Plot::new();
+        for &(milli, decimals) in [(-1_500, 1), (250, 2), (2_000, 0)].iter() {
+            plot.push(Value { milli, decimals });
+        }
+        let mut text = String::new();
+        let stats = plot.stats().unwrap();
+        for &milli in [stats.last, stats.min, stats.max, stats.avg].iter() {
+            plot.write_value(&mut text, milli);
+            text.push(' ');
+        }
+        assert_eq!(text, "2.00 -1.50 2.00 0.25 ");
+    }
+
+    #[test]
+    fn graph_snapshot() {
+        let mut plot = Plot::new();
+        for &milli in [0, 1, 2, 3, 4, 3, 2, 1, 0, 0, 4].iter() {
+            plot.push(Value { milli: milli * 1000, decimals: 0 });
+        }
+        let mut target = Ascii::new(12, GRAPH_TOP as u32 + 7);
+        plot.draw(&mut target).unwrap();
+        assert_eq!(
+            target.below(GRAPH_TOP),
+            [
+                "------------",
+                "     #     #",
+                "    # #    #",
+                "   #   #  # ",
+                "  #     # # ",
+                " #       ## ",
+                "------------",
+            ]
+        );
+    }
+
+    #[test]
+    fn newest_values_fill_the_width() {
+        let mut plot = Plot::new();
+        for milli in 0..10 {
+            plot.push(Value { milli, decimals: 0 });
+        }
+        let mut target = Ascii::new(4, GRAPH_TOP as u32 + 6);
+        plot.draw(&mut target).unwrap();
+        // 6 to 9 on a scale from 0 to 9, three rows high
+        assert_eq!(target.below(GRAPH_TOP + 1), ["   #", "### ", "    ", "    ", "----"]);
+    }
+}
@@ -0,0 +1,157 @@
+//! Real time clock
+//!
+//! The RTC and its clock source are in the backup domain, kept by the coin cell
+//! (`BT1`) while the board is off. The 32.768 kHz crystal (LSE) is used when it
+//! starts, otherwise the internal LSI, which is less accurate and stops without
+//! main power. The clock source can only be chosen again after a backup domain
+//! reset, so once running the RTC is left as it is at boot.
+
+use stm32g0xx_hal::stm32::{PWR, RCC, RTC};
+
+use crate::clock::DateTime;
+
+const APBENR1_RTCAPBEN: u32 = 1 << 10;
+const APBENR1_PWREN: u32 = 1 << 28;
+const CR1_DBP: u32 = 1 << 8;
+const BDCR_LSEON: u32 = 1 << 0;
+const BDCR_LSERDY: u32 = 1 << 1;
+const BDCR_RTCSEL_SHIFT: u32 = 8;
+const BDCR_RTCSEL: u32 = 0b11 << BDCR_RTCSEL_SHIFT;
+const BDCR_RTCEN: u32 = 1 << 15;
+const CSR_LSION: u32 = 1 << 0;
+const CSR_LSIRDY: u32 = 1 << 1;
+
+const ICSR_INITS: u32 = 1 << 4;
+const ICSR_RSF: u32 = 1 << 5;
+const ICSR_INITF: u32 = 1 << 6;
+const ICSR_INIT: u32 = 1 << 7;
+const WPR_KEYS: [u32; 2] = [0xCA, 0x53];
+const WPR_LOCK: u32 = 0xFF;
+
+/// Loops waiting for the LSE, about a second at 64 MHz
+const LSE_TIMEOUT: u32 = 16_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Source {
+    /// 32.768 kHz crystal
+    Lse = 0b01,
+    /// Internal RC, about 32 kHz
+    Lsi = 0b10,
+}
+
+impl Source {
+    /// Asynchronous and synchronous prescaler for a 1 Hz calendar
+    fn prescalers(self) -> (u32, u32) {
+        match self {
+            Source::Lse => (127, 255),
+            Source::Lsi => (127, 249),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Source::Lse => "LSE",
+            Source::Lsi => "LSI",
+        }
+    }
+}
+
+pub struct Rtc {
+    rtc: RTC,
+    source: Source,
+}
+
+impl Rtc {
+    /// Start the RTC, a calendar kept running by the coin cell goes on
+    pub fn new(rtc: RTC) -> Self {
+        let rcc = unsafe { &*RCC::ptr() };
+        let pwr = unsafe { &*PWR::ptr() };
+
+        rcc.apbenr1.modify(|r, w| unsafe { w.bits(r.bits() | APBENR1_PWREN | APBENR1_RTCAPBEN) });
+        // backup domain write access
+        pwr.cr1.modify(|r, w| unsafe { w.bits(r.bits() | CR1_DBP) });
+
+        let bdcr = rcc.bdcr.read().bits();
+        let running = match (bdcr & BDCR_RTCSEL) >> BDCR_RTCSEL_SHIFT {
+            0b01 if bdcr & BDCR_RTCEN != 0 => Some(Source::Lse),
+            0b10 if bdcr & BDCR_RTCEN != 0 => Some(Source::Lsi),
+            _ => None,
+        };
+        // the LSI is in the main power domain, it is started at every boot
+        if running != Some(Source::Lse) {
+            rcc.csr.modify(|r, w| unsafe { w.bits(r.bits() | CSR_LSION) });
+            while rcc.csr.read().bits() & CSR_LSIRDY == 0 {}
+        }
+        let source = match running {
+            Some(source) => source,
+            None => {
+                rcc.bdcr.modify(|r, w| unsafe { w.bits(r.bits() | BDCR_LSEON) });
+                let lse = (0..LSE_TIMEOUT).any(|_| rcc.bdcr.read().bits() & BDCR_LSERDY != 0);
+                let source = if lse { Source::Lse } else { Source::Lsi };
+                rcc.bdcr.modify(|r, w| unsafe {
+                    let bdcr = r.bits() & !BDCR_RTCSEL;
+                    let bdcr = if lse { bdcr } else { bdcr & !BDCR_LSEON };
+                    w.bits(bdcr | (source as u32) << BDCR_RTCSEL_SHIFT | BDCR_RTCEN)
+                });
+                source
+            }
+        };
+
+        let mut rtc = Self { rtc, source };
+        if running.is_none() {
+            let (prediv_a, prediv_s) = source.prescalers();
+            rtc.configure(|rtc| rtc.prer.write(|w| unsafe { w.bits(prediv_a << 16 | prediv_s) }));
+        }
+        rtc
+    }
+
+    pub fn source(&self) -> Source {
+        self.source
+    }
+
+    /// The calendar was set
+    pub fn is_set(&self) -> bool {
+        self.rtc.icsr.read().bits() & ICSR_INITS != 0
+    }
+
+    /// Date and time with the milliseconds into the second, None when the calendar
+    /// was not set
+    pub fn now(&self) -> Option<(DateTime, u16)> {
+        if !self.is_set() {
+            return None;
+        }
+        // TR and SSR lock the shadow DR until it is read
+        let ssr = self.rtc.ssr.read().bits() & 0xFFFF;
+        let tr = self.rtc.tr.read().bits();
+        let dr = self.rtc.dr.read().bits();
+        let prediv_s = self.rtc.prer.read().bits() & 0x7FFF;
+        let millis = (prediv_s.saturating_sub(ssr) * 1000 / (prediv_s + 1)) as u16;
+        DateTime::from_bcd(tr, dr).map(|time| (time, millis))
+    }
+
+    /// Set the calendar to `time`, which has to be valid
+    pub fn set(&mut self, time: DateTime) {
+        let (tr, dr) = time.to_bcd();
+        self.configure(|rtc| {
+            rtc.tr.write(|w| unsafe { w.bits(tr) });
+            rtc.dr.write(|w| unsafe { w.bits(dr) });
+        });
+    }
+
+    /// Change the calendar or prescalers in init mode
+    fn configure<F: FnOnce(&RTC)>(&mut self, f: F) {
+        let rtc = &self.rtc;
+        for &key in WPR_KEYS.iter() {
+            rtc.wpr.write(|w| unsafe { w.bits(key) });
+        }
+        rtc.icsr.modify(|r, w| unsafe { w.bits(r.bits() | ICSR_INIT) });
+        while rtc.icsr.read().bits() & ICSR_INITF == 0 {}
+
+        f(rtc);
+
+        rtc.icsr.modify(|r, w| unsafe { w.bits(r.bits() & !(ICSR_INIT | ICSR_RSF)) });
+        rtc.wpr.write(|w| unsafe { w.bits(WPR_LOCK) });
+        // the shadow registers are updated again
+        while rtc.icsr.read().bits() & ICSR_RSF == 0 {}
+    }
+}
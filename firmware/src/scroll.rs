@@ -0,0 +1,225 @@
+//! Hardware scrolling with the SSD1362 display start line
+//!
+//! The 64 rows of display RAM are used as a circular screen buffer. Text lines are
+//! stacked from the bottom of the screen. Adding a line reuses the RAM rows of the
+//! line that scrolls out at the top and moves the start line, so only the rows of
+//! the new line have to be written.
+
+use core::ops::Range;
+
+/// Rows of display RAM, equal to the panel height
+pub const RAM_ROWS: u8 = 64;
+
+/// Maps text lines onto display RAM rows
+pub struct ScrollRing {
+    line_height: u8,
+    /// Start line as currently set on the display
+    start: u8,
+    /// Start line once the pending scroll has completed
+    target: u8,
+}
+
+impl ScrollRing {
+    pub fn new(line_height: u8) -> Self {
+        Self {
+            line_height,
+            start: 0,
+            target: 0,
+        }
+    }
+
+    pub fn line_height(&self) -> u8 {
+        self.line_height
+    }
+
+    /// Number of text lines on screen, including a partially visible top line
+    pub fn lines(&self) -> u8 {
+        RAM_ROWS.div_ceil(self.line_height)
+    }
+
+    /// Start line as currently set on the display
+    pub fn start(&self) -> u8 {
+        self.start
+    }
+
+    /// Forget the scroll position, e.g. after clearing the display RAM
+    pub fn reset(&mut self) {
+        self.start = 0;
+        self.target = 0;
+    }
+
+    /// RAM row holding pixel row `y` of the text line `line` lines above the bottom line.
+    ///
+    /// Rows are mapped as if the pending scroll has completed, so a new bottom line can
+    /// be drawn while it scrolls into view.
+    pub fn ram_row(&self, line: u8, y: u8) -> u8 {
+        let top = self.target as i16 - (self.line_height as i16 * (line as i16 + 1));
+        (top + y as i16).rem_euclid(RAM_ROWS as i16) as u8
+    }
+
+    /// Pixel rows of `line` that are on screen. Only the top line can be cut off.
+    pub fn rows(&self, line: u8) -> Range<u8> {
+        let top = RAM_ROWS as i16 - (self.line_height as i16 * (line as i16 + 1));
+        let first = if top < 0 { (-top).min(self.line_height as i16) } else { 0 };
+        first as u8..self.line_height
+    }
+
+    /// Pixel rows of the bottom line that have scrolled into view
+    pub fn visible_rows(&self) -> u8 {
+        self.line_height.saturating_sub(self.pending())
+    }
+
+    /// True while a smooth scroll is in progress
+    pub fn is_scrolling(&self) -> bool {
+        self.start != self.target
+    }
+
+    /// Scroll up by `lines` text lines.
+    ///
+    /// The start line is not moved until `step` or `finish` is called.
+    /// Only a single line can be scrolled smoothly, call `finish` first if
+    /// a scroll is still in progress.
+    pub fn push_lines(&mut self, lines: u8) {
+        let rows = self.line_height as u16 * lines as u16;
+        self.target = ((self.target as u16 + rows) % RAM_ROWS as u16) as u8;
+    }
+
+    /// Move the start line at most `rows` rows towards the target.
+    ///
+    /// Returns the new start line when it changed.
+    pub fn step(&mut self, rows: u8) -> Option<u8> {
+        let pending = self.pending();
+        if pending == 0 {
+            return None;
+        }
+        self.start = (self.start + pending.min(rows)) % RAM_ROWS;
+        Some(self.start)
+    }
+
+    /// Complete the pending scroll at once.
+    ///
+    /// Returns the new start line when it changed.
+    pub fn finish(&mut self) -> Option<u8> {
+        self.step(RAM_ROWS)
+    }
+
+    fn pending(&self) -> u8 {
+        (self.target + RAM_ROWS - self.start) % RAM_ROWS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lines_on_screen() {
+        assert_eq!(ScrollRing::new(8).lines(), 8);
+        assert_eq!(ScrollRing::new(10).lines(), 7);
+        assert_eq!(ScrollRing::new(12).lines(), 6);
+        assert_eq!(ScrollRing::new(16).lines(), 4);
+    }
+
+    #[test]
+    fn ram_rows_follow_the_start_line() {
+        let mut ring = ScrollRing::new(8);
+        // the bottom line sits above the start line, in the last rows of RAM
+        assert_eq!(ring.ram_row(0, 0), 56);
+        assert_eq!(ring.ram_row(7, 0), 0);
+        ring.push_lines(1);
+        assert_eq!(ring.ram_row(0, 0), 0);
+        assert_eq!(ring.ram_row(0, 7), 7);
+        assert_eq!(ring.ram_row(1, 0), 56);
+        assert_eq!(ring.ram_row(7, 0), 8);
+    }
+
+    #[test]
+    fn ram_rows_wrap_at_64() {
+        let mut ring = ScrollRing::new(10);
+        ring.push_lines(6);
+        assert_eq!(ring.ram_row(0, 9), 59);
+        // the next line starts in row 60 and goes on from row 0
+        ring.push_lines(1);
+        let rows: Vec<u8> = (0..10).map(|y| ring.ram_row(0, y)).collect();
+        assert_eq!(rows, [60, 61, 62, 63, 0, 1, 2, 3, 4, 5]);
+        // each RAM row once over the lines on screen, the cut off top line shares
+        // its hidden rows with the bottom line
+        let mut used = [0; RAM_ROWS as usize];
+        for line in 0..ring.lines() {
+            for y in ring.rows(line) {
+                used[ring.ram_row(line, y) as usize] += 1;
+            }
+        }
+        assert!(used.iter().all(|&n| n == 1));
+    }
+
+    #[test]
+    fn top_line_is_cut_off() {
+        let ring = ScrollRing::new(10);
+        assert_eq!(ring.rows(0), 0..10);
+        assert_eq!(ring.rows(5), 0..10);
+        // 70 rows for 64, the top 6 rows of line 6 are off screen
+        assert_eq!(ring.rows(6), 6..10);
+        // further up nothing is visible
+        assert_eq!(ring.rows(7), 10..10);
+
+        let ring = ScrollRing::new(12);
+        assert_eq!(ring.rows(5), 8..12);
+        let ring = ScrollRing::new(8);
+        assert_eq!(ring.rows(7), 0..8);
+        assert!(ring.rows(8).is_empty());
+    }
+
+    #[test]
+    fn push_lines_wraps_past_the_end() {
+        let mut ring = ScrollRing::new(8);
+        ring.push_lines(7);
+        assert_eq!(ring.finish(), Some(56));
+        ring.push_lines(3);
+        assert_eq!(ring.finish(), Some(16));
+        // a whole screen moves the start line all the way round
+        ring.push_lines(8);
+        assert_eq!(ring.finish(), None);
+        assert_eq!(ring.start(), 16);
+
+        let mut ring = ScrollRing::new(12);
+        ring.push_lines(30);
+        assert_eq!(ring.finish(), Some((30 * 12 % 64) as u8));
+    }
+
+    #[test]
+    fn smooth_scroll_steps() {
+        let mut ring = ScrollRing::new(8);
+        assert_eq!(ring.visible_rows(), 8);
+        assert_eq!(ring.step(3), None);
+
+        ring.push_lines(1);
+        assert!(ring.is_scrolling());
+        assert_eq!(ring.visible_rows(), 0);
+        assert_eq!(ring.step(3), Some(3));
+        assert_eq!(ring.visible_rows(), 3);
+        assert_eq!(ring.step(3), Some(6));
+        // the last step stops at the target
+        assert_eq!(ring.step(3), Some(8));
+        assert_eq!(ring.visible_rows(), 8);
+        assert!(!ring.is_scrolling());
+        assert_eq!(ring.step(3), None);
+    }
+
+    #[test]
+    fn smooth_scroll_wraps() {
+        let mut ring = ScrollRing::new(10);
+        ring.push_lines(6);
+        ring.finish();
+        ring.push_lines(1);
+        assert_eq!(ring.step(3), Some(63));
+        assert_eq!(ring.step(3), Some(2));
+        assert_eq!(ring.visible_rows(), 6);
+        assert_eq!(ring.step(10), Some(6));
+        assert_eq!(ring.visible_rows(), 10);
+
+        ring.reset();
+        assert_eq!(ring.start(), 0);
+        assert!(!ring.is_scrolling());
+    }
+}
@@ -0,0 +1,466 @@
+//! Search in the kept lines
+//!
+//! "Find" in the menu opens an `Entry`: turning the encoder moves a wheel of
+//! characters, the button adds the selected one to the search term. The wheel also
+//! holds "del" to remove the last character and "ok" to start the search.
+//!
+//! `Search` then steps through the matches in `Lines`, newest match first: turning
+//! the encoder to the left goes to earlier matches, to the right to later ones,
+//! past the oldest or the newest match it wraps around to the other end. Case is
+//! ignored and a match has to be on one line, text wrapped onto the next line is
+//! not found.
+
+use core::fmt::{self, Write};
+use core::ops::Range;
+
+use arrayvec::ArrayString;
+
+/// Longest search term
+pub const MAX_TERM: usize = 16;
+
+pub type Term = ArrayString<[u8; MAX_TERM]>;
+
+/// Characters on the wheel after "ok" and "del", matching ignores case
+const CHARACTERS: &[u8] = b" abcdefghijklmnopqrstuvwxyz0123456789.,:;=-+_*/#%()[]<>!?'\"@";
+/// Wheel positions before the characters
+const COMMANDS: usize = 2;
+/// Wheel positions shown on either side of the selected one
+const NEIGHBOURS: i32 = 3;
+
+/// What is under the wheel
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pick {
+    /// Start the search
+    Done,
+    /// Remove the last character
+    Delete,
+    Char(u8),
+}
+
+impl Pick {
+    fn at(position: usize) -> Self {
+        match position {
+            0 => Pick::Done,
+            1 => Pick::Delete,
+            _ => Pick::Char(CHARACTERS[position - COMMANDS]),
+        }
+    }
+}
+
+impl fmt::Display for Pick {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pick::Done => f.write_str("ok"),
+            Pick::Delete => f.write_str("del"),
+            Pick::Char(b' ') => f.write_str("spc"),
+            Pick::Char(c) => f.write_char(*c as char),
+        }
+    }
+}
+
+/// Search term entry with a character wheel
+pub struct Entry {
+    text: Term,
+    position: usize,
+}
+
+impl Entry {
+    /// Empty term, the wheel is on 'a'
+    pub fn new() -> Self {
+        Self {
+            text: Term::new(),
+            position: COMMANDS + 1,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn picked(&self) -> Pick {
+        Pick::at(self.position)
+    }
+
+    /// Move the wheel by `step` positions
+    pub fn turn(&mut self, step: i32) {
+        self.position = wheel(self.position, step);
+    }
+
+    /// Use what is under the wheel, returns true on "ok". A full term takes no
+    /// more characters.
+    pub fn press(&mut self) -> bool {
+        match self.picked() {
+            Pick::Done => return true,
+            Pick::Delete => {
+                self.text.pop();
+            }
+            Pick::Char(c) => {
+                self.text.try_push(c as char).ok();
+            }
+        }
+        false
+    }
+
+    /// Write the wheel around the selected position, like "  x   y   z  [ok] del  spc  a "
+    pub fn write_wheel<W: Write>(&self, w: &mut W) -> fmt::Result {
+        for step in -NEIGHBOURS..=NEIGHBOURS {
+            let pick = Pick::at(wheel(self.position, step));
+            if step == 0 {
+                write!(w, "[{}]", pick)?;
+            } else {
+                write!(w, " {} ", pick)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wheel `position` moved by `step`, wrapping around
+fn wheel(position: usize, step: i32) -> usize {
+    let len = (COMMANDS + CHARACTERS.len()) as i32;
+    (position as i32 + step).rem_euclid(len) as usize
+}
+
+/// Lines to search in, numbered from old to new
+pub trait Lines {
+    /// Number of the newest line
+    fn newest(&self) -> u32;
+    /// Text of line `number`, None when it is not kept
+    fn text(&self, number: u32) -> Option<&[u8]>;
+}
+
+/// Where the term was found
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Found {
+    pub line: u32,
+    pub column: usize,
+}
+
+/// Matches of a search term, one of them selected
+pub struct Search {
+    term: Term,
+    active: bool,
+    found: Option<Found>,
+}
+
+impl Search {
+    pub fn new() -> Self {
+        Self {
+            term: Term::new(),
+            active: false,
+            found: None,
+        }
+    }
+
+    /// Search for `term`, the next `step` selects the newest match
+    pub fn start(&mut self, term: &str) {
+        self.term = Term::from(term).unwrap_or_default();
+        self.active = !self.term.is_empty();
+        self.found = None;
+    }
+
+    pub fn stop(&mut self) {
+        self.active = false;
+        self.found = None;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn term(&self) -> &str {
+        &self.term
+    }
+
+    /// The selected match, None when the term is not in the lines
+    pub fn found(&self) -> Option<Found> {
+        self.found
+    }
+
+    /// Columns of the selected match
+    pub fn columns(&self) -> Option<Range<usize>> {
+        self.found.map(|found| found.column..found.column + self.term.len())
+    }
+
+    /// Move `steps` matches, negative steps go to earlier matches, wrapping around
+    /// at the oldest and the newest match. Without a selected match, or once its
+    /// line is no longer kept, the newest match is selected first.
+    pub fn step<L: Lines>(&mut self, steps: i32, lines: &L) {
+        if !self.active {
+            return;
+        }
+        let mut found = match self.found.filter(|found| lines.text(found.line).is_some()) {
+            Some(found) => found,
+            None => match self.earlier(lines, None) {
+                Some(found) => found,
+                None => {
+                    self.found = None;
+                    return;
+                }
+            },
+        };
+        for _ in 0..steps.abs() {
+            let next = if steps < 0 {
+                self.earlier(lines, Some(found))
+                    .or_else(|| self.earlier(lines, None))
+            } else {
+                self.later(lines, found.line, found.column + 1)
+                    .or_else(|| self.later(lines, oldest(lines), 0))
+            };
+            found = next.unwrap_or(found);
+        }
+        self.found = Some(found);
+    }
+
+    /// The last match before `before`, or the last one in the lines
+    fn earlier<L: Lines>(&self, lines: &L, before: Option<Found>) -> Option<Found> {
+        let (mut line, mut end) = match before {
+            Some(found) => (found.line, found.column),
+            None => (lines.newest(), usize::MAX),
+        };
+        loop {
+            let text = lines.text(line)?;
+            let end_column = end.min(text.len());
+            if let Some(column) = (0..end_column).rev().find(|&column| self.is_at(text, column)) {
+                return Some(Found { line, column });
+            }
+            line = line.checked_sub(1)?;
+            end = usize::MAX;
+        }
+    }
+
+    /// The first match from column `start` of `line` on
+    fn later<L: Lines>(&self, lines: &L, mut line: u32, mut start: usize) -> Option<Found> {
+        while line <= lines.newest() {
+            let text = lines.text(line)?;
+            if let Some(column) = (start..text.len()).find(|&column| self.is_at(text, column)) {
+                return Some(Found { line, column });
+            }
+            line += 1;
+            start = 0;
+        }
+        None
+    }
+
+    /// The term is in `text` at `column`
+    fn is_at(&self, text: &[u8], column: usize) -> bool {
+        let term = self.term.as_bytes();
+        text.get(column..column + term.len())
+            .is_some_and(|text| text.eq_ignore_ascii_case(term))
+    }
+}
+
+/// Number of the oldest kept line
+fn oldest<L: Lines>(lines: &L) -> u32 {
+    let mut line = lines.newest();
+    while line > 0 && lines.text(line - 1).is_some() {
+        line -= 1;
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Kept lines numbered from `first` on
+    struct Kept {
+        first: u32,
+        lines: Vec<&'static [u8]>,
+    }
+
+    impl Lines for Kept {
+        fn newest(&self) -> u32 {
+            self.first + self.lines.len() as u32 - 1
+        }
+
+        fn text(&self, number: u32) -> Option<&[u8]> {
+            let index = number.checked_sub(self.first)?;
+            self.lines.get(index as usize).copied()
+        }
+    }
+
+    fn kept() -> Kept {
+        Kept {
+            first: 10,
+            lines: vec![b"Error 1, error 2", b"ok", b"ERROR 3", b"t=21.5"],
+        }
+    }
+
+    fn wheel_text(entry: &Entry) -> String {
+        let mut text = String::new();
+        entry.write_wheel(&mut text).unwrap();
+        text
+    }
+
+    fn found(line: u32, column: usize) -> Option<Found> {
+        Some(Found { line, column })
+    }
+
+    #[test]
+    fn wheel_wraps_both_ways() {
+        let mut entry = Entry::new();
+        assert_eq!(entry.picked(), Pick::Char(b'a'));
+        entry.turn(-1);
+        assert_eq!(entry.picked(), Pick::Char(b' '));
+        entry.turn(-2);
+        assert_eq!(entry.picked(), Pick::Done);
+        // left of "ok" is the last character
+        entry.turn(-1);
+        assert_eq!(entry.picked(), Pick::Char(b'@'));
+        entry.turn(1);
+        assert_eq!(entry.picked(), Pick::Done);
+        // a whole turn of the wheel ends where it started
+        entry.turn((COMMANDS + CHARACTERS.len()) as i32 * 2 + 1);
+        assert_eq!(entry.picked(), Pick::Delete);
+        assert_eq!(wheel_text(&entry), " \"  @  ok [del] spc  a  b ");
+    }
+
+    #[test]
+    fn press_on_an_empty_term() {
+        let mut entry = Entry::new();
+        entry.turn(-2);
+        assert_eq!(entry.picked(), Pick::Delete);
+        assert!(!entry.press());
+        assert_eq!(entry.text(), "");
+        entry.turn(-1);
+        // "ok" is taken with an empty term as well, the search then isn't started
+        assert!(entry.press());
+        let mut search = Search::new();
+        search.start(entry.text());
+        assert!(!search.is_active());
+    }
+
+    #[test]
+    fn press_adds_and_deletes() {
+        let mut entry = Entry::new();
+        assert!(!entry.press());
+        entry.turn(2);
+        assert!(!entry.press());
+        entry.turn(-3);
+        assert!(!entry.press());
+        assert_eq!(entry.text(), "ac ");
+        entry.turn(-1);
+        assert!(!entry.press());
+        assert!(!entry.press());
+        assert_eq!(entry.text(), "a");
+        entry.turn(-1);
+        assert!(entry.press());
+        assert_eq!(entry.text(), "a");
+    }
+
+    #[test]
+    fn full_term_takes_no_more_characters() {
+        let mut entry = Entry::new();
+        for _ in 0..MAX_TERM + 3 {
+            assert!(!entry.press());
+        }
+        assert_eq!(entry.text(), "a".repeat(MAX_TERM));
+        // delete and ok still work
+        entry.turn(-2);
+        entry.press();
+        assert_eq!(entry.text().len(), MAX_TERM - 1);
+        entry.turn(-1);
+        assert!(entry.press());
+    }
+
+    #[test]
+    fn newest_match_first() {
+        let mut search = Search::new();
+        search.start("error");
+        assert_eq!(search.found(), None);
+        search.step(0, &kept());
+        assert_eq!(search.found(), found(12, 0));
+        assert_eq!(search.columns(), Some(0..5));
+    }
+
+    #[test]
+    fn steps_across_lines() {
+        let lines = kept();
+        let mut search = Search::new();
+        search.start("error");
+        search.step(-1, &lines);
+        // two matches on line 10, the later one first
+        assert_eq!(search.found(), found(10, 9));
+        search.step(-1, &lines);
+        assert_eq!(search.found(), found(10, 0));
+        search.step(1, &lines);
+        assert_eq!(search.found(), found(10, 9));
+        search.step(1, &lines);
+        assert_eq!(search.found(), found(12, 0));
+    }
+
+    #[test]
+    fn steps_wrap_at_both_ends() {
+        let lines = kept();
+        let mut search = Search::new();
+        search.start("error");
+        search.step(0, &lines);
+        // past the newest match to the oldest
+        search.step(1, &lines);
+        assert_eq!(search.found(), found(10, 0));
+        // and back past the oldest to the newest
+        search.step(-1, &lines);
+        assert_eq!(search.found(), found(12, 0));
+        search.step(-4, &lines);
+        assert_eq!(search.found(), found(10, 9));
+    }
+
+    #[test]
+    fn single_match_stays() {
+        let lines = kept();
+        let mut search = Search::new();
+        search.start("21");
+        search.step(3, &lines);
+        assert_eq!(search.found(), found(13, 2));
+        search.step(-2, &lines);
+        assert_eq!(search.found(), found(13, 2));
+    }
+
+    #[test]
+    fn case_is_ignored() {
+        let lines = kept();
+        let mut search = Search::new();
+        search.start("ErRoR 3");
+        search.step(0, &lines);
+        assert_eq!(search.found(), found(12, 0));
+        assert_eq!(search.columns(), Some(0..7));
+        search.start("OK");
+        search.step(0, &lines);
+        assert_eq!(search.found(), found(11, 0));
+    }
+
+    #[test]
+    fn no_match() {
+        let lines = kept();
+        let mut search = Search::new();
+        search.start("warning");
+        search.step(-1, &lines);
+        assert!(search.is_active());
+        assert_eq!(search.found(), None);
+        assert_eq!(search.columns(), None);
+        // not across the end of a line
+        search.start("2ok");
+        search.step(1, &lines);
+        assert_eq!(search.found(), None);
+    }
+
+    #[test]
+    fn line_no_longer_kept() {
+        let mut lines = kept();
+        let mut search = Search::new();
+        search.start("error");
+        search.step(-2, &lines);
+        assert_eq!(search.found(), found(10, 0));
+        // line 10 scrolled out, the newest match is selected again
+        lines.first = 11;
+        lines.lines.remove(0);
+        search.step(-1, &lines);
+        assert_eq!(search.found(), found(12, 0));
+
+        search.stop();
+        assert!(!search.is_active());
+        search.step(0, &lines);
+        assert_eq!(search.found(), None);
+    }
+}
@@ -0,0 +1,673 @@
+//! Self-test at boot
+//!
+//! Holding the button while the board powers up runs a go/no-go test instead of
+//! starting the logger:
+//!
+//! 1. button: release it, then press it once more
+//! 2. display: all pixels on, a checker board and a gray ramp, one second each
+//! 3. LEDs: red, green, both
+//! 4. the display and the LEDs are confirmed by pressing the button
+//! 5. encoder: turn right, then left
+//! 6. UART loopback: TX1 has to be connected to RX1
+//! 7. battery: the voltage on PA4 has to be in `BATTERY_MV`
+//! 8. flash: the configuration is stored and read back
+//!
+//! Steps waiting for the operator fail after `TIMEOUT_MS`. The button counts
+//! once it stayed pressed or released for `DEBOUNCE_MS`. The sequence runs on
+//! `Hardware`, which `Board` implements over the board traits, so it runs on the
+//! mock board too.
+
+use core::fmt::{self, Write};
+use core::ops::RangeInclusive;
+
+use arrayvec::ArrayString;
+use display_interface::WriteOnlyDataCommand;
+use embedded_hal::blocking::delay::DelayMs;
+
+use crate::board::{BatterySensor, CaptureUart, InputEvent, InputEvents, Led, StatusLeds, Storage};
+use crate::config::{Config, ConfigStore};
+use crate::oled::{DisplayMode, ROW_BYTES};
+use crate::terminal::Terminal;
+
+/// Time the operator has for each step
+pub const TIMEOUT_MS: u32 = 10_000;
+/// Plausible battery voltage
+pub const BATTERY_MV: RangeInclusive<u16> = 2_000..=4_500;
+/// Sent on TX1, expected back on RX1
+const LOOPBACK: &[u8] = b"\x55\xAA\x00\xFFSelfTest\r\n";
+/// Time a pattern or LED state is shown
+const SHOW_MS: u32 = 1_000;
+/// Polling interval while waiting for the operator
+const POLL_MS: u32 = 10;
+/// Time the button has to stay pressed or released
+pub const DEBOUNCE_MS: u32 = 20;
+/// Time the results are shown when the button is not pressed
+pub const RESULTS_MS: u32 = 60_000;
+/// Time `Board::loopback` waits for the byte to come back
+const LOOPBACK_TIMEOUT_MS: u32 = 100;
+
+/// Test screens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pattern {
+    /// All pixels at full brightness with the display mode of the controller
+    AllOn,
+    /// 8x8 squares
+    Checker,
+    /// 16 bands from black to white
+    GrayRamp,
+}
+
+impl Pattern {
+    /// Display RAM data of screen row `row`, for the patterns drawn into RAM
+    pub fn row(self, row: u8, data: &mut [u8; ROW_BYTES]) {
+        for (column, byte) in data.iter_mut().enumerate() {
+            // two pixels per byte
+            let x = column * 2;
+            *byte = match self {
+                Pattern::AllOn => 0xFF,
+                Pattern::Checker if (x / 8 + row as usize / 8) % 2 == 0 => 0xFF,
+                Pattern::Checker => 0x00,
+                Pattern::GrayRamp => {
+                    let gray = (x * 16 / (ROW_BYTES * 2)) as u8;
+                    gray << 4 | gray
+                }
+            };
+        }
+    }
+}
+
+/// Board functions used by the self-test
+pub trait Hardware {
+    /// Show `pattern`, None for the text
+    fn show_pattern(&mut self, pattern: Option<Pattern>);
+    /// Show a line of text on the display and the debug UART
+    fn message(&mut self, text: &str);
+    fn set_leds(&mut self, red: bool, green: bool);
+    fn delay_ms(&mut self, ms: u32);
+    fn button_held(&mut self) -> bool;
+    /// Encoder steps since the last call, positive to the right
+    fn encoder_steps(&mut self) -> i32;
+    /// Send `byte` on TX1, the byte received on RX1 or None after a short time
+    fn loopback(&mut self, byte: u8) -> Option<u8>;
+    fn battery_mv(&mut self) -> Option<u16>;
+    /// Store the configuration and read it back, true when it is the same
+    fn flash_verify(&mut self) -> bool;
+}
+
+/// The board as the self-test sees it, before the interrupts are enabled
+pub struct Board<'a, DI, W, U, L, I, B, D, F> {
+    pub terminal: &'a mut Terminal<DI>,
+    /// Gets the messages too
+    pub debug: &'a mut W,
+    pub uart: U,
+    pub leds: &'a mut L,
+    pub inputs: I,
+    pub battery: &'a mut B,
+    pub delay: &'a mut D,
+    pub config_store: &'a mut ConfigStore<F>,
+    /// Stored for the flash test
+    pub config: Config,
+}
+
+impl<DI, W, U, L, I, B, D, F> Hardware for Board<'_, DI, W, U, L, I, B, D, F>
+where
+    DI: WriteOnlyDataCommand,
+    W: Write,
+    U: CaptureUart,
+    L: StatusLeds,
+    I: InputEvents,
+    B: BatterySensor,
+    D: DelayMs<u32>,
+    F: Storage,
+{
+    fn show_pattern(&mut self, pattern: Option<Pattern>) {
+        let terminal = &mut self.terminal;
+        match pattern {
+            Some(Pattern::AllOn) => terminal.display_mode(DisplayMode::AllOn).unwrap(),
+            Some(pattern) => {
+                terminal.display_mode(DisplayMode::Normal).unwrap();
+                terminal.show_rows(|row, data| pattern.row(row, data)).unwrap();
+            }
+            None => {
+                terminal.display_mode(DisplayMode::Normal).unwrap();
+                terminal.show_page(false);
+                terminal.render().unwrap();
+            }
+        }
+    }
+
+    fn message(&mut self, text: &str) {
+        writeln!(self.terminal, "{}", text).ok();
+        self.terminal.render().unwrap();
+        writeln!(self.debug, "{}", text).ok();
+    }
+
+    fn set_leds(&mut self, red: bool, green: bool) {
+        self.leds.set(Led::Red, red);
+        self.leds.set(Led::Green, green);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay.delay_ms(ms);
+    }
+
+    fn button_held(&mut self) -> bool {
+        self.inputs.button_held()
+    }
+
+    fn encoder_steps(&mut self) -> i32 {
+        let mut steps = 0;
+        while let Some(event) = self.inputs.poll() {
+            if let InputEvent::Turn(turn) = event {
+                steps += turn;
+            }
+        }
+        steps
+    }
+
+    fn loopback(&mut self, byte: u8) -> Option<u8> {
+        nb::block!(self.uart.write(byte)).ok()?;
+        for _ in 0..LOOPBACK_TIMEOUT_MS {
+            match self.uart.read() {
+                Ok(received) => return Some(received),
+                Err(nb::Error::WouldBlock) => self.delay.delay_ms(1),
+                Err(nb::Error::Other(_)) => return None,
+            }
+        }
+        None
+    }
+
+    fn battery_mv(&mut self) -> Option<u16> {
+        self.battery.battery_mv()
+    }
+
+    fn flash_verify(&mut self) -> bool {
+        self.config_store.save(&self.config).is_ok()
+            && self.config_store.load().0 == self.config
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Test {
+    Button,
+    Display,
+    Encoder,
+    Loopback,
+    Battery,
+    Flash,
+}
+
+impl Test {
+    pub fn name(self) -> &'static str {
+        match self {
+            Test::Button => "button",
+            Test::Display => "display+LEDs",
+            Test::Encoder => "encoder",
+            Test::Loopback => "UART loopback",
+            Test::Battery => "battery",
+            Test::Flash => "flash",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Pass,
+    Fail(&'static str),
+}
+
+/// Outcome of every test in the order they ran
+pub struct Report {
+    results: [(Test, Outcome); 6],
+    /// The battery voltage, shown with its result
+    battery_mv: Option<u16>,
+}
+
+impl Report {
+    pub fn passed(&self) -> bool {
+        self.results.iter().all(|&(_, outcome)| outcome == Outcome::Pass)
+    }
+
+    /// Write result `index`, like "flash          pass"
+    pub fn write_result<W: Write>(&self, index: usize, w: &mut W) -> fmt::Result {
+        let (test, outcome) = match self.results.get(index) {
+            Some(&result) => result,
+            None => return Ok(()),
+        };
+        write!(w, "{:<14} ", test.name())?;
+        match outcome {
+            Outcome::Pass => w.write_str("pass")?,
+            Outcome::Fail(reason) => write!(w, "FAIL {}", reason)?,
+        }
+        match (test, self.battery_mv) {
+            (Test::Battery, Some(mv)) => write!(w, " {}.{:02}V", mv / 1000, mv % 1000 / 10),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Run all tests, then show the results
+pub fn run<H: Hardware>(hw: &mut H) -> Report {
+    hw.message("Self-test");
+    let button = test_button(hw);
+    let display = test_display(hw);
+    let encoder = test_encoder(hw);
+    let loopback = test_loopback(hw);
+    let battery_mv = hw.battery_mv();
+    let battery = match battery_mv {
+        Some(mv) if BATTERY_MV.contains(&mv) => Outcome::Pass,
+        Some(_) => Outcome::Fail("out of range"),
+        None => Outcome::Fail("no reading"),
+    };
+    let flash = if hw.flash_verify() {
+        Outcome::Pass
+    } else {
+        Outcome::Fail("verify")
+    };
+
+    let report = Report {
+        results: [
+            (Test::Button, button),
+            (Test::Display, display),
+            (Test::Encoder, encoder),
+            (Test::Loopback, loopback),
+            (Test::Battery, battery),
+            (Test::Flash, flash),
+        ],
+        battery_mv,
+    };
+    for index in 0..report.results.len() {
+        let mut text = ArrayString::<[u8; 40]>::new();
+        report.write_result(index, &mut text).ok();
+        hw.message(&text);
+    }
+    hw.message(if report.passed() { "self-test: PASS" } else { "self-test: FAIL" });
+    report
+}
+
+/// Keep the results on the display until the button is pressed and released,
+/// or for `RESULTS_MS`
+pub fn wait_for_start<H: Hardware>(hw: &mut H) {
+    hw.message("Press to start");
+    if wait_for_button(hw, true, RESULTS_MS) {
+        wait_for_button(hw, false, TIMEOUT_MS);
+    }
+}
+
+/// Wait until `done` or the timeout, returns false on the timeout
+fn wait_for<H: Hardware, F: FnMut(&mut H) -> bool>(hw: &mut H, timeout_ms: u32, mut done: F) -> bool {
+    let mut waited = 0;
+    while !done(hw) {
+        if waited >= timeout_ms {
+            return false;
+        }
+        hw.delay_ms(POLL_MS);
+        waited += POLL_MS;
+    }
+    true
+}
+
+/// Wait until the button stayed `held` for `DEBOUNCE_MS`, returns false on the
+/// timeout
+fn wait_for_button<H: Hardware>(hw: &mut H, held: bool, timeout_ms: u32) -> bool {
+    let mut stable_ms = 0;
+    wait_for(hw, timeout_ms, |hw| {
+        if hw.button_held() == held {
+            stable_ms += POLL_MS;
+        } else {
+            stable_ms = 0;
+        }
+        stable_ms >= DEBOUNCE_MS
+    })
+}
+
+/// A press and release within the timeout
+fn wait_for_press<H: Hardware>(hw: &mut H) -> bool {
+    wait_for_button(hw, true, TIMEOUT_MS) && wait_for_button(hw, false, TIMEOUT_MS)
+}
+
+fn test_button<H: Hardware>(hw: &mut H) -> Outcome {
+    hw.message("Release the button");
+    if !wait_for_button(hw, false, TIMEOUT_MS) {
+        return Outcome::Fail("stuck");
+    }
+    hw.message("Press the button");
+    if !wait_for_press(hw) {
+        return Outcome::Fail("no press");
+    }
+    Outcome::Pass
+}
+
+fn test_display<H: Hardware>(hw: &mut H) -> Outcome {
+    for &pattern in [Pattern::AllOn, Pattern::Checker, Pattern::GrayRamp].iter() {
+        hw.show_pattern(Some(pattern));
+        hw.delay_ms(SHOW_MS);
+    }
+    hw.show_pattern(None);
+    for &(red, green) in [(true, false), (false, true), (true, true)].iter() {
+        hw.set_leds(red, green);
+        hw.delay_ms(SHOW_MS / 2);
+    }
+    hw.set_leds(false, false);
+
+    hw.message("Display and LEDs ok? Press");
+    if !wait_for_press(hw) {
+        return Outcome::Fail("not confirmed");
+    }
+    Outcome::Pass
+}
+
+fn test_encoder<H: Hardware>(hw: &mut H) -> Outcome {
+    hw.encoder_steps();
+    for &(prompt, right) in [("Turn right", true), ("Turn left", false)].iter() {
+        hw.message(prompt);
+        let mut steps = 0;
+        if !wait_for(hw, TIMEOUT_MS, |hw| {
+            steps = hw.encoder_steps();
+            steps != 0
+        }) {
+            return Outcome::Fail("no steps");
+        }
+        if (steps > 0) != right {
+            return Outcome::Fail("direction");
+        }
+        // the rest of the turn
+        hw.delay_ms(SHOW_MS / 2);
+        hw.encoder_steps();
+    }
+    Outcome::Pass
+}
+
+fn test_loopback<H: Hardware>(hw: &mut H) -> Outcome {
+    for &byte in LOOPBACK.iter() {
+        match hw.loopback(byte) {
+            Some(received) if received == byte => {}
+            Some(_) => return Outcome::Fail("wrong data"),
+            None => return Outcome::Fail("no data"),
+        }
+    }
+    Outcome::Pass
+}
+
+#[cfg(test)]
+// the times the button is held are a list even with one press
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use super::*;
+    use crate::board::mock::{MockBattery, MockClock, MockDisplay, MockFlash, MockInputs, MockLeds, MockUart};
+    use std::ops::Range;
+
+    /// Operator and board over the time passed in delays
+    struct Script {
+        now: u32,
+        held: Vec<Range<u32>>,
+        /// Time and encoder steps
+        turns: Vec<(u32, i32)>,
+        /// Added to the byte sent, None for no data
+        loopback: Option<u8>,
+        battery_mv: Option<u16>,
+        flash: bool,
+        messages: Vec<String>,
+        patterns: Vec<Option<Pattern>>,
+    }
+
+    impl Script {
+        fn new(held: &[Range<u32>], turns: &[(u32, i32)]) -> Self {
+            Self {
+                now: 0,
+                held: held.to_vec(),
+                turns: turns.to_vec(),
+                loopback: Some(0),
+                battery_mv: Some(3_700),
+                flash: true,
+                messages: Vec::new(),
+                patterns: Vec::new(),
+            }
+        }
+    }
+
+    impl Hardware for Script {
+        fn show_pattern(&mut self, pattern: Option<Pattern>) {
+            self.patterns.push(pattern);
+        }
+
+        fn message(&mut self, text: &str) {
+            self.messages.push(text.to_string());
+        }
+
+        fn set_leds(&mut self, _red: bool, _green: bool) {}
+
+        fn delay_ms(&mut self, ms: u32) {
+            self.now += ms;
+        }
+
+        fn button_held(&mut self) -> bool {
+            self.held.iter().any(|held| held.contains(&self.now))
+        }
+
+        fn encoder_steps(&mut self) -> i32 {
+            let now = self.now;
+            let steps = self.turns.iter().filter(|&&(at, _)| at <= now).map(|&(_, steps)| steps).sum();
+            self.turns.retain(|&(at, _)| at > now);
+            steps
+        }
+
+        fn loopback(&mut self, byte: u8) -> Option<u8> {
+            self.loopback.map(|offset| byte.wrapping_add(offset))
+        }
+
+        fn battery_mv(&mut self) -> Option<u16> {
+            self.battery_mv
+        }
+
+        fn flash_verify(&mut self) -> bool {
+            self.flash
+        }
+    }
+
+    #[test]
+    fn button_is_debounced() {
+        // bounces of a single poll don't count
+        let mut script = Script::new(&[0..5, 20..25, 40..100], &[]);
+        assert!(wait_for_button(&mut script, true, TIMEOUT_MS));
+        assert_eq!(script.now, 50);
+        assert!(wait_for_button(&mut script, false, TIMEOUT_MS));
+        assert_eq!(script.now, 110);
+    }
+
+    #[test]
+    fn button_waits_time_out() {
+        let mut script = Script::new(&[0..u32::MAX], &[]);
+        assert_eq!(test_button(&mut script), Outcome::Fail("stuck"));
+        assert_eq!(script.now, TIMEOUT_MS);
+
+        // a press is not confirmed until the release
+        let mut script = Script::new(&[100..20_000], &[]);
+        assert_eq!(test_button(&mut script), Outcome::Fail("no press"));
+    }
+
+    #[test]
+    fn start_after_a_press_or_the_results_time() {
+        let mut script = Script::new(&[1_000..1_200], &[]);
+        wait_for_start(&mut script);
+        assert_eq!(script.messages, ["Press to start"]);
+        assert_eq!(script.now, 1_210);
+
+        let mut script = Script::new(&[], &[]);
+        wait_for_start(&mut script);
+        assert_eq!(script.now, RESULTS_MS);
+
+        // held for good, the logger starts anyway
+        let mut script = Script::new(&[0..u32::MAX], &[]);
+        wait_for_start(&mut script);
+        assert_eq!(script.now, TIMEOUT_MS + DEBOUNCE_MS - POLL_MS);
+    }
+
+    #[test]
+    fn encoder_directions() {
+        let start = 2 * SHOW_MS;
+        let mut script = Script::new(&[], &[(start, 1), (start + 2_000, -2)]);
+        script.now = start - 1_000;
+        assert_eq!(test_encoder(&mut script), Outcome::Pass);
+
+        let mut script = Script::new(&[], &[(100, -1)]);
+        assert_eq!(test_encoder(&mut script), Outcome::Fail("direction"));
+        let mut script = Script::new(&[], &[(100, 1)]);
+        assert_eq!(test_encoder(&mut script), Outcome::Fail("no steps"));
+        assert_eq!(script.messages, ["Turn right", "Turn left"]);
+    }
+
+    #[test]
+    fn loopback_data() {
+        let mut script = Script::new(&[], &[]);
+        assert_eq!(test_loopback(&mut script), Outcome::Pass);
+        script.loopback = Some(1);
+        assert_eq!(test_loopback(&mut script), Outcome::Fail("wrong data"));
+        script.loopback = None;
+        assert_eq!(test_loopback(&mut script), Outcome::Fail("no data"));
+    }
+
+    #[test]
+    fn results_are_shown() {
+        // released, pressed, display confirmed
+        let mut script = Script::new(&[0..100, 1_000..1_100, 9_000..9_100], &[]);
+        script.turns = vec![(11_000, 1), (12_000, -1)];
+        script.battery_mv = Some(1_500);
+        script.flash = false;
+        let report = run(&mut script);
+        assert!(!report.passed());
+        let patterns = [Some(Pattern::AllOn), Some(Pattern::Checker), Some(Pattern::GrayRamp), None];
+        assert_eq!(script.patterns, patterns);
+        let results: Vec<&str> = script.messages.iter().rev().take(7).rev().map(|m| m.as_str()).collect();
+        assert_eq!(
+            results,
+            [
+                "button         pass",
+                "display+LEDs   pass",
+                "encoder        pass",
+                "UART loopback  pass",
+                "battery        FAIL out of range 1.50V",
+                "flash          FAIL verify",
+                "self-test: FAIL",
+            ]
+        );
+    }
+
+    #[test]
+    fn no_battery_reading() {
+        let report = Report {
+            results: [
+                (Test::Button, Outcome::Pass),
+                (Test::Display, Outcome::Pass),
+                (Test::Encoder, Outcome::Pass),
+                (Test::Loopback, Outcome::Pass),
+                (Test::Battery, Outcome::Fail("no reading")),
+                (Test::Flash, Outcome::Pass),
+            ],
+            battery_mv: None,
+        };
+        let mut text = String::new();
+        report.write_result(4, &mut text).unwrap();
+        assert_eq!(text, "battery        FAIL no reading");
+        text.clear();
+        report.write_result(6, &mut text).unwrap();
+        assert!(text.is_empty());
+    }
+
+    /// Operator of the mock board: released at boot, pressed, display confirmed,
+    /// turned right and left
+    const HELD: &[Range<u32>] = &[0..100, 1_000..1_100, 9_000..9_100];
+    const TURNS: &[(u32, i32)] = &[(11_000, 1), (12_000, -1)];
+
+    struct MockRun {
+        report: Report,
+        debug: String,
+        uart: MockUart,
+        leds: MockLeds,
+        flash: MockFlash,
+        ms: u32,
+    }
+
+    /// `run` on the mock board
+    fn run_on_mocks(held: &[Range<u32>], turns: &[(u32, i32)], connected: bool) -> MockRun {
+        let clock = MockClock::new();
+        let mut terminal = Terminal::new(MockDisplay::default());
+        let mut debug = String::new();
+        let mut leds = MockLeds::default();
+        let mut battery = MockBattery(Some(3_700));
+        let mut delay = &clock;
+        let mut config_store = ConfigStore::new(MockFlash::new());
+        let mut board = Board {
+            terminal: &mut terminal,
+            debug: &mut debug,
+            uart: MockUart::new(connected),
+            leds: &mut leds,
+            inputs: MockInputs::new(&clock, held, turns),
+            battery: &mut battery,
+            delay: &mut delay,
+            config_store: &mut config_store,
+            config: Config::default(),
+        };
+        let report = run(&mut board);
+        let uart = board.uart;
+        MockRun {
+            report,
+            debug,
+            uart,
+            leds,
+            flash: config_store.release(),
+            ms: clock.now(),
+        }
+    }
+
+    #[test]
+    fn mock_board_passes() {
+        let run = run_on_mocks(HELD, TURNS, true);
+        assert!(run.report.passed(), "{}", run.debug);
+        assert!(run.debug.ends_with("flash          pass\nself-test: PASS\n"));
+        assert!(run.debug.contains("battery        pass 3.70V\n"));
+        assert_eq!(&run.uart.sent[..], LOOPBACK);
+        // red, green, both, off
+        assert_eq!(run.leds.changes, 8);
+        assert!(!run.leds.red && !run.leds.green);
+        // the configuration was stored
+        assert!(run.flash.pages.iter().any(|page| page.iter().any(|&b| b != 0xFF)));
+        assert!(run.ms < 20_000);
+    }
+
+    #[test]
+    fn mock_board_without_loopback() {
+        let run = run_on_mocks(HELD, TURNS, false);
+        assert!(!run.report.passed());
+        assert_eq!(run.report.results[3], (Test::Loopback, Outcome::Fail("no data")));
+        assert!(run.debug.contains("UART loopback  FAIL no data\n"));
+        // gave up after the first byte
+        assert_eq!(&run.uart.sent[..], &LOOPBACK[..1]);
+        let others = run.report.results.iter().filter(|&&(test, _)| test != Test::Loopback);
+        assert!(others.into_iter().all(|&(_, outcome)| outcome == Outcome::Pass));
+    }
+
+    #[test]
+    fn mock_board_with_a_stuck_button() {
+        let run = run_on_mocks(&[0..u32::MAX], TURNS, true);
+        assert!(run.debug.ends_with("self-test: FAIL\n"));
+        assert_eq!(run.report.results[0], (Test::Button, Outcome::Fail("stuck")));
+        assert_eq!(run.report.results[1], (Test::Display, Outcome::Fail("not confirmed")));
+        // the tests without the button still ran
+        assert_eq!(run.report.results[3], (Test::Loopback, Outcome::Pass));
+        assert_eq!(run.report.results[5], (Test::Flash, Outcome::Pass));
+    }
+
+    #[test]
+    fn pattern_rows() {
+        let mut data = [0; ROW_BYTES];
+        Pattern::Checker.row(0, &mut data);
+        assert_eq!(data[..8], [0xFF, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0]);
+        Pattern::Checker.row(8, &mut data);
+        assert_eq!(data[..8], [0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]);
+        Pattern::GrayRamp.row(0, &mut data);
+        assert_eq!((data[0], data[ROW_BYTES / 2], data[ROW_BYTES - 1]), (0x00, 0x88, 0xFF));
+        Pattern::AllOn.row(63, &mut data);
+        assert!(data.iter().all(|&b| b == 0xFF));
+    }
+}
@@ -0,0 +1,717 @@
+//! Binary packet templates
+//!
+//! A template describes the packets of a binary protocol in one line of text,
+//! so that received packets can be shown as labelled field values. The text is
+//! a list of items separated by spaces:
+//!
+//! | item                     | meaning                                           |
+//! |--------------------------|---------------------------------------------------|
+//! | `sync:AA55`              | sync bytes (hex, up to 4) at the start            |
+//! | `len:u8@2+6`             | packet length field, plus bytes it does not count |
+//! | `type:u8@3`              | packet type field                                 |
+//! | `crc:crc16be@2`          | checksum at the end, covering bytes from offset 2 |
+//! | `temp:i16@4*5/10?1`      | field: name, type, offset, scaling, packet type   |
+//!
+//! Field types are `u8`, `i8`, `u16`, `i16`, `u32` and `i32`, little endian
+//! unless followed by `be`. A field value is multiplied by `*m` and divided by
+//! `/d`, a divisor of 10, 100 or 1000 gives 1, 2 or 3 decimals and any other
+//! divisor 2 decimals. `?t` shows the field only in packets of type `t`.
+//!
+//! Checksums are `sum8`, `xor8`, `crc16` (CCITT-FALSE), `crc16modbus` and
+//! `crc32`, they cover the packet up to the checksum.
+//!
+//! Without a length field the packet ends after the last field and the checksum.
+//!
+//! Example: `sync:AA55 len:u8@2+4 type:u8@3 crc:sum8@2 t:i16@4/10?1 rh:u8@6?1`
+
+use core::fmt;
+
+use arrayvec::ArrayString;
+
+use crate::crc::{crc16_ccitt, crc16_modbus, crc32};
+
+/// Longest template text
+pub const MAX_TEMPLATE: usize = 128;
+/// Longest packet
+pub const MAX_FRAME: usize = 128;
+/// Fields per template
+pub const MAX_FIELDS: usize = 8;
+const MAX_SYNC: usize = 4;
+
+/// What is wrong with a template, `at` is the offset of the item in the text
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParseError {
+    pub at: usize,
+    pub kind: ParseErrorKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseErrorKind {
+    /// Longer than `MAX_TEMPLATE`
+    TooLong,
+    /// Item is not `name:value`
+    Syntax,
+    /// Unknown field type
+    Type,
+    Number,
+    /// Sync is not 1 to 4 hex bytes
+    Sync,
+    /// Unknown checksum algorithm
+    Checksum,
+    /// Name longer than 8 characters or not alphanumeric
+    Name,
+    /// More than `MAX_FIELDS` fields
+    TooManyFields,
+    /// Sync, length, type or checksum given twice
+    Duplicate,
+    /// A field lies beyond `MAX_FRAME`
+    Offset,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let what = match self.kind {
+            ParseErrorKind::TooLong => "template too long",
+            ParseErrorKind::Syntax => "expected name:value",
+            ParseErrorKind::Type => "unknown type",
+            ParseErrorKind::Number => "bad number",
+            ParseErrorKind::Sync => "bad sync bytes",
+            ParseErrorKind::Checksum => "unknown checksum",
+            ParseErrorKind::Name => "bad name",
+            ParseErrorKind::TooManyFields => "too many fields",
+            ParseErrorKind::Duplicate => "given twice",
+            ParseErrorKind::Offset => "offset out of range",
+        };
+        write!(f, "{} at {}", what, self.at)
+    }
+}
+
+/// Why a packet was not decoded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodeError {
+    /// Length field shorter than header and checksum or longer than `MAX_FRAME`
+    Length(u32),
+    /// Shorter than the template
+    TooShort,
+    /// Does not start with the sync bytes
+    Sync,
+    Checksum,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+}
+
+/// Integer in the packet
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Int {
+    kind: Kind,
+    big_endian: bool,
+    offset: usize,
+}
+
+impl Int {
+    fn size(&self) -> usize {
+        match self.kind {
+            Kind::U8 | Kind::I8 => 1,
+            Kind::U16 | Kind::I16 => 2,
+            Kind::U32 | Kind::I32 => 4,
+        }
+    }
+
+    fn end(&self) -> usize {
+        self.offset + self.size()
+    }
+
+    /// The value, None when the packet is too short
+    fn read(&self, packet: &[u8]) -> Option<i64> {
+        let bytes = packet.get(self.offset..self.end())?;
+        let mut raw = 0u32;
+        for i in 0..bytes.len() {
+            let b = if self.big_endian { bytes[i] } else { bytes[bytes.len() - 1 - i] };
+            raw = raw << 8 | b as u32;
+        }
+        Some(match self.kind {
+            Kind::U8 | Kind::U16 | Kind::U32 => raw as i64,
+            Kind::I8 => raw as u8 as i8 as i64,
+            Kind::I16 => raw as u16 as i16 as i64,
+            Kind::I32 => raw as i32 as i64,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Algorithm {
+    Sum8,
+    Xor8,
+    Crc16,
+    Crc16Modbus,
+    Crc32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Checksum {
+    algorithm: Algorithm,
+    big_endian: bool,
+    /// First byte covered
+    start: usize,
+}
+
+impl Checksum {
+    fn size(&self) -> usize {
+        match self.algorithm {
+            Algorithm::Sum8 | Algorithm::Xor8 => 1,
+            Algorithm::Crc16 | Algorithm::Crc16Modbus => 2,
+            Algorithm::Crc32 => 4,
+        }
+    }
+
+    fn check(&self, packet: &[u8]) -> bool {
+        let end = packet.len() - self.size();
+        let data = match packet.get(self.start..end) {
+            Some(data) => data,
+            None => return false,
+        };
+        let value = match self.algorithm {
+            Algorithm::Sum8 => data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) as u32,
+            Algorithm::Xor8 => data.iter().fold(0u8, |sum, &b| sum ^ b) as u32,
+            Algorithm::Crc16 => crc16_ccitt(data) as u32,
+            Algorithm::Crc16Modbus => crc16_modbus(data) as u32,
+            Algorithm::Crc32 => crc32(data),
+        };
+        let kind = match self.size() {
+            1 => Kind::U8,
+            2 => Kind::U16,
+            _ => Kind::U32,
+        };
+        let field = Int { kind, big_endian: self.big_endian, offset: end };
+        field.read(packet) == Some(value as i64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Field {
+    name: ArrayString<[u8; 8]>,
+    value: Int,
+    mul: i32,
+    div: u32,
+    /// Only shown in packets of this type
+    only_type: Option<i64>,
+}
+
+/// A parsed template
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Template {
+    sync: [u8; MAX_SYNC],
+    sync_len: usize,
+    /// Length field and the bytes it does not count
+    length: Option<(Int, i32)>,
+    kind: Option<Int>,
+    checksum: Option<Checksum>,
+    fields: [Option<Field>; MAX_FIELDS],
+    /// Bytes needed to know the packet length
+    header_len: usize,
+    /// Shortest packet with a length field: header and checksum
+    min_len: usize,
+    /// Packet length without a length field
+    fixed_len: usize,
+}
+
+impl Template {
+    /// Template without fields, packets are not decoded
+    pub const fn empty() -> Self {
+        Self {
+            sync: [0; MAX_SYNC],
+            sync_len: 0,
+            length: None,
+            kind: None,
+            checksum: None,
+            fields: [None; MAX_FIELDS],
+            header_len: 0,
+            min_len: 0,
+            fixed_len: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fixed_len == 0 && self.length.is_none()
+    }
+
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        if text.len() > MAX_TEMPLATE {
+            return Err(ParseError { at: MAX_TEMPLATE, kind: ParseErrorKind::TooLong });
+        }
+
+        let mut template = Template::empty();
+        let mut fields = 0;
+        let mut at = 0;
+        for item in text.split(' ') {
+            let start = at;
+            at += item.len() + 1;
+            if item.is_empty() {
+                continue;
+            }
+            template
+                .parse_item(item, &mut fields)
+                .map_err(|kind| ParseError { at: start, kind })?;
+        }
+
+        let mut end = template.sync_len;
+        for int in template.length.iter().map(|(int, _)| int).chain(template.kind.iter()) {
+            end = end.max(int.end());
+        }
+        template.header_len = end;
+        for field in template.fields.iter().flatten() {
+            end = end.max(field.value.end());
+        }
+        let checksum_len = template.checksum.map_or(0, |checksum| checksum.size());
+        template.min_len = template.header_len + checksum_len;
+        end += checksum_len;
+        if end > MAX_FRAME {
+            return Err(ParseError { at: text.len(), kind: ParseErrorKind::Offset });
+        }
+        template.fixed_len = end;
+        Ok(template)
+    }
+
+    fn parse_item(&mut self, item: &str, fields: &mut usize) -> Result<(), ParseErrorKind> {
+        let colon = item.find(':').ok_or(ParseErrorKind::Syntax)?;
+        let (name, value) = (&item[..colon], &item[colon + 1..]);
+
+        match name {
+            "sync" => {
+                if self.sync_len != 0 {
+                    return Err(ParseErrorKind::Duplicate);
+                }
+                let hex = value.as_bytes();
+                if hex.is_empty() || hex.len() % 2 != 0 || hex.len() > 2 * MAX_SYNC {
+                    return Err(ParseErrorKind::Sync);
+                }
+                for (i, pair) in hex.chunks(2).enumerate() {
+                    let pair = core::str::from_utf8(pair).map_err(|_| ParseErrorKind::Sync)?;
+                    self.sync[i] = u8::from_str_radix(pair, 16).map_err(|_| ParseErrorKind::Sync)?;
+                }
+                self.sync_len = hex.len() / 2;
+            }
+            "len" => {
+                if self.length.is_some() {
+                    return Err(ParseErrorKind::Duplicate);
+                }
+                let (int, adjust) = match value.find(['+', '-']) {
+                    Some(i) => (&value[..i], number(&value[i..])?),
+                    None => (value, 0),
+                };
+                self.length = Some((int_field(int)?, adjust));
+            }
+            "type" => {
+                if self.kind.is_some() {
+                    return Err(ParseErrorKind::Duplicate);
+                }
+                self.kind = Some(int_field(value)?);
+            }
+            "crc" => {
+                if self.checksum.is_some() {
+                    return Err(ParseErrorKind::Duplicate);
+                }
+                let (algorithm, start) = match value.find('@') {
+                    Some(i) => (&value[..i], number(&value[i + 1..])?),
+                    None => (value, 0),
+                };
+                let (algorithm, big_endian) = endianness(algorithm);
+                let algorithm = match algorithm {
+                    "sum8" => Algorithm::Sum8,
+                    "xor8" => Algorithm::Xor8,
+                    "crc16" => Algorithm::Crc16,
+                    "crc16modbus" => Algorithm::Crc16Modbus,
+                    "crc32" => Algorithm::Crc32,
+                    _ => return Err(ParseErrorKind::Checksum),
+                };
+                if start < 0 || start as usize >= MAX_FRAME {
+                    return Err(ParseErrorKind::Offset);
+                }
+                self.checksum = Some(Checksum { algorithm, big_endian, start: start as usize });
+            }
+            _ => {
+                if name.is_empty()
+                    || !name.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_')
+                {
+                    return Err(ParseErrorKind::Name);
+                }
+                let name = ArrayString::from(name).map_err(|_| ParseErrorKind::Name)?;
+                let slot = self.fields.get_mut(*fields).ok_or(ParseErrorKind::TooManyFields)?;
+
+                // the suffixes come in the order *, /, ?
+                let (value, only_type) = split_suffix(value, '?');
+                let (value, div) = split_suffix(value, '/');
+                let (value, mul) = split_suffix(value, '*');
+                let div = div.map(number).transpose()?.unwrap_or(1);
+                if div <= 0 {
+                    return Err(ParseErrorKind::Number);
+                }
+                *slot = Some(Field {
+                    name,
+                    value: int_field(value)?,
+                    mul: mul.map(number).transpose()?.unwrap_or(1),
+                    div: div as u32,
+                    only_type: only_type.map(number).transpose()?.map(|t| t as i64),
+                });
+                *fields += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Length of the packet starting with `header`, None while the header is incomplete
+    fn packet_len(&self, header: &[u8]) -> Option<Result<usize, DecodeError>> {
+        if header.len() < self.header_len {
+            return None;
+        }
+        let (field, adjust) = match self.length {
+            Some(length) => length,
+            None => return Some(Ok(self.fixed_len)),
+        };
+        let value = field.read(header)?;
+        let len = value + adjust as i64;
+        if len < self.min_len as i64 || len > MAX_FRAME as i64 {
+            return Some(Err(DecodeError::Length(value as u32)));
+        }
+        Some(Ok(len as usize))
+    }
+
+    /// Check a whole packet
+    pub fn decode<'a>(&'a self, packet: &'a [u8]) -> Result<Frame<'a>, DecodeError> {
+        if !packet.starts_with(&self.sync[..self.sync_len]) {
+            return Err(DecodeError::Sync);
+        }
+        let packet = match self.packet_len(packet) {
+            None => return Err(DecodeError::TooShort),
+            Some(Err(err)) => return Err(err),
+            Some(Ok(len)) => packet.get(..len).ok_or(DecodeError::TooShort)?,
+        };
+        if let Some(checksum) = self.checksum {
+            if !checksum.check(packet) {
+                return Err(DecodeError::Checksum);
+            }
+        }
+        Ok(Frame { template: self, packet })
+    }
+}
+
+/// `u16be@4` and the like
+fn int_field(text: &str) -> Result<Int, ParseErrorKind> {
+    let at = text.find('@').ok_or(ParseErrorKind::Syntax)?;
+    let (kind, big_endian) = endianness(&text[..at]);
+    let kind = match kind {
+        "u8" => Kind::U8,
+        "i8" => Kind::I8,
+        "u16" => Kind::U16,
+        "i16" => Kind::I16,
+        "u32" => Kind::U32,
+        "i32" => Kind::I32,
+        _ => return Err(ParseErrorKind::Type),
+    };
+    let offset = number(&text[at + 1..])?;
+    if offset < 0 || offset as usize >= MAX_FRAME {
+        return Err(ParseErrorKind::Offset);
+    }
+    Ok(Int { kind, big_endian, offset: offset as usize })
+}
+
+/// Strip an optional "le" or "be" from a type name
+fn endianness(name: &str) -> (&str, bool) {
+    if let Some(stem) = name.strip_suffix("be") {
+        (stem, true)
+    } else {
+        (name.strip_suffix("le").unwrap_or(name), false)
+    }
+}
+
+/// Split "value<c>suffix"
+fn split_suffix(text: &str, c: char) -> (&str, Option<&str>) {
+    match text.find(c) {
+        Some(i) => (&text[..i], Some(&text[i + 1..])),
+        None => (text, None),
+    }
+}
+
+/// Decimal or `0x` hex number with an optional sign
+fn number(text: &str) -> Result<i32, ParseErrorKind> {
+    let (negative, digits) = match text.as_bytes().first() {
+        Some(b'-') => (true, &text[1..]),
+        Some(b'+') => (false, &text[1..]),
+        _ => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        u32::from_str_radix(hex, 16)
+    } else {
+        digits.parse::<u32>()
+    };
+    let value = value.map_err(|_| ParseErrorKind::Number)? as i32;
+    if digits.starts_with('+') || value < 0 {
+        return Err(ParseErrorKind::Number);
+    }
+    Ok(if negative { -value } else { value })
+}
+
+/// A packet that matches its template
+pub struct Frame<'a> {
+    template: &'a Template,
+    packet: &'a [u8],
+}
+
+/// "T1 temp=21.5 rh=40", fields of other types are left out
+impl fmt::Display for Frame<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = self.template.kind.and_then(|kind| kind.read(self.packet));
+        let mut first = true;
+        if let Some(kind) = kind {
+            write!(f, "T{}", kind)?;
+            first = false;
+        }
+
+        for field in self.template.fields.iter().flatten() {
+            if field.only_type.is_some() && field.only_type != kind {
+                continue;
+            }
+            let value = match field.value.read(self.packet) {
+                Some(value) => value,
+                None => continue,
+            };
+            if !first {
+                f.write_str(" ")?;
+            }
+            first = false;
+            write!(f, "{}=", field.name)?;
+            write_scaled(f, value * field.mul as i64, field.div)?;
+        }
+        Ok(())
+    }
+}
+
+/// `value / div` with the decimals of a power of ten divisor, otherwise 2.
+/// The decimals come from the remainder, so any 32 bit value times any
+/// multiplier is shown without overflow.
+fn write_scaled(f: &mut fmt::Formatter, value: i64, div: u32) -> fmt::Result {
+    let decimals = match div {
+        1 => return write!(f, "{}", value),
+        10 => 1,
+        100 => 2,
+        1000 => 3,
+        _ => 2,
+    };
+    let unit = 10i64.pow(decimals as u32);
+    let div = div as i64;
+    let integer = (value / div).abs();
+    let fraction = ((value % div) * unit / div).abs();
+    let sign = if value < 0 && (integer != 0 || fraction != 0) { "-" } else { "" };
+    write!(f, "{}{}.{:0width$}", sign, integer, fraction, width = decimals)
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::Length(len) => write!(f, "-- bad length {}", len),
+            DecodeError::TooShort => f.write_str("-- short packet"),
+            DecodeError::Sync => f.write_str("-- no sync"),
+            DecodeError::Checksum => f.write_str("-- checksum error"),
+        }
+    }
+}
+
+/// Finds packets in a byte stream using the sync bytes and length of a template
+pub struct Decoder {
+    buf: [u8; MAX_FRAME],
+    len: usize,
+    /// The packet was handed out, start over on the next byte
+    done: bool,
+}
+
+impl Decoder {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; MAX_FRAME],
+            len: 0,
+            done: false,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.len = 0;
+        self.done = false;
+    }
+
+    /// Add a byte, returns a packet or an error when one is complete.
+    /// Bytes before the sync bytes are skipped.
+    pub fn push<'a>(
+        &'a mut self,
+        template: &'a Template,
+        b: u8,
+    ) -> Option<Result<Frame<'a>, DecodeError>> {
+        if self.done {
+            self.reset();
+        }
+        if template.is_empty() {
+            return None;
+        }
+
+        self.buf[self.len] = b;
+        self.len += 1;
+        self.resync(template, 0);
+
+        let len = match template.packet_len(&self.buf[..self.len]) {
+            None => return None,
+            Some(Ok(len)) => len,
+            Some(Err(err)) => {
+                // not a packet after all, look for the next sync
+                self.resync(template, 1);
+                return Some(Err(err));
+            }
+        };
+        if self.len < len {
+            return None;
+        }
+        self.done = true;
+        Some(template.decode(&self.buf[..len]))
+    }
+
+    /// Drop `skip` bytes and then bytes until the buffer starts with (a part of) the sync
+    fn resync(&mut self, template: &Template, skip: usize) {
+        let sync = &template.sync[..template.sync_len];
+        let mut start = skip.min(self.len);
+        while start < self.len {
+            let n = (self.len - start).min(sync.len());
+            if self.buf[start..start + n] == sync[..n] {
+                break;
+            }
+            start += 1;
+        }
+        self.buf.copy_within(start..self.len, 0);
+        self.len -= start;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "sync:AA55 len:u8@2+4 type:u8@3 crc:sum8@2 t:i16@4/10?1 rh:u8@6?1";
+
+    fn parse_error(text: &str) -> ParseErrorKind {
+        Template::parse(text).unwrap_err().kind
+    }
+
+    fn show(template: &str, packet: &[u8]) -> String {
+        let template = Template::parse(template).unwrap();
+        match template.decode(packet) {
+            Ok(frame) => format!("{}", frame),
+            Err(err) => format!("{}", err),
+        }
+    }
+
+    #[test]
+    fn example_packets() {
+        assert_eq!(show(EXAMPLE, &[0xAA, 0x55, 0x04, 0x01, 0xD7, 0x00, 0x28, 0x04]), "T1 t=21.5 rh=40");
+        assert_eq!(show(EXAMPLE, &[0xAA, 0x55, 0x04, 0x01, 0xFB, 0xFF, 0x28, 0x27]), "T1 t=-0.5 rh=40");
+        // fields of type 1 are left out
+        assert_eq!(show(EXAMPLE, &[0xAA, 0x55, 0x01, 0x02, 0x03]), "T2");
+        assert_eq!(show(EXAMPLE, &[0xAA, 0x55, 0x01, 0x02, 0x04]), "-- checksum error");
+        assert_eq!(show(EXAMPLE, &[0xAA, 0x55, 0x00, 0x02, 0x02]), "-- bad length 0");
+        assert_eq!(show(EXAMPLE, &[0xAA, 0x55, 0x04, 0x01]), "-- short packet");
+        assert_eq!(show(EXAMPLE, &[0xAA, 0x56, 0x01, 0x02, 0x03]), "-- no sync");
+    }
+
+    #[test]
+    fn endianness_and_signs() {
+        let template = "a:u16@0 b:u16be@0 c:i8@2 d:u32le@3 e:i32be@3";
+        let packet = [0x01, 0x02, 0xFF, 0xFE, 0xFF, 0xFF, 0xFF];
+        assert_eq!(show(template, &packet), "a=513 b=258 c=-1 d=4294967294 e=-16777217");
+    }
+
+    #[test]
+    fn scaling() {
+        let packet = [0xF9, 0xFF, 0xFF, 0xFF];
+        assert_eq!(show("v:i32@0/2", &packet), "v=-3.50");
+        assert_eq!(show("v:i32@0*3/1000", &packet), "v=-0.021");
+        assert_eq!(show("v:i32@0*-1/10", &packet), "v=0.7");
+        // rounds towards zero, without a sign for zero
+        assert_eq!(show("v:i32@0/800", &packet), "v=0.00");
+        assert_eq!(show("v:i32@0/0x10", &packet), "v=-0.43");
+    }
+
+    #[test]
+    fn large_values_do_not_overflow() {
+        let max = [0xFF, 0xFF, 0xFF, 0xFF];
+        assert_eq!(show("v:u32@0*2147483647/1000", &max), "v=9223372030412324.865");
+        assert_eq!(show("v:u32@0*-2147483647/100", &max), "v=-92233720304123248.65");
+        let min = [0x00, 0x00, 0x00, 0x80];
+        assert_eq!(show("v:i32@0*-2147483647/7", &min), "v=658812288039986322.28");
+        assert_eq!(show("v:i32@0*2147483647", &min), "v=-4611686016279904256");
+    }
+
+    #[test]
+    fn non_ascii_text_is_an_error() {
+        // suffixes of types and checksums that end inside a character
+        assert_eq!(parse_error("t:xé@1"), ParseErrorKind::Type);
+        assert_eq!(parse_error("t:é@1"), ParseErrorKind::Type);
+        assert_eq!(parse_error("crc:sumé"), ParseErrorKind::Checksum);
+        assert_eq!(parse_error("té:u8@0"), ParseErrorKind::Name);
+        assert_eq!(parse_error("sync:AAé"), ParseErrorKind::Sync);
+        assert_eq!(parse_error("t:u8@é"), ParseErrorKind::Number);
+        assert_eq!(parse_error("t:u8@0/é"), ParseErrorKind::Number);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let cases = [
+            ("t", ParseErrorKind::Syntax),
+            ("t:u8", ParseErrorKind::Syntax),
+            ("t:u64@0", ParseErrorKind::Type),
+            ("t:u8@-1", ParseErrorKind::Offset),
+            ("t:u32@126", ParseErrorKind::Offset),
+            ("t:u8@0/0", ParseErrorKind::Number),
+            ("t:u8@0*+-2", ParseErrorKind::Number),
+            ("t:u8@0*4294967295", ParseErrorKind::Number),
+            ("sync:AAB", ParseErrorKind::Sync),
+            ("sync:0102030405", ParseErrorKind::Sync),
+            ("crc:md5", ParseErrorKind::Checksum),
+            ("toolongname:u8@0", ParseErrorKind::Name),
+            ("type:u8@0 type:u8@1", ParseErrorKind::Duplicate),
+            ("a:u8@0 b:u8@0 c:u8@0 d:u8@0 e:u8@0 f:u8@0 g:u8@0 h:u8@0 i:u8@0", ParseErrorKind::TooManyFields),
+        ];
+        for &(text, kind) in cases.iter() {
+            assert_eq!(parse_error(text), kind, "{}", text);
+        }
+        assert_eq!(
+            Template::parse("t:u8@0  crc:md5"),
+            Err(ParseError { at: 8, kind: ParseErrorKind::Checksum })
+        );
+        let long = "a:u8@0 ".repeat(MAX_TEMPLATE / 7 + 1);
+        assert_eq!(parse_error(&long), ParseErrorKind::TooLong);
+    }
+
+    #[test]
+    fn stream_resyncs_after_garbage() {
+        let template = Template::parse(EXAMPLE).unwrap();
+        let mut decoder = Decoder::new();
+        let stream = [
+            0x00, 0xAA, 0xAA, 0x55, 0x01, 0x02, 0x03, // noise, then a packet
+            0xAA, 0x55, 0x00, 0x07, // bad length
+            0xAA, 0x55, 0x01, 0x02, 0x03,
+        ];
+        let mut lines = Vec::new();
+        for &b in stream.iter() {
+            match decoder.push(&template, b) {
+                Some(Ok(frame)) => lines.push(format!("{}", frame)),
+                Some(Err(err)) => lines.push(format!("{}", err)),
+                None => {}
+            }
+        }
+        assert_eq!(lines, ["T2", "-- bad length 0", "T2"]);
+    }
+}
@@ -0,0 +1,877 @@
+//! Text terminal on the SSD1362
+//!
+//! Writing only updates the text, `render` moves it to the display. New lines
+//! are added by moving the display start line (see `scroll`), so a new line costs
+//! one text row of SPI traffic instead of a full screen.
+//!
+//! A page of fixed text, like a menu, or a canvas of pixels can be shown over the
+//! scrolling text. The scrolling text is kept up to date underneath and redrawn
+//! when it is shown again.
+//!
+//! The font and the wrap mode (see `wrap`) can be changed at runtime. The kept
+//! lines are then wrapped again at the new line width, lines that were wrapped
+//! before are joined first.
+//!
+//! Marker lines (see `markers`) are kept numbered, so the kept lines can be shown
+//! on the page from a marker on. The kept lines are searched as `search::Lines`.
+
+use core::fmt;
+use core::ops::Range;
+
+use display_interface::{DisplayError, WriteOnlyDataCommand};
+
+use crate::canvas::Canvas;
+use crate::font::{Font, FONT_6X8};
+use crate::oled::{DisplayMode, Oled, Rotation, HEIGHT, ROW_BYTES, WIDTH};
+use crate::scroll::{ScrollRing, RAM_ROWS};
+use crate::search::Lines;
+use crate::wrap::{self, WrapMode};
+
+/// Characters per line with the narrowest font
+const MAX_COLUMNS: usize = 64;
+/// Text lines on screen with the lowest font
+const MAX_LINES: usize = 11;
+/// Characters kept of a line that is cut off at the screen edge
+const LINE_COLUMNS: usize = 128;
+/// Lines kept, the ones that scrolled off the screen are used when reflowing
+const HISTORY: usize = 32;
+
+/// Gray level of the text
+const TEXT_LEVEL: u8 = 0xF;
+/// Gray level of the marker at the end of cut off lines
+const MARKER_LEVEL: u8 = 0x6;
+const MARKER: u8 = b'>';
+/// Columns panned per encoder step
+const PAN_STEP: usize = 4;
+
+#[derive(Clone, Copy)]
+struct Line {
+    text: [u8; LINE_COLUMNS],
+    len: usize,
+    /// Characters drawn inverted, one bit per column
+    inverse: u128,
+    /// Continues the line above, it was wrapped at the end of the screen
+    wrapped: bool,
+    /// Number of the marker on this line, 0 for none
+    mark: u16,
+    dirty: bool,
+}
+
+impl Line {
+    const fn empty() -> Self {
+        Self {
+            text: [b' '; LINE_COLUMNS],
+            len: 0,
+            inverse: 0,
+            wrapped: false,
+            mark: 0,
+            dirty: true,
+        }
+    }
+
+    /// Replace the text, only marking the line dirty when it changed
+    fn set(&mut self, text: &[u8], inverse: bool) {
+        let mut line = Line::empty();
+        let len = text.len().min(LINE_COLUMNS);
+        line.text[..len].copy_from_slice(&text[..len]);
+        line.len = if inverse { MAX_COLUMNS } else { len };
+        line.inverse = if inverse { !0 } else { 0 };
+        self.replace(line);
+    }
+
+    /// Replace the line, only marking it dirty when it changed
+    fn replace(&mut self, mut line: Line) {
+        let changed = line.text != self.text || line.len != self.len || line.inverse != self.inverse;
+        line.dirty = self.dirty || changed;
+        *self = line;
+    }
+}
+
+/// What is on the display
+#[derive(Clone, Copy, PartialEq)]
+enum Screen {
+    Text,
+    Page,
+    Canvas,
+}
+
+pub struct Terminal<DI> {
+    oled: Oled<DI>,
+    font: &'static Font,
+    ring: ScrollRing,
+    lines: [Line; HISTORY],
+    /// Index in `lines` of the bottom line
+    bottom: usize,
+    /// Number of the bottom line, counting the lines added since boot
+    number: u32,
+    cursor: usize,
+    /// Lines added since the last render
+    added: usize,
+    /// Rows per render when scrolling smoothly, 0 scrolls a line at once
+    smooth_step: u8,
+    wrap: WrapMode,
+    /// First column shown of lines that are cut off
+    pan: usize,
+    /// Fixed text shown instead of the scrolling text, top line first
+    page: [Line; MAX_LINES],
+    screen: Screen,
+}
+
+impl<DI> Terminal<DI>
+where
+    DI: WriteOnlyDataCommand,
+{
+    pub fn new(iface: DI) -> Self {
+        let font = &FONT_6X8;
+        Self {
+            oled: Oled::new(iface),
+            font,
+            ring: ScrollRing::new(font.height),
+            lines: [Line::empty(); HISTORY],
+            bottom: 0,
+            number: 0,
+            cursor: 0,
+            added: 0,
+            smooth_step: 0,
+            wrap: WrapMode::Char,
+            pan: 0,
+            page: [Line::empty(); MAX_LINES],
+            screen: Screen::Text,
+        }
+    }
+
+    pub fn init(&mut self, rotation: Rotation) -> Result<(), DisplayError> {
+        self.oled.init(rotation)?;
+        self.ring.reset();
+        Ok(())
+    }
+
+    /// Contrast current, default 0x7F
+    pub fn contrast(&mut self, contrast: u8) -> Result<(), DisplayError> {
+        self.oled.contrast(contrast)
+    }
+
+    /// Pre-charge voltage level (0 - 31)
+    pub fn pre_charge_voltage(&mut self, level: u8) -> Result<(), DisplayError> {
+        self.oled.pre_charge_voltage(level)
+    }
+
+    /// Turn the panel on or off, see `burn_in`
+    pub fn on(&mut self, on: bool) -> Result<(), DisplayError> {
+        self.oled.on(on)
+    }
+
+    /// Show the RAM or test screens, see `selftest`
+    pub fn display_mode(&mut self, mode: DisplayMode) -> Result<(), DisplayError> {
+        self.oled.display_mode(mode)
+    }
+
+    /// Shift the image vertically, see `burn_in`
+    pub fn display_offset(&mut self, rows: u8) -> Result<(), DisplayError> {
+        self.oled.display_offset(rows)
+    }
+
+    /// Scroll new lines into view `rows` pixel rows per render instead of at once.
+    /// 0 disables smooth scrolling.
+    pub fn smooth_scroll(&mut self, rows: u8) {
+        self.smooth_step = rows;
+    }
+
+    pub fn write_string(&mut self, s: &str) {
+        for b in s.bytes() {
+            self.write_byte(b);
+        }
+    }
+
+    pub fn write_byte(&mut self, b: u8) {
+        self.write_flagged(b, false);
+    }
+
+    /// Write a byte, received bytes with errors are shown inverted
+    pub fn write_flagged(&mut self, b: u8, error: bool) {
+        match b {
+            b'\n' => self.new_line(),
+            b'\r' => self.cursor = 0,
+            b'\t' => {
+                let stop = wrap::tab_stop(self.cursor).min(self.line_width());
+                while self.cursor < stop {
+                    self.put(b' ', error);
+                }
+            }
+            _ => self.put(b, error),
+        }
+    }
+
+    fn put(&mut self, b: u8, error: bool) {
+        if self.cursor == self.line_width() {
+            match self.wrap {
+                WrapMode::Off => return,
+                WrapMode::Word if b == b' ' => {
+                    // the space ends the line, it is kept out of view for reflowing
+                    let line = &mut self.lines[self.bottom];
+                    line.text[self.cursor] = b' ';
+                    line.len = self.cursor + 1;
+                    self.new_line();
+                    self.lines[self.bottom].wrapped = true;
+                    return;
+                }
+                _ => self.wrap_line(b),
+            }
+        }
+
+        let line = &mut self.lines[self.bottom];
+        line.text[self.cursor] = b;
+        if error {
+            line.inverse |= 1 << self.cursor;
+        } else {
+            line.inverse &= !(1 << self.cursor);
+        }
+        self.cursor += 1;
+        line.len = line.len.max(self.cursor);
+        line.dirty = true;
+    }
+
+    /// Continue the full bottom line on a new line before writing `next`
+    fn wrap_line(&mut self, next: u8) {
+        let previous = self.lines[self.bottom];
+        let keep = wrap::break_at(self.wrap, &previous.text[..previous.len], next);
+        self.new_line();
+
+        // in word mode the last word moves along
+        let moved = previous.len - keep;
+        let line = &mut self.lines[self.bottom];
+        line.text[..moved].copy_from_slice(&previous.text[keep..previous.len]);
+        line.inverse = previous.inverse >> keep;
+        line.len = moved;
+        line.wrapped = true;
+        self.cursor = moved;
+
+        if moved > 0 {
+            let index = self.index(1);
+            let line = &mut self.lines[index];
+            for c in line.text[keep..].iter_mut() {
+                *c = b' ';
+            }
+            line.inverse &= !(!0 << keep);
+            line.len = keep;
+            line.dirty = true;
+        }
+    }
+
+    pub fn font(&self) -> &'static Font {
+        self.font
+    }
+
+    /// Switch to `font` and redraw the screen. The kept lines are wrapped again to
+    /// the new width.
+    pub fn set_font(&mut self, font: &'static Font) -> Result<(), DisplayError> {
+        if core::ptr::eq(font, self.font) {
+            return Ok(());
+        }
+        self.font = font;
+        self.reflow()
+    }
+
+    /// Switch the wrap mode and redraw the screen with the kept lines wrapped again
+    pub fn set_wrap(&mut self, wrap: WrapMode) -> Result<(), DisplayError> {
+        if wrap == self.wrap {
+            return Ok(());
+        }
+        self.wrap = wrap;
+        self.pan = 0;
+        self.reflow()
+    }
+
+    /// Pan cut off lines by `steps` encoder steps, negative steps pan to the left
+    pub fn pan(&mut self, steps: i32) {
+        if self.wrap != WrapMode::Off || steps == 0 {
+            return;
+        }
+        let longest = (0..self.ring.lines())
+            .map(|line| self.lines[self.index(line)].len)
+            .max()
+            .unwrap_or(0);
+        let max = wrap::max_pan(longest, self.columns());
+        let pan = self.pan as i32 + steps * PAN_STEP as i32;
+        let pan = (pan.max(0) as usize).min(max.max(self.pan));
+        if pan != self.pan {
+            self.pan = pan;
+            for line in self.lines.iter_mut() {
+                line.dirty = true;
+            }
+        }
+    }
+
+    /// Write the kept lines again with the current font and wrap mode and redraw
+    /// the screen. Lines beyond the kept line width are not restored.
+    fn reflow(&mut self) -> Result<(), DisplayError> {
+        let old = self.lines;
+        let oldest = (0..HISTORY)
+            .rev()
+            .map(|line| self.index(line as u8))
+            .find(|&index| old[index].len > 0 || old[index].wrapped)
+            .unwrap_or(self.bottom);
+        let newest = self.bottom;
+
+        self.ring = ScrollRing::new(self.font.height);
+        self.lines = [Line::empty(); HISTORY];
+        self.bottom = 0;
+        self.cursor = 0;
+
+        let mut index = oldest;
+        loop {
+            let line = &old[index];
+            if index != oldest && !line.wrapped {
+                self.new_line();
+            }
+            if !line.wrapped && line.mark != 0 {
+                self.lines[self.bottom].mark = line.mark;
+            }
+            for (column, &c) in line.text[..line.len].iter().enumerate() {
+                self.write_flagged(c, line.inverse & (1 << column) != 0);
+            }
+            if index == newest {
+                break;
+            }
+            index = (index + 1) % HISTORY;
+        }
+
+        // everything is drawn again from the top of display RAM
+        self.added = 0;
+        self.oled.clear()?;
+        self.ring.reset();
+        self.oled.start_line(0)?;
+        for line in self.lines.iter_mut().chain(self.page.iter_mut()) {
+            line.dirty = true;
+        }
+        if self.screen == Screen::Canvas {
+            self.screen = Screen::Text;
+        }
+        Ok(())
+    }
+
+    /// Characters per line with the current font
+    pub fn columns(&self) -> usize {
+        (WIDTH as usize / self.font.width as usize).min(MAX_COLUMNS)
+    }
+
+    /// Characters a line holds before it is wrapped or cut off
+    fn line_width(&self) -> usize {
+        match self.wrap {
+            WrapMode::Off => LINE_COLUMNS,
+            WrapMode::Char | WrapMode::Word => self.columns(),
+        }
+    }
+
+    /// Text and inverted characters of the line `line` lines above the bottom line,
+    /// from the first panned column on
+    pub fn line(&self, line: usize) -> Option<(&[u8], u128)> {
+        if line >= HISTORY {
+            return None;
+        }
+        let text = &self.lines[self.index(line as u8)];
+        let start = self.pan.min(text.len);
+        Some((&text.text[start..text.len], text.inverse.checked_shr(start as u32).unwrap_or(0)))
+    }
+
+    /// Write `text` as a line of its own, marked with marker `number`
+    pub fn write_marker(&mut self, number: u16, text: &str) {
+        if self.cursor > 0 || self.lines[self.bottom].wrapped {
+            self.new_line();
+        }
+        self.lines[self.bottom].mark = number;
+        self.write_string(text);
+        self.new_line();
+    }
+
+    /// The line of marker `number` as lines above the bottom line, None when it is
+    /// no longer kept
+    pub fn find_marker(&self, number: u16) -> Option<usize> {
+        (0..HISTORY).find(|&line| self.lines[self.index(line as u8)].mark == number)
+    }
+
+    /// Set the page to the kept lines from the line `line` lines above the bottom
+    /// line on, or from higher up to fill the page. Marker lines and the `found`
+    /// columns of `line` are highlighted, cut off lines are shifted to show them.
+    pub fn set_page_history(&mut self, line: usize, found: Option<Range<usize>>) {
+        let rows = self.page_lines();
+        // the top row is cut off when the font height doesn't divide the screen height
+        let cut_off = rows * self.font.height as usize > HEIGHT as usize;
+        let top = (line + cut_off as usize).max(rows.saturating_sub(1)).min(HISTORY - 1);
+        let shift = match (&found, self.wrap) {
+            (Some(columns), WrapMode::Off) => columns.end.saturating_sub(self.columns()),
+            _ => 0,
+        };
+        for row in 0..rows {
+            let mut text = self.lines[self.index((top - row) as u8)];
+            if text.mark != 0 {
+                text.len = MAX_COLUMNS.max(text.len);
+                text.inverse = !0;
+            }
+            if let Some(columns) = found.clone().filter(|_| top - row == line) {
+                for column in columns.take_while(|&column| column < LINE_COLUMNS) {
+                    text.inverse |= 1 << column;
+                }
+            }
+            if shift > 0 {
+                text.text.copy_within(shift.., 0);
+                text.len = text.len.saturating_sub(shift);
+                text.inverse >>= shift;
+            }
+            self.page[row].replace(text);
+        }
+    }
+
+    /// Number of the bottom line, see `search::Lines`
+    pub fn line_number(&self) -> u32 {
+        self.number
+    }
+
+    /// Lines of text on a page
+    pub fn page_lines(&self) -> usize {
+        (self.ring.lines() as usize).min(MAX_LINES)
+    }
+
+    /// Set line `row` (0 is the top) of the page. Highlighted lines are inverted.
+    pub fn set_page_line(&mut self, row: usize, text: &str, highlight: bool) {
+        if let Some(line) = self.page.get_mut(row) {
+            line.set(text.as_bytes(), highlight);
+        }
+    }
+
+    /// Show the page instead of the scrolling text, or go back to the scrolling text
+    pub fn show_page(&mut self, show: bool) {
+        let screen = if show { Screen::Page } else { Screen::Text };
+        if screen == self.screen {
+            return;
+        }
+        self.screen = screen;
+        let lines: &mut [Line] = if show { &mut self.page } else { &mut self.lines };
+        for line in lines.iter_mut() {
+            line.dirty = true;
+        }
+    }
+
+    /// Draw `canvas` over the text right away. It stays until `show_page` is called.
+    pub fn show_canvas(&mut self, canvas: &Canvas) -> Result<(), DisplayError> {
+        if let Some(start) = self.ring.finish() {
+            self.oled.start_line(start)?;
+        }
+        // screen row 0 is at the start line
+        let start = self.ring.start();
+        self.oled.write_rows(start, canvas.rows(0..HEIGHT - start))?;
+        self.oled.write_rows(0, canvas.rows(HEIGHT - start..HEIGHT))?;
+        self.screen = Screen::Canvas;
+        Ok(())
+    }
+
+    /// Draw the rows made by `row` over the text right away, like `show_canvas`
+    /// without a frame buffer
+    pub fn show_rows<F>(&mut self, mut row: F) -> Result<(), DisplayError>
+    where
+        F: FnMut(u8, &mut [u8; ROW_BYTES]),
+    {
+        if let Some(start) = self.ring.finish() {
+            self.oled.start_line(start)?;
+        }
+        let start = self.ring.start();
+        let mut data = [0; ROW_BYTES];
+        for screen_row in 0..HEIGHT {
+            row(screen_row, &mut data);
+            self.oled.write_rows((start + screen_row) % HEIGHT, &data)?;
+        }
+        self.screen = Screen::Canvas;
+        Ok(())
+    }
+
+    pub fn is_canvas_shown(&self) -> bool {
+        self.screen == Screen::Canvas
+    }
+
+    /// Draw the text or the page on `canvas` like it is on the screen once
+    /// scrolled, for screenshots. A shown canvas is not drawn, its owner has it.
+    pub fn draw(&self, canvas: &mut Canvas) {
+        let lines = self.ring.lines();
+        let height = self.font.height as usize;
+        for line in 0..lines {
+            let (text, received) = match self.screen {
+                Screen::Page => match self.page.get((lines - 1 - line) as usize) {
+                    Some(text) => (text, false),
+                    None => continue,
+                },
+                Screen::Text | Screen::Canvas => (&self.lines[self.index(line)], true),
+            };
+            let (columns, marker, inverse) = self.shown(text, received);
+            // the bottom line is at the bottom of the screen, the top one may be cut off
+            let bottom = HEIGHT as usize - line as usize * height;
+            for y in 0..height {
+                if let Some(row) = (bottom + y).checked_sub(height) {
+                    let buf = canvas.row_mut(row as u8);
+                    draw_text_row(self.font, &text.text[columns.clone()], inverse, marker, y as u8, buf);
+                }
+            }
+        }
+    }
+
+    /// Clear the screen
+    pub fn clear(&mut self) {
+        self.lines = [Line::empty(); HISTORY];
+        self.cursor = 0;
+    }
+
+    /// Move changes to the display
+    pub fn render(&mut self) -> Result<(), DisplayError> {
+        match self.screen {
+            Screen::Page => return self.render_page(),
+            Screen::Canvas => return Ok(()),
+            Screen::Text => {}
+        }
+
+        if self.added > 0 {
+            self.scroll()?;
+        }
+
+        let before = self.ring.visible_rows();
+        let step = if self.smooth_step == 0 { RAM_ROWS } else { self.smooth_step };
+        if let Some(start) = self.ring.step(step) {
+            self.oled.start_line(start)?;
+        }
+        let after = self.ring.visible_rows();
+
+        // rows of the bottom line are drawn as they scroll into view
+        if !self.lines[self.bottom].dirty && after > before {
+            self.draw_line(0, self.lines[self.bottom], before..after, true)?;
+        }
+
+        for line in 0..self.ring.lines() {
+            let index = self.index(line);
+            if self.lines[index].dirty {
+                let rows = if line == 0 { 0..after } else { self.ring.rows(line) };
+                self.draw_line(line, self.lines[index], rows, true)?;
+                self.lines[index].dirty = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Draw the page without scrolling, new lines are scrolled in once it is hidden
+    fn render_page(&mut self) -> Result<(), DisplayError> {
+        if let Some(start) = self.ring.finish() {
+            self.oled.start_line(start)?;
+        }
+
+        let lines = self.ring.lines();
+        for row in 0..self.page_lines() {
+            if self.page[row].dirty {
+                let line = lines - 1 - row as u8;
+                self.draw_line(line, self.page[row], self.ring.rows(line), false)?;
+                self.page[row].dirty = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn scroll(&mut self) -> Result<(), DisplayError> {
+        let added = core::mem::replace(&mut self.added, 0);
+
+        if self.ring.is_scrolling() {
+            // the previous bottom line didn't completely scroll into view yet
+            if let Some(start) = self.ring.finish() {
+                self.oled.start_line(start)?;
+            }
+            let previous = self.index(added as u8);
+            self.lines[previous].dirty = true;
+        }
+
+        self.ring.push_lines(added as u8);
+
+        if added > 1 || self.smooth_step == 0 {
+            if let Some(start) = self.ring.finish() {
+                self.oled.start_line(start)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn new_line(&mut self) {
+        self.bottom = (self.bottom + 1) % HISTORY;
+        self.number = self.number.wrapping_add(1);
+        self.lines[self.bottom] = Line::empty();
+        self.cursor = 0;
+        self.added = (self.added + 1).min(self.ring.lines() as usize);
+    }
+
+    /// Index in `lines` of the line `line` lines above the bottom line
+    fn index(&self, line: u8) -> usize {
+        (self.bottom + HISTORY - line as usize) % HISTORY
+    }
+
+    /// Columns of `text` on the screen, if it ends in the continuation marker and
+    /// its inverted characters from the first shown column on. Received text is
+    /// panned and cut off when it is not wrapped.
+    fn shown(&self, text: &Line, received: bool) -> (Range<usize>, bool, u128) {
+        let (columns, marker) = if received && self.wrap == WrapMode::Off {
+            wrap::visible(text.len, self.pan, self.columns())
+        } else {
+            (0..text.len, false)
+        };
+        let inverse = text.inverse.checked_shr(columns.start as u32).unwrap_or(0);
+        (columns, marker, inverse)
+    }
+
+    /// Draw `text` on the screen position of the line `line` lines above the bottom line.
+    /// Received text is panned and cut off when it is not wrapped.
+    fn draw_line(&mut self, line: u8, text: Line, rows: Range<u8>, received: bool) -> Result<(), DisplayError> {
+        let mut buf = [0u8; ROW_BYTES];
+
+        let (columns, marker, inverse) = self.shown(&text, received);
+        for y in rows {
+            draw_text_row(self.font, &text.text[columns.clone()], inverse, marker, y, &mut buf);
+            self.oled.write_rows(self.ring.ram_row(line, y), &buf)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<DI> Lines for Terminal<DI> {
+    fn newest(&self) -> u32 {
+        self.number
+    }
+
+    fn text(&self, number: u32) -> Option<&[u8]> {
+        let line = self.number.wrapping_sub(number) as usize;
+        if line >= HISTORY {
+            return None;
+        }
+        let text = &self.lines[(self.bottom + HISTORY - line) % HISTORY];
+        Some(&text.text[..text.len])
+    }
+}
+
+impl<DI> fmt::Write for Terminal<DI>
+where
+    DI: WriteOnlyDataCommand,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_string(s);
+        Ok(())
+    }
+}
+
+/// Render pixel row `y` of `text` into one row of display RAM.
+/// Characters with their bit set in `inverse` are drawn dark on a lit cell.
+/// With `marker` the continuation marker is drawn in the last column.
+fn draw_text_row(font: &Font, text: &[u8], inverse: u128, marker: bool, y: u8, buf: &mut [u8; ROW_BYTES]) {
+    *buf = [0; ROW_BYTES];
+
+    let columns = WIDTH as usize / font.width as usize;
+    for (column, &c) in text.iter().take(columns).enumerate() {
+        let mut bits = font.glyph_row(c, y);
+        if inverse & (1 << column) != 0 {
+            bits = !bits;
+        }
+        draw_bits(font, bits, column, TEXT_LEVEL, buf);
+    }
+    if marker {
+        draw_bits(font, font.glyph_row(MARKER, y), columns - 1, MARKER_LEVEL, buf);
+    }
+}
+
+/// Draw one glyph row `bits` in `column` at gray level `level`
+fn draw_bits(font: &Font, bits: u8, column: usize, level: u8, buf: &mut [u8; ROW_BYTES]) {
+    let x0 = column * font.width as usize;
+    for bit in 0..font.width as usize {
+        if bits & (0x80 >> bit) == 0 {
+            continue;
+        }
+        let x = x0 + bit;
+        if x % 2 == 0 {
+            buf[x / 2] |= level << 4;
+        } else {
+            buf[x / 2] |= level;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::font::{FONT_4X6, FONT_8X16};
+    use display_interface::DataFormat;
+
+    /// Takes everything the terminal sends
+    struct Null;
+
+    impl WriteOnlyDataCommand for Null {
+        fn send_commands(&mut self, _cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+            Ok(())
+        }
+
+        fn send_data(&mut self, _buf: DataFormat<'_>) -> Result<(), DisplayError> {
+            Ok(())
+        }
+    }
+
+    fn terminal() -> Terminal<Null> {
+        let mut terminal = Terminal::new(Null);
+        terminal.init(Rotation::Rotate180).unwrap();
+        terminal
+    }
+
+    /// Kept lines from the top, empty lines above the first text are left out
+    fn lines(terminal: &Terminal<Null>) -> Vec<String> {
+        let mut lines: Vec<String> = (0..HISTORY)
+            .rev()
+            .map(|line| String::from_utf8(terminal.line(line).unwrap().0.to_vec()).unwrap())
+            .skip_while(|line| line.is_empty())
+            .collect();
+        if lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+        lines
+    }
+
+    fn alphabet(len: usize) -> String {
+        (b'a'..=b'z').cycle().take(len).map(|c| c as char).collect()
+    }
+
+    #[test]
+    fn wraps_at_the_font_width() {
+        let mut terminal = terminal();
+        assert_eq!(terminal.columns(), 42);
+        let text = alphabet(100);
+        terminal.write_string(&text);
+        assert_eq!(lines(&terminal), [&text[..42], &text[42..84], &text[84..]]);
+    }
+
+    #[test]
+    fn reflow_to_a_narrower_and_a_wider_font() {
+        let mut terminal = terminal();
+        let text = alphabet(100);
+        terminal.write_string("first\n");
+        terminal.write_string(&text);
+        terminal.write_string("\nlast");
+
+        terminal.set_font(&FONT_8X16).unwrap();
+        assert_eq!(terminal.columns(), 32);
+        assert_eq!(lines(&terminal), ["first", &text[..32], &text[32..64], &text[64..96], &text[96..], "last"]);
+
+        terminal.set_font(&FONT_4X6).unwrap();
+        assert_eq!(terminal.columns(), 64);
+        assert_eq!(lines(&terminal), ["first", &text[..64], &text[64..], "last"]);
+
+        // writing goes on at the end of the last line
+        terminal.write_string(" line");
+        assert_eq!(lines(&terminal).last().unwrap(), "last line");
+    }
+
+    #[test]
+    fn reflow_keeps_markers_and_errors() {
+        let mut terminal = terminal();
+        terminal.write_byte(b'a');
+        terminal.write_flagged(b'P', true);
+        terminal.write_string(&alphabet(41));
+        terminal.write_marker(3, "-- 3 --");
+
+        terminal.set_font(&FONT_8X16).unwrap();
+        assert_eq!(terminal.find_marker(3), Some(1));
+        // the inverted character moved along
+        let (text, inverse) = terminal.line(3).unwrap();
+        assert_eq!((text[1], inverse), (b'P', 0b10));
+    }
+
+    #[test]
+    fn tabs_expand_to_the_next_stop() {
+        let mut terminal = terminal();
+        terminal.write_string("a\tb\t\tc\n\tx");
+        assert_eq!(lines(&terminal), ["a       b               c", "        x"]);
+    }
+
+    #[test]
+    fn tab_at_the_end_of_the_line_stops_at_the_edge() {
+        let mut terminal = terminal();
+        terminal.write_string(&alphabet(40));
+        terminal.write_string("\tz");
+        assert_eq!(lines(&terminal), [format!("{}  ", alphabet(40)), "z".to_string()]);
+    }
+
+    #[test]
+    fn word_wrap() {
+        let mut terminal = terminal();
+        terminal.set_wrap(WrapMode::Word).unwrap();
+        let words = "the quick brown fox jumps over the lazy dog ";
+        terminal.write_string(words);
+        terminal.write_string(words);
+        assert_eq!(
+            lines(&terminal),
+            [
+                "the quick brown fox jumps over the lazy ",
+                "dog the quick brown fox jumps over the ",
+                "lazy dog ",
+            ]
+        );
+
+        // back to character wrapping joins the lines first
+        terminal.set_wrap(WrapMode::Char).unwrap();
+        let joined = words.repeat(2);
+        assert_eq!(lines(&terminal), [&joined[..42], &joined[42..84], &joined[84..]]);
+    }
+
+    #[test]
+    fn word_longer_than_the_line() {
+        let mut terminal = terminal();
+        terminal.set_wrap(WrapMode::Word).unwrap();
+        let word = alphabet(50);
+        terminal.write_string("ab ");
+        terminal.write_string(&word);
+        assert_eq!(lines(&terminal), ["ab ", &word[..42], &word[42..]]);
+    }
+
+    #[test]
+    fn cut_off_and_panned() {
+        let mut terminal = terminal();
+        terminal.set_wrap(WrapMode::Off).unwrap();
+        let long = alphabet(60);
+        terminal.write_string(&long);
+        terminal.write_string("\nshort");
+        assert_eq!(lines(&terminal), [long.as_str(), "short"]);
+        let bottom = terminal.index(1);
+        assert_eq!(terminal.shown(&terminal.lines[bottom], true), (0..41, true, 0));
+
+        terminal.pan(2);
+        assert_eq!(terminal.line(1).unwrap().0, &long.as_bytes()[8..]);
+        assert_eq!(terminal.line(0).unwrap().0, b"");
+        assert_eq!(terminal.shown(&terminal.lines[bottom], true), (8..49, true, 0));
+
+        // no further than the end of the longest line
+        terminal.pan(10);
+        assert_eq!(terminal.shown(&terminal.lines[bottom], true), (18..60, false, 0));
+        terminal.pan(-100);
+        assert_eq!(terminal.line(1).unwrap().0, long.as_bytes());
+    }
+
+    #[test]
+    fn cut_off_lines_keep_the_start() {
+        let mut terminal = terminal();
+        terminal.set_wrap(WrapMode::Off).unwrap();
+        terminal.write_string(&alphabet(LINE_COLUMNS + 10));
+        assert_eq!(lines(&terminal), [alphabet(LINE_COLUMNS)]);
+
+        // and are wrapped again in full
+        terminal.set_wrap(WrapMode::Char).unwrap();
+        assert_eq!(lines(&terminal).len(), LINE_COLUMNS.div_ceil(42));
+    }
+
+    #[test]
+    fn same_font_keeps_the_screen() {
+        let mut terminal = terminal();
+        terminal.write_string("text");
+        terminal.render().unwrap();
+        terminal.set_font(&FONT_6X8).unwrap();
+        assert!(!terminal.lines[terminal.bottom].dirty);
+    }
+}
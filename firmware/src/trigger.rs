@@ -0,0 +1,68 @@
+//! Screen hold trigger
+//!
+//! Armed with a pattern, the trigger fires on the first received line that
+//! contains it. The screen is then held: received data is dropped so the line
+//! stays in view, until the trigger is armed again, turned off or the screen is
+//! cleared. Armed over the remote control, see `protocol`.
+
+use arrayvec::ArrayVec;
+use protocol::{TriggerState, MAX_TRIGGER};
+
+pub struct Trigger {
+    pattern: ArrayVec<[u8; MAX_TRIGGER]>,
+    state: TriggerState,
+}
+
+impl Trigger {
+    pub fn new() -> Self {
+        Self {
+            pattern: ArrayVec::new(),
+            state: TriggerState::Off,
+        }
+    }
+
+    /// Arm on `pattern`, an empty pattern turns the trigger off. A held screen is
+    /// released. Returns false when the pattern is too long.
+    pub fn arm(&mut self, pattern: &[u8]) -> bool {
+        let mut armed = ArrayVec::new();
+        if armed.try_extend_from_slice(pattern).is_err() {
+            return false;
+        }
+        self.pattern = armed;
+        self.state = if pattern.is_empty() {
+            TriggerState::Off
+        } else {
+            TriggerState::Armed
+        };
+        true
+    }
+
+    pub fn state(&self) -> TriggerState {
+        self.state
+    }
+
+    /// Received data is not shown
+    pub fn is_holding(&self) -> bool {
+        self.state == TriggerState::Fired
+    }
+
+    /// Show received data again, the trigger is off until it is armed again
+    pub fn release(&mut self) {
+        if self.state == TriggerState::Fired {
+            self.state = TriggerState::Off;
+        }
+    }
+
+    /// Check a received line, returns true when the trigger fires on it
+    pub fn check(&mut self, line: &[u8]) -> bool {
+        if self.state != TriggerState::Armed {
+            return false;
+        }
+        let pattern = &self.pattern[..];
+        if line.windows(pattern.len()).any(|window| window == pattern) {
+            self.state = TriggerState::Fired;
+            return true;
+        }
+        false
+    }
+}
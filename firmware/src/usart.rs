@@ -0,0 +1,47 @@
+//! Runtime settings of the capture UARTs (USART1, and USART2 for RX2)
+//!
+//! The HAL sets up the USARTs once and splits them into `Tx` and `Rx`, so changing
+//! the baud rate or line parameters afterwards is done on the registers directly.
+
+use stm32g0xx_hal::stm32::{usart1::RegisterBlock, USART1, USART2};
+
+use crate::config::UartConfig;
+use crate::line::{LineError, CR1_MASK, CR2_MASK, CR2_SWAP};
+
+const CR1_UE: u32 = 1 << 0;
+const RTOR_RTO: u32 = 0xFF_FFFF;
+const ISR_TC: u32 = 1 << 6;
+
+/// Kernel clock dividers selected by USART_PRESC
+const PRESCALERS: [u32; 12] = [1, 2, 4, 6, 8, 10, 12, 16, 32, 64, 128, 256];
+
+/// Reconfigure USART1 for `uart`, with a receiver timeout of `timeout_bits`
+/// bit times, and USART2 for the same line without swapping its pins. `clock`
+/// is the USART kernel clock in Hz.
+///
+/// Must not be interrupted by the `Rx` or `Tx` users of the USARTs.
+pub fn configure(uart: &UartConfig, timeout_bits: u32, clock: u32) -> Result<(), LineError> {
+    let cr1 = uart.line.cr1()?;
+    let cr2 = uart.line.cr2();
+    let (usart1, usart2) = unsafe { (&*USART1::ptr(), &*USART2::ptr()) };
+    apply(usart1, uart.baudrate, clock, cr1, cr2, timeout_bits);
+    // only RX2 is used, on PA3
+    apply(usart2, uart.baudrate, clock, cr1, cr2 & !CR2_SWAP, timeout_bits);
+    Ok(())
+}
+
+fn apply(usart: &RegisterBlock, baudrate: u32, clock: u32, cr1: u32, cr2: u32, timeout_bits: u32) {
+    // let the last character go out before disabling
+    while usart.isr.read().bits() & ISR_TC == 0 {}
+
+    usart.cr1.modify(|r, w| unsafe { w.bits(r.bits() & !CR1_UE) });
+
+    let presc = PRESCALERS[(usart.presc.read().bits() as usize).min(PRESCALERS.len() - 1)];
+    let brr = (clock / presc + baudrate / 2) / baudrate;
+    usart.brr.write(|w| unsafe { w.bits(brr) });
+    usart
+        .rtor
+        .modify(|r, w| unsafe { w.bits((r.bits() & !RTOR_RTO) | (timeout_bits & RTOR_RTO)) });
+    usart.cr2.modify(|r, w| unsafe { w.bits((r.bits() & !CR2_MASK) | cr2) });
+    usart.cr1.modify(|r, w| unsafe { w.bits((r.bits() & !CR1_MASK) | cr1 | CR1_UE) });
+}
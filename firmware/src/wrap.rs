@@ -0,0 +1,127 @@
+//! Line wrapping
+//!
+//! Lines longer than the screen are either continued on the next line, at the
+//! last column or before the last word, or cut off at the screen edge. Cut off
+//! lines end in a continuation marker and are panned with the encoder.
+//!
+//! Tabs are expanded to spaces up to the next tab stop.
+
+use core::ops::Range;
+
+/// Columns from one tab stop to the next
+pub const TAB_WIDTH: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WrapMode {
+    /// Continue on the next line after the last column
+    Char = 0,
+    /// Continue on the next line, words that don't fit move along
+    Word = 1,
+    /// Cut off at the screen edge
+    Off = 2,
+}
+
+impl WrapMode {
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(WrapMode::Char),
+            1 => Some(WrapMode::Word),
+            2 => Some(WrapMode::Off),
+            _ => None,
+        }
+    }
+}
+
+/// Column of the cursor after a tab at `column`
+pub fn tab_stop(column: usize) -> usize {
+    (column / TAB_WIDTH + 1) * TAB_WIDTH
+}
+
+/// Characters of the full line `text` that stay on it when `next` is written.
+/// The rest moves to the next line. In word mode the line is broken after its
+/// last space, unless `next` is a space itself or the line is one long word.
+pub fn break_at(mode: WrapMode, text: &[u8], next: u8) -> usize {
+    if mode != WrapMode::Word || next == b' ' {
+        return text.len();
+    }
+    match text.iter().rposition(|&c| c == b' ') {
+        Some(space) => space + 1,
+        None => text.len(),
+    }
+}
+
+/// Columns of a cut off line of `len` characters that are on a screen `columns`
+/// wide when panned by `pan` columns, and if the last column shows the
+/// continuation marker instead
+pub fn visible(len: usize, pan: usize, columns: usize) -> (Range<usize>, bool) {
+    let start = pan.min(len);
+    if len - start > columns {
+        (start..start + columns.saturating_sub(1), true)
+    } else {
+        (start..len, false)
+    }
+}
+
+/// Largest useful pan for cut off lines of up to `longest` characters
+pub fn max_pan(longest: usize, columns: usize) -> usize {
+    longest.saturating_sub(columns)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tab_stops() {
+        assert_eq!(tab_stop(0), 8);
+        assert_eq!(tab_stop(7), 8);
+        // a tab on a stop goes to the next one
+        assert_eq!(tab_stop(8), 16);
+        assert_eq!(tab_stop(41), 48);
+    }
+
+    #[test]
+    fn char_mode_breaks_at_the_last_column() {
+        assert_eq!(break_at(WrapMode::Char, b"one two", b'x'), 7);
+        assert_eq!(break_at(WrapMode::Off, b"one two", b'x'), 7);
+    }
+
+    #[test]
+    fn word_mode_moves_the_last_word() {
+        assert_eq!(break_at(WrapMode::Word, b"one two", b'x'), 4);
+        assert_eq!(break_at(WrapMode::Word, b"one two ", b'x'), 8);
+        // a space ends the word, nothing moves
+        assert_eq!(break_at(WrapMode::Word, b"one two", b' '), 7);
+        // a word longer than the line is broken at the last column
+        assert_eq!(break_at(WrapMode::Word, b"onetwo", b'x'), 6);
+        assert_eq!(break_at(WrapMode::Word, b" onetwo", b'x'), 1);
+    }
+
+    #[test]
+    fn cut_off_lines() {
+        // fits, no marker
+        assert_eq!(visible(10, 0, 10), (0..10, false));
+        // one too long, the last column is the marker
+        assert_eq!(visible(11, 0, 10), (0..9, true));
+        assert_eq!(visible(11, 1, 10), (1..11, false));
+        assert_eq!(visible(30, 8, 10), (8..17, true));
+        // panned past the end of a short line
+        assert_eq!(visible(5, 8, 10), (5..5, false));
+        assert_eq!(visible(0, 0, 10), (0..0, false));
+    }
+
+    #[test]
+    fn pan_limit() {
+        assert_eq!(max_pan(30, 10), 20);
+        assert_eq!(max_pan(10, 10), 0);
+        assert_eq!(max_pan(3, 10), 0);
+    }
+
+    #[test]
+    fn stored_modes() {
+        for v in 0..3 {
+            assert_eq!(WrapMode::from_u8(v).map(|mode| mode as u8), Some(v));
+        }
+        assert_eq!(WrapMode::from_u8(3), None);
+    }
+}
@@ -1,12 +1,13 @@
+//! SSD1362 Commands
+//! Based on the command table from the OEL9M1020-O-E datasheet
 
 // mod interface;
 use crate::interface::DisplayInterface;
+use crate::error::CommandError;
 // use interface::DisplayInterface;
 
-/// SSD1362 Commands
-/// Based on the command table from the OEL9M1020-O-E datasheet
-///
-
+/// Longest command: gray scale table, command byte + 15 levels
+pub const MAX_COMMAND_LEN: usize = 16;
 
 /// Commands
 #[derive(Debug)]
@@ -28,8 +29,7 @@ pub enum Command {
 
     /// Set Re-map
     /// Column Address Re-map, Nibble Re-map, Horizontal Address, COM Re-map, SEG Split Odd Even, SEG left/right remap
-    // Remap(bool, bool, bool, bool, bool, bool),
-    Remap(u8), // just a single value for now
+    Remap(RemapConfig),
 
     /// Set display start line
     /// Vertical shift by setting the starting address of display RAM from 0 ~ 63
@@ -58,19 +58,22 @@ pub enum Command {
     /// Turn display on or off.
     DisplayOn(bool),
 
-    /// PWM Phase length selection Phaswe 1 and Phase 2
-    PhaseLength(u8),
+    /// PWM Phase length selection, Phase 1 and Phase 2.
+    /// Each from 1-15 DCLKs
+    PhaseLength(u8, u8),
 
     /// Set up display clock.
     /// First value is oscillator frequency, increasing with higher value
     /// Second value is divide ratio - 1
+    /// Both range from 0-15
     DisplayClockDiv(u8, u8),
 
      /// Set second precharge period. each value is from 1-15
     PreChargePeriod(u8),
 
-    /// GrayScale - configure 16 levels
-    // GrayScale()
+    /// GrayScale - configure the pulse widths of GS1 - GS15.
+    /// Levels must be ascending
+    GrayScale([u8; 15]),
 
     /// linear LUT
     DefaultGrayScale(),
@@ -91,59 +94,245 @@ pub enum Command {
     /// If True: Lock OLED driver IC MCU interface from entering command
     CommandLock(bool),
 
-    // FadeLevel(u8),
+    /// Fade out or blink the display.
+    /// Second value is the time interval of each step, 0-15 (8 frames per step)
+    FadeBlink(FadeMode, u8),
+
+    /// Only show rows start - end (0-63), the other rows are off
+    PartialDisplay(u8, u8),
+
+    /// Show all rows again
+    ExitPartialDisplay,
+
+    /// Setup continuous horizontal scrolling
+    HorizontalScroll(HScrollSetup),
+
+    /// Stop scrolling. RAM must be rewritten afterwards
+    DeactivateScroll,
 
-    // Blink(u8),
+    /// Start scrolling as set up by `HorizontalScroll`
+    ActivateScroll,
 }
 
 impl Command {
     /// Send command to SSD1362
-    pub fn send<DI>(self, iface: &mut DI) -> Result<(), DI::Error>
+    pub fn send<DI>(self, iface: &mut DI) -> Result<(), CommandError<DI::Error>>
     where
         DI: DisplayInterface,
     {
+        let encoded = self.encode().ok_or(CommandError::OutOfRange)?;
 
-        // Transform command into a fixed size array of 7 u8 and the real length for sending
-        let (data, len) = match self {
-            Command::ColumnAddress(start, end) => ([0x15, start, end, 0, 0, 0, 0], 3),
-            Command::RowAddress(start, end) => ([0x75, start, end, 0, 0, 0, 0], 3),
-            Command::Contrast(val) => ([0x81, val, 0, 0, 0, 0, 0], 2),
-            Command::Remap(remap) => ([0xA0, remap, 0, 0, 0, 0, 0], 2),
-            Command::StartLine(line) => ([0xA1, line, 0, 0, 0, 0, 0], 2),
-            Command::DisplayOffset(offset) => ([0xA2, offset, 0, 0, 0, 0, 0], 2),
-            Command::VScrollArea(above, lines) => ([0xA3, above, lines, 0, 0, 0, 0], 3),
-            Command::Mode(mode) => ([mode as u8, 0, 0, 0, 0, 0, 0], 1),
-            Command::Multiplex(ratio) => ([0xA8, ratio, 0, 0, 0, 0, 0], 2),
-            Command::InternalVDD(en) => ([0xAB, en as u8, 0, 0, 0, 0, 0], 2),
-            Command::InternalIREF(en) => ([0xAD, (en as u8) << 4 | 0x8E, 0, 0, 0, 0, 0], 2),
-            Command::DisplayOn(on) => ([0xAE | (on as u8), 0, 0, 0, 0, 0, 0], 1),
-            Command::PhaseLength(len) => ([0xB1, len, 0, 0, 0, 0, 0], 2),
+        // Send command over the interface
+        iface
+            .send_commands(encoded.as_bytes())
+            .map_err(CommandError::Interface)
+    }
+
+    /// Transform command into the bytes to send.
+    /// Returns None when a parameter is out of range
+    pub fn encode(&self) -> Option<Encoded> {
+        let encoded = match *self {
+            Command::ColumnAddress(start, end) => {
+                check(start <= end && end <= 0x7F)?;
+                Encoded::new(&[0x15, start, end])
+            },
+            Command::RowAddress(start, end) => {
+                check(start <= end && end <= 0x3F)?;
+                Encoded::new(&[0x75, start, end])
+            },
+            Command::Contrast(val) => Encoded::new(&[0x81, val]),
+            Command::Remap(remap) => Encoded::new(&[0xA0, remap.bits()]),
+            Command::StartLine(line) => {
+                check(line <= 0x3F)?;
+                Encoded::new(&[0xA1, line])
+            },
+            Command::DisplayOffset(offset) => {
+                check(offset <= 0x3F)?;
+                Encoded::new(&[0xA2, offset])
+            },
+            Command::VScrollArea(above, lines) => {
+                check(above <= 0x3F && lines <= 0x40 && above + lines <= 0x40)?;
+                Encoded::new(&[0xA3, above, lines])
+            },
+            Command::Mode(mode) => Encoded::new(&[mode as u8]),
+            Command::Multiplex(ratio) => {
+                check((3..=0x3F).contains(&ratio))?;
+                Encoded::new(&[0xA8, ratio])
+            },
+            Command::InternalVDD(en) => Encoded::new(&[0xAB, en as u8]),
+            Command::InternalIREF(en) => Encoded::new(&[0xAD, (en as u8) << 4 | 0x8E]),
+            Command::DisplayOn(on) => Encoded::new(&[0xAE | (on as u8)]),
+            Command::PhaseLength(phase1, phase2) => {
+                check((1..=0xF).contains(&phase1) && (1..=0xF).contains(&phase2))?;
+                Encoded::new(&[0xB1, (phase2 << 4) | phase1])
+            },
             Command::DisplayClockDiv(fosc, div) => {
-                ([0xB3, ((0xF & fosc) << 4) | (0xF & div), 0, 0, 0, 0, 0], 2)
+                check(fosc <= 0xF && div <= 0xF)?;
+                Encoded::new(&[0xB3, (fosc << 4) | div])
+            },
+            Command::PreChargePeriod(period) => {
+                check((1..=0xF).contains(&period))?;
+                Encoded::new(&[0xB6, period])
+            },
+            Command::GrayScale(levels) => {
+                check(levels.windows(2).all(|w| w[0] < w[1]))?;
+                let mut encoded = Encoded::new(&[0xB8]);
+                encoded.extend(&levels);
+                encoded
             },
-            Command::PreChargePeriod(period) => ([0xB6, period, 0, 0, 0, 0, 0], 2),
-            Command::DefaultGrayScale()=> ([0xB9, 0, 0, 0, 0, 0, 0], 2),
-            Command::PreChargeVoltage(vol) => ([0xBC, vol, 0, 0, 0, 0, 0], 2),
-            Command::PreChargeCapacitor(cap) => ([0xBD, cap as u8, 0, 0, 0, 0, 0], 2),
-            Command::VcomhDeselect(level) => ([0xBE, (level as u8), 0, 0, 0, 0, 0], 2),
-            Command::CommandLock(lock) => ([0xFD, ((lock as u8) & 0x1 << 2) | 0x12, 0, 0, 0, 0, 0], 2),
+            Command::DefaultGrayScale()=> Encoded::new(&[0xB9]),
+            Command::PreChargeVoltage(vol) => {
+                check(vol <= 0x1F)?;
+                Encoded::new(&[0xBC, vol])
+            },
+            Command::PreChargeCapacitor(cap) => Encoded::new(&[0xBD, cap as u8]),
+            Command::VcomhDeselect(level) => Encoded::new(&[0xBE, (level as u8)]),
+            Command::CommandLock(lock) => Encoded::new(&[0xFD, ((lock as u8) << 2) | 0x12]),
+            Command::FadeBlink(mode, interval) => {
+                check(interval <= 0xF)?;
+                Encoded::new(&[0x23, ((mode as u8) << 4) | interval])
+            },
+            Command::PartialDisplay(start, end) => {
+                check(start <= end && end <= 0x3F)?;
+                Encoded::new(&[0xA9, start, end])
+            },
+            Command::ExitPartialDisplay => Encoded::new(&[0xAA]),
+            Command::HorizontalScroll(setup) => {
+                check(setup.start_row <= setup.end_row && setup.end_row <= 0x3F)?;
+                check(setup.start_column <= setup.end_column && setup.end_column <= 0x7F)?;
+                Encoded::new(&[
+                    setup.direction as u8,
+                    0x00, // dummy
+                    setup.start_row,
+                    setup.interval as u8,
+                    setup.end_row,
+                    setup.start_column,
+                    setup.end_column,
+                ])
+            },
+            Command::DeactivateScroll => Encoded::new(&[0x2E]),
+            Command::ActivateScroll => Encoded::new(&[0x2F]),
         };
 
-        // Send command over the interface
-        iface.send_commands(&data[0..len])
+        Some(encoded)
     }
 }
 
+fn check(valid: bool) -> Option<()> {
+    if valid {
+        Some(())
+    } else {
+        None
+    }
+}
 
+/// Bytes of an encoded command
+#[derive(Debug)]
+pub struct Encoded {
+    buf: [u8; MAX_COMMAND_LEN],
+    len: usize,
+}
 
-/// Vcomh Deselect level
+impl Encoded {
+    fn new(bytes: &[u8]) -> Self {
+        let mut encoded = Self {
+            buf: [0; MAX_COMMAND_LEN],
+            len: 0,
+        };
+        encoded.extend(bytes);
+        encoded
+    }
+
+    fn extend(&mut self, bytes: &[u8]) {
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+/// Re-map settings (A0h)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RemapConfig {
+    /// A[0] Column address re-map
+    pub column_remap: bool,
+    /// A[1] Swap the pixels of a byte
+    pub nibble_remap: bool,
+    /// A[2] Vertical instead of horizontal address increment
+    pub vertical_address_increment: bool,
+    /// A[4] Scan from COM[N-1] to COM0
+    pub com_remap: bool,
+    /// A[6] Odd/even split of SEG pins
+    pub seg_split_odd_even: bool,
+    /// A[7] SEG left/right re-map
+    pub seg_left_right_remap: bool,
+}
+
+impl RemapConfig {
+    pub fn bits(&self) -> u8 {
+        (self.column_remap as u8)
+            | (self.nibble_remap as u8) << 1
+            | (self.vertical_address_increment as u8) << 2
+            | (self.com_remap as u8) << 4
+            | (self.seg_split_odd_even as u8) << 6
+            | (self.seg_left_right_remap as u8) << 7
+    }
+}
+
+/// Fade out and blinking mode
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)]
+pub enum FadeMode {
+    Disabled = 0b00,
+    FadeOut = 0b10,
+    Blink = 0b11,
+}
+
+/// Horizontal scroll direction
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)]
+pub enum ScrollDirection {
+    Right = 0x26,
+    Left = 0x27,
+}
+
+/// Frames between horizontal scroll steps
+#[derive(Debug, Clone, Copy)]
+#[allow(dead_code)]
+pub enum ScrollInterval {
+    Frames6 = 0b000,
+    Frames10 = 0b001,
+    Frames100 = 0b010,
+    Frames200 = 0b011,
+    Frames300 = 0b100,
+    Frames400 = 0b101,
+    Frames500 = 0b110,
+    Frames2 = 0b111,
+}
+
+/// Continuous horizontal scroll setup
+#[derive(Debug, Clone, Copy)]
+pub struct HScrollSetup {
+    pub direction: ScrollDirection,
+    /// Rows 0-63
+    pub start_row: u8,
+    pub end_row: u8,
+    /// Columns 0-127
+    pub start_column: u8,
+    pub end_column: u8,
+    pub interval: ScrollInterval,
+}
+
+/// Display mode
 #[derive(Debug, Clone, Copy)]
 #[allow(dead_code)]
 pub enum DisplayMode {
-    Normal = 0x4,
-    AllOn = 0x5,
-    AllOff = 0x6,
-    Inverse = 0x7
+    Normal = 0xA4,
+    AllOn = 0xA5,
+    AllOff = 0xA6,
+    Inverse = 0xA7
 }
 
 /// Vcomh Deselect level
@@ -152,8 +341,165 @@ pub enum DisplayMode {
 pub enum VcomhLevel {
     /// 0.72 * Vcc
     V072 = 0b000,
-    /// 0.77 * Vcc
+    /// 0.82 * Vcc
     V082 = 0b101,
-    /// 0.83 * Vcc
+    /// 0.86 * Vcc
     V086 = 0b111
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{Phase, RecordingInterface};
+
+    /// Bytes sent for `command`, all with D/C low
+    fn sent(command: Command) -> Vec<u8> {
+        let mut iface = RecordingInterface::new();
+        command.send(&mut iface).unwrap();
+        assert!(iface.log().iter().all(|&(phase, _)| phase == Phase::Command));
+        iface.commands().collect()
+    }
+
+    fn scroll(end_row: u8, end_column: u8) -> HScrollSetup {
+        HScrollSetup {
+            direction: ScrollDirection::Left,
+            start_row: 0x08,
+            end_row,
+            start_column: 0x10,
+            end_column,
+            interval: ScrollInterval::Frames100,
+        }
+    }
+
+    /// Encodings from the command table of the datasheet
+    #[test]
+    fn encodings() {
+        let all_remap = RemapConfig {
+            column_remap: true,
+            nibble_remap: true,
+            vertical_address_increment: true,
+            com_remap: true,
+            seg_split_odd_even: true,
+            seg_left_right_remap: true,
+        };
+        let mut levels = [0; 15];
+        for (i, level) in levels.iter_mut().enumerate() {
+            *level = i as u8 * 2 + 1;
+        }
+        let table: Vec<(Command, &[u8])> = vec![
+            (Command::ColumnAddress(0x00, 0x7F), &[0x15, 0x00, 0x7F]),
+            (Command::RowAddress(0x00, 0x3F), &[0x75, 0x00, 0x3F]),
+            (Command::Contrast(0x7F), &[0x81, 0x7F]),
+            (Command::Remap(RemapConfig::default()), &[0xA0, 0x00]),
+            (Command::Remap(all_remap), &[0xA0, 0xD7]),
+            (
+                Command::Remap(RemapConfig { nibble_remap: true, com_remap: true, ..RemapConfig::default() }),
+                &[0xA0, 0x12],
+            ),
+            (Command::StartLine(0x20), &[0xA1, 0x20]),
+            (Command::DisplayOffset(0x3F), &[0xA2, 0x3F]),
+            (Command::VScrollArea(0x08, 0x38), &[0xA3, 0x08, 0x38]),
+            (Command::Mode(DisplayMode::Normal), &[0xA4]),
+            (Command::Mode(DisplayMode::AllOn), &[0xA5]),
+            (Command::Mode(DisplayMode::AllOff), &[0xA6]),
+            (Command::Mode(DisplayMode::Inverse), &[0xA7]),
+            (Command::Multiplex(0x3F), &[0xA8, 0x3F]),
+            (Command::InternalVDD(true), &[0xAB, 0x01]),
+            (Command::InternalVDD(false), &[0xAB, 0x00]),
+            (Command::InternalIREF(true), &[0xAD, 0x9E]),
+            (Command::InternalIREF(false), &[0xAD, 0x8E]),
+            (Command::DisplayOn(true), &[0xAF]),
+            (Command::DisplayOn(false), &[0xAE]),
+            (Command::PhaseLength(2, 2), &[0xB1, 0x22]),
+            (Command::PhaseLength(0x1, 0xF), &[0xB1, 0xF1]),
+            (Command::DisplayClockDiv(0xA, 0x0), &[0xB3, 0xA0]),
+            (Command::DisplayClockDiv(0x0, 0xF), &[0xB3, 0x0F]),
+            (Command::PreChargePeriod(0x4), &[0xB6, 0x04]),
+            (
+                Command::GrayScale(levels),
+                &[0xB8, 1, 3, 5, 7, 9, 11, 13, 15, 17, 19, 21, 23, 25, 27, 29],
+            ),
+            (Command::DefaultGrayScale(), &[0xB9]),
+            (Command::PreChargeVoltage(0x1F), &[0xBC, 0x1F]),
+            (Command::PreChargeCapacitor(true), &[0xBD, 0x01]),
+            (Command::PreChargeCapacitor(false), &[0xBD, 0x00]),
+            (Command::VcomhDeselect(VcomhLevel::V072), &[0xBE, 0x00]),
+            (Command::VcomhDeselect(VcomhLevel::V082), &[0xBE, 0x05]),
+            (Command::VcomhDeselect(VcomhLevel::V086), &[0xBE, 0x07]),
+            (Command::CommandLock(true), &[0xFD, 0x16]),
+            (Command::CommandLock(false), &[0xFD, 0x12]),
+            (Command::FadeBlink(FadeMode::Disabled, 0x0), &[0x23, 0x00]),
+            (Command::FadeBlink(FadeMode::FadeOut, 0x3), &[0x23, 0x23]),
+            (Command::FadeBlink(FadeMode::Blink, 0xF), &[0x23, 0x3F]),
+            (Command::PartialDisplay(0x08, 0x37), &[0xA9, 0x08, 0x37]),
+            (Command::ExitPartialDisplay, &[0xAA]),
+            (
+                Command::HorizontalScroll(scroll(0x37, 0x6F)),
+                &[0x27, 0x00, 0x08, 0x02, 0x37, 0x10, 0x6F],
+            ),
+            (
+                Command::HorizontalScroll(HScrollSetup {
+                    direction: ScrollDirection::Right,
+                    interval: ScrollInterval::Frames2,
+                    ..scroll(0x3F, 0x7F)
+                }),
+                &[0x26, 0x00, 0x08, 0x07, 0x3F, 0x10, 0x7F],
+            ),
+            (Command::DeactivateScroll, &[0x2E]),
+            (Command::ActivateScroll, &[0x2F]),
+        ];
+        for (command, bytes) in table {
+            let text = format!("{:?}", command);
+            assert_eq!(sent(command), bytes, "{}", text);
+        }
+    }
+
+    #[test]
+    fn out_of_range() {
+        let mut descending = [0; 15];
+        for (i, level) in descending.iter_mut().enumerate() {
+            *level = 30 - i as u8;
+        }
+        let table = vec![
+            Command::ColumnAddress(0x10, 0x0F),
+            Command::ColumnAddress(0x00, 0x80),
+            Command::RowAddress(0x01, 0x00),
+            Command::RowAddress(0x00, 0x40),
+            Command::StartLine(0x40),
+            Command::DisplayOffset(0x40),
+            Command::VScrollArea(0x40, 0x00),
+            Command::VScrollArea(0x00, 0x41),
+            Command::VScrollArea(0x20, 0x21),
+            Command::Multiplex(0x02),
+            Command::Multiplex(0x40),
+            Command::PhaseLength(0x0, 0x1),
+            Command::PhaseLength(0x1, 0x10),
+            Command::DisplayClockDiv(0x10, 0x0),
+            Command::DisplayClockDiv(0x0, 0x10),
+            Command::PreChargePeriod(0x0),
+            Command::PreChargePeriod(0x10),
+            Command::GrayScale(descending),
+            Command::GrayScale([0x10; 15]),
+            Command::PreChargeVoltage(0x20),
+            Command::FadeBlink(FadeMode::Blink, 0x10),
+            Command::PartialDisplay(0x09, 0x08),
+            Command::PartialDisplay(0x00, 0x40),
+            Command::HorizontalScroll(scroll(0x40, 0x7F)),
+            Command::HorizontalScroll(scroll(0x07, 0x7F)),
+            Command::HorizontalScroll(scroll(0x3F, 0x80)),
+        ];
+        for command in table {
+            let text = format!("{:?}", command);
+            let mut iface = RecordingInterface::new();
+            assert!(matches!(command.send(&mut iface), Err(CommandError::OutOfRange)), "{}", text);
+            assert!(iface.log().is_empty(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn longest_command_fits() {
+        let levels = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+        let encoded = Command::GrayScale(levels).encode().unwrap();
+        assert_eq!(encoded.as_bytes().len(), MAX_COMMAND_LEN);
+    }
+}
@@ -0,0 +1,88 @@
+//! SPI1 transmit DMA for the SSD1362 interface
+
+use stm32g0xx_hal::stm32::{DMA, DMAMUX, RCC, SPI1};
+
+use crate::interface::TxDma;
+
+/// DMAMUX request id of SPI1_TX
+const DMAREQ_SPI1_TX: u8 = 17;
+
+/// Channel 1 flags in DMA_ISR / DMA_IFCR
+const CH1_TCIF: u32 = 1 << 1;
+const CH1_TEIF: u32 = 1 << 3;
+const CH1_FLAGS: u32 = 0xF;
+
+/// DMA errors
+#[derive(Debug)]
+pub enum DmaError {
+    /// Bus error while reading the buffer
+    Transfer,
+}
+
+/// SPI1 TX on DMA channel 1 (DMAMUX channel 0)
+///
+/// No interrupt is enabled: `is_done` polls the transfer complete flag, then
+/// waits for the last bytes to leave the SPI bus.
+pub struct Spi1TxDma {
+    dma: DMA,
+    _dmamux: DMAMUX,
+}
+
+impl Spi1TxDma {
+    /// Take DMA channel 1 for SPI1. SPI1 must already be configured as master.
+    pub fn new(dma: DMA, dmamux: DMAMUX) -> Self {
+        let rcc = unsafe { &(*RCC::ptr()) };
+        rcc.ahbenr.modify(|_, w| w.dmaen().set_bit());
+
+        dmamux
+            .dmamux_c0cr
+            .write(|w| unsafe { w.dmareq_id().bits(DMAREQ_SPI1_TX) });
+
+        let spi = unsafe { &(*SPI1::ptr()) };
+        dma.cpar1
+            .write(|w| unsafe { w.pa().bits(&spi.dr as *const _ as u32) });
+        spi.cr2.modify(|_, w| w.txdmaen().set_bit());
+
+        Self { dma, _dmamux: dmamux }
+    }
+}
+
+impl TxDma for Spi1TxDma {
+    type Error = DmaError;
+
+    unsafe fn start(&mut self, ptr: *const u8, len: usize) {
+        self.dma.ccr1.modify(|_, w| w.en().clear_bit());
+        self.dma.ifcr.write(|w| w.bits(CH1_FLAGS));
+
+        self.dma.cmar1.write(|w| w.ma().bits(ptr as u32));
+        self.dma.cndtr1.write(|w| w.ndt().bits(len as u16));
+
+        // memory to peripheral, increment memory address.
+        // 8 bit access on both sides, otherwise the SPI packs two frames per write
+        self.dma.ccr1.write(|w| w.dir().set_bit().minc().set_bit().en().set_bit());
+    }
+
+    fn is_done(&mut self) -> Result<bool, Self::Error> {
+        let isr = self.dma.isr.read().bits();
+
+        if isr & CH1_TEIF != 0 {
+            self.dma.ifcr.write(|w| unsafe { w.bits(CH1_FLAGS) });
+            self.dma.ccr1.modify(|_, w| w.en().clear_bit());
+            return Err(DmaError::Transfer);
+        }
+
+        if isr & CH1_TCIF == 0 {
+            return Ok(false);
+        }
+
+        // DMA is complete once the last byte is in the TX FIFO,
+        // the FIFO drains within 4 bytes so just wait for the bus
+        let spi = unsafe { &(*SPI1::ptr()) };
+        while spi.sr.read().ftlvl().bits() != 0 || spi.sr.read().bsy().bit_is_set() {}
+
+        self.dma.ifcr.write(|w| unsafe { w.bits(CH1_FLAGS) });
+        self.dma.ccr1.modify(|_, w| w.en().clear_bit());
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,21 @@
+//! Errors
+
+/// Errors in this crate
+#[derive(Debug)]
+pub enum Error<CommE, PinE> {
+    /// Communication error
+    Comm(CommE),
+    /// Pin setting error
+    Pin(PinE),
+    /// More commands than fit in one batch
+    TooManyCommands,
+}
+
+/// Errors when sending a command
+#[derive(Debug)]
+pub enum CommandError<E> {
+    /// A command parameter is out of range
+    OutOfRange,
+    /// Display interface error
+    Interface(E),
+}
@@ -4,14 +4,6 @@ use embedded_hal as hal;
 use hal::digital::v2::OutputPin;
 
 use crate::error::Error;
-// /// Errors in this crate
-// #[derive(Debug)]
-// pub enum Error<CommE, PinE> {
-//     /// Communication error
-//     Comm(CommE),
-//     /// Pin setting error
-//     Pin(PinE),
-// }
 
 /// A method of communicating with SSD1306
 pub trait DisplayInterface {
@@ -69,7 +61,7 @@ where
     fn send_commands(&mut self, cmds: &[u8]) -> Result<(), Self::Error> {
         self.dc.set_low().map_err(Error::Pin)?;
 
-        self.spi.write(&cmds).map_err(Error::Comm)?;
+        self.spi.write(cmds).map_err(Error::Comm)?;
 
         self.dc.set_high().map_err(Error::Pin)
     }
@@ -78,7 +70,7 @@ where
         // 1 = data, 0 = command
         self.dc.set_high().map_err(Error::Pin)?;
 
-        self.spi.write(&buf).map_err(Error::Comm)
+        self.spi.write(buf).map_err(Error::Comm)
     }
 
     fn send_bounded_data(
@@ -90,7 +82,7 @@ where
     ) -> Result<(), Self::Error> {
         self.dc.set_high().map_err(Error::Pin)?;
 
-        let height = ((lower_right.1 - upper_left.1)) as usize;
+        let height = (lower_right.1 - upper_left.1) as usize;
 
         let starting_page = (upper_left.1) as usize;
 
@@ -103,10 +95,345 @@ where
 
             page_offset += disp_width;
 
-            self.spi.write(&sub_buf).map_err(Error::Comm)?;
+            self.spi.write(sub_buf).map_err(Error::Comm)?;
         }
 
         Ok(())
     }
 
-}
\ No newline at end of file
+}
+
+/// Number of transfers that can be queued on an `AsyncDisplayInterface`
+pub const QUEUE_LEN: usize = 8;
+/// Commands in one batch of `queue_commands`
+pub const MAX_BATCH: usize = 8;
+
+/// A completion based method of communicating with the SSD1362
+///
+/// Commands and data are queued and moved to the display in the background.
+/// `poll` starts the next transfer once the previous one completed, call it
+/// again until it returns `Ok(())`.
+pub trait AsyncDisplayInterface {
+    /// Interface error type
+    type Error;
+    /// Queue a batch of up to `MAX_BATCH` commands.
+    /// Returns `WouldBlock` when the queue is full
+    fn queue_commands(&mut self, cmds: &[u8]) -> nb::Result<(), Self::Error>;
+    /// Queue data for the display RAM, e.g. the framebuffer or a couple of dirty rows.
+    /// Returns `WouldBlock` when the queue is full
+    fn queue_data(&mut self, buf: &'static [u8]) -> nb::Result<(), Self::Error>;
+    /// Start the next queued transfer if the bus is free.
+    /// Returns `Ok(())` once every queued transfer has completed.
+    fn poll(&mut self) -> nb::Result<(), Self::Error>;
+}
+
+/// Transmit-only DMA stream feeding the SPI peripheral
+pub trait TxDma {
+    /// DMA error type
+    type Error;
+    /// Start transmitting `len` bytes starting at `ptr`.
+    ///
+    /// # Safety
+    /// The memory must stay valid and unchanged until `is_done` returned true.
+    unsafe fn start(&mut self, ptr: *const u8, len: usize);
+    /// True once the transfer is complete and the last byte has left the SPI bus.
+    fn is_done(&mut self) -> Result<bool, Self::Error>;
+}
+
+#[derive(Clone, Copy)]
+enum Transfer {
+    Commands([u8; MAX_BATCH], usize),
+    Data(&'static [u8]),
+}
+
+/// DMA driven SPI display interface.
+///
+/// Combines a `TxDma` stream and the data/command pin. The D/C pin is only
+/// toggled between transfers, when the bus is idle. Transfers are polled: the
+/// DMA raises no interrupt, `poll` checks whether the one in flight completed.
+pub struct DmaSpiInterface<DMA, DC> {
+    dma: DMA,
+    dc: DC,
+    queue: [Option<Transfer>; QUEUE_LEN],
+    head: usize,
+    len: usize,
+    /// Commands in flight, static so the interface can be moved meanwhile
+    cmds: &'static mut [u8; MAX_BATCH],
+    busy: bool,
+}
+
+impl<DMA, DC, DmaE, PinE> DmaSpiInterface<DMA, DC>
+where
+    DMA: TxDma<Error = DmaE>,
+    DC: OutputPin<Error = PinE>,
+{
+    /// Create new DMA interface for communication with SSD1362, `cmds` holds
+    /// the command batch on the bus
+    pub fn new(dma: DMA, dc: DC, cmds: &'static mut [u8; MAX_BATCH]) -> Self {
+        Self {
+            dma,
+            dc,
+            queue: [None; QUEUE_LEN],
+            head: 0,
+            len: 0,
+            cmds,
+            busy: false,
+        }
+    }
+
+    /// True when no transfer is queued or in progress
+    pub fn is_idle(&self) -> bool {
+        !self.busy && self.len == 0
+    }
+
+    /// Transfers queued or in progress
+    pub fn pending(&self) -> usize {
+        self.len + self.busy as usize
+    }
+
+    /// Release the DMA stream and D/C pin
+    pub fn release(self) -> (DMA, DC) {
+        (self.dma, self.dc)
+    }
+
+    fn push(&mut self, transfer: Transfer) -> nb::Result<(), Error<DmaE, PinE>> {
+        if self.len == QUEUE_LEN {
+            return Err(nb::Error::WouldBlock);
+        }
+        let tail = (self.head + self.len) % QUEUE_LEN;
+        self.queue[tail] = Some(transfer);
+        self.len += 1;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<Transfer> {
+        if self.len == 0 {
+            return None;
+        }
+        let transfer = self.queue[self.head].take();
+        self.head = (self.head + 1) % QUEUE_LEN;
+        self.len -= 1;
+        transfer
+    }
+}
+
+impl<DMA, DC, DmaE, PinE> AsyncDisplayInterface for DmaSpiInterface<DMA, DC>
+where
+    DMA: TxDma<Error = DmaE>,
+    DC: OutputPin<Error = PinE>,
+{
+    type Error = Error<DmaE, PinE>;
+
+    fn queue_commands(&mut self, cmds: &[u8]) -> nb::Result<(), Self::Error> {
+        if cmds.len() > MAX_BATCH {
+            return Err(nb::Error::Other(Error::TooManyCommands));
+        }
+        let mut buf = [0; MAX_BATCH];
+        buf[..cmds.len()].copy_from_slice(cmds);
+        self.push(Transfer::Commands(buf, cmds.len()))
+    }
+
+    fn queue_data(&mut self, buf: &'static [u8]) -> nb::Result<(), Self::Error> {
+        self.push(Transfer::Data(buf))
+    }
+
+    fn poll(&mut self) -> nb::Result<(), Self::Error> {
+        if self.busy {
+            if !self.dma.is_done().map_err(Error::Comm)? {
+                return Err(nb::Error::WouldBlock);
+            }
+            self.busy = false;
+        }
+
+        let transfer = match self.pop() {
+            Some(transfer) => transfer,
+            None => return Ok(()),
+        };
+
+        // the bus is idle here, so D/C can't change under a byte in flight
+        match transfer {
+            Transfer::Commands(cmds, len) => {
+                // 1 = data, 0 = command
+                self.dc.set_low().map_err(Error::Pin)?;
+                *self.cmds = cmds;
+                unsafe { self.dma.start(self.cmds.as_ptr(), len) };
+            },
+            Transfer::Data(buf) => {
+                self.dc.set_high().map_err(Error::Pin)?;
+                unsafe { self.dma.start(buf.as_ptr(), buf.len()) };
+            },
+        }
+        self.busy = true;
+
+        Err(nb::Error::WouldBlock)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::RefCell;
+    use core::convert::Infallible;
+    use std::rc::Rc;
+
+    #[derive(Debug, PartialEq)]
+    enum Sent {
+        Commands(Vec<u8>),
+        Data(Vec<u8>),
+    }
+
+    /// SPI bus and D/C line seen by the mocks
+    #[derive(Default)]
+    struct Bus {
+        dc_high: bool,
+        /// `is_done` calls until the transfer in flight completes
+        remaining: Option<u32>,
+        sent: Vec<Sent>,
+        /// D/C changes while a transfer was in flight
+        dc_glitches: u32,
+    }
+
+    struct MockDma(Rc<RefCell<Bus>>);
+
+    impl TxDma for MockDma {
+        type Error = Infallible;
+
+        unsafe fn start(&mut self, ptr: *const u8, len: usize) {
+            let bytes = core::slice::from_raw_parts(ptr, len).to_vec();
+            let mut bus = self.0.borrow_mut();
+            let sent = if bus.dc_high { Sent::Data(bytes) } else { Sent::Commands(bytes) };
+            bus.sent.push(sent);
+            bus.remaining = Some(2);
+        }
+
+        fn is_done(&mut self) -> Result<bool, Infallible> {
+            let mut bus = self.0.borrow_mut();
+            Ok(match bus.remaining {
+                Some(0) | None => {
+                    bus.remaining = None;
+                    true
+                }
+                Some(n) => {
+                    bus.remaining = Some(n - 1);
+                    false
+                }
+            })
+        }
+    }
+
+    struct MockDc(Rc<RefCell<Bus>>);
+
+    impl MockDc {
+        fn set(&mut self, high: bool) {
+            let mut bus = self.0.borrow_mut();
+            if bus.remaining.is_some() {
+                bus.dc_glitches += 1;
+            }
+            bus.dc_high = high;
+        }
+    }
+
+    impl OutputPin for MockDc {
+        type Error = Infallible;
+
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.set(false);
+            Ok(())
+        }
+
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.set(true);
+            Ok(())
+        }
+    }
+
+    fn interface() -> (DmaSpiInterface<MockDma, MockDc>, Rc<RefCell<Bus>>) {
+        let bus = Rc::new(RefCell::new(Bus::default()));
+        let cmds = Box::leak(Box::new([0; MAX_BATCH]));
+        let iface = DmaSpiInterface::new(MockDma(bus.clone()), MockDc(bus.clone()), cmds);
+        (iface, bus)
+    }
+
+    /// Poll until every transfer completed, returns the number of polls
+    fn drain(iface: &mut DmaSpiInterface<MockDma, MockDc>) -> usize {
+        for polls in 1..100 {
+            match iface.poll() {
+                Ok(()) => return polls,
+                Err(nb::Error::WouldBlock) => {}
+                Err(nb::Error::Other(err)) => panic!("{:?}", err),
+            }
+        }
+        panic!("transfers did not complete");
+    }
+
+    static ROWS: [u8; 4] = [0x12, 0x34, 0x56, 0x78];
+
+    #[test]
+    fn commands_and_data_in_order() {
+        let (mut iface, bus) = interface();
+        iface.queue_commands(&[0x15, 0x00, 0x7F]).unwrap();
+        iface.queue_data(&ROWS).unwrap();
+        iface.queue_commands(&[0xA1, 0x08]).unwrap();
+        iface.queue_data(&ROWS[..2]).unwrap();
+        assert_eq!(iface.pending(), 4);
+
+        drain(&mut iface);
+
+        let bus = bus.borrow();
+        assert_eq!(
+            bus.sent,
+            vec![
+                Sent::Commands(vec![0x15, 0x00, 0x7F]),
+                Sent::Data(ROWS.to_vec()),
+                Sent::Commands(vec![0xA1, 0x08]),
+                Sent::Data(vec![0x12, 0x34]),
+            ]
+        );
+        assert_eq!(bus.dc_glitches, 0);
+        assert!(iface.is_idle());
+    }
+
+    #[test]
+    fn one_transfer_at_a_time() {
+        let (mut iface, bus) = interface();
+        iface.queue_commands(&[0xAF]).unwrap();
+        iface.queue_commands(&[0xA4]).unwrap();
+
+        assert!(iface.poll().is_err());
+        assert_eq!(bus.borrow().sent.len(), 1);
+        assert_eq!(iface.pending(), 2);
+        // still in flight
+        assert!(iface.poll().is_err());
+        assert_eq!(bus.borrow().sent.len(), 1);
+
+        drain(&mut iface);
+        assert_eq!(bus.borrow().sent.len(), 2);
+    }
+
+    #[test]
+    fn full_queue_would_block() {
+        let (mut iface, _bus) = interface();
+        for _ in 0..QUEUE_LEN {
+            iface.queue_commands(&[0xE3]).unwrap();
+        }
+        assert!(matches!(iface.queue_commands(&[0xE3]), Err(nb::Error::WouldBlock)));
+        assert!(matches!(iface.queue_data(&ROWS), Err(nb::Error::WouldBlock)));
+
+        // the first poll takes one off the queue
+        assert!(iface.poll().is_err());
+        iface.queue_data(&ROWS).unwrap();
+    }
+
+    #[test]
+    fn long_batch_is_an_error() {
+        let (mut iface, bus) = interface();
+        let long = [0xE3; MAX_BATCH + 1];
+        assert!(matches!(
+            iface.queue_commands(&long),
+            Err(nb::Error::Other(Error::TooManyCommands))
+        ));
+        iface.queue_commands(&long[..MAX_BATCH]).unwrap();
+        drain(&mut iface);
+        assert_eq!(bus.borrow().sent, vec![Sent::Commands(vec![0xE3; MAX_BATCH])]);
+    }
+}
@@ -23,6 +23,16 @@ use stm32g0xx_hal::{
 
 use nb::block;
 
+// SSD1362 interface and commands, the firmware builds them in as well
+#[allow(dead_code)]
+mod command;
+#[allow(dead_code)]
+mod dma;
+#[allow(dead_code)]
+mod error;
+#[allow(dead_code)]
+mod interface;
+
 use ssd1362::{self, display::DisplayRotation};
 
 
@@ -0,0 +1,169 @@
+//! Recording display interface
+//!
+//! Stores every byte that would go over the bus together with the state of the
+//! D/C pin, so command encodings and command/data sequencing can be checked on the host.
+
+use crate::interface::DisplayInterface;
+
+/// Bytes that can be recorded
+pub const RECORD_LEN: usize = 1024;
+
+/// State of the D/C pin while a byte was sent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Command,
+    Data,
+}
+
+/// Errors of the recording interface
+#[derive(Debug, PartialEq)]
+pub enum RecordError {
+    /// More than `RECORD_LEN` bytes were sent
+    Full,
+}
+
+/// Display interface recording the bytes sent to it
+pub struct RecordingInterface {
+    log: [(Phase, u8); RECORD_LEN],
+    len: usize,
+}
+
+impl RecordingInterface {
+    pub fn new() -> Self {
+        Self {
+            log: [(Phase::Command, 0); RECORD_LEN],
+            len: 0,
+        }
+    }
+
+    /// Everything sent so far, in order
+    pub fn log(&self) -> &[(Phase, u8)] {
+        &self.log[..self.len]
+    }
+
+    /// Bytes sent while D/C was low
+    pub fn commands(&self) -> impl Iterator<Item = u8> + '_ {
+        self.bytes(Phase::Command)
+    }
+
+    /// Bytes sent while D/C was high
+    pub fn data(&self) -> impl Iterator<Item = u8> + '_ {
+        self.bytes(Phase::Data)
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn bytes(&self, phase: Phase) -> impl Iterator<Item = u8> + '_ {
+        self.log()
+            .iter()
+            .filter(move |(p, _)| *p == phase)
+            .map(|(_, b)| *b)
+    }
+
+    fn record(&mut self, phase: Phase, bytes: &[u8]) -> Result<(), RecordError> {
+        if self.len + bytes.len() > RECORD_LEN {
+            return Err(RecordError::Full);
+        }
+        for &b in bytes {
+            self.log[self.len] = (phase, b);
+            self.len += 1;
+        }
+        Ok(())
+    }
+}
+
+impl DisplayInterface for RecordingInterface {
+    type Error = RecordError;
+
+    fn send_commands(&mut self, cmds: &[u8]) -> Result<(), Self::Error> {
+        self.record(Phase::Command, cmds)
+    }
+
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        self.record(Phase::Data, buf)
+    }
+
+    fn send_bounded_data(
+        &mut self,
+        buf: &[u8],
+        disp_width: usize,
+        upper_left: (u8, u8),
+        lower_right: (u8, u8),
+    ) -> Result<(), Self::Error> {
+        // same row slicing as `SpiInterface`
+        for row in upper_left.1..=lower_right.1 {
+            let offset = row as usize * disp_width;
+            let start = offset + upper_left.0 as usize;
+            let end = offset + lower_right.0 as usize;
+            self.record(Phase::Data, &buf[start..end])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{Command, DisplayMode};
+
+    #[test]
+    fn phases_in_order() {
+        let mut iface = RecordingInterface::new();
+        iface.send_commands(&[0x15, 0x00, 0x01]).unwrap();
+        iface.send_data(&[0xAB, 0xCD]).unwrap();
+        iface.send_commands(&[0xAF]).unwrap();
+        assert_eq!(
+            iface.log(),
+            &[
+                (Phase::Command, 0x15),
+                (Phase::Command, 0x00),
+                (Phase::Command, 0x01),
+                (Phase::Data, 0xAB),
+                (Phase::Data, 0xCD),
+                (Phase::Command, 0xAF),
+            ]
+        );
+        assert_eq!(iface.commands().collect::<Vec<_>>(), vec![0x15, 0x00, 0x01, 0xAF]);
+        assert_eq!(iface.data().collect::<Vec<_>>(), vec![0xAB, 0xCD]);
+
+        iface.clear();
+        assert!(iface.log().is_empty());
+    }
+
+    #[test]
+    fn bounded_data_rows() {
+        let mut iface = RecordingInterface::new();
+        // 4 bytes wide, 3 rows
+        let buf: Vec<u8> = (0..12).collect();
+        iface.send_bounded_data(&buf, 4, (1, 1), (3, 2)).unwrap();
+        assert_eq!(iface.data().collect::<Vec<_>>(), vec![5, 6, 9, 10]);
+    }
+
+    #[test]
+    fn full() {
+        let mut iface = RecordingInterface::new();
+        iface.send_data(&[0; RECORD_LEN - 1]).unwrap();
+        assert_eq!(iface.send_data(&[0, 0]), Err(RecordError::Full));
+        // nothing of a batch that doesn't fit is kept
+        assert_eq!(iface.log().len(), RECORD_LEN - 1);
+        iface.send_commands(&[0xAF]).unwrap();
+        assert_eq!(iface.send_commands(&[0xAE]), Err(RecordError::Full));
+    }
+
+    /// The encodings that were wrong before, one byte each or lock bit 2
+    #[test]
+    fn fixed_encodings() {
+        let mut iface = RecordingInterface::new();
+        Command::CommandLock(true).send(&mut iface).unwrap();
+        Command::CommandLock(false).send(&mut iface).unwrap();
+        Command::DefaultGrayScale().send(&mut iface).unwrap();
+        Command::Mode(DisplayMode::Normal).send(&mut iface).unwrap();
+        Command::Mode(DisplayMode::Inverse).send(&mut iface).unwrap();
+        assert_eq!(
+            iface.commands().collect::<Vec<_>>(),
+            vec![0xFD, 0x16, 0xFD, 0x12, 0xB9, 0xA4, 0xA7]
+        );
+    }
+}
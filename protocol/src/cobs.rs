@@ -0,0 +1,290 @@
+//! COBS framing
+//!
+//! A frame is a packet encoded with Consistent Overhead Byte Stuffing between two
+//! zero bytes. The packet has no zero bytes left, so frames can be picked out of
+//! a stream that also carries text, and a receiver that lost track finds the
+//! start of the next frame.
+
+/// Longest packet a `Decoder` takes
+pub const MAX_PACKET: usize = crate::MAX_PACKET;
+
+/// Data bytes in a full block
+const BLOCK: usize = 254;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameError {
+    /// More than `MAX_PACKET` bytes
+    TooLong,
+    /// Frame ended inside a block
+    Truncated,
+}
+
+/// Write `packet` as frame, delimiters included
+pub fn write_frame<F: FnMut(u8)>(packet: &[u8], out: &mut F) {
+    out(0);
+    let mut rest = packet;
+    loop {
+        let limit = rest.len().min(BLOCK);
+        let run = rest[..limit].iter().position(|&b| b == 0).unwrap_or(limit);
+        out(run as u8 + 1);
+        for &b in &rest[..run] {
+            out(b);
+        }
+
+        if run == rest.len() {
+            break;
+        }
+        // a zero ends the block, a full block has none
+        rest = if run < BLOCK {
+            &rest[run + 1..]
+        } else {
+            &rest[run..]
+        };
+    }
+    out(0);
+}
+
+/// Undoes the stuffing of the blocks between two zero bytes, shared with the
+/// packet view of the firmware
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Blocks {
+    /// Data bytes left in the current block
+    remaining: u8,
+    /// The current block ends in a zero, added when another block follows
+    zero_pending: bool,
+}
+
+impl Blocks {
+    pub const fn new() -> Self {
+        Self {
+            remaining: 0,
+            zero_pending: false,
+        }
+    }
+
+    /// Push the nonzero byte `b`, returns the packet byte it stands for. A code
+    /// byte stands for the zero that ended the previous block, if any.
+    pub fn push(&mut self, b: u8) -> Option<u8> {
+        debug_assert!(b != 0);
+        if self.remaining == 0 {
+            let zero = self.zero_pending;
+            self.remaining = b - 1;
+            self.zero_pending = b != 0xFF;
+            if zero {
+                Some(0)
+            } else {
+                None
+            }
+        } else {
+            self.remaining -= 1;
+            Some(b)
+        }
+    }
+
+    /// Bytes were pushed since the last `end`
+    pub fn is_started(&self) -> bool {
+        self.remaining != 0 || self.zero_pending
+    }
+
+    /// End of the packet at a zero byte, starts over
+    pub fn end(&mut self) -> Result<(), FrameError> {
+        let truncated = self.remaining != 0;
+        *self = Self::new();
+        if truncated {
+            Err(FrameError::Truncated)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for Blocks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Streaming decoder, bytes are pushed one at a time. Bytes outside frames are
+/// skipped.
+pub struct Decoder {
+    data: [u8; MAX_PACKET],
+    len: usize,
+    error: Option<FrameError>,
+    /// A frame was started by a zero byte
+    receiving: bool,
+    blocks: Blocks,
+}
+
+impl Decoder {
+    pub const fn new() -> Self {
+        Self {
+            data: [0; MAX_PACKET],
+            len: 0,
+            error: None,
+            receiving: false,
+            blocks: Blocks::new(),
+        }
+    }
+
+    /// Inside a frame, the bytes pushed are part of it
+    pub fn is_receiving(&self) -> bool {
+        self.receiving
+    }
+
+    /// Push byte `b`, returns the packet when its frame ends. The packet stays
+    /// valid until the next push.
+    pub fn push(&mut self, b: u8) -> Option<Result<&[u8], FrameError>> {
+        if b == 0 {
+            return self.delimiter();
+        }
+        if !self.receiving {
+            return None;
+        }
+
+        if let Some(b) = self.blocks.push(b) {
+            self.store(b);
+        }
+        None
+    }
+
+    fn delimiter(&mut self) -> Option<Result<&[u8], FrameError>> {
+        let (len, error) = (self.len, self.error);
+        let started = self.len > 0 || self.blocks.is_started() || error.is_some();
+        let end = self.blocks.end();
+        self.len = 0;
+        self.error = None;
+
+        if !self.receiving || !started {
+            // a frame starts, two zeros in a row are the end of a frame that was
+            // missed and the start of the next one
+            self.receiving = true;
+            return None;
+        }
+        self.receiving = false;
+        match (error, end) {
+            (Some(error), _) | (None, Err(error)) => Some(Err(error)),
+            (None, Ok(())) => Some(Ok(&self.data[..len])),
+        }
+    }
+
+    fn store(&mut self, b: u8) {
+        if self.len == MAX_PACKET {
+            self.error = self.error.or(Some(FrameError::TooLong));
+            return;
+        }
+        self.data[self.len] = b;
+        self.len += 1;
+    }
+}
+
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(packet: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::new();
+        write_frame(packet, &mut |b| frame.push(b));
+        frame
+    }
+
+    /// Packets and errors of the frames in `stream`
+    fn decode(stream: &[u8]) -> Vec<Result<Vec<u8>, FrameError>> {
+        let mut decoder = Decoder::new();
+        let mut packets = Vec::new();
+        for &b in stream {
+            if let Some(packet) = decoder.push(b) {
+                packets.push(packet.map(|packet| packet.to_vec()));
+            }
+        }
+        packets
+    }
+
+    #[test]
+    fn known_frames() {
+        assert_eq!(frame(&[]), [0, 1, 0]);
+        assert_eq!(frame(&[0]), [0, 1, 1, 0]);
+        assert_eq!(frame(&[0x11, 0x22, 0x00, 0x33]), [0, 3, 0x11, 0x22, 2, 0x33, 0]);
+        assert_eq!(frame(&[0x11, 0x00, 0x00]), [0, 2, 0x11, 1, 1, 0]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut seed = 1u32;
+        let mut lengths: Vec<usize> = (0..300).collect();
+        lengths.push(MAX_PACKET);
+        for len in lengths {
+            let packet: Vec<u8> = (0..len)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    // plenty of zeros and long runs without
+                    if len % 3 == 0 { (seed >> 24) as u8 | 1 } else { (seed >> 28) as u8 }
+                })
+                .collect();
+            let frame = frame(&packet);
+            assert!(!frame[1..frame.len() - 1].contains(&0));
+            assert_eq!(decode(&frame), [Ok(packet)], "length {}", len);
+        }
+    }
+
+    #[test]
+    fn full_blocks() {
+        for len in [253, 254, 255, 508, 509] {
+            let packet: Vec<u8> = (0..len).map(|i| (i % 255) as u8 + 1).collect();
+            assert_eq!(decode(&frame(&packet)), [Ok(packet)], "length {}", len);
+        }
+        // a full block has no zero after it, nor a code byte at the end
+        let packet = [1u8; 254];
+        assert_eq!(frame(&packet)[1], 0xFF);
+        assert_eq!(frame(&packet).len(), 254 + 3);
+    }
+
+    #[test]
+    fn text_between_frames_is_skipped() {
+        let mut stream = b"help\r\n".to_vec();
+        stream.extend(frame(b"one"));
+        stream.extend(b"ok\n");
+        stream.extend(frame(b"two"));
+        assert_eq!(decode(&stream), [Ok(b"one".to_vec()), Ok(b"two".to_vec())]);
+    }
+
+    #[test]
+    fn truncated_frame() {
+        // the block promises 4 bytes
+        assert_eq!(decode(&[0, 5, 1, 2, 0]), [Err(FrameError::Truncated)]);
+        // the start of the next frame ends it, that frame is skipped as text and
+        // its end is taken as the start of the one after
+        let mut stream = vec![0, 5, 1, 2];
+        stream.extend(frame(b"lost"));
+        stream.extend(frame(b"ok"));
+        assert_eq!(decode(&stream), [Err(FrameError::Truncated), Ok(b"ok".to_vec())]);
+    }
+
+    #[test]
+    fn too_long() {
+        let packet = vec![7; MAX_PACKET + 1];
+        let mut stream = frame(&packet);
+        stream.extend(frame(b"next"));
+        assert_eq!(decode(&stream), [Err(FrameError::TooLong), Ok(b"next".to_vec())]);
+    }
+
+    #[test]
+    fn blocks_track_the_packet() {
+        let mut blocks = Blocks::new();
+        assert!(!blocks.is_started());
+        assert_eq!(blocks.push(3), None);
+        assert!(blocks.is_started());
+        assert_eq!(blocks.push(0x11), Some(0x11));
+        assert_eq!(blocks.end(), Err(FrameError::Truncated));
+        assert!(!blocks.is_started());
+        // the zero ending a block comes with the next code byte
+        assert_eq!(blocks.push(1), None);
+        assert_eq!(blocks.push(1), Some(0));
+        assert_eq!(blocks.end(), Ok(()));
+    }
+}
@@ -0,0 +1,14 @@
+//! Checksum of the packets
+
+/// CRC-16/CCITT-FALSE (0x1021, init 0xFFFF, not reflected)
+pub fn crc16_ccitt(data: &[u8]) -> u16 {
+    let mut crc = 0xFFFFu16;
+    for &b in data {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            let mask = (crc >> 15).wrapping_neg();
+            crc = (crc << 1) ^ (0x1021 & mask);
+        }
+    }
+    crc
+}
@@ -0,0 +1,31 @@
+//! Remote control protocol of the serial logger
+//!
+//! Binary requests and responses on the debug port, next to the text commands
+//! of the firmware console. A host sends a request, the logger answers each one
+//! with a response carrying the same sequence number.
+//!
+//! ```text
+//! frame  = %x00 cobs(packet) %x00
+//! packet = version seq kind payload crc
+//! ```
+//!
+//! `version` is `VERSION`, `crc` the CRC-16/CCITT-FALSE of the bytes before it,
+//! low byte first. Numbers in the payload are little endian. A device that gets
+//! a packet of another version answers `ErrorCode::Version`, `Request::Hello`
+//! tells the version of the device.
+//!
+//! The screen is read a few rows at a time with `Request::ReadScreen`, see
+//! `MAX_ROWS`, so packets stay short.
+
+#![cfg_attr(not(test), no_std)]
+
+pub mod cobs;
+pub mod crc;
+mod message;
+mod server;
+
+pub use message::{
+    Error, ErrorCode, Filter, Info, Request, Response, Status, TriggerState, MAX_DATA, MAX_PACKET,
+    MAX_ROWS, MAX_ROW_BYTES, MAX_TRIGGER, VERSION,
+};
+pub use server::{serve, Device};
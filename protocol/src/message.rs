@@ -0,0 +1,554 @@
+//! Requests and responses
+
+use core::fmt;
+
+use crate::cobs;
+use crate::crc::crc16_ccitt;
+
+/// Protocol version, the first byte of every packet
+pub const VERSION: u8 = 1;
+
+/// Most screen rows in a `Response::Screen`
+pub const MAX_ROWS: u8 = 4;
+/// Bytes per screen row of the widest screen, two pixels per byte
+pub const MAX_ROW_BYTES: usize = 128;
+/// Most screen bytes in a `Response::Screen`
+pub const MAX_DATA: usize = MAX_ROWS as usize * MAX_ROW_BYTES;
+/// Longest trigger pattern
+pub const MAX_TRIGGER: usize = 32;
+
+/// Version, sequence number and kind
+const HEADER_LEN: usize = 3;
+const CRC_LEN: usize = 2;
+/// Longest packet, a `Response::Screen` with `MAX_DATA` bytes after its first
+/// row and length
+pub const MAX_PACKET: usize = HEADER_LEN + 3 + MAX_DATA + CRC_LEN;
+
+/// What is shown of the received data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Filter {
+    /// Show receive errors and bytes with a parity error inverted
+    pub show_errors: bool,
+    /// Drop control characters other than '\n', '\r' and '\t'
+    pub printable_only: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TriggerState {
+    Off = 0,
+    /// Waiting for a line with the pattern
+    Armed = 1,
+    /// A line with the pattern came in, the screen is held
+    Fired = 2,
+}
+
+impl TriggerState {
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(TriggerState::Off),
+            1 => Some(TriggerState::Armed),
+            2 => Some(TriggerState::Fired),
+            _ => None,
+        }
+    }
+}
+
+/// Answer to `Request::Hello`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Info {
+    /// Protocol version of the device
+    pub version: u8,
+    /// Screen size in pixels, 4 bits per pixel
+    pub width: u16,
+    pub height: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Status {
+    /// Capture UART baud rate
+    pub baudrate: u32,
+    pub filter: Filter,
+    pub trigger: TriggerState,
+}
+
+/// Why a request failed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorCode {
+    /// The packet has another protocol version
+    Version = 1,
+    /// The payload does not fit the request
+    Malformed = 2,
+    /// Unknown request
+    Unknown = 3,
+    /// A value is out of range
+    BadValue = 4,
+    /// The setting was applied but could not be stored
+    SaveFailed = 5,
+}
+
+impl ErrorCode {
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            1 => Some(ErrorCode::Version),
+            2 => Some(ErrorCode::Malformed),
+            3 => Some(ErrorCode::Unknown),
+            4 => Some(ErrorCode::BadValue),
+            5 => Some(ErrorCode::SaveFailed),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ErrorCode::Version => "protocol version mismatch",
+            ErrorCode::Malformed => "malformed request",
+            ErrorCode::Unknown => "unknown request",
+            ErrorCode::BadValue => "value out of range",
+            ErrorCode::SaveFailed => "save failed",
+        })
+    }
+}
+
+/// A packet that cannot be decoded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Error {
+    /// Shorter than header and CRC
+    Short,
+    Crc,
+    /// Packet of this protocol version
+    Version(u8),
+    /// Unknown kind of request or response
+    Kind(u8),
+    /// The payload does not fit the kind
+    Payload,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Short => f.write_str("packet too short"),
+            Error::Crc => f.write_str("bad CRC"),
+            Error::Version(v) => write!(f, "protocol version {}, expected {}", v, VERSION),
+            Error::Kind(kind) => write!(f, "unknown kind 0x{:02X}", kind),
+            Error::Payload => f.write_str("bad payload"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Request<'a> {
+    /// Protocol version and screen size
+    Hello,
+    GetStatus,
+    /// Set and store the capture UART baud rate
+    SetBaudrate(u32),
+    /// Set and store the filter settings
+    SetFilter(Filter),
+    /// Clear the terminal, a held screen is released
+    ClearScreen,
+    /// Hold the screen after the first received line that contains the pattern.
+    /// An empty pattern turns the trigger off and releases the screen.
+    ArmTrigger(&'a [u8]),
+    /// Screen rows `first_row..first_row + rows`, at most `MAX_ROWS`
+    ReadScreen {
+        first_row: u8,
+        rows: u8,
+    },
+}
+
+mod kind {
+    pub const HELLO: u8 = 0x01;
+    pub const GET_STATUS: u8 = 0x02;
+    pub const SET_BAUDRATE: u8 = 0x03;
+    pub const SET_FILTER: u8 = 0x04;
+    pub const CLEAR_SCREEN: u8 = 0x05;
+    pub const ARM_TRIGGER: u8 = 0x06;
+    pub const READ_SCREEN: u8 = 0x07;
+
+    pub const OK: u8 = 0x80;
+    pub const ERROR: u8 = 0x81;
+    pub const INFO: u8 = 0x82;
+    pub const STATUS: u8 = 0x83;
+    pub const SCREEN: u8 = 0x84;
+}
+
+impl<'a> Request<'a> {
+    /// Decode a packet, returns its sequence number and the request
+    pub fn decode(packet: &'a [u8]) -> Result<(u8, Self), Error> {
+        let (seq, kind, payload) = split(packet)?;
+        let mut r = Reader::new(payload);
+        let request = match kind {
+            kind::HELLO => Request::Hello,
+            kind::GET_STATUS => Request::GetStatus,
+            kind::SET_BAUDRATE => Request::SetBaudrate(r.u32()?),
+            kind::SET_FILTER => Request::SetFilter(r.filter()?),
+            kind::CLEAR_SCREEN => Request::ClearScreen,
+            kind::ARM_TRIGGER => {
+                let pattern = r.take(payload.len())?;
+                if pattern.len() > MAX_TRIGGER {
+                    return Err(Error::Payload);
+                }
+                Request::ArmTrigger(pattern)
+            }
+            kind::READ_SCREEN => Request::ReadScreen {
+                first_row: r.u8()?,
+                rows: r.u8()?,
+            },
+            kind => return Err(Error::Kind(kind)),
+        };
+        r.finish()?;
+        Ok((seq, request))
+    }
+
+    /// Write the request as frame with sequence number `seq`
+    pub fn write<F: FnMut(u8)>(&self, seq: u8, out: &mut F) {
+        let mut buf = [0; MAX_PACKET];
+        let mut w = Writer::new(&mut buf);
+        let kind = match *self {
+            Request::Hello => kind::HELLO,
+            Request::GetStatus => kind::GET_STATUS,
+            Request::SetBaudrate(baudrate) => {
+                w.u32(baudrate);
+                kind::SET_BAUDRATE
+            }
+            Request::SetFilter(filter) => {
+                w.filter(filter);
+                kind::SET_FILTER
+            }
+            Request::ClearScreen => kind::CLEAR_SCREEN,
+            Request::ArmTrigger(pattern) => {
+                w.bytes(&pattern[..pattern.len().min(MAX_TRIGGER)]);
+                kind::ARM_TRIGGER
+            }
+            Request::ReadScreen { first_row, rows } => {
+                w.u8(first_row);
+                w.u8(rows);
+                kind::READ_SCREEN
+            }
+        };
+        let len = w.finish(seq, kind);
+        cobs::write_frame(&buf[..len], out);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Response<'a> {
+    /// The request was carried out
+    Ok,
+    Error(ErrorCode),
+    Info(Info),
+    Status(Status),
+    /// Screen rows from `first_row` on, in display RAM format: two pixels per
+    /// byte, the left pixel in the high nibble
+    Screen {
+        first_row: u8,
+        data: &'a [u8],
+    },
+}
+
+impl<'a> Response<'a> {
+    /// Decode a packet, returns its sequence number and the response
+    pub fn decode(packet: &'a [u8]) -> Result<(u8, Self), Error> {
+        let (seq, kind, payload) = split(packet)?;
+        let mut r = Reader::new(payload);
+        let response = match kind {
+            kind::OK => Response::Ok,
+            kind::ERROR => Response::Error(ErrorCode::from_u8(r.u8()?).ok_or(Error::Payload)?),
+            kind::INFO => Response::Info(Info {
+                version: r.u8()?,
+                width: r.u16()?,
+                height: r.u8()?,
+            }),
+            kind::STATUS => Response::Status(Status {
+                baudrate: r.u32()?,
+                filter: r.filter()?,
+                trigger: TriggerState::from_u8(r.u8()?).ok_or(Error::Payload)?,
+            }),
+            kind::SCREEN => {
+                let first_row = r.u8()?;
+                let len = r.u16()? as usize;
+                Response::Screen {
+                    first_row,
+                    data: r.take(len)?,
+                }
+            }
+            kind => return Err(Error::Kind(kind)),
+        };
+        r.finish()?;
+        Ok((seq, response))
+    }
+
+    /// Write the response as frame with the sequence number `seq` of the request
+    pub fn write<F: FnMut(u8)>(&self, seq: u8, out: &mut F) {
+        let mut buf = [0; MAX_PACKET];
+        let mut w = Writer::new(&mut buf);
+        let kind = match *self {
+            Response::Ok => kind::OK,
+            Response::Error(code) => {
+                w.u8(code as u8);
+                kind::ERROR
+            }
+            Response::Info(info) => {
+                w.u8(info.version);
+                w.u16(info.width);
+                w.u8(info.height);
+                kind::INFO
+            }
+            Response::Status(status) => {
+                w.u32(status.baudrate);
+                w.filter(status.filter);
+                w.u8(status.trigger as u8);
+                kind::STATUS
+            }
+            Response::Screen { first_row, data } => {
+                let data = &data[..data.len().min(MAX_DATA)];
+                w.u8(first_row);
+                w.u16(data.len() as u16);
+                w.bytes(data);
+                kind::SCREEN
+            }
+        };
+        let len = w.finish(seq, kind);
+        cobs::write_frame(&buf[..len], out);
+    }
+}
+
+/// Check version and CRC, returns sequence number, kind and payload
+fn split(packet: &[u8]) -> Result<(u8, u8, &[u8]), Error> {
+    if packet.len() < HEADER_LEN + CRC_LEN {
+        return Err(Error::Short);
+    }
+    let (data, crc) = packet.split_at(packet.len() - CRC_LEN);
+    if crc16_ccitt(data) != u16::from_le_bytes([crc[0], crc[1]]) {
+        return Err(Error::Crc);
+    }
+    if data[0] != VERSION {
+        return Err(Error::Version(data[0]));
+    }
+    Ok((data[1], data[2], &data[HEADER_LEN..]))
+}
+
+/// Little endian writer for the payload, after room for the header
+struct Writer<'a> {
+    buf: &'a mut [u8; MAX_PACKET],
+    len: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn new(buf: &'a mut [u8; MAX_PACKET]) -> Self {
+        Self {
+            buf,
+            len: HEADER_LEN,
+        }
+    }
+
+    /// The payload is limited by the callers to fit
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    fn u8(&mut self, v: u8) {
+        self.bytes(&[v])
+    }
+
+    fn u16(&mut self, v: u16) {
+        self.bytes(&v.to_le_bytes())
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.bytes(&v.to_le_bytes())
+    }
+
+    fn filter(&mut self, filter: Filter) {
+        self.u8(filter.show_errors as u8);
+        self.u8(filter.printable_only as u8);
+    }
+
+    /// Fill in the header and add the CRC, returns the packet length
+    fn finish(mut self, seq: u8, kind: u8) -> usize {
+        self.buf[..HEADER_LEN].copy_from_slice(&[VERSION, seq, kind]);
+        let crc = crc16_ccitt(&self.buf[..self.len]);
+        self.bytes(&crc.to_le_bytes());
+        self.len
+    }
+}
+
+/// Little endian reader for the payload
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        if self.buf.len() < n {
+            return Err(Error::Payload);
+        }
+        let (bytes, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        self.take(1).map(|b| b[0])
+    }
+
+    fn bool(&mut self) -> Result<bool, Error> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::Payload),
+        }
+    }
+
+    fn u16(&mut self) -> Result<u16, Error> {
+        self.take(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, Error> {
+        self.take(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn filter(&mut self) -> Result<Filter, Error> {
+        Ok(Filter {
+            show_errors: self.bool()?,
+            printable_only: self.bool()?,
+        })
+    }
+
+    /// All of the payload was read
+    fn finish(&self) -> Result<(), Error> {
+        if self.buf.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Payload)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cobs::Decoder;
+
+    /// Packet of a written frame
+    fn packet<F: FnOnce(&mut dyn FnMut(u8))>(write: F) -> Vec<u8> {
+        let mut frame = Vec::new();
+        write(&mut |b| frame.push(b));
+        let mut decoder = Decoder::new();
+        let mut packets: Vec<Vec<u8>> = Vec::new();
+        for b in frame {
+            if let Some(packet) = decoder.push(b) {
+                packets.push(packet.unwrap().to_vec());
+            }
+        }
+        assert_eq!(packets.len(), 1);
+        packets.pop().unwrap()
+    }
+
+    fn request(request: Request, seq: u8) -> Vec<u8> {
+        packet(|out| request.write(seq, &mut |b| out(b)))
+    }
+
+    fn response(response: Response, seq: u8) -> Vec<u8> {
+        packet(|out| response.write(seq, &mut |b| out(b)))
+    }
+
+    /// Packet with the given bytes before the CRC
+    fn with_crc(data: &[u8]) -> Vec<u8> {
+        let mut packet = data.to_vec();
+        packet.extend_from_slice(&crc16_ccitt(data).to_le_bytes());
+        packet
+    }
+
+    const FILTER: Filter = Filter { show_errors: true, printable_only: false };
+
+    #[test]
+    fn requests_round_trip() {
+        let requests = [
+            Request::Hello,
+            Request::GetStatus,
+            Request::SetBaudrate(921_600),
+            Request::SetFilter(FILTER),
+            Request::ClearScreen,
+            Request::ArmTrigger(b""),
+            Request::ArmTrigger(b"ERR\x00\xFF"),
+            Request::ReadScreen { first_row: 60, rows: 4 },
+        ];
+        for (seq, &sent) in requests.iter().enumerate() {
+            let packet = request(sent, seq as u8);
+            assert_eq!(Request::decode(&packet), Ok((seq as u8, sent)));
+        }
+    }
+
+    #[test]
+    fn responses_round_trip() {
+        let screen = [0x12u8; MAX_DATA];
+        let responses = [
+            Response::Ok,
+            Response::Error(ErrorCode::SaveFailed),
+            Response::Info(Info { version: VERSION, width: 256, height: 64 }),
+            Response::Status(Status { baudrate: 115_200, filter: FILTER, trigger: TriggerState::Fired }),
+            Response::Screen { first_row: 0, data: &[] },
+            Response::Screen { first_row: 60, data: &screen },
+        ];
+        for &sent in responses.iter() {
+            let packet = response(sent, 0xFF);
+            assert_eq!(Response::decode(&packet), Ok((0xFF, sent)));
+        }
+    }
+
+    #[test]
+    fn known_packet() {
+        let packet = request(Request::SetBaudrate(9600), 7);
+        assert_eq!(packet, with_crc(&[VERSION, 7, 0x03, 0x80, 0x25, 0, 0]));
+    }
+
+    #[test]
+    fn bad_crc() {
+        let good = request(Request::SetBaudrate(9600), 7);
+        for i in 0..good.len() {
+            for bit in 0..8 {
+                let mut packet = good.clone();
+                packet[i] ^= 1 << bit;
+                assert_eq!(Request::decode(&packet), Err(Error::Crc), "byte {} bit {}", i, bit);
+            }
+        }
+    }
+
+    #[test]
+    fn truncated_packets() {
+        let good = request(Request::SetBaudrate(9600), 7);
+        for len in 0..HEADER_LEN + CRC_LEN {
+            assert_eq!(Request::decode(&good[..len]), Err(Error::Short), "length {}", len);
+        }
+        // cut off inside the payload, with a CRC over what is left
+        assert_eq!(Request::decode(&with_crc(&[VERSION, 7, 0x03, 0x80, 0x25])), Err(Error::Payload));
+        assert_eq!(Response::decode(&with_crc(&[VERSION, 7, 0x84, 0, 4, 0, 1, 2])), Err(Error::Payload));
+        // and a CRC that no longer fits
+        assert_eq!(Request::decode(&good[..good.len() - 1]), Err(Error::Crc));
+    }
+
+    #[test]
+    fn bad_packets() {
+        assert_eq!(Request::decode(&with_crc(&[2, 0, 0x01])), Err(Error::Version(2)));
+        assert_eq!(Request::decode(&with_crc(&[VERSION, 0, 0x80])), Err(Error::Kind(0x80)));
+        assert_eq!(Response::decode(&with_crc(&[VERSION, 0, 0x01])), Err(Error::Kind(0x01)));
+        // payload left over
+        assert_eq!(Request::decode(&with_crc(&[VERSION, 0, 0x01, 0])), Err(Error::Payload));
+        // not a bool
+        assert_eq!(Request::decode(&with_crc(&[VERSION, 0, 0x04, 2, 0])), Err(Error::Payload));
+        assert_eq!(Response::decode(&with_crc(&[VERSION, 0, 0x81, 9])), Err(Error::Payload));
+        let pattern = [b'x'; MAX_TRIGGER + 1];
+        let mut data = vec![VERSION, 0, 0x06];
+        data.extend_from_slice(&pattern);
+        assert_eq!(Request::decode(&with_crc(&data)), Err(Error::Payload));
+    }
+}
@@ -0,0 +1,243 @@
+//! Device side of the protocol
+
+use crate::message::{
+    Error, ErrorCode, Filter, Info, Request, Response, Status, MAX_DATA, MAX_ROWS, VERSION,
+};
+
+/// What a remote control acts on
+pub trait Device {
+    /// Screen size in pixels
+    fn screen_size(&self) -> (u16, u8);
+
+    fn status(&mut self) -> Status;
+
+    fn set_baudrate(&mut self, baudrate: u32) -> Result<(), ErrorCode>;
+
+    fn set_filter(&mut self, filter: Filter) -> Result<(), ErrorCode>;
+
+    fn clear_screen(&mut self);
+
+    /// Arm the trigger on `pattern`, turn it off when empty
+    fn arm_trigger(&mut self, pattern: &[u8]) -> Result<(), ErrorCode>;
+
+    /// Fill `data` with the screen rows from `first_row` on, two pixels per byte
+    /// with the left pixel in the high nibble
+    fn read_screen(&mut self, first_row: u8, data: &mut [u8]);
+}
+
+/// Carry out the request in `packet` and write the response. Packets with a bad
+/// CRC are dropped, the host sees a timeout.
+pub fn serve<D: Device, F: FnMut(u8)>(device: &mut D, packet: &[u8], out: &mut F) {
+    let (seq, request) = match Request::decode(packet) {
+        Ok(request) => request,
+        Err(Error::Short) | Err(Error::Crc) => return,
+        Err(err) => {
+            let code = match err {
+                Error::Version(_) => ErrorCode::Version,
+                Error::Kind(_) => ErrorCode::Unknown,
+                _ => ErrorCode::Malformed,
+            };
+            // the CRC was good, so the sequence number is
+            Response::Error(code).write(packet[1], out);
+            return;
+        }
+    };
+
+    let mut data = [0; MAX_DATA];
+    let response = match request {
+        Request::Hello => {
+            let (width, height) = device.screen_size();
+            Response::Info(Info {
+                version: VERSION,
+                width,
+                height,
+            })
+        }
+        Request::GetStatus => Response::Status(device.status()),
+        Request::SetBaudrate(baudrate) => done(device.set_baudrate(baudrate)),
+        Request::SetFilter(filter) => done(device.set_filter(filter)),
+        Request::ClearScreen => {
+            device.clear_screen();
+            Response::Ok
+        }
+        Request::ArmTrigger(pattern) => done(device.arm_trigger(pattern)),
+        Request::ReadScreen { first_row, rows } => {
+            let (width, height) = device.screen_size();
+            let len = rows as usize * (width as usize / 2);
+            if rows == 0
+                || rows > MAX_ROWS
+                || len > MAX_DATA
+                || first_row as u16 + rows as u16 > height as u16
+            {
+                Response::Error(ErrorCode::BadValue)
+            } else {
+                device.read_screen(first_row, &mut data[..len]);
+                Response::Screen {
+                    first_row,
+                    data: &data[..len],
+                }
+            }
+        }
+    };
+    response.write(seq, out);
+}
+
+fn done(result: Result<(), ErrorCode>) -> Response<'static> {
+    match result {
+        Ok(()) => Response::Ok,
+        Err(code) => Response::Error(code),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cobs::Decoder;
+    use crate::message::TriggerState;
+
+    struct Mock {
+        baudrate: u32,
+        cleared: bool,
+    }
+
+    impl Device for Mock {
+        fn screen_size(&self) -> (u16, u8) {
+            (256, 64)
+        }
+
+        fn status(&mut self) -> Status {
+            Status {
+                baudrate: self.baudrate,
+                filter: Filter { show_errors: false, printable_only: false },
+                trigger: TriggerState::Off,
+            }
+        }
+
+        fn set_baudrate(&mut self, baudrate: u32) -> Result<(), ErrorCode> {
+            if baudrate == 0 {
+                return Err(ErrorCode::BadValue);
+            }
+            self.baudrate = baudrate;
+            Ok(())
+        }
+
+        fn set_filter(&mut self, _filter: Filter) -> Result<(), ErrorCode> {
+            Err(ErrorCode::SaveFailed)
+        }
+
+        fn clear_screen(&mut self) {
+            self.cleared = true;
+        }
+
+        fn arm_trigger(&mut self, _pattern: &[u8]) -> Result<(), ErrorCode> {
+            Ok(())
+        }
+
+        fn read_screen(&mut self, first_row: u8, data: &mut [u8]) {
+            for b in data.iter_mut() {
+                *b = first_row;
+            }
+        }
+    }
+
+    fn packet(frame: &[u8]) -> Vec<u8> {
+        let mut decoder = Decoder::new();
+        let mut packet = None;
+        for &b in frame {
+            if let Some(p) = decoder.push(b) {
+                packet = Some(p.unwrap().to_vec());
+            }
+        }
+        packet.unwrap()
+    }
+
+    /// Frame of the response to `request`, empty without a response
+    fn exchange(device: &mut Mock, request: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        serve(device, request, &mut |b| out.push(b));
+        out
+    }
+
+    fn ask(device: &mut Mock, request: Request, seq: u8) -> (u8, Vec<u8>) {
+        let mut frame = Vec::new();
+        request.write(seq, &mut |b| frame.push(b));
+        let response = packet(&exchange(device, &packet(&frame)));
+        (response[1], response)
+    }
+
+    fn mock() -> Mock {
+        Mock { baudrate: 9600, cleared: false }
+    }
+
+    #[test]
+    fn requests_are_answered() {
+        let mut device = mock();
+        let (seq, answer) = ask(&mut device, Request::SetBaudrate(115_200), 3);
+        assert_eq!((seq, Response::decode(&answer)), (3, Ok((3, Response::Ok))));
+        assert_eq!(device.baudrate, 115_200);
+
+        let (_, answer) = ask(&mut device, Request::SetBaudrate(0), 4);
+        assert_eq!(Response::decode(&answer), Ok((4, Response::Error(ErrorCode::BadValue))));
+        let filter = Filter { show_errors: true, printable_only: true };
+        let (_, answer) = ask(&mut device, Request::SetFilter(filter), 5);
+        assert_eq!(Response::decode(&answer), Ok((5, Response::Error(ErrorCode::SaveFailed))));
+
+        let (_, answer) = ask(&mut device, Request::ClearScreen, 6);
+        assert_eq!(Response::decode(&answer), Ok((6, Response::Ok)));
+        assert!(device.cleared);
+
+        let (_, answer) = ask(&mut device, Request::Hello, 7);
+        let info = Info { version: VERSION, width: 256, height: 64 };
+        assert_eq!(Response::decode(&answer), Ok((7, Response::Info(info))));
+    }
+
+    #[test]
+    fn screen_rows() {
+        let mut device = mock();
+        let (_, answer) = ask(&mut device, Request::ReadScreen { first_row: 60, rows: 4 }, 1);
+        match Response::decode(&answer) {
+            Ok((1, Response::Screen { first_row: 60, data })) => {
+                assert_eq!(data.len(), 4 * 128);
+                assert!(data.iter().all(|&b| b == 60));
+            }
+            other => panic!("{:?}", other),
+        }
+        for &(first_row, rows) in [(61, 4), (0, 0), (0, MAX_ROWS + 1), (255, 1)].iter() {
+            let (_, answer) = ask(&mut device, Request::ReadScreen { first_row, rows }, 2);
+            assert_eq!(Response::decode(&answer), Ok((2, Response::Error(ErrorCode::BadValue))));
+        }
+    }
+
+    #[test]
+    fn bad_crc_is_not_answered() {
+        let mut device = mock();
+        let mut frame = Vec::new();
+        Request::SetBaudrate(115_200).write(1, &mut |b| frame.push(b));
+        let mut request = packet(&frame);
+        request[3] ^= 0x01;
+        assert!(exchange(&mut device, &request).is_empty());
+        assert_eq!(device.baudrate, 9600);
+        // truncated
+        assert!(exchange(&mut device, &request[..4]).is_empty());
+        assert!(exchange(&mut device, &[]).is_empty());
+    }
+
+    #[test]
+    fn bad_requests_get_an_error() {
+        let mut device = mock();
+        let with_crc = |data: &[u8]| {
+            let mut packet = data.to_vec();
+            packet.extend_from_slice(&crate::crc::crc16_ccitt(data).to_le_bytes());
+            packet
+        };
+        let cases = [
+            (with_crc(&[VERSION + 1, 9, 0x01]), ErrorCode::Version),
+            (with_crc(&[VERSION, 9, 0x7F]), ErrorCode::Unknown),
+            (with_crc(&[VERSION, 9, 0x03, 1]), ErrorCode::Malformed),
+        ];
+        for (request, code) in cases.iter() {
+            let answer = packet(&exchange(&mut device, request));
+            assert_eq!(Response::decode(&answer), Ok((9, Response::Error(*code))));
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! Turn the bridge output of the firmware back into the captured data
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::process;
+
+use oled_sim::bridge::{decode, Channel, Format, Time};
+
+const USAGE: &str = "usage: bridge-decode [--tags] [--time] [--channel RX1|RX2|TX|MARK|LOST] [--raw] [<capture>]";
+
+struct Options {
+    format: Format,
+    /// Only show this channel
+    channel: Option<Channel>,
+    /// Write the received bytes as they are instead of a line per received line
+    raw: bool,
+    capture: Option<String>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        format: Format {
+            tags: false,
+            timestamps: false,
+        },
+        channel: None,
+        raw: false,
+        capture: None,
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tags" => options.format.tags = true,
+            "--time" => options.format.timestamps = true,
+            "--channel" => {
+                options.channel = match args.next().as_deref() {
+                    Some("RX1") => Some(Channel::Rx1),
+                    Some("RX2") => Some(Channel::Rx2),
+                    Some("TX") => Some(Channel::Tx),
+                    Some("MARK") => Some(Channel::Mark),
+                    Some("LOST") => Some(Channel::Lost),
+                    _ => return Err("--channel needs RX1, RX2, TX, MARK or LOST".to_string()),
+                }
+            }
+            "--raw" => options.raw = true,
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if options.capture.is_none() => options.capture = Some(arg),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    if options.channel.is_some() && !options.format.tags {
+        return Err("--channel needs --tags".to_string());
+    }
+
+    Ok(options)
+}
+
+/// A received line being put back together from its records
+struct Line {
+    channel: Option<Channel>,
+    time: Option<Time>,
+    data: Vec<u8>,
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+
+    let input: Box<dyn BufRead> = match &options.capture {
+        Some(path) => match File::open(path) {
+            Ok(file) => Box::new(BufReader::new(file)),
+            Err(err) => {
+                eprintln!("cannot open {}: {}", path, err);
+                process::exit(1);
+            }
+        },
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if !options.format.tags && !options.format.timestamps {
+        // raw bridge output is the captured data already
+        let mut input = input;
+        io::copy(&mut input, &mut out).ok();
+        return;
+    }
+
+    // lines that are cut off continue in the next record of their channel
+    let mut open: Vec<Line> = Vec::new();
+    for (number, record) in input.split(b'\n').enumerate() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                eprintln!("cannot read: {}", err);
+                process::exit(1);
+            }
+        };
+        let record = match decode(options.format, &record) {
+            Ok(record) => record,
+            Err(err) => {
+                eprintln!("line {}: {}", number + 1, err);
+                continue;
+            }
+        };
+        if options.channel.is_some() && record.channel != options.channel {
+            continue;
+        }
+
+        if options.raw {
+            if record.channel == Some(Channel::Lost) {
+                eprintln!("line {}: {} bytes lost", number + 1, record.data.escape_ascii());
+                continue;
+            }
+            out.write_all(&record.data).ok();
+            if record.line_end {
+                out.write_all(b"\n").ok();
+            }
+            continue;
+        }
+
+        let index = match open.iter().position(|line| line.channel == record.channel) {
+            Some(index) => index,
+            None => {
+                open.push(Line {
+                    channel: record.channel,
+                    time: record.time,
+                    data: Vec::new(),
+                });
+                open.len() - 1
+            }
+        };
+        open[index].data.extend_from_slice(&record.data);
+        if record.line_end {
+            let line = open.remove(index);
+            write_line(&mut out, &line).ok();
+        }
+    }
+
+    // the capture ended inside these lines
+    for line in &open {
+        write_line(&mut out, line).ok();
+    }
+}
+
+/// "[12.345] RX1 text", control characters escaped
+fn write_line<W: Write>(w: &mut W, line: &Line) -> io::Result<()> {
+    if let Some(time) = line.time {
+        write!(w, "[{}] ", time)?;
+    }
+    if let Some(channel) = line.channel {
+        write!(w, "{} ", channel.tag())?;
+    }
+    let data = line.data.strip_suffix(b"\r").unwrap_or(&line.data);
+    writeln!(w, "{}", data.escape_ascii())
+}
@@ -0,0 +1,240 @@
+//! Control a logger over the remote control protocol of its debug port
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Read, Write};
+use std::process;
+
+use embedded_graphics::{pixelcolor::Gray4, prelude::*};
+use oled_sim::{
+    canvas::Canvas,
+    oled::{self, Rotation},
+    remote::{Client, Error, Loopback},
+    terminal::Terminal,
+    trigger::Trigger,
+    Panel,
+};
+use protocol::{Device, ErrorCode, Filter, Status, TriggerState};
+
+const USAGE: &str = "usage: remote <port> | --sim <log> [--scale <n>] <command>\n\
+    commands:\n\
+    \x20 info                     protocol version and screen size\n\
+    \x20 status                   baud rate, filters and trigger\n\
+    \x20 baud <rate>              set the capture baud rate\n\
+    \x20 filter [errors] [printable]\n\
+    \x20                          show receive errors, drop control characters\n\
+    \x20 clear                    clear the screen\n\
+    \x20 trigger <pattern>        hold the screen after a line with the pattern\n\
+    \x20 trigger -                turn the trigger off\n\
+    \x20 screenshot <png>         save the screen";
+
+enum Target {
+    /// Serial device of the debug port
+    Port(String),
+    /// Simulated logger showing this log
+    Sim(String),
+}
+
+struct Options {
+    target: Target,
+    scale: usize,
+    command: Vec<String>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut target = None;
+    let mut scale = 1;
+    let mut command = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sim" => target = Some(Target::Sim(args.next().ok_or("--sim needs a log")?)),
+            "--scale" => {
+                let n = args.next().ok_or("--scale needs a number")?;
+                scale = n.parse().map_err(|_| "invalid --scale")?;
+            }
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if target.is_none() => target = Some(Target::Port(arg)),
+            _ => command.push(arg),
+        }
+    }
+
+    match target {
+        Some(target) if !command.is_empty() => Ok(Options {
+            target,
+            scale,
+            command,
+        }),
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+
+    let result = match &options.target {
+        Target::Port(path) => {
+            // the port is set up by the user, reads have to time out:
+            // stty -F <port> 115200 raw -echo min 0 time 10
+            let port = OpenOptions::new().read(true).write(true).open(path);
+            match port {
+                Ok(port) => run(Client::new(port), &options),
+                Err(err) => Err(format!("cannot open {}: {}", path, err)),
+            }
+        }
+        Target::Sim(path) => match fs::read(path) {
+            Ok(log) => run(Client::new(Loopback::new(Simulated::new(&log))), &options),
+            Err(err) => Err(format!("cannot read log: {}", err)),
+        },
+    };
+    if let Err(err) = result {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn run<P: Read + Write>(mut client: Client<P>, options: &Options) -> Result<(), String> {
+    let error = |err: Error| err.to_string();
+    let args: Vec<&str> = options.command.iter().map(String::as_str).collect();
+    match args[..] {
+        ["info"] => {
+            let info = client.hello().map_err(error)?;
+            println!(
+                "protocol {}, screen {}x{}",
+                info.version, info.width, info.height
+            );
+        }
+        ["status"] => {
+            let status = client.status().map_err(error)?;
+            println!("baud rate {}", status.baudrate);
+            println!("show errors {}", status.filter.show_errors);
+            println!("printable only {}", status.filter.printable_only);
+            let trigger = match status.trigger {
+                TriggerState::Off => "off",
+                TriggerState::Armed => "armed",
+                TriggerState::Fired => "fired, screen held",
+            };
+            println!("trigger {}", trigger);
+        }
+        ["baud", rate] => {
+            let rate = rate.parse().map_err(|_| "invalid baud rate")?;
+            client.set_baudrate(rate).map_err(error)?;
+        }
+        ["filter", ref flags @ ..] => {
+            let mut filter = Filter {
+                show_errors: false,
+                printable_only: false,
+            };
+            for &flag in flags {
+                match flag {
+                    "errors" => filter.show_errors = true,
+                    "printable" => filter.printable_only = true,
+                    _ => return Err("filter takes errors and printable".to_string()),
+                }
+            }
+            client.set_filter(filter).map_err(error)?;
+        }
+        ["clear"] => client.clear_screen().map_err(error)?,
+        ["trigger", "-"] => client.arm_trigger(b"").map_err(error)?,
+        ["trigger", pattern] => client.arm_trigger(pattern.as_bytes()).map_err(error)?,
+        ["screenshot", path] => {
+            let screenshot = client.screenshot().map_err(error)?;
+            let file =
+                File::create(path).map_err(|err| format!("cannot create {}: {}", path, err))?;
+            screenshot
+                .png(BufWriter::new(file), options.scale)
+                .map_err(|err| format!("cannot write png: {}", err))?;
+        }
+        _ => return Err(USAGE.to_string()),
+    }
+    Ok(())
+}
+
+/// A logger on the simulated panel that has received a log, answering like the
+/// `remote_request` task of the firmware
+struct Simulated {
+    terminal: Terminal<Panel>,
+    canvas: Canvas,
+    status: Status,
+    trigger: Trigger,
+}
+
+impl Simulated {
+    fn new(log: &[u8]) -> Self {
+        let mut terminal = Terminal::new(Panel::new());
+        terminal.init(Rotation::Rotate180).unwrap();
+        for line in log.split_inclusive(|&b| b == b'\n') {
+            for &b in line {
+                terminal.write_byte(b);
+            }
+            terminal.render().unwrap();
+        }
+
+        Self {
+            terminal,
+            canvas: Canvas::new(),
+            status: Status {
+                baudrate: 115_200,
+                filter: Filter {
+                    show_errors: true,
+                    printable_only: false,
+                },
+                trigger: TriggerState::Off,
+            },
+            trigger: Trigger::new(),
+        }
+    }
+}
+
+impl Device for Simulated {
+    fn screen_size(&self) -> (u16, u8) {
+        (oled::WIDTH, oled::HEIGHT)
+    }
+
+    fn status(&mut self) -> Status {
+        Status {
+            trigger: self.trigger.state(),
+            ..self.status
+        }
+    }
+
+    fn set_baudrate(&mut self, baudrate: u32) -> Result<(), ErrorCode> {
+        if !(300..=4_000_000).contains(&baudrate) {
+            return Err(ErrorCode::BadValue);
+        }
+        self.status.baudrate = baudrate;
+        Ok(())
+    }
+
+    fn set_filter(&mut self, filter: Filter) -> Result<(), ErrorCode> {
+        self.status.filter = filter;
+        Ok(())
+    }
+
+    fn clear_screen(&mut self) {
+        self.terminal.clear();
+        self.terminal.render().unwrap();
+        self.trigger.release();
+    }
+
+    fn arm_trigger(&mut self, pattern: &[u8]) -> Result<(), ErrorCode> {
+        if self.trigger.arm(pattern) {
+            Ok(())
+        } else {
+            Err(ErrorCode::BadValue)
+        }
+    }
+
+    fn read_screen(&mut self, first_row: u8, data: &mut [u8]) {
+        let rows = first_row..first_row + (data.len() / oled::ROW_BYTES) as u8;
+        if !self.terminal.is_canvas_shown() {
+            self.canvas.clear(Gray4::BLACK).unwrap();
+            self.terminal.draw(&mut self.canvas);
+        }
+        data.copy_from_slice(self.canvas.rows(rows));
+    }
+}
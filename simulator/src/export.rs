@@ -0,0 +1,140 @@
+//! Dump the simulated screen
+
+use std::io::Write;
+
+use crate::panel::{Panel, HEIGHT, WIDTH};
+
+/// Characters for gray levels 0 - 15, dark to bright
+const SHADES: &[u8; 16] = b" .,:;-=+o*%#&$@@";
+
+/// Screen as ASCII art, one character per pixel
+pub fn ascii(panel: &Panel) -> String {
+    let mut out = String::with_capacity((WIDTH + 1) * HEIGHT);
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            out.push(SHADES[panel.pixel(x, y) as usize] as char);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Write the screen as 8 bit gray scale PNG, every pixel `scale` x `scale` pixels
+pub fn png<W: Write>(panel: &Panel, w: W, scale: usize) -> Result<(), png::EncodingError> {
+    png_pixels(WIDTH, HEIGHT, |x, y| panel.pixel(x, y), w, scale)
+}
+
+/// Write an image of 4 bit gray levels as PNG like `png`
+pub fn png_pixels<W, F>(
+    width: usize,
+    height: usize,
+    pixel: F,
+    w: W,
+    scale: usize,
+) -> Result<(), png::EncodingError>
+where
+    W: Write,
+    F: Fn(usize, usize) -> u8,
+{
+    let scale = scale.max(1);
+    let (width, height) = (width * scale, height * scale);
+
+    let mut image = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            image.push(pixel(x / scale, y / scale) * 17);
+        }
+    }
+
+    let mut encoder = png::Encoder::new(w, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oled::Rotation;
+    use crate::terminal::Terminal;
+    use embedded_graphics::{pixelcolor::Gray4, prelude::*};
+
+    /// Panel with gray level x at (x, 0) for the first 16 pixels
+    fn ramp() -> Panel {
+        let mut panel = Panel::new();
+        panel.command(0xAF);
+        let pixels = (0..16).map(|x| Pixel(Point::new(x, 0), Gray4::new(x as u8)));
+        panel.draw_iter(pixels).unwrap();
+        panel
+    }
+
+    #[test]
+    fn ascii_rows_and_shades() {
+        let text = ascii(&ramp());
+        let rows: Vec<&str> = text.lines().collect();
+        assert_eq!(rows.len(), HEIGHT);
+        assert!(rows.iter().all(|row| row.len() == WIDTH));
+        assert!(text.ends_with('\n'));
+        assert_eq!(&rows[0][..17], " .,:;-=+o*%#&$@@ ");
+        assert!(rows[1].trim().is_empty());
+    }
+
+    #[test]
+    fn png_is_scaled_8_bit_gray() {
+        let mut file = Vec::new();
+        png(&ramp(), &mut file, 2).unwrap();
+
+        let decoder = ::png::Decoder::new(&file[..]);
+        let mut reader = decoder.read_info().unwrap();
+        let mut image = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut image).unwrap();
+        assert_eq!((info.width, info.height), (WIDTH as u32 * 2, HEIGHT as u32 * 2));
+        assert_eq!(info.color_type, ::png::ColorType::Grayscale);
+        assert_eq!(info.bit_depth, ::png::BitDepth::Eight);
+        // every pixel twice, in two rows, levels stretched to 0 - 255
+        let width = WIDTH * 2;
+        assert_eq!(image[..8], [0, 0, 17, 17, 34, 34, 51, 51]);
+        assert_eq!(image[width..width + 8], image[..8]);
+        assert_eq!(image[30], 255);
+        assert_eq!(image[2 * width], 0);
+    }
+
+    #[test]
+    fn terminal_snapshot() {
+        let mut panel = Panel::new();
+        {
+            let mut terminal = Terminal::new(&mut panel);
+            terminal.init(Rotation::Rotate180).unwrap();
+            terminal.write_string("boot ok\r\n");
+            terminal.write_string("T=21.5\r\n");
+            terminal.render().unwrap();
+        }
+        let text = ascii(&panel);
+        let rows: Vec<&str> = text.lines().map(|row| row.trim_end()).collect();
+        // the two lines above the empty bottom line
+        assert!(rows[..40].iter().all(|row| row.is_empty()));
+        assert_eq!(
+            rows[40..56],
+            [
+                "",
+                "@                  @                @",
+                "@                  @                @",
+                "@@@    @@    @@   @@@          @@   @  @",
+                "@  @  @  @  @  @   @          @  @  @@@",
+                "@  @  @  @  @  @   @ @        @  @  @  @",
+                "@@@    @@    @@     @          @@   @  @",
+                "",
+                "",
+                " @@@         @@     @         @@@@",
+                "  @         @  @   @@         @",
+                "  @   @@@@     @    @         @@@",
+                "  @          @@     @            @",
+                "  @   @@@@  @       @     @   @  @",
+                "  @         @@@@   @@@   @@@   @@",
+                "                          @",
+            ]
+        );
+        assert!(rows[56..].iter().all(|row| row.is_empty()));
+    }
+}
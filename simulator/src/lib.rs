@@ -0,0 +1,103 @@
+//! Host side simulator of the SSD1362 OLED and the firmware display code
+
+// The shared sources also build with the embedded toolchains of their crates:
+// `is_multiple_of` needs Rust 1.87, and resources made in `#[init]` of RTIC use
+// `const fn new`, where a `Default` would go unused.
+#![allow(clippy::manual_is_multiple_of, clippy::new_without_default)]
+
+// SSD1362 interface and commands from oled_test
+#[path = "../../oled_test/src/error.rs"]
+pub mod error;
+#[path = "../../oled_test/src/interface.rs"]
+pub mod interface;
+#[path = "../../oled_test/src/command.rs"]
+pub mod command;
+#[path = "../../oled_test/src/mock.rs"]
+pub mod mock;
+
+// firmware display code
+#[path = "../../firmware/src/bridge.rs"]
+pub mod bridge;
+#[path = "../../firmware/src/burn_in.rs"]
+pub mod burn_in;
+#[path = "../../firmware/src/canvas.rs"]
+pub mod canvas;
+#[path = "../../firmware/src/clock.rs"]
+pub mod clock;
+#[path = "../../firmware/src/config.rs"]
+pub mod config;
+#[path = "../../firmware/src/console.rs"]
+pub mod console;
+#[path = "../../firmware/src/crc.rs"]
+pub mod crc;
+#[path = "../../firmware/src/dma_display.rs"]
+pub mod dma_display;
+#[path = "../../firmware/src/font.rs"]
+pub mod font;
+#[path = "../../firmware/src/frame_sync.rs"]
+pub mod frame_sync;
+#[path = "../../firmware/src/framer.rs"]
+pub mod framer;
+#[path = "../../firmware/src/layout.rs"]
+pub mod layout;
+#[path = "../../firmware/src/line.rs"]
+pub mod line;
+#[path = "../../firmware/src/logger.rs"]
+pub mod logger;
+#[path = "../../firmware/src/macros.rs"]
+pub mod macros;
+#[path = "../../firmware/src/markers.rs"]
+pub mod markers;
+#[path = "../../firmware/src/menu.rs"]
+pub mod menu;
+#[path = "../../firmware/src/modbus.rs"]
+pub mod modbus;
+#[path = "../../firmware/src/nmea.rs"]
+pub mod nmea;
+#[path = "../../firmware/src/oled.rs"]
+pub mod oled;
+#[path = "../../firmware/src/plot.rs"]
+pub mod plot;
+#[path = "../../firmware/src/scroll.rs"]
+pub mod scroll;
+#[path = "../../firmware/src/search.rs"]
+pub mod search;
+#[path = "../../firmware/src/selftest.rs"]
+pub mod selftest;
+#[path = "../../firmware/src/template.rs"]
+pub mod template;
+#[path = "../../firmware/src/terminal.rs"]
+pub mod terminal;
+#[path = "../../firmware/src/trigger.rs"]
+pub mod trigger;
+#[path = "../../firmware/src/wrap.rs"]
+pub mod wrap;
+
+// loaded without a path of its own, so that `board::mock` is found in `board/`
+#[path = "../../firmware/src"]
+mod firmware {
+    pub mod board;
+}
+pub use firmware::board;
+
+/// Stand-in for `rtic::Mutex`, which `logger` takes for the resources shared
+/// between tasks. On the host a plain reference is all there is to lock.
+pub trait Mutex {
+    type T;
+
+    fn lock<R>(&mut self, f: impl FnOnce(&mut Self::T) -> R) -> R;
+}
+
+impl<T> Mutex for &mut T {
+    type T = T;
+
+    fn lock<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(self)
+    }
+}
+
+pub mod export;
+pub mod panel;
+pub mod remote;
+
+pub use panel::Panel;
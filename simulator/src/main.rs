@@ -0,0 +1,199 @@
+//! Feed a recorded UART log through the firmware terminal and show the screen
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Read};
+use std::process;
+
+use embedded_graphics::{pixelcolor::Gray4, prelude::*};
+use oled_sim::{
+    canvas::Canvas,
+    export,
+    font::{FontSize, FONT_6X8},
+    layout::{self, ErrorCounts, Layout, Status},
+    line::LineConfig,
+    oled::Rotation,
+    plot::{Pattern, Plot},
+    terminal::Terminal,
+    wrap::WrapMode,
+    Panel,
+};
+
+const USAGE: &str =
+    "usage: oled-sim [--png <file>] [--scale <n>] [--smooth <rows>] [--plot <pattern>]\n\
+     \x20               [--layout full|status|split] [--font small|medium|large]\n\
+     \x20               [--wrap char|word|off] [--pan <steps>] [<log>]";
+
+struct Options {
+    png: Option<String>,
+    scale: usize,
+    smooth: u8,
+    /// Show the plot view with this pattern instead of the text
+    plot: Option<String>,
+    layout: Layout,
+    /// Font switched to after the log, like it is changed in the menu
+    font: Option<FontSize>,
+    wrap: WrapMode,
+    /// Encoder steps to pan cut off lines by
+    pan: i32,
+    log: Option<String>,
+}
+
+fn parse_args() -> Result<Options, String> {
+    let mut options = Options {
+        png: None,
+        scale: 1,
+        smooth: 0,
+        plot: None,
+        layout: Layout::Full,
+        font: None,
+        wrap: WrapMode::Char,
+        pan: 0,
+        log: None,
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--png" => options.png = Some(args.next().ok_or("--png needs a file")?),
+            "--scale" => {
+                let scale = args.next().ok_or("--scale needs a number")?;
+                options.scale = scale.parse().map_err(|_| "invalid --scale")?;
+            }
+            "--smooth" => {
+                let rows = args.next().ok_or("--smooth needs a number")?;
+                options.smooth = rows.parse().map_err(|_| "invalid --smooth")?;
+            }
+            "--plot" => options.plot = Some(args.next().ok_or("--plot needs a pattern")?),
+            "--layout" => {
+                options.layout = match args.next().as_deref() {
+                    Some("full") => Layout::Full,
+                    Some("status") => Layout::Status,
+                    Some("split") => Layout::SplitPlot,
+                    _ => return Err("--layout needs full, status or split".to_string()),
+                }
+            }
+            "--font" => {
+                options.font = match args.next().as_deref() {
+                    Some("small") => Some(FontSize::Small),
+                    Some("medium") => Some(FontSize::Medium),
+                    Some("large") => Some(FontSize::Large),
+                    _ => return Err("--font needs small, medium or large".to_string()),
+                }
+            }
+            "--wrap" => {
+                options.wrap = match args.next().as_deref() {
+                    Some("char") => WrapMode::Char,
+                    Some("word") => WrapMode::Word,
+                    Some("off") => WrapMode::Off,
+                    _ => return Err("--wrap needs char, word or off".to_string()),
+                }
+            }
+            "--pan" => {
+                let steps = args.next().ok_or("--pan needs a number")?;
+                options.pan = steps.parse().map_err(|_| "invalid --pan")?;
+            }
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ if options.log.is_none() => options.log = Some(arg),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+
+    Ok(options)
+}
+
+fn main() {
+    let options = parse_args().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(1);
+    });
+
+    let mut log = Vec::new();
+    let read = match &options.log {
+        Some(path) => File::open(path).and_then(|mut f| f.read_to_end(&mut log)),
+        None => io::stdin().read_to_end(&mut log),
+    };
+    if let Err(err) = read {
+        eprintln!("cannot read log: {}", err);
+        process::exit(1);
+    }
+
+    let mut panel = Panel::new();
+    {
+        let mut terminal = Terminal::new(&mut panel);
+        terminal.init(Rotation::Rotate180).unwrap();
+        terminal.smooth_scroll(options.smooth);
+        terminal.set_wrap(options.wrap).unwrap();
+
+        // lines are handed to the terminal like the `uart_buffer` task does
+        let pattern = Pattern::new(options.plot.as_deref().unwrap_or(""));
+        let mut plot = Plot::new();
+        let mut line = String::new();
+        for &b in &log {
+            line.push(b as char);
+            if b == b'\n' {
+                terminal.write_string(&line);
+                terminal.render().unwrap();
+                if let Some(value) = pattern.extract(line.as_bytes()) {
+                    plot.push(value);
+                }
+                line.clear();
+            }
+        }
+        terminal.write_string(&line);
+        terminal.render().unwrap();
+        if let Some(font) = options.font {
+            terminal.set_font(font.font()).unwrap();
+            terminal.render().unwrap();
+        }
+        terminal.pan(options.pan);
+        terminal.render().unwrap();
+
+        let mut canvas = Canvas::new();
+        canvas.clear(Gray4::BLACK).unwrap();
+        if options.layout != Layout::Full {
+            // composed like the `timer` task does
+            let status = Status {
+                baudrate: 115_200,
+                line: LineConfig::default(),
+                channel: "RX1",
+                battery_mv: Some(3900),
+                recording: false,
+                errors: ErrorCounts::default(),
+                clock_s: 0,
+            };
+            let font = terminal.font();
+            let regions = options.layout.regions(canvas.size());
+            if let Some(area) = regions.status {
+                status.draw(&mut canvas.cropped(&area), &FONT_6X8).unwrap();
+            }
+            let top = &mut canvas.cropped(&regions.top);
+            if options.plot.is_some() && options.layout == Layout::Status {
+                plot.draw(top).unwrap();
+            } else {
+                layout::draw_lines(top, font, |n| terminal.line(n)).unwrap();
+            }
+            if let Some(area) = regions.bottom {
+                plot.draw(&mut canvas.cropped(&area)).unwrap();
+            }
+            terminal.show_canvas(&canvas).unwrap();
+        } else if options.plot.is_some() {
+            plot.draw(&mut canvas).unwrap();
+            terminal.show_canvas(&canvas).unwrap();
+        }
+    }
+
+    match &options.png {
+        Some(path) => {
+            let file = File::create(path).unwrap_or_else(|err| {
+                eprintln!("cannot create {}: {}", path, err);
+                process::exit(1);
+            });
+            if let Err(err) = export::png(&panel, BufWriter::new(file), options.scale) {
+                eprintln!("cannot write png: {}", err);
+                process::exit(1);
+            }
+        }
+        None => print!("{}", export::ascii(&panel)),
+    }
+}
@@ -0,0 +1,392 @@
+//! Simulated SSD1362 panel
+//!
+//! Decodes the command and data stream into display RAM. The screen is shown in RAM
+//! order after applying the start line, display offset and display mode. Mirroring
+//! through the re-map register is not simulated, only the nibble order.
+
+use core::convert::Infallible;
+
+use display_interface::{DataFormat, DisplayError, WriteOnlyDataCommand};
+use embedded_graphics::{
+    pixelcolor::{Gray4, GrayColor},
+    prelude::*,
+};
+
+use crate::interface::DisplayInterface;
+
+/// Width in pixels
+pub const WIDTH: usize = 256;
+/// Height in pixels
+pub const HEIGHT: usize = 64;
+/// Bytes in one row of display RAM
+const ROW_BYTES: usize = WIDTH / 2;
+
+/// Re-map register bits
+const REMAP_NIBBLE: u8 = 1 << 1;
+const REMAP_VERTICAL: u8 = 1 << 2;
+
+pub struct Panel {
+    ram: [[u8; ROW_BYTES]; HEIGHT],
+    columns: (u8, u8),
+    rows: (u8, u8),
+    column: u8,
+    row: u8,
+    remap: u8,
+    start_line: u8,
+    offset: u8,
+    mode: u8,
+    contrast: u8,
+    on: bool,
+    /// Command being received
+    cmd: [u8; 16],
+    cmd_len: usize,
+}
+
+impl Default for Panel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Panel {
+    /// Panel in its reset state
+    pub fn new() -> Self {
+        Self {
+            ram: [[0; ROW_BYTES]; HEIGHT],
+            columns: (0, ROW_BYTES as u8 - 1),
+            rows: (0, HEIGHT as u8 - 1),
+            column: 0,
+            row: 0,
+            remap: 0,
+            start_line: 0,
+            offset: 0,
+            mode: 0xA4,
+            contrast: 0x7F,
+            on: false,
+            cmd: [0; 16],
+            cmd_len: 0,
+        }
+    }
+
+    /// Gray level (0 - 15) of the pixel at screen position `x`, `y`
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        if !self.on {
+            return 0;
+        }
+        let level = self.ram_pixel(x, self.ram_row(y));
+        match self.mode {
+            0xA5 => 0xF,
+            0xA6 => 0,
+            0xA7 => 0xF - level,
+            _ => level,
+        }
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.on
+    }
+
+    pub fn contrast(&self) -> u8 {
+        self.contrast
+    }
+
+    pub fn start_line(&self) -> u8 {
+        self.start_line
+    }
+
+    /// Feed one byte sent with D/C low
+    pub fn command(&mut self, byte: u8) {
+        self.cmd[self.cmd_len] = byte;
+        self.cmd_len += 1;
+
+        if self.cmd_len < 1 + params(self.cmd[0]) {
+            return;
+        }
+        self.cmd_len = 0;
+
+        let p = &self.cmd[1..];
+        match self.cmd[0] {
+            0x15 => {
+                self.columns = (p[0], p[1]);
+                self.column = p[0];
+            }
+            0x75 => {
+                self.rows = (p[0], p[1]);
+                self.row = p[0];
+            }
+            0x81 => self.contrast = p[0],
+            0xA0 => self.remap = p[0],
+            0xA1 => self.start_line = p[0] % HEIGHT as u8,
+            0xA2 => self.offset = p[0] % HEIGHT as u8,
+            0xA4..=0xA7 => self.mode = self.cmd[0],
+            0xAE => self.on = false,
+            0xAF => self.on = true,
+            _ => {}
+        }
+    }
+
+    /// Feed one byte sent with D/C high
+    pub fn data(&mut self, byte: u8) {
+        self.ram[self.row as usize % HEIGHT][self.column as usize % ROW_BYTES] = byte;
+
+        if self.remap & REMAP_VERTICAL != 0 {
+            if self.row >= self.rows.1 {
+                self.row = self.rows.0;
+                self.column = if self.column >= self.columns.1 { self.columns.0 } else { self.column + 1 };
+            } else {
+                self.row += 1;
+            }
+        } else if self.column >= self.columns.1 {
+            self.column = self.columns.0;
+            self.row = if self.row >= self.rows.1 { self.rows.0 } else { self.row + 1 };
+        } else {
+            self.column += 1;
+        }
+    }
+
+    fn ram_row(&self, y: usize) -> usize {
+        (y + self.start_line as usize + self.offset as usize) % HEIGHT
+    }
+
+    fn ram_pixel(&self, x: usize, row: usize) -> u8 {
+        let byte = self.ram[row][x / 2];
+        let high_first = self.remap & REMAP_NIBBLE != 0;
+        if (x & 1 == 0) == high_first {
+            byte >> 4
+        } else {
+            byte & 0xF
+        }
+    }
+
+    fn set_ram_pixel(&mut self, x: usize, row: usize, level: u8) {
+        let high_first = self.remap & REMAP_NIBBLE != 0;
+        let byte = &mut self.ram[row][x / 2];
+        if (x & 1 == 0) == high_first {
+            *byte = (*byte & 0x0F) | (level << 4);
+        } else {
+            *byte = (*byte & 0xF0) | (level & 0xF);
+        }
+    }
+}
+
+/// Number of parameter bytes following a command byte
+fn params(cmd: u8) -> usize {
+    match cmd {
+        0x15 | 0x75 | 0xA3 | 0xA9 => 2,
+        0x81 | 0xA0 | 0xA1 | 0xA2 | 0xA8 | 0xAB | 0xAD | 0xB1 | 0xB3 | 0xB6 | 0xBC
+        | 0xBD | 0xBE | 0xFD | 0x23 => 1,
+        0xB8 => 15,
+        0x26 | 0x27 => 6,
+        _ => 0,
+    }
+}
+
+impl DisplayInterface for Panel {
+    type Error = Infallible;
+
+    fn send_commands(&mut self, cmds: &[u8]) -> Result<(), Self::Error> {
+        cmds.iter().for_each(|&b| self.command(b));
+        Ok(())
+    }
+
+    fn send_data(&mut self, buf: &[u8]) -> Result<(), Self::Error> {
+        buf.iter().for_each(|&b| self.data(b));
+        Ok(())
+    }
+
+    fn send_bounded_data(
+        &mut self,
+        buf: &[u8],
+        disp_width: usize,
+        upper_left: (u8, u8),
+        lower_right: (u8, u8),
+    ) -> Result<(), Self::Error> {
+        for row in upper_left.1..=lower_right.1 {
+            let offset = row as usize * disp_width;
+            let start = offset + upper_left.0 as usize;
+            let end = offset + lower_right.0 as usize;
+            DisplayInterface::send_data(self, &buf[start..end])?;
+        }
+        Ok(())
+    }
+}
+
+impl WriteOnlyDataCommand for Panel {
+    fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+        match cmd {
+            DataFormat::U8(cmds) => cmds.iter().for_each(|&b| self.command(b)),
+            DataFormat::U8Iter(cmds) => cmds.for_each(|b| self.command(b)),
+            _ => return Err(DisplayError::DataFormatNotImplemented),
+        }
+        Ok(())
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        match buf {
+            DataFormat::U8(buf) => buf.iter().for_each(|&b| self.data(b)),
+            DataFormat::U8Iter(buf) => buf.for_each(|b| self.data(b)),
+            _ => return Err(DisplayError::DataFormatNotImplemented),
+        }
+        Ok(())
+    }
+}
+
+/// Lets code that takes the interface by value (like the firmware terminal)
+/// draw on a panel that is inspected afterwards
+impl WriteOnlyDataCommand for &mut Panel {
+    fn send_commands(&mut self, cmd: DataFormat<'_>) -> Result<(), DisplayError> {
+        WriteOnlyDataCommand::send_commands(&mut **self, cmd)
+    }
+
+    fn send_data(&mut self, buf: DataFormat<'_>) -> Result<(), DisplayError> {
+        WriteOnlyDataCommand::send_data(&mut **self, buf)
+    }
+}
+
+impl OriginDimensions for Panel {
+    fn size(&self) -> Size {
+        Size::new(WIDTH as u32, HEIGHT as u32)
+    }
+}
+
+/// Draws in screen coordinates, at the current start line
+impl DrawTarget for Panel {
+    type Color = Gray4;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        for Pixel(point, color) in pixels {
+            if point.x < 0 || point.y < 0 || point.x >= WIDTH as i32 || point.y >= HEIGHT as i32 {
+                continue;
+            }
+            let row = self.ram_row(point.y as usize);
+            self.set_ram_pixel(point.x as usize, row, color.luma());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn powered(cmds: &[u8]) -> Panel {
+        let mut panel = Panel::new();
+        panel.command(0xAF);
+        cmds.iter().for_each(|&b| panel.command(b));
+        panel
+    }
+
+    #[test]
+    fn off_until_display_on() {
+        let mut panel = Panel::new();
+        panel.data(0xFF);
+        assert!(!panel.is_on());
+        assert_eq!(panel.pixel(0, 0), 0);
+        panel.command(0xAF);
+        assert_eq!(panel.pixel(0, 0), 0xF);
+        panel.command(0xAE);
+        assert_eq!(panel.pixel(0, 0), 0);
+    }
+
+    #[test]
+    fn nibble_order() {
+        // left pixel in the low nibble after reset
+        let mut panel = powered(&[]);
+        panel.data(0x1F);
+        assert_eq!((panel.pixel(0, 0), panel.pixel(1, 0)), (0xF, 0x1));
+        // and in the high nibble with the nibble re-map
+        let mut panel = powered(&[0xA0, REMAP_NIBBLE]);
+        panel.data(0x1F);
+        assert_eq!((panel.pixel(0, 0), panel.pixel(1, 0)), (0x1, 0xF));
+    }
+
+    #[test]
+    fn column_and_row_window() {
+        // columns 2 - 3 (pixels 4 - 7) of rows 10 - 11
+        let mut panel = powered(&[0xA0, REMAP_NIBBLE, 0x15, 2, 3, 0x75, 10, 11]);
+        for b in [0x12, 0x34, 0x56, 0x78] {
+            panel.data(b);
+        }
+        let row = |panel: &Panel, y| (4..8).map(|x| panel.pixel(x, y)).collect::<Vec<_>>();
+        assert_eq!(row(&panel, 10), [1, 2, 3, 4]);
+        assert_eq!(row(&panel, 11), [5, 6, 7, 8]);
+        assert_eq!(panel.pixel(3, 10) + panel.pixel(8, 10) + panel.pixel(4, 12), 0);
+        // the address wraps to the start of the window
+        panel.data(0xAB);
+        assert_eq!(row(&panel, 10), [0xA, 0xB, 3, 4]);
+    }
+
+    #[test]
+    fn vertical_address_increment() {
+        let mut panel = powered(&[0xA0, REMAP_NIBBLE | REMAP_VERTICAL, 0x15, 0, 1, 0x75, 0, 1]);
+        for b in [0x11, 0x22, 0x33, 0x44] {
+            panel.data(b);
+        }
+        // down the column first, then the next column
+        assert_eq!((panel.pixel(0, 0), panel.pixel(0, 1)), (1, 2));
+        assert_eq!((panel.pixel(2, 0), panel.pixel(2, 1)), (3, 4));
+    }
+
+    #[test]
+    fn start_line_and_offset() {
+        let mut panel = powered(&[0x75, 8, 8]);
+        panel.data(0x0F);
+        assert_eq!(panel.pixel(0, 8), 0xF);
+        panel.command(0xA1);
+        panel.command(8);
+        assert_eq!(panel.start_line(), 8);
+        // RAM row 8 is the top of the screen now
+        assert_eq!((panel.pixel(0, 0), panel.pixel(0, 8)), (0xF, 0));
+        // the offset adds to it, both wrap at 64
+        panel.command(0xA2);
+        panel.command(60);
+        assert_eq!(panel.pixel(0, 4), 0xF);
+        panel.command(0xA1);
+        panel.command(64 + 8);
+        assert_eq!(panel.start_line(), 8);
+    }
+
+    #[test]
+    fn display_modes() {
+        let mut panel = powered(&[]);
+        panel.data(0x03);
+        let modes = [(0xA4, 3), (0xA5, 0xF), (0xA6, 0), (0xA7, 0xC)];
+        for &(mode, level) in modes.iter() {
+            panel.command(mode);
+            assert_eq!(panel.pixel(0, 0), level, "{:02X}", mode);
+        }
+    }
+
+    #[test]
+    fn parameters_are_skipped() {
+        // contrast, a gray scale table and a scroll setup, split over calls
+        let mut cmds = vec![0x81, 0x40, 0xB8];
+        cmds.extend_from_slice(&[0x15; 15]);
+        cmds.extend_from_slice(&[0x26, 0, 0, 0, 0, 0, 0, 0xA1, 0x10]);
+        let mut panel = powered(&[]);
+        for chunk in cmds.chunks(4) {
+            DisplayInterface::send_commands(&mut panel, chunk).unwrap();
+        }
+        assert_eq!(panel.contrast(), 0x40);
+        assert_eq!(panel.start_line(), 0x10);
+        // the gray scale table was not taken for a column address
+        DisplayInterface::send_data(&mut panel, &[0x0F]).unwrap();
+        assert_eq!(panel.pixel(0, 0x30), 0xF);
+    }
+
+    #[test]
+    fn drawing_follows_the_start_line() {
+        let mut panel = powered(&[0xA1, 20]);
+        // off screen pixels are dropped
+        let pixels = [Pixel(Point::new(5, 0), Gray4::new(9)), Pixel(Point::new(-1, 0), Gray4::WHITE)];
+        panel.draw_iter(pixels).unwrap();
+        assert_eq!(panel.pixel(5, 0), 9);
+        panel.command(0xA1);
+        panel.command(0);
+        assert_eq!(panel.pixel(5, 20), 9);
+    }
+}
@@ -0,0 +1,471 @@
+//! Host side of the remote control protocol
+//!
+//! `Client` sends requests to a logger over its debug port and waits for the
+//! responses. The port is anything to read and write bytes, a serial device opened
+//! as file or a `Loopback` to a `Device` in the same process. A read that returns
+//! no bytes is taken as timeout.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use protocol::{
+    cobs, serve, Device, ErrorCode, Filter, Info, Request, Response, Status, MAX_ROWS, MAX_TRIGGER,
+};
+
+use crate::export;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// No response came in
+    Timeout,
+    /// A response that cannot be decoded
+    Protocol(protocol::Error),
+    /// The logger did not carry out the request
+    Device(ErrorCode),
+    /// A response that does not fit the request
+    Unexpected,
+    /// Trigger pattern longer than `MAX_TRIGGER`
+    TooLong,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Timeout => f.write_str("no response"),
+            Error::Protocol(err) => write!(f, "bad response: {}", err),
+            Error::Device(code) => write!(f, "logger: {}", code),
+            Error::Unexpected => f.write_str("unexpected response"),
+            Error::TooLong => write!(f, "pattern longer than {} bytes", MAX_TRIGGER),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// The screen of a logger, 4 bit gray levels
+pub struct Screenshot {
+    pub width: usize,
+    pub height: usize,
+    /// Rows in display RAM format, two pixels per byte with the left pixel in the
+    /// high nibble
+    pub data: Vec<u8>,
+}
+
+impl Screenshot {
+    /// Gray level of pixel `x`, `y`
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        let byte = self.data[y * self.width / 2 + x / 2];
+        if x & 1 == 0 {
+            byte >> 4
+        } else {
+            byte & 0xF
+        }
+    }
+
+    /// Write as PNG, every pixel `scale` x `scale` pixels
+    pub fn png<W: Write>(&self, w: W, scale: usize) -> Result<(), png::EncodingError> {
+        export::png_pixels(self.width, self.height, |x, y| self.pixel(x, y), w, scale)
+    }
+}
+
+pub struct Client<P> {
+    port: P,
+    seq: u8,
+    decoder: cobs::Decoder,
+    input: [u8; 64],
+    /// Bytes in `input` and the next one to decode
+    input_len: usize,
+    input_pos: usize,
+}
+
+impl<P: Read + Write> Client<P> {
+    pub fn new(port: P) -> Self {
+        Self {
+            port,
+            seq: 0,
+            decoder: cobs::Decoder::new(),
+            input: [0; 64],
+            input_len: 0,
+            input_pos: 0,
+        }
+    }
+
+    /// Protocol version and screen size of the logger
+    pub fn hello(&mut self) -> Result<Info, Error> {
+        self.request(Request::Hello, |response| match response {
+            Response::Info(info) => Ok(info),
+            _ => Err(Error::Unexpected),
+        })
+    }
+
+    pub fn status(&mut self) -> Result<Status, Error> {
+        self.request(Request::GetStatus, |response| match response {
+            Response::Status(status) => Ok(status),
+            _ => Err(Error::Unexpected),
+        })
+    }
+
+    pub fn set_baudrate(&mut self, baudrate: u32) -> Result<(), Error> {
+        self.request(Request::SetBaudrate(baudrate), ok)
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) -> Result<(), Error> {
+        self.request(Request::SetFilter(filter), ok)
+    }
+
+    pub fn clear_screen(&mut self) -> Result<(), Error> {
+        self.request(Request::ClearScreen, ok)
+    }
+
+    /// Arm the trigger, an empty pattern turns it off
+    pub fn arm_trigger(&mut self, pattern: &[u8]) -> Result<(), Error> {
+        if pattern.len() > MAX_TRIGGER {
+            return Err(Error::TooLong);
+        }
+        self.request(Request::ArmTrigger(pattern), ok)
+    }
+
+    /// Read the whole screen, `MAX_ROWS` at a time
+    pub fn screenshot(&mut self) -> Result<Screenshot, Error> {
+        let info = self.hello()?;
+        let (width, height) = (info.width as usize, info.height);
+        let mut data = Vec::with_capacity(width / 2 * height as usize);
+
+        let mut first_row = 0;
+        while first_row < height {
+            let rows = MAX_ROWS.min(height - first_row);
+            let request = Request::ReadScreen { first_row, rows };
+            let chunk = self.request(request, |response| match response {
+                Response::Screen {
+                    first_row: row,
+                    data,
+                } if row == first_row => Ok(data.to_vec()),
+                _ => Err(Error::Unexpected),
+            })?;
+            if chunk.len() != rows as usize * width / 2 {
+                return Err(Error::Unexpected);
+            }
+            data.extend_from_slice(&chunk);
+            first_row += rows;
+        }
+
+        Ok(Screenshot {
+            width,
+            height: height as usize,
+            data,
+        })
+    }
+
+    /// Send `request` and hand its response to `handle`. Responses to earlier
+    /// requests and bytes outside frames, like console output, are skipped.
+    fn request<T, F>(&mut self, request: Request, handle: F) -> Result<T, Error>
+    where
+        F: FnOnce(Response) -> Result<T, Error>,
+    {
+        self.seq = self.seq.wrapping_add(1);
+        let mut frame = Vec::new();
+        request.write(self.seq, &mut |b| frame.push(b));
+        self.port.write_all(&frame)?;
+        self.port.flush()?;
+
+        loop {
+            let b = self.read_byte()?;
+            let packet = match self.decoder.push(b) {
+                Some(Ok(packet)) => packet,
+                // a damaged frame, the response may still follow
+                Some(Err(_)) | None => continue,
+            };
+            let (seq, response) = match Response::decode(packet) {
+                Ok(response) => response,
+                Err(err @ protocol::Error::Version(_)) => return Err(Error::Protocol(err)),
+                // damaged, the response may still follow
+                Err(_) => continue,
+            };
+            if seq != self.seq {
+                continue;
+            }
+            return match response {
+                Response::Error(code) => Err(Error::Device(code)),
+                response => handle(response),
+            };
+        }
+    }
+
+    fn read_byte(&mut self) -> Result<u8, Error> {
+        if self.input_pos == self.input_len {
+            self.input_len = self.port.read(&mut self.input)?;
+            self.input_pos = 0;
+            if self.input_len == 0 {
+                return Err(Error::Timeout);
+            }
+        }
+        self.input_pos += 1;
+        Ok(self.input[self.input_pos - 1])
+    }
+}
+
+fn ok(response: Response) -> Result<(), Error> {
+    match response {
+        Response::Ok => Ok(()),
+        _ => Err(Error::Unexpected),
+    }
+}
+
+/// Port to a `Device` in the same process: requests written to it are served
+/// right away, the responses are read back
+pub struct Loopback<D> {
+    pub device: D,
+    decoder: cobs::Decoder,
+    output: VecDeque<u8>,
+}
+
+impl<D: Device> Loopback<D> {
+    pub fn new(device: D) -> Self {
+        Self {
+            device,
+            decoder: cobs::Decoder::new(),
+            output: VecDeque::new(),
+        }
+    }
+}
+
+impl<D: Device> Write for Loopback<D> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &b in buf {
+            if let Some(Ok(packet)) = self.decoder.push(b) {
+                let output = &mut self.output;
+                serve(&mut self.device, packet, &mut |b| output.push_back(b));
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<D> Read for Loopback<D> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.output.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol::TriggerState;
+
+    const WIDTH: usize = 256;
+    /// Not a multiple of `MAX_ROWS`, so the last read is shorter
+    const HEIGHT: usize = 10;
+
+    fn level(x: usize, y: usize) -> u8 {
+        ((x + 3 * y) & 0xF) as u8
+    }
+
+    struct Mock {
+        baudrate: u32,
+        filter: Filter,
+        trigger: Vec<u8>,
+        cleared: bool,
+        /// `set_filter` fails to store
+        locked: bool,
+        /// First row and length of every screen read
+        reads: Vec<(u8, usize)>,
+    }
+
+    impl Device for Mock {
+        fn screen_size(&self) -> (u16, u8) {
+            (WIDTH as u16, HEIGHT as u8)
+        }
+
+        fn status(&mut self) -> Status {
+            Status {
+                baudrate: self.baudrate,
+                filter: self.filter,
+                trigger: if self.trigger.is_empty() {
+                    TriggerState::Off
+                } else {
+                    TriggerState::Armed
+                },
+            }
+        }
+
+        fn set_baudrate(&mut self, baudrate: u32) -> Result<(), ErrorCode> {
+            if baudrate == 0 {
+                return Err(ErrorCode::BadValue);
+            }
+            self.baudrate = baudrate;
+            Ok(())
+        }
+
+        fn set_filter(&mut self, filter: Filter) -> Result<(), ErrorCode> {
+            self.filter = filter;
+            if self.locked {
+                return Err(ErrorCode::SaveFailed);
+            }
+            Ok(())
+        }
+
+        fn clear_screen(&mut self) {
+            self.cleared = true;
+        }
+
+        fn arm_trigger(&mut self, pattern: &[u8]) -> Result<(), ErrorCode> {
+            self.trigger = pattern.to_vec();
+            Ok(())
+        }
+
+        fn read_screen(&mut self, first_row: u8, data: &mut [u8]) {
+            self.reads.push((first_row, data.len()));
+            for (i, b) in data.iter_mut().enumerate() {
+                let (y, x) = (first_row as usize + i / (WIDTH / 2), i % (WIDTH / 2) * 2);
+                *b = level(x, y) << 4 | level(x + 1, y);
+            }
+        }
+    }
+
+    fn client() -> Client<Loopback<Mock>> {
+        Client::new(Loopback::new(Mock {
+            baudrate: 115_200,
+            filter: Filter {
+                show_errors: false,
+                printable_only: false,
+            },
+            trigger: Vec::new(),
+            cleared: false,
+            locked: false,
+            reads: Vec::new(),
+        }))
+    }
+
+    /// Port that takes requests and never answers
+    struct Silent;
+
+    impl Read for Silent {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Ok(0)
+        }
+    }
+
+    impl Write for Silent {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn hello() {
+        let info = client().hello().unwrap();
+        assert_eq!(info.version, protocol::VERSION);
+        assert_eq!((info.width, info.height), (WIDTH as u16, HEIGHT as u8));
+    }
+
+    #[test]
+    fn settings() {
+        let mut client = client();
+        client.set_baudrate(9600).unwrap();
+        let filter = Filter {
+            show_errors: true,
+            printable_only: true,
+        };
+        client.set_filter(filter).unwrap();
+        let status = client.status().unwrap();
+        assert_eq!(status.baudrate, 9600);
+        assert_eq!(status.filter, filter);
+        assert_eq!(status.trigger, TriggerState::Off);
+        assert_eq!(client.port.device.baudrate, 9600);
+    }
+
+    #[test]
+    fn device_errors() {
+        let mut client = client();
+        assert!(matches!(client.set_baudrate(0), Err(Error::Device(ErrorCode::BadValue))));
+        client.port.device.locked = true;
+        let filter = Filter {
+            show_errors: true,
+            printable_only: false,
+        };
+        assert!(matches!(client.set_filter(filter), Err(Error::Device(ErrorCode::SaveFailed))));
+        // the client goes on with the next request
+        assert_eq!(client.status().unwrap().baudrate, 115_200);
+    }
+
+    #[test]
+    fn clear_screen() {
+        let mut client = client();
+        client.clear_screen().unwrap();
+        assert!(client.port.device.cleared);
+    }
+
+    #[test]
+    fn arm_trigger() {
+        let mut client = client();
+        client.arm_trigger(b"ERROR").unwrap();
+        assert_eq!(client.port.device.trigger, b"ERROR");
+        assert_eq!(client.status().unwrap().trigger, TriggerState::Armed);
+        client.arm_trigger(b"").unwrap();
+        assert_eq!(client.status().unwrap().trigger, TriggerState::Off);
+
+        let long = [b'x'; MAX_TRIGGER + 1];
+        assert!(matches!(client.arm_trigger(&long), Err(Error::TooLong)));
+        client.arm_trigger(&long[..MAX_TRIGGER]).unwrap();
+        assert_eq!(client.port.device.trigger.len(), MAX_TRIGGER);
+    }
+
+    #[test]
+    fn screenshot() {
+        let mut client = client();
+        let shot = client.screenshot().unwrap();
+        assert_eq!((shot.width, shot.height), (WIDTH, HEIGHT));
+        assert_eq!(shot.data.len(), WIDTH / 2 * HEIGHT);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                assert_eq!(shot.pixel(x, y), level(x, y), "pixel {}, {}", x, y);
+            }
+        }
+        let row_bytes = WIDTH / 2;
+        assert_eq!(
+            client.port.device.reads,
+            [(0, 4 * row_bytes), (4, 4 * row_bytes), (8, 2 * row_bytes)]
+        );
+    }
+
+    #[test]
+    fn screenshot_png_round_trip() {
+        let shot = client().screenshot().unwrap();
+        let mut file = Vec::new();
+        shot.png(&mut file, 1).unwrap();
+
+        let decoder = png::Decoder::new(&file[..]);
+        let mut reader = decoder.read_info().unwrap();
+        let mut image = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut image).unwrap();
+        assert_eq!((info.width, info.height), (WIDTH as u32, HEIGHT as u32));
+        assert_eq!(info.color_type, png::ColorType::Grayscale);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                assert_eq!(image[y * WIDTH + x] / 17, shot.pixel(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn silent_port_times_out() {
+        let mut client = Client::new(Silent);
+        assert!(matches!(client.hello(), Err(Error::Timeout)));
+        assert!(matches!(client.screenshot(), Err(Error::Timeout)));
+    }
+}